@@ -0,0 +1,132 @@
+//! brotli helper
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use brotli::CompressorWriter;
+use bytes::Bytes;
+use futures::stream::Stream;
+use tokio::io::AsyncRead;
+
+use crate::write_encoder::{Compressor, WriteEncoder, compress_reader};
+
+/// Default quality, a good trade-off between speed and size for on-the-fly compression
+pub const DEFAULT_QUALITY: u32 = 5;
+
+/// Maximum quality, useful for data compressed only once
+pub const MAX_QUALITY: u32 = 11;
+
+const BUFFER_SIZE: usize = 8192;
+
+/// Base 2 logarithm of the sliding window size (4 MiB)
+const WINDOW_SIZE: u32 = 22;
+
+impl Compressor for CompressorWriter<Vec<u8>> {
+    fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(self.get_mut())
+    }
+
+    fn finish(self) -> io::Result<Vec<u8>> {
+        Ok(self.into_inner())
+    }
+}
+
+/// An async BrotliEncoder that implements [Stream] for another [Stream]
+///
+/// Useful for on-the-fly brotli compression of http responses
+pub struct BrotliEncoder<T> {
+    inner: WriteEncoder<T, CompressorWriter<Vec<u8>>>,
+}
+
+impl<T> BrotliEncoder<T> {
+    /// Returns a new [BrotliEncoder] with [DEFAULT_QUALITY]
+    pub fn new(inner: T) -> Self {
+        Self::with_quality(inner, DEFAULT_QUALITY)
+    }
+
+    /// Returns a new [BrotliEncoder] with the given quality (0-11)
+    pub fn with_quality(inner: T, quality: u32) -> Self {
+        let compressor = CompressorWriter::new(
+            Vec::new(),
+            BUFFER_SIZE,
+            quality.min(MAX_QUALITY),
+            WINDOW_SIZE,
+        );
+        Self {
+            inner: WriteEncoder::new(inner, compressor),
+        }
+    }
+
+    /// If set, this is the number of bytes after which the compressor will be notified to flush
+    /// some data, so the compression produces more steady output and a little earlier.
+    pub fn flush_window(mut self, flush_window: Option<usize>) -> Self {
+        self.inner.set_flush_window(flush_window);
+        self
+    }
+
+    /// Returns the wrapped [Stream]
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T, O> Stream for BrotliEncoder<T>
+where
+    T: Stream<Item = Result<O, io::Error>> + Unpin,
+    O: Into<Bytes>,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().inner).poll_next(cx)
+    }
+}
+
+/// Read all data from `reader` and return it brotli compressed (assumes small inputs)
+pub async fn compress_vec<R>(reader: &mut R, size_hint: usize) -> Result<Vec<u8>, io::Error>
+where
+    R: AsyncRead + Unpin,
+{
+    let compressor = CompressorWriter::new(Vec::new(), BUFFER_SIZE, DEFAULT_QUALITY, WINDOW_SIZE);
+    compress_reader(compressor, reader, size_hint).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Read;
+
+    use futures::StreamExt;
+
+    const BODY: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod \
+        tempor incididunt ut labore et dolore magnam aliquam quaerat voluptatem.";
+
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(data, BUFFER_SIZE)
+            .read_to_end(&mut decoded)
+            .unwrap();
+        decoded
+    }
+
+    #[tokio::test]
+    async fn test_brotli_stream() {
+        let chunks: Vec<Result<Vec<u8>, io::Error>> =
+            BODY.chunks(10).map(|chunk| Ok(chunk.to_vec())).collect();
+        let mut encoder = BrotliEncoder::new(futures::stream::iter(chunks)).flush_window(Some(20));
+
+        let mut encoded = Vec::new();
+        while let Some(res) = encoder.next().await {
+            encoded.extend_from_slice(&res.unwrap());
+        }
+
+        assert_eq!(decompress(&encoded), BODY);
+    }
+
+    #[tokio::test]
+    async fn test_brotli_compress_vec() {
+        let encoded = compress_vec(&mut &BODY[..], BODY.len()).await.unwrap();
+        assert_eq!(decompress(&encoded), BODY);
+    }
+}
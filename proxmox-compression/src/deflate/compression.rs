@@ -19,6 +19,17 @@ pub enum Level {
     Precise(u32),
 }
 
+impl Level {
+    pub(crate) fn to_compression(&self) -> Compression {
+        match *self {
+            Level::Fastest => Compression::fast(),
+            Level::Best => Compression::best(),
+            Level::Default => Compression::new(3),
+            Level::Precise(val) => Compression::new(val),
+        }
+    }
+}
+
 #[derive(Eq, PartialEq)]
 enum EncoderState {
     Reading,
@@ -70,16 +81,9 @@ impl<T> DeflateEncoderBuilder<T> {
     }
 
     pub fn build(self) -> DeflateEncoder<T> {
-        let level = match self.level {
-            Level::Fastest => Compression::fast(),
-            Level::Best => Compression::best(),
-            Level::Default => Compression::new(3),
-            Level::Precise(val) => Compression::new(val),
-        };
-
         DeflateEncoder {
             inner: self.inner,
-            compressor: Compress::new(level, self.is_zlib),
+            compressor: Compress::new(self.level.to_compression(), self.is_zlib),
             buffer: ByteBuffer::with_capacity(self.buffer_size),
             input_buffer: Bytes::new(),
             state: EncoderState::Reading,
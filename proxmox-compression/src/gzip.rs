@@ -0,0 +1,116 @@
+//! gzip helper
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use futures::stream::Stream;
+use tokio::io::AsyncRead;
+
+use crate::Level;
+use crate::write_encoder::{Compressor, WriteEncoder, compress_reader};
+
+impl Compressor for GzEncoder<Vec<u8>> {
+    fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(self.get_mut())
+    }
+
+    fn finish(self) -> io::Result<Vec<u8>> {
+        GzEncoder::finish(self)
+    }
+}
+
+/// An async GzipEncoder that implements [Stream] for another [Stream]
+///
+/// Useful for on-the-fly gzip compression of http responses
+pub struct GzipEncoder<T> {
+    inner: WriteEncoder<T, GzEncoder<Vec<u8>>>,
+}
+
+impl<T> GzipEncoder<T> {
+    /// Returns a new [GzipEncoder] with default level
+    pub fn new(inner: T) -> Self {
+        Self::with_level(inner, Level::Default)
+    }
+
+    /// Returns a new [GzipEncoder] with the given level
+    pub fn with_level(inner: T, level: Level) -> Self {
+        let compressor = GzEncoder::new(Vec::new(), level.to_compression());
+        Self {
+            inner: WriteEncoder::new(inner, compressor),
+        }
+    }
+
+    /// If set, this is the number of bytes after which the compressor will be notified to flush
+    /// some data, so the compression produces more steady output and a little earlier.
+    pub fn flush_window(mut self, flush_window: Option<usize>) -> Self {
+        self.inner.set_flush_window(flush_window);
+        self
+    }
+
+    /// Returns the wrapped [Stream]
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T, O> Stream for GzipEncoder<T>
+where
+    T: Stream<Item = Result<O, io::Error>> + Unpin,
+    O: Into<Bytes>,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().inner).poll_next(cx)
+    }
+}
+
+/// Read all data from `reader` and return it gzip compressed (assumes small inputs)
+pub async fn compress_vec<R>(reader: &mut R, size_hint: usize) -> Result<Vec<u8>, io::Error>
+where
+    R: AsyncRead + Unpin,
+{
+    let compressor = GzEncoder::new(Vec::new(), Level::Default.to_compression());
+    compress_reader(compressor, reader, size_hint).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use futures::StreamExt;
+
+    const BODY: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod \
+        tempor incididunt ut labore et dolore magnam aliquam quaerat voluptatem.";
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut decoded).unwrap();
+        decoded
+    }
+
+    #[tokio::test]
+    async fn test_gzip_stream_against_flate2() {
+        let chunks: Vec<Result<Vec<u8>, io::Error>> =
+            BODY.chunks(10).map(|chunk| Ok(chunk.to_vec())).collect();
+        let mut encoder = GzipEncoder::new(futures::stream::iter(chunks)).flush_window(Some(20));
+
+        let mut encoded = Vec::new();
+        while let Some(res) = encoder.next().await {
+            encoded.extend_from_slice(&res.unwrap());
+        }
+
+        assert_eq!(gunzip(&encoded), BODY);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_compress_vec() {
+        let encoded = compress_vec(&mut &BODY[..], BODY.len()).await.unwrap();
+        assert_eq!(gunzip(&encoded), BODY);
+    }
+}
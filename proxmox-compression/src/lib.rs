@@ -4,7 +4,11 @@ pub use deflate::{
     DeflateDecoder, DeflateDecoderBuilder, DeflateEncoder, DeflateEncoderBuilder, Level,
 };
 
+#[cfg(feature = "brotli")]
+pub mod brotli;
 mod deflate;
+pub mod gzip;
 pub mod tar;
+mod write_encoder;
 pub mod zip;
 pub mod zstd;
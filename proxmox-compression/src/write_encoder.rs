@@ -0,0 +1,102 @@
+//! Stream adapter for compressors implementing [Write]
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::ready;
+use futures::stream::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// A compressor writing its output into a memory buffer
+pub(crate) trait Compressor: Write {
+    /// Take the compressed data produced so far
+    fn take_output(&mut self) -> Vec<u8>;
+
+    /// Finish the compressed stream and return the remaining output
+    fn finish(self) -> io::Result<Vec<u8>>;
+}
+
+/// Compress all data read from `reader` at once (for small inputs)
+pub(crate) async fn compress_reader<C, R>(
+    mut compressor: C,
+    reader: &mut R,
+    size_hint: usize,
+) -> io::Result<Vec<u8>>
+where
+    C: Compressor,
+    R: AsyncRead + Unpin,
+{
+    let mut buffer = Vec::with_capacity(size_hint);
+    reader.read_to_end(&mut buffer).await?;
+    compressor.write_all(&buffer)?;
+    compressor.finish()
+}
+
+pub(crate) struct WriteEncoder<T, C> {
+    inner: T,
+    compressor: Option<C>,
+    /// This is the current amount of sent data and the window size used for intermittent flushing
+    /// of the compression layer.
+    flush_window: Option<(usize, usize)>,
+}
+
+impl<T, C> WriteEncoder<T, C> {
+    pub(crate) fn new(inner: T, compressor: C) -> Self {
+        Self {
+            inner,
+            compressor: Some(compressor),
+            flush_window: None,
+        }
+    }
+
+    pub(crate) fn set_flush_window(&mut self, flush_window: Option<usize>) {
+        self.flush_window = flush_window.map(|n| (0, n));
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, O, C> Stream for WriteEncoder<T, C>
+where
+    T: Stream<Item = Result<O, io::Error>> + Unpin,
+    O: Into<Bytes>,
+    C: Compressor + Unpin,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let compressor = match this.compressor.as_mut() {
+                Some(compressor) => compressor,
+                None => return Poll::Ready(None),
+            };
+
+            let output = match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(res) => {
+                    let buf: Bytes = res?.into();
+                    compressor.write_all(&buf)?;
+
+                    if let Some((at, window)) = &mut this.flush_window {
+                        *at = at.saturating_add(buf.len());
+                        if *at >= *window {
+                            *at = 0;
+                            compressor.flush()?;
+                        }
+                    }
+
+                    compressor.take_output()
+                }
+                None => this.compressor.take().unwrap().finish()?,
+            };
+
+            if !output.is_empty() {
+                return Poll::Ready(Some(Ok(output.into())));
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Abstraction over running external commands.
+//!
+//! All external tools (`smartctl`, `zpool`, `lsblk`, `sgdisk`, ...) are run through a
+//! [`CommandRunner`], which is part of the [`Disks`](crate::Disks) context. This allows
+//! replacing the real commands with recorded outputs, see [`RecordedCommandRunner`].
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Error, bail, format_err};
+
+/// Runs external commands on behalf of the disk management functions.
+pub trait CommandRunner: Send + Sync {
+    /// Run `command` to completion and return its output.
+    ///
+    /// If a `timeout` is given, the command is killed once it elapsed and an error is returned.
+    /// A non-zero exit status is not considered an error here.
+    fn output(&self, command: &mut Command, timeout: Option<Duration>) -> Result<Output, Error>;
+
+    /// Run `command` and return its standard output as string.
+    ///
+    /// This behaves like [`proxmox_sys::command::run_command`]: the `exit_code_check` function
+    /// decides which exit codes are considered successful, by default only `0` is.
+    fn run(
+        &self,
+        mut command: Command,
+        exit_code_check: Option<fn(i32) -> bool>,
+    ) -> Result<String, Error> {
+        let output = self
+            .output(&mut command, None)
+            .map_err(|err| format_err!("failed to execute {:?} - {}", command, err))?;
+
+        proxmox_sys::command::command_output_as_string(output, exit_code_check)
+            .map_err(|err| format_err!("command {:?} failed - {}", command, err))
+    }
+}
+
+/// Runs commands on the actual system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn output(&self, command: &mut Command, timeout: Option<Duration>) -> Result<Output, Error> {
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return Ok(command.output()?),
+        };
+
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Read the pipes in the background, so the command cannot block on a full pipe, but keep
+        // the child here, so it is only ever killed before it was reaped.
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                // Kill the process to avoid leaving it running indefinitely.
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!("timed out after {}s", timeout.as_secs());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        Ok(Output {
+            status,
+            stdout: join_pipe(stdout)?,
+            stderr: join_pipe(stderr)?,
+        })
+    }
+}
+
+type PipeReader = Option<std::thread::JoinHandle<std::io::Result<Vec<u8>>>>;
+
+/// Read all of `pipe` in a separate thread.
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> PipeReader {
+    pipe.map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut data = Vec::new();
+            pipe.read_to_end(&mut data)?;
+            Ok(data)
+        })
+    })
+}
+
+fn join_pipe(reader: PipeReader) -> Result<Vec<u8>, Error> {
+    match reader {
+        Some(reader) => match reader.join() {
+            Ok(result) => Ok(result?),
+            Err(_) => bail!("reading command output failed"),
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Returns recorded outputs instead of running commands.
+///
+/// Outputs are looked up by the command line, which is the program followed by its arguments,
+/// separated by single spaces (for example `zpool status -p -P tank`). Running a command without
+/// recorded output fails. All executed command lines are kept, so that tests can check which
+/// commands a mutating operation would have run.
+///
+/// ```
+/// # use std::path::Path;
+/// # use std::sync::Arc;
+/// use proxmox_disks::{Disks, RecordedCommandRunner};
+///
+/// let runner = Arc::new(RecordedCommandRunner::new().with_output("umount /mnt/test", ""));
+/// let disks = Disks::new().with_command_runner(runner.clone());
+///
+/// disks.unmount_by_mountpoint(Path::new("/mnt/test")).unwrap();
+/// assert_eq!(runner.calls(), ["umount /mnt/test"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct RecordedCommandRunner {
+    outputs: HashMap<String, Output>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl RecordedCommandRunner {
+    /// Create a runner without any recorded outputs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a successful run of `command_line` printing `stdout`.
+    pub fn with_output<S: Into<Vec<u8>>>(self, command_line: &str, stdout: S) -> Self {
+        self.with_exit_code(command_line, 0, stdout, Vec::new())
+    }
+
+    /// Record a run of `command_line` with an explicit exit code and error output.
+    pub fn with_exit_code<S: Into<Vec<u8>>, E: Into<Vec<u8>>>(
+        mut self,
+        command_line: &str,
+        exit_code: i32,
+        stdout: S,
+        stderr: E,
+    ) -> Self {
+        let output = Output {
+            status: ExitStatus::from_raw((exit_code & 0xff) << 8),
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+        };
+        self.outputs.insert(command_line.to_string(), output);
+        self
+    }
+
+    /// The command lines executed so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl CommandRunner for RecordedCommandRunner {
+    fn output(&self, command: &mut Command, _timeout: Option<Duration>) -> Result<Output, Error> {
+        let command_line = command_line(command);
+        self.calls.lock().unwrap().push(command_line.clone());
+
+        match self.outputs.get(&command_line) {
+            Some(output) => Ok(output.clone()),
+            None => bail!("no recorded output for '{command_line}'"),
+        }
+    }
+}
+
+/// Format a command as its program followed by its arguments.
+fn command_line(command: &Command) -> String {
+    let mut line = command.get_program().to_string_lossy().into_owned();
+    for arg in command.get_args() {
+        line.push(' ');
+        line.push_str(&arg.to_string_lossy());
+    }
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recorded_command_runner() -> Result<(), Error> {
+        let runner = RecordedCommandRunner::new()
+            .with_output("echo hello", "hello\n")
+            .with_exit_code("false", 1, "", "failed\n");
+
+        let mut command = Command::new("echo");
+        command.arg("hello");
+        assert_eq!(runner.run(command, None)?, "hello\n");
+
+        let err = runner.run(Command::new("false"), None).unwrap_err();
+        assert!(err.to_string().contains("status code: 1 - failed"));
+        assert_eq!(
+            runner.run(Command::new("false"), Some(|code| code == 1))?,
+            ""
+        );
+
+        assert!(runner.run(Command::new("true"), None).is_err());
+
+        assert_eq!(runner.calls(), ["echo hello", "false", "false", "true"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_command_runner_timeout() {
+        let mut command = Command::new("sleep");
+        command.arg("10");
+        let err = SystemCommandRunner
+            .output(&mut command, Some(Duration::from_millis(100)))
+            .unwrap_err();
+        assert!(err.to_string().starts_with("timed out"));
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out; echo err >&2; exit 3"]);
+        let output = SystemCommandRunner
+            .output(&mut command, Some(Duration::from_secs(10)))
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+}
@@ -0,0 +1,152 @@
+//! Block device lookup, via udev or directly from a sysfs tree.
+
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use libc::dev_t;
+
+/// A device as seen by udev, or read directly from a sysfs tree which is not the real `/sys`.
+pub(crate) enum Device {
+    Udev(udev::Device),
+    Sysfs(SysfsDevice),
+}
+
+impl Device {
+    /// Look up the device at `syspath`.
+    ///
+    /// udev only knows about the real `/sys`, so for any other `sysfs_root` the device is read
+    /// from sysfs directly, with the properties limited to the kernel's `uevent` data.
+    pub(crate) fn from_syspath(sysfs_root: &Path, syspath: &Path) -> io::Result<Self> {
+        if sysfs_root == Path::new("/sys") {
+            Ok(Device::Udev(udev::Device::from_syspath(syspath)?))
+        } else {
+            Ok(Device::Sysfs(SysfsDevice::from_syspath(
+                sysfs_root, syspath,
+            )?))
+        }
+    }
+
+    pub(crate) fn devnum(&self) -> Option<dev_t> {
+        match self {
+            Device::Udev(device) => device.devnum(),
+            Device::Sysfs(device) => device.devnum,
+        }
+    }
+
+    pub(crate) fn sysname(&self) -> &OsStr {
+        match self {
+            Device::Udev(device) => device.sysname(),
+            Device::Sysfs(device) => &device.sysname,
+        }
+    }
+
+    pub(crate) fn syspath(&self) -> &Path {
+        match self {
+            Device::Udev(device) => device.syspath(),
+            Device::Sysfs(device) => &device.syspath,
+        }
+    }
+
+    pub(crate) fn devnode(&self) -> Option<&Path> {
+        match self {
+            Device::Udev(device) => device.devnode(),
+            Device::Sysfs(device) => device.devnode.as_deref(),
+        }
+    }
+
+    pub(crate) fn parent(&self) -> Option<Self> {
+        match self {
+            Device::Udev(device) => device.parent().map(Device::Udev),
+            Device::Sysfs(device) => device.parent().map(Device::Sysfs),
+        }
+    }
+
+    pub(crate) fn property_value(&self, key: &str) -> Option<&OsStr> {
+        match self {
+            Device::Udev(device) => device.property_value(key),
+            Device::Sysfs(device) => device
+                .properties
+                .get(OsStr::new(key))
+                .map(OsString::as_os_str),
+        }
+    }
+}
+
+/// A device read from a sysfs tree without udev.
+pub(crate) struct SysfsDevice {
+    sysfs_root: PathBuf,
+    syspath: PathBuf,
+    sysname: OsString,
+    devnum: Option<dev_t>,
+    devnode: Option<PathBuf>,
+    properties: HashMap<OsString, OsString>,
+}
+
+impl SysfsDevice {
+    fn from_syspath(sysfs_root: &Path, syspath: &Path) -> io::Result<Self> {
+        let sysfs_root = sysfs_root.canonicalize()?;
+        // like udev, resolve symlinks such as `block/<name>` to the device directory
+        let syspath = syspath.canonicalize()?;
+        if !syspath.starts_with(&sysfs_root) || syspath == sysfs_root {
+            proxmox_lang::io_bail!("not a device below {:?}: {:?}", sysfs_root, syspath);
+        }
+
+        let sysname = syspath.file_name().map(OsStr::to_owned).unwrap_or_default();
+
+        let properties = match std::fs::read(syspath.join("uevent")) {
+            Ok(data) => parse_uevent(&data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        let number = |key: &str| -> Option<u32> {
+            std::str::from_utf8(properties.get(OsStr::new(key))?.as_bytes())
+                .ok()?
+                .parse()
+                .ok()
+        };
+        let devnum = match (number("MAJOR"), number("MINOR")) {
+            (Some(major), Some(minor)) => Some(libc::makedev(major, minor)),
+            _ => None,
+        };
+
+        let devnode = properties
+            .get(OsStr::new("DEVNAME"))
+            .map(|name| Path::new("/dev").join(name));
+
+        Ok(Self {
+            sysfs_root,
+            syspath,
+            sysname,
+            devnum,
+            devnode,
+            properties,
+        })
+    }
+
+    /// The closest ancestor directory which is a device, i.e. has a `uevent` file.
+    fn parent(&self) -> Option<Self> {
+        self.syspath
+            .ancestors()
+            .skip(1)
+            .take_while(|path| path.starts_with(&self.sysfs_root) && *path != self.sysfs_root)
+            .find(|path| path.join("uevent").exists())
+            .and_then(|path| Self::from_syspath(&self.sysfs_root, path).ok())
+    }
+}
+
+/// Parse the `KEY=value` lines of a sysfs `uevent` file.
+fn parse_uevent(data: &[u8]) -> HashMap<OsString, OsString> {
+    data.split(|b| *b == b'\n')
+        .filter_map(|line| {
+            let pos = line.iter().position(|b| *b == b'=')?;
+            Some((
+                OsStr::from_bytes(&line[..pos]).to_owned(),
+                OsStr::from_bytes(&line[pos + 1..]).to_owned(),
+            ))
+        })
+        .collect()
+}
@@ -11,6 +11,7 @@ use once_cell::sync::OnceCell;
 
 use proxmox_lang::io_format_err;
 
+use crate::device::Device;
 use crate::{BlockDevStat, DiskType, Disks};
 
 /// Queries (and caches) various information about a specific disk.
@@ -18,7 +19,7 @@ use crate::{BlockDevStat, DiskType, Disks};
 /// This belongs to a `Disks` and provides information for a single disk.
 pub struct Disk {
     manager: Arc<Disks>,
-    device: udev::Device,
+    device: Device,
     info: DiskInfo,
 }
 
@@ -45,8 +46,8 @@ struct DiskInfo {
 }
 
 impl Disk {
-    /// Create a new `Disk` from a udev device and its managing context.
-    pub(crate) fn new(manager: Arc<Disks>, device: udev::Device) -> Self {
+    /// Create a new `Disk` from a device and its managing context.
+    pub(crate) fn new(manager: Arc<Disks>, device: Device) -> Self {
         Self {
             manager,
             device,
@@ -54,6 +55,12 @@ impl Disk {
         }
     }
 
+    /// The disk management context this disk belongs to.
+    #[cfg(feature = "operations")]
+    pub(crate) fn manager(&self) -> &Disks {
+        &self.manager
+    }
+
     /// Try to get the device number for this disk.
     ///
     /// (In udev this can fail...)
@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use std::ffi::OsString;
 use std::io;
 use std::os::unix::fs::MetadataExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{Context as _, Error, format_err};
@@ -11,7 +11,7 @@ use once_cell::sync::OnceCell;
 
 use proxmox_sys::linux::procfs::{MountInfo, mountinfo::Device};
 
-use crate::{BlockDevStat, Disk};
+use crate::{BlockDevStat, CommandRunner, Disk, SystemCommandRunner};
 
 /// Disk management context.
 ///
@@ -21,10 +21,24 @@ use crate::{BlockDevStat, Disk};
 /// Several methods on [`Disk`](crate::Disk) (such as `disk_by_node`, `disk_by_sys_path`, and
 /// `disk_by_name`) require `self: &Arc<Self>`, so callers that need them should wrap the
 /// `Disks` in an `Arc` via [`into_arc`](Self::into_arc).
-#[derive(Default)]
+///
+/// External commands are run through the context's [`CommandRunner`], and `/sys` and `/proc` are
+/// accessed relative to its sysfs and procfs roots, all of which can be replaced for testing. udev
+/// is only queried for the real `/sys`, below any other sysfs root devices are read directly and
+/// only provide the kernel's `uevent` properties. Mount information is always read from the real
+/// `/proc`.
 pub struct Disks {
     mount_info: OnceCell<MountInfo>,
     mounted_devices: OnceCell<HashSet<dev_t>>,
+    command_runner: Arc<dyn CommandRunner>,
+    sysfs_root: PathBuf,
+    procfs_root: PathBuf,
+}
+
+impl Default for Disks {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Disks {
@@ -35,9 +49,56 @@ impl Disks {
         Self {
             mount_info: OnceCell::new(),
             mounted_devices: OnceCell::new(),
+            command_runner: Arc::new(SystemCommandRunner),
+            sysfs_root: PathBuf::from("/sys"),
+            procfs_root: PathBuf::from("/proc"),
         }
     }
 
+    /// Use a different [`CommandRunner`] for external commands.
+    pub fn with_command_runner(mut self, command_runner: Arc<dyn CommandRunner>) -> Self {
+        self.command_runner = command_runner;
+        self
+    }
+
+    /// Use a different directory instead of `/sys`.
+    pub fn with_sysfs_root<P: Into<PathBuf>>(mut self, sysfs_root: P) -> Self {
+        self.sysfs_root = sysfs_root.into();
+        self
+    }
+
+    /// Use a different directory instead of `/proc`.
+    pub fn with_procfs_root<P: Into<PathBuf>>(mut self, procfs_root: P) -> Self {
+        self.procfs_root = procfs_root.into();
+        self
+    }
+
+    /// The [`CommandRunner`] used for external commands.
+    pub fn command_runner(&self) -> &dyn CommandRunner {
+        &*self.command_runner
+    }
+
+    /// The directory used instead of `/sys`.
+    pub fn sysfs_root(&self) -> &Path {
+        &self.sysfs_root
+    }
+
+    /// Get the path of `path` (relative to `/sys`) below the sysfs root.
+    pub(crate) fn sys_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.sysfs_root.join(path)
+    }
+
+    /// The directory used instead of `/proc`.
+    pub fn procfs_root(&self) -> &Path {
+        &self.procfs_root
+    }
+
+    /// Get the path of `path` (relative to `/proc`) below the procfs root.
+    #[cfg(feature = "discovery")]
+    pub(crate) fn proc_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.procfs_root.join(path)
+    }
+
     /// Wrap this context in an `Arc` for use with `disk_by_*` methods.
     pub fn into_arc(self) -> Arc<Self> {
         Arc::new(self)
@@ -63,28 +124,28 @@ impl Disks {
 
     /// Get a `Disk` for a specific device number.
     pub fn disk_by_dev_num(self: &Arc<Self>, devnum: dev_t) -> io::Result<Disk> {
-        self.disk_by_sys_path(format!(
-            "/sys/dev/block/{}:{}",
+        self.disk_by_sys_path(self.sys_path(format!(
+            "dev/block/{}:{}",
             unsafe { libc::major(devnum) },
             unsafe { libc::minor(devnum) },
-        ))
+        )))
     }
 
     /// Get a `Disk` for a path in `/sys`.
     pub fn disk_by_sys_path<P: AsRef<Path>>(self: &Arc<Self>, path: P) -> io::Result<Disk> {
-        let device = udev::Device::from_syspath(path.as_ref())?;
+        let device = crate::device::Device::from_syspath(&self.sysfs_root, path.as_ref())?;
         Ok(Disk::new(self.clone(), device))
     }
 
     /// Get a `Disk` for a name in `/sys/block/<name>`.
     pub fn disk_by_name(self: &Arc<Self>, name: &str) -> io::Result<Disk> {
-        let syspath = format!("/sys/block/{name}");
+        let syspath = self.sys_path(format!("block/{name}"));
         self.disk_by_sys_path(syspath)
     }
 
     /// Get a `Disk` for a name in `/sys/class/block/<name>`.
     pub fn partition_by_name(self: &Arc<Self>, name: &str) -> io::Result<Disk> {
-        let syspath = format!("/sys/class/block/{name}");
+        let syspath = self.sys_path(format!("class/block/{name}"));
         self.disk_by_sys_path(syspath)
     }
 
@@ -183,13 +244,13 @@ impl Disks {
         }
 
         let dev = device.into_dev_t();
-        let sys_path = format!(
-            "/sys/dev/block/{}:{}",
+        let sys_path = self.sys_path(format!(
+            "dev/block/{}:{}",
             unsafe { libc::major(dev) },
             unsafe { libc::minor(dev) }
-        );
+        ));
 
-        crate::disk::read_stat_from_sysfs(Path::new(&sys_path))
+        crate::disk::read_stat_from_sysfs(&sys_path)
             .with_context(|| format!("could not read stats for {}", path.as_ref().display()))?
             .ok_or_else(|| format_err!("could not read disk stats for {}", path.as_ref().display()))
     }
@@ -3,12 +3,20 @@
 //! The crate is organized into feature-gated layers:
 //!
 //! - **Core** (always available): [`Disks`], [`Disk`], [`BlockDevStat`] - sysfs/udev queries
-//!   with no subprocess calls.
-//! - **`smart`**: S.M.A.R.T. health queries via `smartctl` with configurable timeout.
-//! - **`discovery`**: Full disk enumeration via [`DiskUsageQuery`], including LVM/ZFS detection.
-//! - **`operations`**: Mutating disk operations (wipe, partition, format, mount).
+//!   with no subprocess calls, and the [`CommandRunner`] used by the other layers.
+//! - **`smart`**: S.M.A.R.T. health queries via `smartctl` with configurable timeout, including
+//!   the NVMe health log.
+//! - **`smart-history`**: Persistent S.M.A.R.T. history (RRD based) with a wear trend check.
+//! - **`discovery`**: Full disk enumeration via [`DiskUsageQuery`], including LVM/ZFS/md
+//!   detection, listing of LVM volume groups, logical volumes and thin pools, and of md arrays.
+//! - **`operations`**: Mutating disk operations (wipe, partition, format, mount). Together with
+//!   `discovery` this also includes ZFS pool and dataset, LVM volume group and thin pool, and md
+//!   array management.
 //! - **`api-types`**: `proxmox-schema` API type derives.
 
+mod command;
+pub use command::{CommandRunner, RecordedCommandRunner, SystemCommandRunner};
+mod device;
 mod disk;
 pub use disk::Disk;
 mod disks;
@@ -20,6 +28,10 @@ pub use types::*;
 mod smart;
 #[cfg(feature = "smart")]
 pub use smart::*;
+#[cfg(feature = "smart-history")]
+mod smart_history;
+#[cfg(feature = "smart-history")]
+pub use smart_history::*;
 #[cfg(feature = "discovery")]
 mod completion;
 #[cfg(feature = "discovery")]
@@ -27,7 +39,13 @@ pub use completion::*;
 #[cfg(feature = "discovery")]
 mod lvm;
 #[cfg(feature = "discovery")]
-pub(crate) use lvm::*;
+pub(crate) use lvm::{LvmDeviceUsage, get_lvm_devices};
+#[cfg(feature = "discovery")]
+pub use lvm::{LvmLogicalVolume, LvmPhysicalVolume, LvmVolumeGroup};
+#[cfg(feature = "discovery")]
+mod mdraid;
+#[cfg(feature = "discovery")]
+pub use mdraid::*;
 #[cfg(feature = "discovery")]
 mod scan;
 #[cfg(feature = "discovery")]
@@ -51,3 +69,13 @@ mod parse_helpers;
 mod operations;
 #[cfg(feature = "operations")]
 pub use operations::*;
+#[cfg(all(feature = "discovery", feature = "operations"))]
+mod lvm_ops;
+#[cfg(all(feature = "discovery", feature = "operations"))]
+mod mdraid_ops;
+#[cfg(all(feature = "discovery", feature = "operations"))]
+pub use mdraid_ops::*;
+#[cfg(all(feature = "discovery", feature = "operations"))]
+mod zfs_ops;
+#[cfg(all(feature = "discovery", feature = "operations"))]
+pub use zfs_ops::*;
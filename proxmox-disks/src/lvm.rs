@@ -1,11 +1,15 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::os::unix::fs::MetadataExt;
 use std::sync::LazyLock;
 
-use anyhow::Error;
+use anyhow::{Error, format_err};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::LsblkInfo;
+#[cfg(feature = "api-types")]
+use proxmox_schema::api;
+
+use crate::{Disks, LsblkInfo};
 
 static LVM_UUIDS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
     let mut set = HashSet::new();
@@ -13,10 +17,48 @@ static LVM_UUIDS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
     set
 });
 
-/// Get set of devices used by LVM (pvs).
+/// How a device is used by LVM.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LvmDeviceUsage {
+    /// The volume group the physical volume belongs to.
+    pub(crate) vg: Option<String>,
+    /// The logical volumes with extents on the physical volume.
+    pub(crate) lvs: Vec<String>,
+}
+
+impl LvmDeviceUsage {
+    pub(crate) fn merge(&mut self, other: &LvmDeviceUsage) {
+        if self.vg.is_none() {
+            self.vg.clone_from(&other.vg);
+        }
+        for lv in &other.lvs {
+            if !self.lvs.contains(lv) {
+                self.lvs.push(lv.clone());
+            }
+        }
+    }
+}
+
+/// Map hidden LV names (e.g. `[data_tdata]`) to the LV owning them.
+fn owning_lv_name(lv_name: &str) -> Option<&str> {
+    let name = lv_name.trim_start_matches('[').trim_end_matches(']');
+    if name.is_empty() || name.ends_with("_pmspare") {
+        return None;
+    }
+    Some(
+        name.strip_suffix("_tdata")
+            .or_else(|| name.strip_suffix("_tmeta"))
+            .unwrap_or(name),
+    )
+}
+
+/// Get the devices used by LVM (pvs), with their volume group and logical volumes.
 ///
-/// The set is indexed by using the unix raw device number (dev_t is u64)
-pub(crate) fn get_lvm_devices(lsblk_info: &[LsblkInfo]) -> Result<HashSet<u64>, Error> {
+/// The map is indexed by using the unix raw device number (dev_t is u64)
+pub(crate) fn get_lvm_devices(
+    disks: &Disks,
+    lsblk_info: &[LsblkInfo],
+) -> Result<HashMap<u64, LvmDeviceUsage>, Error> {
     const PVS_BIN_PATH: &str = "pvs";
 
     let mut command = std::process::Command::new(PVS_BIN_PATH);
@@ -25,36 +67,300 @@ pub(crate) fn get_lvm_devices(lsblk_info: &[LsblkInfo]) -> Result<HashSet<u64>,
         "json",
         "--noheadings",
         "--readonly",
+        "--segments",
         "-o",
-        "pv_name",
+        "pv_name,vg_name,lv_name",
     ]);
 
-    let output = proxmox_sys::command::run_command(command, None)?;
+    let output = disks.command_runner().run(command, None)?;
 
-    let mut device_set: HashSet<u64> = HashSet::new();
+    let mut device_map: HashMap<u64, LvmDeviceUsage> = HashMap::new();
 
     for info in lsblk_info.iter() {
         if let Some(partition_type) = &info.partition_type
             && LVM_UUIDS.contains(partition_type.as_str())
         {
             let meta = std::fs::metadata(&info.path)?;
-            device_set.insert(meta.rdev());
+            device_map.entry(meta.rdev()).or_default();
         }
     }
 
     let output: Value = output.parse()?;
 
-    match output["report"][0]["pv"].as_array() {
+    match output["report"][0]["pvseg"].as_array() {
         Some(list) => {
             for info in list {
                 if let Some(pv_name) = info["pv_name"].as_str() {
                     let meta = std::fs::metadata(pv_name)?;
-                    device_set.insert(meta.rdev());
+                    let usage = device_map.entry(meta.rdev()).or_default();
+                    if usage.vg.is_none() {
+                        usage.vg = json_string(info, "vg_name");
+                    }
+                    if let Some(lv) = info["lv_name"].as_str().and_then(owning_lv_name)
+                        && !usage.lvs.iter().any(|name| name == lv)
+                    {
+                        usage.lvs.push(lv.to_string());
+                    }
                 }
             }
         }
-        None => return Ok(device_set),
+        None => return Ok(device_map),
     }
 
-    Ok(device_set)
+    Ok(device_map)
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// An LVM volume group.
+pub struct LvmVolumeGroup {
+    /// The volume group name.
+    pub name: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Free space in bytes.
+    pub free: u64,
+    /// Number of physical volumes.
+    pub pv_count: u64,
+    /// Number of logical volumes.
+    pub lv_count: u64,
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// An LVM physical volume.
+pub struct LvmPhysicalVolume {
+    /// The device path.
+    pub name: String,
+    /// The volume group, if the physical volume belongs to one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vg: Option<String>,
+    /// Size in bytes.
+    pub size: u64,
+    /// Free space in bytes.
+    pub free: u64,
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// An LVM logical volume.
+pub struct LvmLogicalVolume {
+    /// The logical volume name.
+    pub name: String,
+    /// The volume group name.
+    pub vg: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// The segment type, e.g. `linear`, `thin-pool` or `thin`.
+    pub segment_type: String,
+    /// The thin pool of a thin volume.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool: Option<String>,
+    /// Used data space of thin pools and thin volumes in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_percent: Option<f64>,
+    /// Used metadata space of thin pools in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_percent: Option<f64>,
+    /// Metadata size of thin pools in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_size: Option<u64>,
+}
+
+impl LvmLogicalVolume {
+    /// Check whether this is a thin pool.
+    pub fn is_thin_pool(&self) -> bool {
+        self.segment_type == "thin-pool"
+    }
+}
+
+/// Get a non-empty string field of an LVM JSON report entry.
+fn json_string(item: &Value, field: &str) -> Option<String> {
+    item[field]
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+fn json_number<T: std::str::FromStr>(item: &Value, field: &str) -> Result<Option<T>, Error> {
+    match item[field].as_str().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format_err!("invalid value '{value}' for '{field}' in LVM report")),
+    }
+}
+
+fn json_required<T: std::str::FromStr>(item: &Value, field: &str) -> Result<T, Error> {
+    json_number(item, field)?.ok_or_else(|| format_err!("missing '{field}' in LVM report"))
+}
+
+/// Get the entries of an LVM JSON report (`--reportformat json`).
+fn parse_lvm_report<'a>(output: &'a Value, kind: &str) -> &'a [Value] {
+    output["report"][0][kind]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn parse_vgs(output: &str) -> Result<Vec<LvmVolumeGroup>, Error> {
+    let output: Value = output.parse()?;
+    parse_lvm_report(&output, "vg")
+        .iter()
+        .map(|item| {
+            Ok(LvmVolumeGroup {
+                name: json_string(item, "vg_name")
+                    .ok_or_else(|| format_err!("missing 'vg_name' in LVM report"))?,
+                size: json_required(item, "vg_size")?,
+                free: json_required(item, "vg_free")?,
+                pv_count: json_required(item, "pv_count")?,
+                lv_count: json_required(item, "lv_count")?,
+            })
+        })
+        .collect()
+}
+
+fn parse_pvs(output: &str) -> Result<Vec<LvmPhysicalVolume>, Error> {
+    let output: Value = output.parse()?;
+    parse_lvm_report(&output, "pv")
+        .iter()
+        .map(|item| {
+            Ok(LvmPhysicalVolume {
+                name: json_string(item, "pv_name")
+                    .ok_or_else(|| format_err!("missing 'pv_name' in LVM report"))?,
+                vg: json_string(item, "vg_name"),
+                size: json_required(item, "pv_size")?,
+                free: json_required(item, "pv_free")?,
+            })
+        })
+        .collect()
+}
+
+fn parse_lvs(output: &str) -> Result<Vec<LvmLogicalVolume>, Error> {
+    let output: Value = output.parse()?;
+    parse_lvm_report(&output, "lv")
+        .iter()
+        .map(|item| {
+            Ok(LvmLogicalVolume {
+                name: json_string(item, "lv_name")
+                    .ok_or_else(|| format_err!("missing 'lv_name' in LVM report"))?,
+                vg: json_string(item, "vg_name")
+                    .ok_or_else(|| format_err!("missing 'vg_name' in LVM report"))?,
+                size: json_required(item, "lv_size")?,
+                segment_type: json_string(item, "segtype").unwrap_or_default(),
+                pool: json_string(item, "pool_lv"),
+                data_percent: json_number(item, "data_percent")?,
+                metadata_percent: json_number(item, "metadata_percent")?,
+                metadata_size: json_number(item, "lv_metadata_size")?,
+            })
+        })
+        .collect()
+}
+
+fn lvm_report_command(program: &str, fields: &str) -> std::process::Command {
+    let mut command = std::process::Command::new(program);
+    command.args([
+        "--reportformat",
+        "json",
+        "--units",
+        "b",
+        "--nosuffix",
+        "--readonly",
+        "-o",
+        fields,
+    ]);
+    command
+}
+
+impl Disks {
+    /// List all LVM volume groups.
+    pub fn lvm_volume_groups(&self) -> Result<Vec<LvmVolumeGroup>, Error> {
+        let command = lvm_report_command("vgs", "vg_name,vg_size,vg_free,pv_count,lv_count");
+        parse_vgs(&self.command_runner().run(command, None)?)
+    }
+
+    /// List all LVM physical volumes.
+    pub fn lvm_physical_volumes(&self) -> Result<Vec<LvmPhysicalVolume>, Error> {
+        let command = lvm_report_command("pvs", "pv_name,vg_name,pv_size,pv_free");
+        parse_pvs(&self.command_runner().run(command, None)?)
+    }
+
+    /// List the LVM logical volumes of a volume group, or of all volume groups.
+    ///
+    /// Hidden volumes, like the data and metadata volumes of thin pools, are not included.
+    pub fn lvm_logical_volumes(&self, vg: Option<&str>) -> Result<Vec<LvmLogicalVolume>, Error> {
+        let mut command = lvm_report_command(
+            "lvs",
+            "lv_name,vg_name,lv_size,segtype,pool_lv,data_percent,metadata_percent,lv_metadata_size",
+        );
+        if let Some(vg) = vg {
+            command.arg(vg);
+        }
+        parse_lvs(&self.command_runner().run(command, None)?)
+    }
+
+    /// List the LVM thin pools of a volume group, or of all volume groups.
+    pub fn lvm_thin_pools(&self, vg: Option<&str>) -> Result<Vec<LvmLogicalVolume>, Error> {
+        let mut lvs = self.lvm_logical_volumes(vg)?;
+        lvs.retain(LvmLogicalVolume::is_thin_pool);
+        Ok(lvs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_owning_lv_name() {
+        assert_eq!(owning_lv_name("root"), Some("root"));
+        assert_eq!(owning_lv_name("[data_tdata]"), Some("data"));
+        assert_eq!(owning_lv_name("[data_tmeta]"), Some("data"));
+        assert_eq!(owning_lv_name("[lvol0_pmspare]"), None);
+        assert_eq!(owning_lv_name(""), None);
+    }
+
+    #[test]
+    fn test_parse_lvs() -> Result<(), Error> {
+        let output = r#"  {
+      "report": [
+          {
+              "lv": [
+                  {"lv_name":"data", "vg_name":"pve", "lv_size":"invalid", "segtype":"thin-pool", "pool_lv":"", "data_percent":"12.05", "metadata_percent":"1.17", "lv_metadata_size":"1606418432"}
+              ]
+          }
+      ]
+  }"#;
+        assert!(parse_lvs(output).is_err());
+
+        let output = r#"  {
+      "report": [
+          {
+              "lv": [
+                  {"lv_name":"data", "vg_name":"pve", "lv_size":"141696266240", "segtype":"thin-pool", "pool_lv":"", "data_percent":"12.05", "metadata_percent":"1.17", "lv_metadata_size":"1606418432"},
+                  {"lv_name":"root", "vg_name":"pve", "lv_size":"68719476736", "segtype":"linear", "pool_lv":"", "data_percent":"", "metadata_percent":"", "lv_metadata_size":""},
+                  {"lv_name":"vm-100-disk-0", "vg_name":"pve", "lv_size":"34359738368", "segtype":"thin", "pool_lv":"data", "data_percent":"45.23", "metadata_percent":"", "lv_metadata_size":""}
+              ]
+          }
+      ]
+  }"#;
+        let lvs = parse_lvs(output)?;
+        assert_eq!(lvs.len(), 3);
+        assert!(lvs[0].is_thin_pool());
+        assert_eq!(lvs[0].data_percent, Some(12.05));
+        assert_eq!(lvs[0].metadata_percent, Some(1.17));
+        assert_eq!(lvs[0].metadata_size, Some(1606418432));
+        assert_eq!(lvs[1].segment_type, "linear");
+        assert_eq!(lvs[1].data_percent, None);
+        assert_eq!(lvs[2].pool.as_deref(), Some("data"));
+
+        Ok(())
+    }
 }
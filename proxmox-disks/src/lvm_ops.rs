@@ -0,0 +1,287 @@
+//! LVM volume group and thin pool operations.
+//!
+//! All operations validate their input against the current LVM state as reported by `vgs`, `pvs`
+//! and `lvs` before running any mutating command.
+
+use anyhow::{Error, bail, format_err};
+
+use crate::{Disk, Disks, LvmLogicalVolume, LvmVolumeGroup};
+
+/// Minimal thin pool metadata size (1 GiB).
+const THIN_POOL_METADATA_MIN: u64 = 1024 * 1024 * 1024;
+/// Maximal thin pool metadata size (16 GiB), the upper limit of the thin provisioning target.
+const THIN_POOL_METADATA_MAX: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Calculate data and metadata size of a thin pool using (up to) `available` bytes.
+///
+/// Like Proxmox VE, 99% of the space are used, metadata gets 1% of that, clamped to 1-16 GiB.
+/// Space for the metadata and the pool metadata spare volume is taken from the data size.
+fn thin_pool_sizes(available: u64) -> Result<(u64, u64), Error> {
+    let usable = available / 100 * 99;
+    let metadata = (usable / 100).clamp(THIN_POOL_METADATA_MIN, THIN_POOL_METADATA_MAX);
+
+    match usable.checked_sub(2 * metadata) {
+        Some(data) if data > 0 => Ok((data, metadata)),
+        _ => bail!("not enough free space for a thin pool ({available} bytes available)"),
+    }
+}
+
+impl Disks {
+    /// Initialize `disk` as LVM physical volume and create the volume group `vg` on it.
+    pub fn lvm_create_volume_group(&self, vg: &str, disk: &Disk) -> Result<(), Error> {
+        validate_lvm_name(vg)?;
+        if self.lvm_volume_groups()?.iter().any(|info| info.name == vg) {
+            bail!("volume group '{vg}' already exists");
+        }
+        let device = self.prepare_physical_volume(disk)?;
+
+        let mut command = std::process::Command::new("vgcreate");
+        command.arg(vg).arg(&device);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Initialize `disk` as LVM physical volume and add it to the volume group `vg`.
+    pub fn lvm_extend_volume_group(&self, vg: &str, disk: &Disk) -> Result<(), Error> {
+        self.lvm_volume_group(vg)?;
+        let device = self.prepare_physical_volume(disk)?;
+
+        let mut command = std::process::Command::new("vgextend");
+        command.arg(vg).arg(&device);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Remove the volume group `vg` and wipe the LVM labels of its physical volumes.
+    ///
+    /// Fails if the volume group still contains logical volumes.
+    pub fn lvm_remove_volume_group(&self, vg: &str) -> Result<(), Error> {
+        let info = self.lvm_volume_group(vg)?;
+        if info.lv_count > 0 {
+            bail!(
+                "volume group '{vg}' still contains {} logical volume(s)",
+                info.lv_count
+            );
+        }
+
+        let devices: Vec<String> = self
+            .lvm_physical_volumes()?
+            .into_iter()
+            .filter(|pv| pv.vg.as_deref() == Some(vg))
+            .map(|pv| pv.name)
+            .collect();
+
+        let mut command = std::process::Command::new("vgremove");
+        command.arg(vg);
+        self.command_runner().run(command, None)?;
+
+        if !devices.is_empty() {
+            let mut command = std::process::Command::new("pvremove");
+            command.args(&devices);
+            self.command_runner().run(command, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Create the thin pool `pool` in the volume group `vg`.
+    ///
+    /// Without `size`, all free space of the volume group is used. Of the available space, 99%
+    /// are used, of which 1% (at least 1 GiB and at most 16 GiB) are reserved for the metadata.
+    pub fn lvm_create_thin_pool(
+        &self,
+        vg: &str,
+        pool: &str,
+        size: Option<u64>,
+    ) -> Result<(), Error> {
+        validate_lvm_name(pool)?;
+        let info = self.lvm_volume_group(vg)?;
+        if self
+            .lvm_logical_volumes(Some(vg))?
+            .iter()
+            .any(|lv| lv.name == pool)
+        {
+            bail!("logical volume '{vg}/{pool}' already exists");
+        }
+
+        let available = match size {
+            Some(size) if size > info.free => {
+                bail!("volume group '{vg}' has only {} bytes free", info.free)
+            }
+            Some(size) => size,
+            None => info.free,
+        };
+        let (data, metadata) = thin_pool_sizes(available)?;
+
+        let mut command = std::process::Command::new("lvcreate");
+        command
+            .args(["--type", "thin-pool"])
+            .arg(format!("-L{data}b"))
+            .arg("--poolmetadatasize")
+            .arg(format!("{metadata}b"))
+            .arg("-n")
+            .arg(pool)
+            .arg(vg);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Create the volume group `vg` on `disk` and a thin pool `pool` using all of its space.
+    ///
+    /// This is the usual setup for thin provisioned guest storage, see
+    /// [`lvm_create_thin_pool`](Disks::lvm_create_thin_pool) for how the pool is sized.
+    pub fn lvm_create_thin_pool_on_disk(
+        &self,
+        disk: &Disk,
+        vg: &str,
+        pool: &str,
+    ) -> Result<(), Error> {
+        validate_lvm_name(pool)?;
+        self.lvm_create_volume_group(vg, disk)?;
+        self.lvm_create_thin_pool(vg, pool, None)
+    }
+
+    /// Grow the thin pool `vg/pool` by `size` bytes, or by all free space of the volume group.
+    pub fn lvm_extend_thin_pool(
+        &self,
+        vg: &str,
+        pool: &str,
+        size: Option<u64>,
+    ) -> Result<(), Error> {
+        self.lvm_thin_pool(vg, pool)?;
+
+        let mut command = std::process::Command::new("lvextend");
+        match size {
+            Some(0) => bail!("cannot extend thin pool '{vg}/{pool}' by 0 bytes"),
+            Some(size) => command.arg(format!("-L+{size}b")),
+            None => command.args(["-l", "+100%FREE"]),
+        };
+        command.arg(format!("{vg}/{pool}"));
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Remove the thin pool `vg/pool`.
+    ///
+    /// Fails if the pool still contains thin volumes.
+    pub fn lvm_remove_thin_pool(&self, vg: &str, pool: &str) -> Result<(), Error> {
+        self.lvm_volume_group(vg)?;
+        validate_lvm_name(pool)?;
+        let lvs = self.lvm_logical_volumes(Some(vg))?;
+        find_thin_pool(&lvs, vg, pool)?;
+
+        let volumes = lvs
+            .iter()
+            .filter(|lv| lv.pool.as_deref() == Some(pool))
+            .count();
+        if volumes > 0 {
+            bail!("thin pool '{vg}/{pool}' still contains {volumes} volume(s)");
+        }
+
+        let mut command = std::process::Command::new("lvremove");
+        command.arg("-y").arg(format!("{vg}/{pool}"));
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    fn lvm_volume_group(&self, vg: &str) -> Result<LvmVolumeGroup, Error> {
+        validate_lvm_name(vg)?;
+        self.lvm_volume_groups()?
+            .into_iter()
+            .find(|info| info.name == vg)
+            .ok_or_else(|| format_err!("volume group '{vg}' does not exist"))
+    }
+
+    fn lvm_thin_pool(&self, vg: &str, pool: &str) -> Result<LvmLogicalVolume, Error> {
+        self.lvm_volume_group(vg)?;
+        validate_lvm_name(pool)?;
+        let lvs = self.lvm_logical_volumes(Some(vg))?;
+        find_thin_pool(&lvs, vg, pool).cloned()
+    }
+
+    /// Check that `disk` is unused and initialize it as physical volume.
+    ///
+    /// The disk must not be mounted, have holders, be a member of an md array, an LVM physical
+    /// volume, or used by a ZFS pool.
+    fn prepare_physical_volume(&self, disk: &Disk) -> Result<String, Error> {
+        let pools = self.zpool_list(None, true)?;
+        let device = self.check_disks_unused([disk], &pools)?.remove(0);
+
+        let mut command = std::process::Command::new("pvcreate");
+        command.arg(&device);
+        self.command_runner().run(command, None)?;
+
+        Ok(device)
+    }
+}
+
+fn find_thin_pool<'a>(
+    lvs: &'a [LvmLogicalVolume],
+    vg: &str,
+    pool: &str,
+) -> Result<&'a LvmLogicalVolume, Error> {
+    match lvs.iter().find(|lv| lv.name == pool) {
+        Some(lv) if lv.is_thin_pool() => Ok(lv),
+        Some(_) => bail!("logical volume '{vg}/{pool}' is not a thin pool"),
+        None => bail!("thin pool '{vg}/{pool}' does not exist"),
+    }
+}
+
+/// Validate a volume group or logical volume name.
+fn validate_lvm_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.len() > 127 {
+        bail!("invalid LVM name '{name}' - must be between 1 and 127 characters");
+    }
+    if name.starts_with('-') || name == "." || name == ".." {
+        bail!("invalid LVM name '{name}'");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '.' | '-')))
+    {
+        bail!("invalid character '{c}' in LVM name '{name}'");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_thin_pool_sizes() -> Result<(), Error> {
+        // small pools get the minimal metadata size
+        let (data, metadata) = thin_pool_sizes(10 * GIB)?;
+        assert_eq!(metadata, GIB);
+        assert_eq!(data, 10 * GIB / 100 * 99 - 2 * GIB);
+
+        let (data, metadata) = thin_pool_sizes(500 * GIB)?;
+        assert_eq!(metadata, 500 * GIB / 100 * 99 / 100);
+        assert_eq!(data + 2 * metadata, 500 * GIB / 100 * 99);
+
+        let (_, metadata) = thin_pool_sizes(4096 * GIB)?;
+        assert_eq!(metadata, 16 * GIB);
+
+        assert!(thin_pool_sizes(2 * GIB).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_lvm_name() {
+        for name in ["pve", "data", "vm-100-disk-0", "a+b_c.d"] {
+            assert!(validate_lvm_name(name).is_ok(), "{name} should be valid");
+        }
+        let too_long = "a".repeat(128);
+        for name in ["", ".", "..", "-vg", "my vg", "vg/lv", too_long.as_str()] {
+            assert!(validate_lvm_name(name).is_err(), "{name} should be invalid");
+        }
+    }
+}
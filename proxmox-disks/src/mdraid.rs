@@ -0,0 +1,487 @@
+//! Linux software RAID (md) arrays.
+//!
+//! Arrays are read from `/proc/mdstat`, and refined with the attributes from
+//! `/sys/block/<array>/md/` where available.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Error, bail, format_err};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "api-types")]
+use proxmox_schema::api;
+
+use crate::Disks;
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// The state of an md array member.
+pub enum MdMemberState {
+    /// The member is active and in sync.
+    InSync,
+    /// The member is active, but its data is still being rebuilt.
+    Rebuilding,
+    /// The member is a spare.
+    Spare,
+    /// The member failed.
+    Faulty,
+    /// The member is the write journal of the array.
+    Journal,
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// A member device of an md array.
+pub struct MdMember {
+    /// The kernel name of the device (e.g. `sda1`).
+    pub name: String,
+    /// The state of the member.
+    pub state: MdMemberState,
+    /// The role (slot) of the member in the array, not set for spares and faulty members.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u64>,
+    /// The member is marked write-mostly.
+    #[serde(default)]
+    pub write_mostly: bool,
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+/// A synchronization operation of an md array.
+pub enum MdSyncAction {
+    /// Initial synchronization of the array.
+    Resync,
+    /// Rebuilding data onto a replaced member.
+    Recovery,
+    /// Checking the redundant data.
+    Check,
+    /// Checking and repairing the redundant data.
+    Repair,
+    /// Changing the layout of the array.
+    Reshape,
+}
+
+impl std::str::FromStr for MdSyncAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(match s {
+            "resync" => Self::Resync,
+            "recovery" | "recover" => Self::Recovery,
+            "check" => Self::Check,
+            "repair" => Self::Repair,
+            "reshape" => Self::Reshape,
+            _ => bail!("unknown md sync action '{s}'"),
+        })
+    }
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// Progress of a running (or pending) synchronization of an md array.
+pub struct MdSyncStatus {
+    /// The synchronization operation.
+    pub action: MdSyncAction,
+    /// The operation is delayed or pending, and has not started yet.
+    #[serde(default)]
+    pub pending: bool,
+    /// Progress in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f64>,
+    /// Estimated minutes until the operation finishes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_minutes: Option<f64>,
+    /// Current speed in KiB per second.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<u64>,
+}
+
+#[cfg_attr(feature = "api-types", api(
+    properties: {
+        members: {
+            type: Array,
+            items: {
+                type: MdMember,
+            },
+        },
+    },
+))]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// An md array.
+pub struct MdArray {
+    /// The kernel name of the array (e.g. `md0`).
+    pub name: String,
+    /// The RAID level (e.g. `raid1`), not known for inactive arrays.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// The array is active (running).
+    pub active: bool,
+    /// The array is read-only.
+    #[serde(default)]
+    pub read_only: bool,
+    /// The array state from sysfs (e.g. `clean`, `active` or `inactive`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub array_state: Option<String>,
+    /// Size in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The number of devices the array consists of when complete.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raid_disks: Option<u64>,
+    /// The number of active, in sync devices.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_disks: Option<u64>,
+    /// The array is missing devices.
+    pub degraded: bool,
+    /// The member devices, including spares and faulty devices.
+    pub members: Vec<MdMember>,
+    /// The running synchronization, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync: Option<MdSyncStatus>,
+}
+
+impl MdArray {
+    /// Get a member by its kernel name.
+    pub fn member(&self, name: &str) -> Option<&MdMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+/// Parse a member entry of an array line in `/proc/mdstat`, e.g. `sdb1[2](F)`.
+fn parse_mdstat_member(text: &str) -> Result<MdMember, Error> {
+    let (name, rest) = text
+        .split_once('[')
+        .ok_or_else(|| format_err!("invalid md member '{text}'"))?;
+    let (_index, flags) = rest
+        .split_once(']')
+        .ok_or_else(|| format_err!("invalid md member '{text}'"))?;
+
+    let mut member = MdMember {
+        name: name.to_string(),
+        state: MdMemberState::InSync,
+        slot: None,
+        write_mostly: false,
+    };
+
+    for flag in flags.split(')').filter(|flag| !flag.is_empty()) {
+        match flag.trim_start_matches('(') {
+            "F" => member.state = MdMemberState::Faulty,
+            "S" => member.state = MdMemberState::Spare,
+            "J" => member.state = MdMemberState::Journal,
+            "R" => member.state = MdMemberState::Rebuilding,
+            "W" => member.write_mostly = true,
+            _ => (), // ignore unknown flags
+        }
+    }
+
+    Ok(member)
+}
+
+/// Parse a synchronization status line of `/proc/mdstat`, e.g.
+/// `[=>...]  recovery =  8.5% (89472/1046528) finish=0.7min speed=22368K/sec` or
+/// `resync=DELAYED`.
+fn parse_mdstat_sync(line: &str) -> Option<MdSyncStatus> {
+    let line = match line.strip_prefix('[') {
+        Some(rest) => rest.split_once(']')?.1,
+        None => line,
+    };
+    let (action, rest) = line.split_once('=')?;
+    let action: MdSyncAction = action.trim().parse().ok()?;
+    let rest = rest.trim_start();
+
+    if rest.starts_with("DELAYED") || rest.starts_with("PENDING") {
+        return Some(MdSyncStatus {
+            action,
+            pending: true,
+            progress: None,
+            finish_minutes: None,
+            speed: None,
+        });
+    }
+
+    let field = |name: &str, suffix: &str| {
+        rest.split_whitespace()
+            .find_map(|item| item.strip_prefix(name)?.strip_suffix(suffix))
+    };
+
+    Some(MdSyncStatus {
+        action,
+        pending: false,
+        progress: rest
+            .split_once('%')
+            .and_then(|(value, _)| value.parse().ok()),
+        finish_minutes: field("finish=", "min").and_then(|value| value.parse().ok()),
+        speed: field("speed=", "K/sec").and_then(|value| value.parse().ok()),
+    })
+}
+
+/// Parse the contents of `/proc/mdstat`.
+pub fn parse_mdstat(text: &str) -> Result<Vec<MdArray>, Error> {
+    let mut arrays: Vec<MdArray> = Vec::new();
+
+    for line in text.lines() {
+        if line.trim().is_empty()
+            || line.starts_with("Personalities")
+            || line.starts_with("unused devices")
+        {
+            continue;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            let (name, rest) = line
+                .split_once(" : ")
+                .ok_or_else(|| format_err!("unable to parse mdstat line '{line}'"))?;
+
+            let mut items = rest.split_whitespace().peekable();
+            let active = match items.next() {
+                Some("active") => true,
+                Some("inactive") => false,
+                _ => bail!("unable to parse mdstat line '{line}'"),
+            };
+
+            let mut read_only = false;
+            while let Some(flag) = items.next_if(|item| item.starts_with('(')) {
+                read_only |= flag.contains("read-only");
+            }
+
+            let level = items.next_if(|item| !item.contains('[')).map(String::from);
+
+            let members = items
+                .map(parse_mdstat_member)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            arrays.push(MdArray {
+                name: name.trim().to_string(),
+                level,
+                active,
+                read_only,
+                array_state: None,
+                size: None,
+                raid_disks: None,
+                active_disks: None,
+                degraded: false,
+                members,
+                sync: None,
+            });
+            continue;
+        }
+
+        let array = match arrays.last_mut() {
+            Some(array) => array,
+            None => bail!("unexpected mdstat line '{line}'"),
+        };
+        let line = line.trim();
+
+        if let Some((blocks, _)) = line.split_once(" blocks") {
+            array.size = blocks
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .map(|blocks| blocks * 1024);
+
+            // '[2/1] [_U]' - devices in total and active devices
+            if let Some((raid_disks, active_disks)) = line
+                .split_whitespace()
+                .filter_map(|item| item.strip_prefix('[')?.strip_suffix(']'))
+                .find_map(|item| item.split_once('/'))
+                && let (Ok(raid_disks), Ok(active_disks)) =
+                    (raid_disks.parse(), active_disks.parse())
+            {
+                array.raid_disks = Some(raid_disks);
+                array.active_disks = Some(active_disks);
+                array.degraded = active_disks < raid_disks;
+            }
+        } else if let Some(sync) = parse_mdstat_sync(line) {
+            array.sync = Some(sync);
+        }
+    }
+
+    Ok(arrays)
+}
+
+fn read_md_attribute(md_dir: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(md_dir.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Refine an array with the attributes in `/sys/block/<array>/md/`.
+fn update_from_sysfs(array: &mut MdArray, md_dir: &Path) {
+    if let Some(state) = read_md_attribute(md_dir, "array_state") {
+        array.array_state = Some(state);
+    }
+    if let Some(level) = read_md_attribute(md_dir, "level").filter(|level| !level.is_empty()) {
+        array.level = Some(level);
+    }
+    if let Some(degraded) =
+        read_md_attribute(md_dir, "degraded").and_then(|v| v.parse::<u64>().ok())
+    {
+        array.degraded = degraded > 0;
+    }
+
+    for member in array.members.iter_mut() {
+        let dev_dir = md_dir.join(format!("dev-{}", member.name));
+
+        member.slot = read_md_attribute(&dev_dir, "slot").and_then(|slot| slot.parse().ok());
+
+        let state = match read_md_attribute(&dev_dir, "state") {
+            Some(state) => state,
+            None => continue,
+        };
+        let flags: Vec<&str> = state.split(',').collect();
+
+        member.write_mostly = flags.contains(&"write_mostly");
+        member.state = if flags.contains(&"faulty") {
+            MdMemberState::Faulty
+        } else if flags.contains(&"journal") {
+            MdMemberState::Journal
+        } else if flags.contains(&"in_sync") {
+            MdMemberState::InSync
+        } else if member.slot.is_some() || flags.contains(&"replacement") {
+            // spares get a slot assigned once the recovery onto them started
+            MdMemberState::Rebuilding
+        } else {
+            MdMemberState::Spare
+        };
+    }
+
+    if array.sync.is_none()
+        && let Some(action) = read_md_attribute(md_dir, "sync_action")
+        && let Ok(action) = action.parse::<MdSyncAction>()
+    {
+        // 'sync_completed' is '<done> / <total>' in sectors, or 'none'/'delayed'
+        let progress = read_md_attribute(md_dir, "sync_completed").and_then(|completed| {
+            let (done, total) = completed.split_once('/')?;
+            let done: f64 = done.trim().parse().ok()?;
+            let total: f64 = total.trim().parse().ok()?;
+            (total > 0.0).then(|| done * 100.0 / total)
+        });
+        array.sync = Some(MdSyncStatus {
+            action,
+            pending: progress.is_none(),
+            progress,
+            finish_minutes: None,
+            speed: read_md_attribute(md_dir, "sync_speed").and_then(|speed| speed.parse().ok()),
+        });
+    }
+}
+
+impl Disks {
+    /// List the md arrays.
+    ///
+    /// Returns an empty list if the md driver is not loaded.
+    pub fn md_arrays(&self) -> Result<Vec<MdArray>, Error> {
+        let path = self.proc_path("mdstat");
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => bail!("unable to read {path:?} - {err}"),
+        };
+
+        let mut arrays = parse_mdstat(&text)?;
+        for array in arrays.iter_mut() {
+            let md_dir = self.sys_path(format!("block/{}/md", array.name));
+            if md_dir.is_dir() {
+                update_from_sysfs(array, &md_dir);
+            }
+        }
+
+        Ok(arrays)
+    }
+}
+
+/// Map the kernel names of all md member devices to the name of their array.
+pub(crate) fn md_member_map(arrays: &[MdArray]) -> HashMap<String, String> {
+    arrays
+        .iter()
+        .flat_map(|array| {
+            array
+                .members
+                .iter()
+                .map(|member| (member.name.clone(), array.name.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MDSTAT: &str = "\
+Personalities : [raid1] [raid6] [raid5] [raid4] [linear] [multipath] [raid0] [raid10]
+md1 : active raid1 sdb2[1] sda2[0]
+      487253824 blocks super 1.2 [2/2] [UU]
+      bitmap: 2/4 pages [8KB], 65536KB chunk
+
+md0 : active raid1 sdc1[2](F) sdd1[1] sde1[3]
+      1046528 blocks super 1.2 [2/1] [_U]
+      [=>...................]  recovery =  8.5% (89472/1046528) finish=0.7min speed=22368K/sec
+
+md3 : active (auto-read-only) raid5 sdh[3](S) sdg[1](W) sdf[0]
+      209584128 blocks super 1.2 level 5, 512k chunk, algorithm 2 [2/2] [UU]
+        resync=DELAYED
+
+md127 : inactive sdi1[0](S)
+      1046528 blocks super 1.2
+
+unused devices: <none>
+";
+
+    #[test]
+    fn test_parse_mdstat() -> Result<(), Error> {
+        let arrays = parse_mdstat(MDSTAT)?;
+        assert_eq!(arrays.len(), 4);
+
+        let md1 = &arrays[0];
+        assert_eq!(md1.name, "md1");
+        assert_eq!(md1.level.as_deref(), Some("raid1"));
+        assert!(md1.active);
+        assert_eq!(md1.size, Some(487253824 * 1024));
+        assert_eq!(md1.raid_disks, Some(2));
+        assert!(!md1.degraded);
+        assert_eq!(md1.sync, None);
+        assert_eq!(md1.members.len(), 2);
+
+        let md0 = &arrays[1];
+        assert!(md0.degraded);
+        assert_eq!(md0.member("sdc1").unwrap().state, MdMemberState::Faulty);
+        assert_eq!(md0.member("sdd1").unwrap().state, MdMemberState::InSync);
+        let sync = md0.sync.as_ref().unwrap();
+        assert_eq!(sync.action, MdSyncAction::Recovery);
+        assert_eq!(sync.progress, Some(8.5));
+        assert_eq!(sync.finish_minutes, Some(0.7));
+        assert_eq!(sync.speed, Some(22368));
+
+        let md3 = &arrays[2];
+        assert!(md3.read_only);
+        assert_eq!(md3.level.as_deref(), Some("raid5"));
+        assert_eq!(md3.member("sdh").unwrap().state, MdMemberState::Spare);
+        assert!(md3.member("sdg").unwrap().write_mostly);
+        assert!(md3.sync.as_ref().unwrap().pending);
+
+        let md127 = &arrays[3];
+        assert!(!md127.active);
+        assert_eq!(md127.level, None);
+        assert_eq!(md127.members[0].name, "sdi1");
+
+        let members = md_member_map(&arrays);
+        assert_eq!(members.get("sda2").map(String::as_str), Some("md1"));
+        assert_eq!(members.get("sdi1").map(String::as_str), Some("md127"));
+
+        assert!(parse_mdstat("md0 active raid1\n").is_err());
+        assert!(parse_mdstat("")?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! md array (software RAID) operations via `mdadm`.
+//!
+//! All operations validate their input against the current arrays as reported by
+//! [`Disks::md_arrays`] before running any mutating command. Disks for new or assembled arrays
+//! must not be mounted, held by another device, or used by LVM or ZFS either.
+
+use std::path::Path;
+
+use anyhow::{Error, bail, format_err};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "api-types")]
+use proxmox_schema::api;
+
+use crate::{Disk, Disks, MdArray, MdMember, MdMemberState};
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+/// The RAID level of a new md array.
+pub enum MdRaidLevel {
+    /// Striping without redundancy.
+    Raid0,
+    /// Mirror.
+    Raid1,
+    /// Striping with single parity.
+    Raid5,
+    /// Striping with double parity.
+    Raid6,
+    /// Striped mirrors.
+    Raid10,
+}
+
+impl MdRaidLevel {
+    /// The minimal number of devices for this level.
+    pub fn min_devices(self) -> usize {
+        match self {
+            Self::Raid0 | Self::Raid1 | Self::Raid10 => 2,
+            Self::Raid5 => 3,
+            Self::Raid6 => 4,
+        }
+    }
+
+    fn mdadm_level(self) -> &'static str {
+        match self {
+            Self::Raid0 => "raid0",
+            Self::Raid1 => "raid1",
+            Self::Raid5 => "raid5",
+            Self::Raid6 => "raid6",
+            Self::Raid10 => "raid10",
+        }
+    }
+}
+
+impl Disks {
+    /// Create and start the md array `/dev/md/<name>` from `disks`.
+    ///
+    /// Fails if a disk is in use, see [`md_assemble`](Self::md_assemble). `mdadm` is not forced,
+    /// so it refuses disks which look like they contain data.
+    pub fn md_create(&self, name: &str, level: MdRaidLevel, disks: &[Disk]) -> Result<(), Error> {
+        validate_md_name(name)?;
+        if disks.len() < level.min_devices() {
+            bail!(
+                "{} needs at least {} devices",
+                level.mdadm_level(),
+                level.min_devices()
+            );
+        }
+        let devices = self.check_disks_unused(disks, &self.zpool_list(None, true)?)?;
+
+        let mut command = std::process::Command::new("mdadm");
+        command
+            .arg("--create")
+            .arg(format!("/dev/md/{name}"))
+            .arg("--metadata=1.2")
+            .arg(format!("--level={}", level.mdadm_level()))
+            .arg(format!("--raid-devices={}", devices.len()))
+            .args(&devices);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Assemble the existing md array `/dev/md/<name>` from `disks`.
+    ///
+    /// Fails if a disk is mounted, has holders, is a member of an md array, an LVM physical
+    /// volume, or used by a ZFS pool.
+    pub fn md_assemble(&self, name: &str, disks: &[Disk]) -> Result<(), Error> {
+        validate_md_name(name)?;
+        if disks.is_empty() {
+            bail!("no devices given to assemble md array '{name}' from");
+        }
+        let devices = self.check_disks_unused(disks, &self.zpool_list(None, true)?)?;
+
+        let mut command = std::process::Command::new("mdadm");
+        command
+            .arg("--assemble")
+            .arg(format!("/dev/md/{name}"))
+            .args(&devices);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Assemble all md arrays found on the system's devices.
+    pub fn md_assemble_scan(&self) -> Result<(), Error> {
+        let mut command = std::process::Command::new("mdadm");
+        command.args(["--assemble", "--scan"]);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Mark the member `device` (kernel name or device path) of `array` (e.g. `md0`) as faulty.
+    pub fn md_fail(&self, array: &str, device: &str) -> Result<(), Error> {
+        let info = self.md_array(array)?;
+        let member = md_member(&info, device)?;
+        if member.state == MdMemberState::Faulty {
+            bail!(
+                "device '{}' of md array '{array}' already failed",
+                member.name
+            );
+        }
+
+        self.md_manage(array, "--fail", &member.name)
+    }
+
+    /// Remove the member `device` (kernel name or device path) from `array` (e.g. `md0`).
+    ///
+    /// Only faulty members and spares can be removed, use [`md_fail`](Self::md_fail) first to
+    /// remove an active member.
+    pub fn md_remove(&self, array: &str, device: &str) -> Result<(), Error> {
+        let info = self.md_array(array)?;
+        let member = md_member(&info, device)?;
+        if !matches!(member.state, MdMemberState::Faulty | MdMemberState::Spare) {
+            bail!(
+                "device '{}' is an active member of md array '{array}', fail it first",
+                member.name
+            );
+        }
+
+        self.md_manage(array, "--remove", &member.name)
+    }
+
+    fn md_manage(&self, array: &str, action: &str, member: &str) -> Result<(), Error> {
+        let mut command = std::process::Command::new("mdadm");
+        command
+            .arg("--manage")
+            .arg(format!("/dev/{array}"))
+            .arg(action)
+            .arg(format!("/dev/{member}"));
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    fn md_array(&self, array: &str) -> Result<MdArray, Error> {
+        self.md_arrays()?
+            .into_iter()
+            .find(|info| info.name == array)
+            .ok_or_else(|| format_err!("md array '{array}' does not exist"))
+    }
+}
+
+/// Get the kernel name of a device path, resolving symlinks like `/dev/disk/by-id/...`.
+fn kernel_name(device: &str) -> String {
+    let path = std::fs::canonicalize(device).unwrap_or_else(|_| Path::new(device).to_path_buf());
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| device.to_string())
+}
+
+fn md_member<'a>(array: &'a MdArray, device: &str) -> Result<&'a MdMember, Error> {
+    let name = if device.starts_with('/') {
+        kernel_name(device)
+    } else {
+        device.to_string()
+    };
+    array.member(&name).ok_or_else(|| {
+        format_err!(
+            "device '{device}' is not a member of md array '{}'",
+            array.name
+        )
+    })
+}
+
+/// Validate the name of a new array, which is used below `/dev/md/`.
+fn validate_md_name(name: &str) -> Result<(), Error> {
+    if name.is_empty()
+        || name.len() > 32
+        || name.starts_with(['.', '-'])
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        bail!("invalid md array name '{name}'");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_md_name() {
+        for name in ["boot", "data_1", "md-root.2"] {
+            assert!(validate_md_name(name).is_ok(), "{name} should be valid");
+        }
+        for name in ["", ".", "..", "-x", "a/b", "a b", &"a".repeat(33)] {
+            assert!(validate_md_name(name).is_err(), "{name} should be invalid");
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use std::os::unix::fs::{FileExt, OpenOptionsExt};
 use std::path::Path;
 
+#[cfg(feature = "discovery")]
+use anyhow::format_err;
 use anyhow::{Context as _, Error, bail};
 
 use proxmox_log::info;
 
-use crate::{Disk, FileSystemType};
+use crate::{Disk, Disks, FileSystemType};
+#[cfg(feature = "discovery")]
+use crate::{ZFSPoolInfo, md_member_map};
 
 /// Try to reload the partition table
 pub fn reread_partition_table(disk: &Disk) -> Result<(), Error> {
@@ -18,7 +22,7 @@ pub fn reread_partition_table(disk: &Disk) -> Result<(), Error> {
     command.arg("--rereadpt");
     command.arg(disk_path);
 
-    proxmox_sys::command::run_command(command, None)?;
+    disk.manager().command_runner().run(command, None)?;
 
     Ok(())
 }
@@ -36,7 +40,7 @@ pub fn initialize_gpt_disk(disk: &Disk, uuid: Option<&str>) -> Result<(), Error>
     command.arg(disk_path);
     command.args(["-U", uuid]);
 
-    proxmox_sys::command::run_command(command, None)?;
+    disk.manager().command_runner().run(command, None)?;
 
     Ok(())
 }
@@ -69,7 +73,7 @@ pub fn wipe_blockdev(disk: &Disk) -> Result<(), Error> {
     let mut wipefs_command = std::process::Command::new("wipefs");
     wipefs_command.arg("--all").args(&to_wipe);
 
-    let wipefs_output = proxmox_sys::command::run_command(wipefs_command, None)?;
+    let wipefs_output = disk.manager().command_runner().run(wipefs_command, None)?;
     info!("wipefs output: {wipefs_output}");
 
     zero_disk_start_and_end(disk)?;
@@ -114,7 +118,9 @@ pub(crate) fn change_parttype(part_disk: &Disk, part_type: &str) -> Result<(), E
         let mut sgdisk_command = std::process::Command::new("sgdisk");
         let major = unsafe { libc::major(stat.st_rdev) };
         let minor = unsafe { libc::minor(stat.st_rdev) };
-        let partnum_path = &format!("/sys/dev/block/{major}:{minor}/partition");
+        let partnum_path = part_disk
+            .manager()
+            .sys_path(format!("dev/block/{major}:{minor}/partition"));
         let partnum: u32 = std::fs::read_to_string(partnum_path)?.trim_end().parse()?;
         sgdisk_command.arg(format!("-t{partnum}:{part_type}"));
         let part_disk_parent = match part_disk.parent() {
@@ -126,7 +132,10 @@ pub(crate) fn change_parttype(part_disk: &Disk, part_type: &str) -> Result<(), E
             None => bail!("disk {:?} has no node in /dev", part_disk.syspath()),
         };
         sgdisk_command.arg(part_disk_parent_path);
-        let sgdisk_output = proxmox_sys::command::run_command(sgdisk_command, None)?;
+        let sgdisk_output = part_disk
+            .manager()
+            .command_runner()
+            .run(sgdisk_command, None)?;
         info!("sgdisk output: {sgdisk_output}");
     }
     Ok(())
@@ -143,7 +152,7 @@ pub fn create_single_linux_partition(disk: &Disk) -> Result<Disk, Error> {
     command.args(["-n1", "-t1:8300"]);
     command.arg(disk_path);
 
-    proxmox_sys::command::run_command(command, None)?;
+    disk.manager().command_runner().run(command, None)?;
 
     let mut partitions = disk.partitions()?;
 
@@ -166,7 +175,7 @@ pub fn create_file_system(disk: &Disk, fs_type: FileSystemType) -> Result<(), Er
     command.args(["-t", &fs_type]);
     command.arg(disk_path);
 
-    proxmox_sys::command::run_command(command, None)?;
+    disk.manager().command_runner().run(command, None)?;
 
     Ok(())
 }
@@ -184,7 +193,7 @@ pub fn get_fs_uuid(disk: &Disk) -> Result<String, Error> {
     command.args(["-o", "export"]);
     command.arg(disk_path);
 
-    let output = proxmox_sys::command::run_command(command, None)?;
+    let output = disk.manager().command_runner().run(command, None)?;
 
     for line in output.lines() {
         if let Some(uuid) = line.strip_prefix("UUID=") {
@@ -197,30 +206,101 @@ pub fn get_fs_uuid(disk: &Disk) -> Result<String, Error> {
 
 /// Mount a disk by its UUID and the mount point.
 pub fn mount_by_uuid(uuid: &str, mount_point: &Path) -> Result<(), Error> {
-    let mut command = std::process::Command::new("mount");
-    command.arg(format!("UUID={uuid}"));
-    command.arg(mount_point);
-
-    proxmox_sys::command::run_command(command, None)?;
-    Ok(())
+    Disks::new().mount_by_uuid(uuid, mount_point)
 }
 
 /// Create bind mount.
 pub fn bind_mount(path: &Path, target: &Path) -> Result<(), Error> {
-    let mut command = std::process::Command::new("mount");
-    command.arg("--bind");
-    command.arg(path);
-    command.arg(target);
-
-    proxmox_sys::command::run_command(command, None)?;
-    Ok(())
+    Disks::new().bind_mount(path, target)
 }
 
 /// Unmount a disk by its mount point.
 pub fn unmount_by_mountpoint(path: &Path) -> Result<(), Error> {
-    let mut command = std::process::Command::new("umount");
-    command.arg(path);
+    Disks::new().unmount_by_mountpoint(path)
+}
 
-    proxmox_sys::command::run_command(command, None)?;
-    Ok(())
+impl Disks {
+    /// Mount a disk by its UUID and the mount point.
+    pub fn mount_by_uuid(&self, uuid: &str, mount_point: &Path) -> Result<(), Error> {
+        let mut command = std::process::Command::new("mount");
+        command.arg(format!("UUID={uuid}"));
+        command.arg(mount_point);
+
+        self.command_runner().run(command, None)?;
+        Ok(())
+    }
+
+    /// Create bind mount.
+    pub fn bind_mount(&self, path: &Path, target: &Path) -> Result<(), Error> {
+        let mut command = std::process::Command::new("mount");
+        command.arg("--bind");
+        command.arg(path);
+        command.arg(target);
+
+        self.command_runner().run(command, None)?;
+        Ok(())
+    }
+
+    /// Unmount a disk by its mount point.
+    pub fn unmount_by_mountpoint(&self, path: &Path) -> Result<(), Error> {
+        let mut command = std::process::Command::new("umount");
+        command.arg(path);
+
+        self.command_runner().run(command, None)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "discovery")]
+impl Disks {
+    /// Check that none of `disks` is in use and return their device paths.
+    ///
+    /// A disk is in use if it is mounted, has holders, is a member of an md array, an LVM physical
+    /// volume, or used by one of the ZFS `pools`, as returned by `zpool_list(None, true)`.
+    pub(crate) fn check_disks_unused<'a>(
+        &self,
+        disks: impl IntoIterator<Item = &'a Disk>,
+        pools: &[ZFSPoolInfo],
+    ) -> Result<Vec<String>, Error> {
+        let members = md_member_map(&self.md_arrays()?);
+        let physical_volumes = self.lvm_physical_volumes()?;
+
+        let mut devices: Vec<String> = Vec::new();
+        for disk in disks {
+            let device = disk_device_path(disk)?;
+
+            if devices.contains(&device) {
+                bail!("device '{device}' is given more than once");
+            }
+            if let Some(array) = members.get(disk.sysname().to_string_lossy().as_ref()) {
+                bail!("device '{device}' is already a member of md array '{array}'");
+            }
+            if disk.is_mounted()? {
+                bail!("disk '{device}' is mounted");
+            }
+            if disk.has_holders()? {
+                bail!("disk '{device}' is in use (has holders)");
+            }
+            if let Some(pv) = physical_volumes.iter().find(|pv| pv.name == device) {
+                match &pv.vg {
+                    Some(vg) => bail!("disk '{device}' is already used by volume group '{vg}'"),
+                    None => bail!("disk '{device}' is already an LVM physical volume"),
+                }
+            }
+            crate::zfs_ops::check_device_unused(pools, &device)?;
+
+            devices.push(device);
+        }
+        Ok(devices)
+    }
+}
+
+/// Get the device node of `disk` as string, failing if it has none.
+#[cfg(feature = "discovery")]
+pub(crate) fn disk_device_path(disk: &Disk) -> Result<String, Error> {
+    Ok(disk
+        .device_path()
+        .ok_or_else(|| format_err!("disk {:?} has no device path", disk.sysname()))?
+        .to_string_lossy()
+        .into_owned())
 }
@@ -6,19 +6,19 @@ use anyhow::{Error, bail};
 use proxmox_schema::api_types::{BLOCKDEVICE_NAME_REGEX, UUID_REGEX};
 
 use crate::{
-    Disk, DiskUsageInfo, DiskUsageType, Disks, LsblkInfo, PartitionInfo, PartitionUsageType,
-    get_lvm_devices, zfs_devices,
+    Disk, DiskUsageInfo, DiskUsageType, Disks, LsblkInfo, LvmDeviceUsage, PartitionInfo,
+    PartitionUsageType, get_lvm_devices, md_member_map, zfs_devices,
 };
 
 static ISCSI_PATH_REGEX: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"host[^/]*/session[^/]*").unwrap());
 
 /// Use lsblk to read partition type uuids and file system types.
-pub(crate) fn get_lsblk_info() -> Result<Vec<LsblkInfo>, Error> {
+pub(crate) fn get_lsblk_info(disks: &Disks) -> Result<Vec<LsblkInfo>, Error> {
     let mut command = std::process::Command::new("lsblk");
     command.args(["--json", "-o", "path,parttype,fstype,uuid"]);
 
-    let output = proxmox_sys::command::run_command(command, None)?;
+    let output = disks.command_runner().run(command, None)?;
 
     let mut output: serde_json::Value = output.parse()?;
 
@@ -43,19 +43,31 @@ fn get_file_system_devices(lsblk_info: &[LsblkInfo]) -> Result<HashSet<u64>, Err
     Ok(device_set)
 }
 
+/// Usage of a disk as determined from its partitions.
+struct PartitionScan {
+    used: DiskUsageType,
+    /// The combined LVM usage of all partitions which are physical volumes.
+    lvm_usage: LvmDeviceUsage,
+    /// The md arrays partitions are members of.
+    md_arrays: Vec<String>,
+}
+
+/// Determine the usage of a disk from its partitions.
 fn scan_partitions(
     disk_manager: &Arc<Disks>,
-    lvm_devices: &HashSet<u64>,
+    lvm_devices: &HashMap<u64, LvmDeviceUsage>,
     zfs_devices: &HashSet<u64>,
+    md_members: &HashMap<String, String>,
     device: &str,
-) -> Result<DiskUsageType, Error> {
-    let mut sys_path = std::path::PathBuf::from("/sys/block");
-    sys_path.push(device);
+) -> Result<PartitionScan, Error> {
+    let sys_path = disk_manager.sys_path(format!("block/{device}"));
 
     let mut used = DiskUsageType::Unused;
 
     let mut found_lvm = false;
+    let mut lvm_usage = LvmDeviceUsage::default();
     let mut found_zfs = false;
+    let mut md_arrays = Vec::new();
     let mut found_mountpoints = false;
     let mut found_dm = false;
     let mut found_partitions = false;
@@ -72,6 +84,12 @@ fn scan_partitions(
 
         found_partitions = true;
 
+        if let Some(array) = md_members.get(name)
+            && !md_arrays.contains(array)
+        {
+            md_arrays.push(array.clone());
+        }
+
         let mut part_path = sys_path.clone();
         part_path.push(name);
 
@@ -79,8 +97,9 @@ fn scan_partitions(
 
         let devnum = data.devnum()?;
 
-        if lvm_devices.contains(&devnum) {
+        if let Some(usage) = lvm_devices.get(&devnum) {
             found_lvm = true;
+            lvm_usage.merge(usage);
         }
 
         if data.is_mounted()? {
@@ -102,13 +121,19 @@ fn scan_partitions(
         used = DiskUsageType::LVM;
     } else if found_zfs {
         used = DiskUsageType::ZFS;
+    } else if !md_arrays.is_empty() {
+        used = DiskUsageType::MdRaid;
     } else if found_dm {
         used = DiskUsageType::DeviceMapper;
     } else if found_partitions {
         used = DiskUsageType::Partitions;
     }
 
-    Ok(used)
+    Ok(PartitionScan {
+        used,
+        lvm_usage,
+        md_arrays,
+    })
 }
 
 /// Builder for querying disk usage information.
@@ -117,6 +142,7 @@ fn scan_partitions(
 /// is intentionally not included - query it separately and merge at the API layer.
 pub struct DiskUsageQuery {
     partitions: bool,
+    disks: Option<Arc<Disks>>,
 }
 
 impl Default for DiskUsageQuery {
@@ -127,7 +153,10 @@ impl Default for DiskUsageQuery {
 
 impl DiskUsageQuery {
     pub const fn new() -> Self {
-        Self { partitions: false }
+        Self {
+            partitions: false,
+            disks: None,
+        }
     }
 
     /// Enable or disable partition information (default: disabled).
@@ -136,6 +165,12 @@ impl DiskUsageQuery {
         self
     }
 
+    /// Use an existing disk management context (default: a new one for each query).
+    pub fn disks(mut self, disks: Arc<Disks>) -> Self {
+        self.disks = Some(disks);
+        self
+    }
+
     /// Query all disks.
     pub fn query(&self) -> Result<HashMap<String, DiskUsageInfo>, Error> {
         get_disks(self, None)
@@ -159,8 +194,9 @@ impl DiskUsageQuery {
 
 fn get_partitions_info(
     partitions: HashMap<u64, Disk>,
-    lvm_devices: &HashSet<u64>,
+    lvm_devices: &HashMap<u64, LvmDeviceUsage>,
     zfs_devices: &HashSet<u64>,
+    md_members: &HashMap<String, String>,
     file_system_devices: &HashSet<u64>,
     lsblk_infos: &[LsblkInfo],
 ) -> Vec<PartitionInfo> {
@@ -173,12 +209,22 @@ fn get_partitions_info(
                 .map(|p| p.to_string_lossy().to_string());
 
             let mut used = PartitionUsageType::Unused;
+            let mut lvm_usage = None;
+
+            let md_array = disk
+                .sysname()
+                .to_str()
+                .and_then(|name| md_members.get(name))
+                .cloned();
 
             if let Ok(devnum) = disk.devnum() {
-                if lvm_devices.contains(&devnum) {
+                if let Some(usage) = lvm_devices.get(&devnum) {
                     used = PartitionUsageType::LVM;
+                    lvm_usage = Some(usage.clone());
                 } else if zfs_devices.contains(&devnum) {
                     used = PartitionUsageType::ZFS;
+                } else if md_array.is_some() {
+                    used = PartitionUsageType::MdRaid;
                 } else if file_system_devices.contains(&devnum) {
                     used = PartitionUsageType::FileSystem;
                 }
@@ -204,6 +250,10 @@ fn get_partitions_info(
                 }
             }
 
+            let lvm_usage = lvm_usage
+                .filter(|_| used == PartitionUsageType::LVM)
+                .unwrap_or_default();
+
             PartitionInfo {
                 name: disk.sysname().to_str().unwrap_or("?").to_string(),
                 devpath,
@@ -213,6 +263,9 @@ fn get_partitions_info(
                 size: disk.size().ok(),
                 gpt: disk.has_gpt(),
                 uuid,
+                lvm_vg: lvm_usage.vg,
+                lvm_lvs: lvm_usage.lvs,
+                md_array,
             }
         })
         .collect()
@@ -223,17 +276,29 @@ fn get_disks(
     opts: &DiskUsageQuery,
     disks: Option<Vec<String>>,
 ) -> Result<HashMap<String, DiskUsageInfo>, Error> {
-    let disk_manager = Arc::new(Disks::new());
+    let disk_manager = match &opts.disks {
+        Some(disks) => Arc::clone(disks),
+        None => Arc::new(Disks::new()),
+    };
 
-    let lsblk_info = get_lsblk_info()?;
+    let lsblk_info = get_lsblk_info(&disk_manager)?;
 
-    let zfs_devices =
-        zfs_devices(&lsblk_info, None).or_else(|err| -> Result<HashSet<u64>, Error> {
+    let zfs_devices = zfs_devices(&disk_manager, &lsblk_info, None).or_else(
+        |err| -> Result<HashSet<u64>, Error> {
             proxmox_log::error!("error getting zfs devices: {err}");
             Ok(HashSet::new())
-        })?;
+        },
+    )?;
+
+    let lvm_devices = get_lvm_devices(&disk_manager, &lsblk_info)?;
 
-    let lvm_devices = get_lvm_devices(&lsblk_info)?;
+    let md_members = match disk_manager.md_arrays() {
+        Ok(arrays) => md_member_map(&arrays),
+        Err(err) => {
+            proxmox_log::error!("error getting md arrays: {err}");
+            HashMap::new()
+        }
+    };
 
     let file_system_devices = get_file_system_devices(&lsblk_info)?;
 
@@ -241,8 +306,8 @@ fn get_disks(
 
     let mut result = HashMap::new();
 
-    for item in proxmox_sys::fs::scan_subdir(libc::AT_FDCWD, "/sys/block", &BLOCKDEVICE_NAME_REGEX)?
-    {
+    let sys_block = disk_manager.sys_path("block");
+    for item in proxmox_sys::fs::scan_subdir(libc::AT_FDCWD, &sys_block, &BLOCKDEVICE_NAME_REGEX)? {
         let item = item?;
 
         let name = match item.file_name().to_str() {
@@ -256,7 +321,7 @@ fn get_disks(
             continue;
         }
 
-        let sys_path = format!("/sys/block/{name}");
+        let sys_path = sys_block.join(&name);
 
         if let Ok(target) = std::fs::read_link(&sys_path)
             && let Some(target) = target.to_str()
@@ -280,9 +345,11 @@ fn get_disks(
         };
 
         let mut usage = DiskUsageType::Unused;
+        let mut lvm_usage = LvmDeviceUsage::default();
 
-        if lvm_devices.contains(&devnum) {
+        if let Some(disk_lvm_usage) = lvm_devices.get(&devnum) {
             usage = DiskUsageType::LVM;
+            lvm_usage.merge(disk_lvm_usage);
         }
 
         match disk.is_mounted() {
@@ -295,6 +362,11 @@ fn get_disks(
             usage = DiskUsageType::ZFS;
         }
 
+        let mut md_arrays: Vec<String> = md_members.get(&name).cloned().into_iter().collect();
+        if usage == DiskUsageType::Unused && !md_arrays.is_empty() {
+            usage = DiskUsageType::MdRaid;
+        }
+
         let vendor = disk
             .vendor()
             .unwrap_or(None)
@@ -317,6 +389,7 @@ fn get_disks(
                     parts,
                     &lvm_devices,
                     &zfs_devices,
+                    &md_members,
                     &file_system_devices,
                     &lsblk_info,
                 ))
@@ -326,10 +399,22 @@ fn get_disks(
         };
 
         if usage != DiskUsageType::Mounted {
-            match scan_partitions(&disk_manager, &lvm_devices, &zfs_devices, &name) {
-                Ok(part_usage) => {
-                    if part_usage != DiskUsageType::Unused {
-                        usage = part_usage;
+            match scan_partitions(
+                &disk_manager,
+                &lvm_devices,
+                &zfs_devices,
+                &md_members,
+                &name,
+            ) {
+                Ok(scan) => {
+                    if scan.used != DiskUsageType::Unused {
+                        usage = scan.used;
+                    }
+                    lvm_usage.merge(&scan.lvm_usage);
+                    for array in scan.md_arrays {
+                        if !md_arrays.contains(&array) {
+                            md_arrays.push(array);
+                        }
                     }
                 }
                 Err(_) => continue, // skip devices if scan_partitions fail
@@ -344,6 +429,10 @@ fn get_disks(
             usage = DiskUsageType::DeviceMapper;
         }
 
+        if usage != DiskUsageType::LVM {
+            lvm_usage = LvmDeviceUsage::default();
+        }
+
         let info = DiskUsageInfo {
             name: name.clone(),
             vendor,
@@ -357,6 +446,9 @@ fn get_disks(
             used: usage,
             gpt: disk.has_gpt(),
             rpm: disk.ata_rotation_rate_rpm(),
+            lvm_vg: lvm_usage.vg,
+            lvm_lvs: lvm_usage.lvs,
+            md_arrays,
         };
 
         result.insert(name, info);
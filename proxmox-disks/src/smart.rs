@@ -6,11 +6,9 @@ use std::{
 };
 
 use ::serde::{Deserialize, Serialize};
-use anyhow::{Error, bail};
-use nix::sys::signal::{self, Signal};
-use nix::unistd::Pid;
+use anyhow::{Error, bail, format_err};
 
-use crate::SmartStatus;
+use crate::{Disks, SmartStatus};
 
 #[cfg(feature = "api-types")]
 use proxmox_schema::api;
@@ -42,6 +40,90 @@ pub struct SmartAttribute {
     pub threshold: Option<f64>,
 }
 
+#[cfg_attr(feature = "api-types", api(
+    properties: {
+        "temperature-sensors": {
+            description: "Temperature sensor readings in degrees Celsius.",
+            type: Array,
+            optional: true,
+            items: {
+                type: Integer,
+                description: "Temperature in degrees Celsius.",
+            },
+        },
+    },
+))]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// NVMe SMART / health information log
+pub struct NvmeHealthLog {
+    /// Critical warning flags, non-zero if the controller reports a critical condition
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critical_warning: Option<u64>,
+    /// Composite temperature in degrees Celsius
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<i64>,
+    /// Remaining spare capacity in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_spare: Option<u64>,
+    /// Spare capacity threshold in percent, below which a critical warning is raised
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_spare_threshold: Option<u64>,
+    /// Vendor estimate of the used lifetime in percent (can exceed 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentage_used: Option<u64>,
+    /// Number of unrecovered data integrity errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_errors: Option<u64>,
+    /// Number of shutdowns without prior shutdown notification (power loss)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsafe_shutdowns: Option<u64>,
+    /// Number of power cycles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_cycles: Option<u64>,
+    /// Number of power-on hours
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_on_hours: Option<u64>,
+    /// Data read, in units of 512000 bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_units_read: Option<u64>,
+    /// Data written, in units of 512000 bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_units_written: Option<u64>,
+    /// Number of error log entries over the controller's lifetime
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_log_entries: Option<u64>,
+    /// Temperature sensor readings in degrees Celsius
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub temperature_sensors: Vec<i64>,
+}
+
+impl NvmeHealthLog {
+    fn from_smartctl(log: &serde_json::Map<String, serde_json::Value>) -> Self {
+        let u64_field = |name: &str| log.get(name).and_then(serde_json::Value::as_u64);
+        Self {
+            critical_warning: u64_field("critical_warning"),
+            temperature: log.get("temperature").and_then(serde_json::Value::as_i64),
+            available_spare: u64_field("available_spare"),
+            available_spare_threshold: u64_field("available_spare_threshold"),
+            percentage_used: u64_field("percentage_used"),
+            media_errors: u64_field("media_errors"),
+            unsafe_shutdowns: u64_field("unsafe_shutdowns"),
+            power_cycles: u64_field("power_cycles"),
+            power_on_hours: u64_field("power_on_hours"),
+            data_units_read: u64_field("data_units_read"),
+            data_units_written: u64_field("data_units_written"),
+            error_log_entries: u64_field("num_err_log_entries"),
+            temperature_sensors: log
+                .get("temperature_sensors")
+                .and_then(serde_json::Value::as_array)
+                .map(|list| list.iter().filter_map(serde_json::Value::as_i64).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
 #[cfg_attr(feature = "api-types", api(
     properties: {
         status: {
@@ -59,6 +141,10 @@ pub struct SmartAttribute {
                 type: SmartAttribute,
             },
         },
+        nvme: {
+            type: NvmeHealthLog,
+            optional: true,
+        },
     },
 ))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -68,6 +154,36 @@ pub struct SmartData {
     pub status: SmartStatus,
     pub wearout: Option<f64>,
     pub attributes: Vec<SmartAttribute>,
+    /// NVMe health information, only available for NVMe devices.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nvme: Option<NvmeHealthLog>,
+}
+
+impl SmartData {
+    /// The number of reallocated sectors (ATA attribute 5).
+    pub fn reallocated_sectors(&self) -> Option<u64> {
+        self.ata_raw_value(5)
+    }
+
+    /// The number of sectors pending reallocation (ATA attribute 197).
+    pub fn pending_sectors(&self) -> Option<u64> {
+        self.ata_raw_value(197)
+    }
+
+    /// The number of media errors (NVMe).
+    pub fn media_errors(&self) -> Option<u64> {
+        self.nvme.as_ref().and_then(|log| log.media_errors)
+    }
+
+    /// Get the leading number of the raw value of an ATA attribute.
+    ///
+    /// Raw values can contain additional vendor specific information, e.g. `0 (Average 1)`.
+    fn ata_raw_value(&self, id: u64) -> Option<u64> {
+        let attribute = self.attributes.iter().find(|attr| attr.id == Some(id))?;
+        let raw = attribute.raw.trim_start();
+        let end = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+        raw[..end].parse().ok()
+    }
 }
 
 /// Default timeout for smartctl invocations (30 seconds).
@@ -83,8 +199,25 @@ pub fn get_smart_data(
     health_only: bool,
     timeout: Duration,
 ) -> Result<SmartData, Error> {
-    let output = run_smartctl(disk_path, health_only, timeout)?;
+    Disks::new().smart_data(disk_path, health_only, timeout)
+}
+
+impl Disks {
+    /// Read S.M.A.R.T. data for a block device via `smartctl`.
+    ///
+    /// See [`get_smart_data`], but runs `smartctl` via this context's command runner.
+    pub fn smart_data(
+        &self,
+        disk_path: &Path,
+        health_only: bool,
+        timeout: Duration,
+    ) -> Result<SmartData, Error> {
+        let output = run_smartctl(self, disk_path, health_only, timeout)?;
+        parse_smart_output(&output)
+    }
+}
 
+fn parse_smart_output(output: &str) -> Result<SmartData, Error> {
     let output: serde_json::Value = output.parse()?;
 
     let mut wearout = None;
@@ -155,8 +288,11 @@ pub fn get_smart_data(
         }
     }
 
+    let mut nvme = None;
+
     // NVME devices
     if let Some(list) = output["nvme_smart_health_information_log"].as_object() {
+        nvme = Some(NvmeHealthLog::from_smartctl(list));
         for (name, value) in list {
             if name == "percentage_used" {
                 // extract wearout from nvme text, allow for decimal values
@@ -190,10 +326,16 @@ pub fn get_smart_data(
         status,
         wearout,
         attributes,
+        nvme,
     })
 }
 
-fn run_smartctl(disk_path: &Path, health_only: bool, timeout: Duration) -> Result<String, Error> {
+fn run_smartctl(
+    disks: &Disks,
+    disk_path: &Path,
+    health_only: bool,
+    timeout: Duration,
+) -> Result<String, Error> {
     let mut command = std::process::Command::new("smartctl");
     command.arg("-H");
     if !health_only {
@@ -202,36 +344,11 @@ fn run_smartctl(disk_path: &Path, health_only: bool, timeout: Duration) -> Resul
     // always request JSON output so the caller can parse the result
     command.arg("-j").arg(disk_path);
 
-    let child = command
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()?;
-
-    let pid = Pid::from_raw(child.id() as i32);
-
-    let (tx, rx) = crossbeam_channel::bounded(1);
-    std::thread::spawn(move || {
-        let _ = tx.send(child.wait_with_output());
-    });
-
-    let output = match rx.recv_timeout(timeout) {
-        Ok(result) => result?,
-        Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
-            // Kill the smartctl process to avoid leaving it running indefinitely.
-            let _ = signal::kill(pid, Signal::SIGKILL);
-            bail!(
-                "smartctl timed out after {}s for {}",
-                timeout.as_secs(),
-                disk_path.display(),
-            );
-        }
-        Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
-            bail!(
-                "smartctl thread terminated unexpectedly for {}",
-                disk_path.display(),
-            );
-        }
-    };
+    // the runner kills smartctl once the timeout elapsed
+    let output = disks
+        .command_runner()
+        .output(&mut command, Some(timeout))
+        .map_err(|err| format_err!("smartctl failed for {} - {err}", disk_path.display()))?;
 
     let exitcode = output.status.code().unwrap_or(-1);
     // only bits 0-1 in the smartctl exit code are fatal errors
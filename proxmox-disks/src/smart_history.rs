@@ -0,0 +1,545 @@
+//! Persistent S.M.A.R.T. history and wear trend analysis.
+//!
+//! [`SmartHistory`] records selected values of [`SmartData`] snapshots in one RRD file per disk
+//! and value, and checks the recorded history for disks which wear out faster than before or
+//! start to accumulate bad sectors.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Error, bail};
+use serde::{Deserialize, Serialize};
+
+use proxmox_rrd::rrd::{AggregationFn, Archive, DataSourceType, Database};
+use proxmox_sys::fs::CreateOptions;
+
+#[cfg(feature = "api-types")]
+use proxmox_schema::api;
+
+use crate::SmartData;
+
+const HOUR: u64 = 3600;
+const DAY: u64 = 24 * HOUR;
+
+/// Recorded values are written to disk at most once per this many seconds of sample time.
+const SAVE_INTERVAL: u64 = DAY;
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// A S.M.A.R.T. value tracked over time.
+pub enum SmartHistoryMetric {
+    /// Used lifetime in percent (100 - wearout).
+    WearUsed,
+    /// Number of reallocated sectors (ATA).
+    ReallocatedSectors,
+    /// Number of sectors pending reallocation (ATA).
+    PendingSectors,
+    /// Number of media errors (NVMe).
+    MediaErrors,
+}
+
+impl SmartHistoryMetric {
+    /// All tracked values.
+    pub const ALL: [SmartHistoryMetric; 4] = [
+        Self::WearUsed,
+        Self::ReallocatedSectors,
+        Self::PendingSectors,
+        Self::MediaErrors,
+    ];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::WearUsed => "wear-used",
+            Self::ReallocatedSectors => "reallocated-sectors",
+            Self::PendingSectors => "pending-sectors",
+            Self::MediaErrors => "media-errors",
+        }
+    }
+
+    fn value(self, data: &SmartData) -> Option<f64> {
+        match self {
+            Self::WearUsed => data.wearout.map(|wearout| 100.0 - wearout),
+            Self::ReallocatedSectors => data.reallocated_sectors().map(|value| value as f64),
+            Self::PendingSectors => data.pending_sectors().map(|value| value as f64),
+            Self::MediaErrors => data.media_errors().map(|value| value as f64),
+        }
+    }
+}
+
+/// Options for the wear trend check, see [`SmartHistory::trend`].
+#[derive(Clone, Debug)]
+pub struct SmartTrendOptions {
+    /// Length of the recent period in days (default: 30).
+    pub recent_days: u64,
+    /// Length of the baseline period before the recent period in days (default: 180).
+    pub baseline_days: u64,
+    /// Wear is accelerating if the recent rate exceeds the baseline rate by this factor
+    /// (default: 2).
+    pub acceleration_factor: f64,
+    /// Recent wear rates below this value in percent per day are never considered accelerating
+    /// (default: 0.005, that is about 2% per year).
+    pub min_wear_rate: f64,
+}
+
+impl Default for SmartTrendOptions {
+    fn default() -> Self {
+        Self {
+            recent_days: 30,
+            baseline_days: 180,
+            acceleration_factor: 2.0,
+            min_wear_rate: 0.005,
+        }
+    }
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// Result of the wear trend check of a disk.
+pub struct SmartTrend {
+    /// The last recorded used lifetime in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wear_used: Option<f64>,
+    /// Wear rate in the recent period, in percent per day.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_wear_rate: Option<f64>,
+    /// Wear rate in the baseline period, in percent per day.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_wear_rate: Option<f64>,
+    /// Estimated days until the lifetime is used up, at the recent wear rate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_days_left: Option<f64>,
+    /// The recent wear rate is considerably higher than the baseline wear rate.
+    pub wear_accelerating: bool,
+    /// Increase of reallocated sectors in the recent period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reallocated_sectors_increase: Option<f64>,
+    /// Increase of pending sectors in the recent period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_sectors_increase: Option<f64>,
+    /// Increase of media errors in the recent period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_errors_increase: Option<f64>,
+}
+
+impl SmartTrend {
+    /// Check whether the disk should be looked at, because its wear is accelerating or it
+    /// recently got new bad sectors or media errors.
+    pub fn needs_attention(&self) -> bool {
+        self.wear_accelerating
+            || [
+                self.reallocated_sectors_increase,
+                self.pending_sectors_increase,
+                self.media_errors_increase,
+            ]
+            .into_iter()
+            .any(|increase| increase.is_some_and(|increase| increase > 0.0))
+    }
+}
+
+/// Persistent history of S.M.A.R.T. values.
+///
+/// Values are stored below `<base>/<disk-id>/`, with hourly resolution for 60 days and daily
+/// resolution for 5 years. The disk id should be stable across reboots and device renames, for
+/// example the serial number or WWN.
+///
+/// Loaded files are kept in memory and recorded values are only written back once per day of
+/// sample time, so [`flush`](Self::flush) should be called before the history is dropped, which
+/// otherwise writes pending values on a best-effort basis.
+pub struct SmartHistory {
+    base: PathBuf,
+    file_options: CreateOptions,
+    databases: Mutex<HashMap<PathBuf, CachedDatabase>>,
+}
+
+struct CachedDatabase {
+    database: Database,
+    /// Sample time of the last value written to disk.
+    saved: u64,
+    dirty: bool,
+}
+
+impl SmartHistory {
+    /// Create a history stored below `base`, creating files and directories with `file_options`.
+    pub fn new<P: Into<PathBuf>>(base: P, file_options: CreateOptions) -> Self {
+        Self {
+            base: base.into(),
+            file_options,
+            databases: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record the values of `data` for the disk `disk_id` at `time` (epoch).
+    ///
+    /// Values which are not available for the disk are skipped, as are values for points in time
+    /// before the last recorded one, which is logged.
+    pub fn record(&self, disk_id: &str, data: &SmartData, time: u64) -> Result<(), Error> {
+        let dir = self.disk_dir(disk_id)?;
+        proxmox_sys::fs::create_path(&dir, Some(self.file_options), Some(self.file_options))?;
+
+        let mut databases = self.databases.lock().unwrap();
+
+        for metric in SmartHistoryMetric::ALL {
+            let value = match metric.value(data) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let path = dir.join(metric.file_name());
+            let cached = match load_cached(&mut databases, &path)? {
+                Some(cached) => cached,
+                None => databases.entry(path.clone()).or_insert(CachedDatabase {
+                    database: new_database(),
+                    saved: 0,
+                    dirty: false,
+                }),
+            };
+
+            let last_update = cached.database.last_update();
+            if time as f64 <= last_update {
+                proxmox_log::warn!(
+                    "skipping {metric:?} of disk '{disk_id}' at {time}, not after the last \
+                     recorded value at {last_update}"
+                );
+                continue;
+            }
+
+            cached.database.update(time as f64, value);
+            cached.dirty = true;
+
+            if time >= cached.saved + SAVE_INTERVAL {
+                cached.save(&path, self.file_options)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write all recorded values which were not written to disk yet.
+    pub fn flush(&self) -> Result<(), Error> {
+        let mut databases = self.databases.lock().unwrap();
+        for (path, cached) in databases.iter_mut() {
+            if cached.dirty {
+                cached.save(path, self.file_options)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the recorded daily values of `metric` for the disk `disk_id` between `start` and
+    /// `end` (epoch), as pairs of time and value.
+    pub fn history(
+        &self,
+        disk_id: &str,
+        metric: SmartHistoryMetric,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<(u64, f64)>, Error> {
+        let path = self.disk_dir(disk_id)?.join(metric.file_name());
+        let mut databases = self.databases.lock().unwrap();
+        let database = match load_cached(&mut databases, &path)? {
+            Some(cached) => &cached.database,
+            None => return Ok(Vec::new()),
+        };
+
+        let entry = database.extract_data(AggregationFn::Maximum, DAY, Some(start), Some(end))?;
+
+        Ok(entry
+            .data
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value.map(|value| (entry.start + index as u64 * entry.resolution, value))
+            })
+            .collect())
+    }
+
+    /// Check the recorded history of the disk `disk_id` up to `now` (epoch).
+    pub fn trend(
+        &self,
+        disk_id: &str,
+        now: u64,
+        options: &SmartTrendOptions,
+    ) -> Result<SmartTrend, Error> {
+        let recent_start = now.saturating_sub(options.recent_days * DAY);
+        let baseline_start = recent_start.saturating_sub(options.baseline_days * DAY);
+
+        let recent_increase = |metric| -> Result<Option<f64>, Error> {
+            Ok(increase(&self.history(
+                disk_id,
+                metric,
+                recent_start,
+                now,
+            )?))
+        };
+
+        let wear = self.history(disk_id, SmartHistoryMetric::WearUsed, baseline_start, now)?;
+
+        let mut trend = analyze_wear(&wear, recent_start, options);
+        trend.reallocated_sectors_increase =
+            recent_increase(SmartHistoryMetric::ReallocatedSectors)?;
+        trend.pending_sectors_increase = recent_increase(SmartHistoryMetric::PendingSectors)?;
+        trend.media_errors_increase = recent_increase(SmartHistoryMetric::MediaErrors)?;
+
+        Ok(trend)
+    }
+
+    fn disk_dir(&self, disk_id: &str) -> Result<PathBuf, Error> {
+        if disk_id.is_empty()
+            || disk_id.starts_with('.')
+            || !disk_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+        {
+            bail!("invalid disk id '{disk_id}'");
+        }
+        Ok(self.base.join(disk_id))
+    }
+}
+
+impl Drop for SmartHistory {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            proxmox_log::error!("failed to write S.M.A.R.T. history - {err}");
+        }
+    }
+}
+
+impl CachedDatabase {
+    fn save(&mut self, path: &Path, file_options: CreateOptions) -> Result<(), Error> {
+        self.database.save(path, file_options, false)?;
+        self.saved = self.database.last_update() as u64;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Get the database at `path` from `databases`, loading it if it exists on disk.
+fn load_cached<'a>(
+    databases: &'a mut HashMap<PathBuf, CachedDatabase>,
+    path: &Path,
+) -> Result<Option<&'a mut CachedDatabase>, Error> {
+    if !databases.contains_key(path) {
+        let database = match Database::load(path, false) {
+            Ok(database) => database,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => bail!("unable to load {path:?} - {err}"),
+        };
+        let saved = database.last_update() as u64;
+        databases.insert(
+            path.to_owned(),
+            CachedDatabase {
+                database,
+                saved,
+                dirty: false,
+            },
+        );
+    }
+    Ok(databases.get_mut(path))
+}
+
+fn new_database() -> Database {
+    Database::new(
+        DataSourceType::Gauge,
+        vec![
+            Archive::new(AggregationFn::Maximum, HOUR, 60 * 24),
+            Archive::new(AggregationFn::Maximum, DAY, 5 * 366),
+        ],
+    )
+}
+
+/// The increase between the first and the last value.
+fn increase(points: &[(u64, f64)]) -> Option<f64> {
+    match (points.first(), points.last()) {
+        (Some((_, first)), Some((_, last))) if points.len() > 1 => Some(last - first),
+        _ => None,
+    }
+}
+
+/// The rate of change per day between the first and the last value.
+fn rate_per_day(points: &[(u64, f64)]) -> Option<f64> {
+    match (points.first(), points.last()) {
+        (Some((start, first)), Some((end, last))) if end > start => {
+            Some((last - first) / ((end - start) as f64 / DAY as f64))
+        }
+        _ => None,
+    }
+}
+
+/// Compare the wear rate after `recent_start` with the one before it.
+///
+/// Both periods share the value at their boundary, so that the rates cover the whole time span.
+fn analyze_wear(
+    points: &[(u64, f64)],
+    recent_start: u64,
+    options: &SmartTrendOptions,
+) -> SmartTrend {
+    let split = points.partition_point(|(time, _)| *time < recent_start);
+    let boundary = split.saturating_sub(1);
+
+    let recent_wear_rate = rate_per_day(&points[boundary..]);
+    let baseline_wear_rate = rate_per_day(&points[..split]);
+
+    let wear_accelerating = match (recent_wear_rate, baseline_wear_rate) {
+        (Some(recent), Some(baseline)) => {
+            recent >= options.min_wear_rate && recent > baseline * options.acceleration_factor
+        }
+        _ => false,
+    };
+
+    let wear_used = points.last().map(|(_, value)| *value);
+
+    let estimated_days_left = match (wear_used, recent_wear_rate) {
+        (Some(used), Some(rate)) if rate > 0.0 => Some((100.0 - used).max(0.0) / rate),
+        _ => None,
+    };
+
+    SmartTrend {
+        wear_used,
+        recent_wear_rate,
+        baseline_wear_rate,
+        estimated_days_left,
+        wear_accelerating,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Daily points starting at day 0 with the given per-day wear increase.
+    fn wear_points(rates: &[(u64, f64)]) -> Vec<(u64, f64)> {
+        let mut points = vec![(0, 10.0)];
+        let mut value = 10.0;
+        for (days, rate) in rates {
+            for _ in 0..*days {
+                value += rate;
+                points.push((points.len() as u64 * DAY, value));
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_analyze_wear() {
+        let options = SmartTrendOptions::default();
+
+        // constant wear
+        let points = wear_points(&[(200, 0.01)]);
+        let trend = analyze_wear(&points, 170 * DAY, &options);
+        assert!(!trend.wear_accelerating);
+        assert!((trend.recent_wear_rate.unwrap() - 0.01).abs() < 1e-9);
+        assert!((trend.baseline_wear_rate.unwrap() - 0.01).abs() < 1e-9);
+        assert!((trend.estimated_days_left.unwrap() - 8800.0).abs() < 1e-6);
+
+        // wear rate tripled in the recent period
+        let points = wear_points(&[(170, 0.01), (30, 0.03)]);
+        let trend = analyze_wear(&points, 170 * DAY, &options);
+        assert!(trend.wear_accelerating);
+        assert!(trend.needs_attention());
+
+        // accelerating, but still negligible
+        let points = wear_points(&[(170, 0.001), (30, 0.003)]);
+        let trend = analyze_wear(&points, 170 * DAY, &options);
+        assert!(!trend.wear_accelerating);
+
+        // no baseline yet
+        let points = wear_points(&[(20, 0.05)]);
+        let trend = analyze_wear(&points, 0, &options);
+        assert!(!trend.wear_accelerating);
+        assert_eq!(trend.baseline_wear_rate, None);
+
+        assert_eq!(analyze_wear(&[], 0, &options), SmartTrend::default());
+    }
+
+    #[test]
+    fn test_increase() {
+        assert_eq!(increase(&[]), None);
+        assert_eq!(increase(&[(0, 3.0)]), None);
+        assert_eq!(increase(&[(0, 3.0), (DAY, 3.0), (2 * DAY, 8.0)]), Some(5.0));
+    }
+
+    #[test]
+    fn test_smart_history() -> Result<(), Error> {
+        let base = std::env::temp_dir().join(format!(
+            "proxmox-disks-smart-history-{}",
+            std::process::id()
+        ));
+        let history = SmartHistory::new(&base, CreateOptions::new());
+
+        let mut data = SmartData {
+            status: crate::SmartStatus::Passed,
+            wearout: None,
+            attributes: Vec::new(),
+            nvme: Some(Default::default()),
+        };
+
+        let now = 400 * DAY;
+        let start = now - 200 * DAY;
+        for day in 0..=200 {
+            let recent = day > 170;
+            let used = 10.0
+                + day as f64 * 0.01
+                + if recent {
+                    (day - 170) as f64 * 0.02
+                } else {
+                    0.0
+                };
+            data.wearout = Some(100.0 - used);
+            data.nvme.as_mut().unwrap().media_errors = Some(if day > 190 { 2 } else { 0 });
+            history.record("S4EWNX0R123456", &data, start + day * DAY)?;
+        }
+
+        let points = history.history("S4EWNX0R123456", SmartHistoryMetric::WearUsed, start, now)?;
+        assert!(points.len() > 190);
+
+        let trend = history.trend("S4EWNX0R123456", now, &SmartTrendOptions::default())?;
+        assert!(trend.wear_accelerating);
+        assert_eq!(trend.media_errors_increase, Some(2.0));
+        assert_eq!(trend.reallocated_sectors_increase, None);
+
+        // unknown disks have no history
+        let trend = history.trend("unknown", now, &SmartTrendOptions::default())?;
+        assert!(!trend.needs_attention());
+
+        assert!(history.record("../escape", &data, now).is_err());
+
+        // older samples are skipped
+        history.record("S4EWNX0R123456", &data, start)?;
+        let trend = history.trend("S4EWNX0R123456", now, &SmartTrendOptions::default())?;
+        assert_eq!(trend.media_errors_increase, Some(2.0));
+
+        // values are written at most once per day, and on flush
+        let path = base.join("S4EWNX0R123456").join("wear-used");
+        let on_disk = |path: &Path| Database::load(path, false).unwrap().last_update();
+        assert_eq!(on_disk(&path), now as f64);
+        data.wearout = Some(50.0);
+        history.record("S4EWNX0R123456", &data, now + HOUR)?;
+        assert_eq!(on_disk(&path), now as f64);
+        history.flush()?;
+        assert_eq!(on_disk(&path), (now + HOUR) as f64);
+
+        // and when the history is dropped
+        history.record("S4EWNX0R123456", &data, now + 2 * HOUR)?;
+        drop(history);
+        assert_eq!(on_disk(&path), (now + 2 * HOUR) as f64);
+
+        let history = SmartHistory::new(&base, CreateOptions::new());
+        let points = history.history(
+            "S4EWNX0R123456",
+            SmartHistoryMetric::WearUsed,
+            now,
+            now + DAY,
+        )?;
+        assert_eq!(points.last(), Some(&(now, 50.0)));
+
+        std::fs::remove_dir_all(&base)?;
+
+        Ok(())
+    }
+}
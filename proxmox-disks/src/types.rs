@@ -83,6 +83,8 @@ pub enum PartitionUsageType {
     BIOS,
     /// Partition contains a file system label
     FileSystem,
+    /// Partition is a member of an md (software RAID) array
+    MdRaid,
 }
 
 #[cfg_attr(feature = "api-types", api)]
@@ -105,9 +107,21 @@ pub enum DiskUsageType {
     Partitions,
     /// Disk contains a file system label
     FileSystem,
+    /// Disk is a member of an md (software RAID) array
+    MdRaid,
 }
 
-#[cfg_attr(feature = "api-types", api)]
+#[cfg_attr(feature = "api-types", api(
+    properties: {
+        "lvm-lvs": {
+            optional: true,
+            items: {
+                type: String,
+                description: "LVM logical volume name.",
+            }
+        }
+    }
+))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 #[non_exhaustive]
@@ -129,6 +143,15 @@ pub struct PartitionInfo {
     pub gpt: bool,
     /// UUID
     pub uuid: Option<String>,
+    /// The LVM volume group, if the partition is an LVM physical volume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lvm_vg: Option<String>,
+    /// The LVM logical volumes using the partition
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lvm_lvs: Vec<String>,
+    /// The md array the partition is a member of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub md_array: Option<String>,
 }
 
 #[cfg_attr(feature = "api-types", api(
@@ -144,6 +167,20 @@ pub struct PartitionInfo {
             items: {
                 type: PartitionInfo
             }
+        },
+        "lvm-lvs": {
+            optional: true,
+            items: {
+                type: String,
+                description: "LVM logical volume name.",
+            }
+        },
+        "md-arrays": {
+            optional: true,
+            items: {
+                type: String,
+                description: "md array name.",
+            }
         }
     }
 ))]
@@ -177,6 +214,15 @@ pub struct DiskUsageInfo {
     pub gpt: bool,
     /// RPM
     pub rpm: Option<u64>,
+    /// The LVM volume group, if the disk or one of its partitions is an LVM physical volume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lvm_vg: Option<String>,
+    /// The LVM logical volumes using the disk or its partitions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lvm_lvs: Vec<String>,
+    /// The md arrays the disk or its partitions are members of
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub md_arrays: Vec<String>,
 }
 
 #[cfg_attr(feature = "api-types", api)]
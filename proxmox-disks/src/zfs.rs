@@ -68,10 +68,11 @@ pub fn zfs_pool_stats(pool: &str) -> Result<Option<BlockDevStat>, Error> {
 ///
 /// The set is indexed by using the unix raw device number (dev_t is u64)
 pub(crate) fn zfs_devices(
+    disks: &Disks,
     lsblk_info: &[LsblkInfo],
     pool: Option<String>,
 ) -> Result<HashSet<u64>, Error> {
-    let list = zpool_list(pool.as_deref(), true)?;
+    let list = disks.zpool_list(pool.as_deref(), true)?;
 
     let mut device_set = HashSet::new();
     for entry in list {
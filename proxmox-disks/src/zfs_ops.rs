@@ -0,0 +1,1175 @@
+//! Typed ZFS pool and dataset operations.
+//!
+//! All operations validate their input, partially against the current pool state as reported by
+//! `zpool list` and `zpool status`, before running any mutating command. Disks for new pools and
+//! vdevs must not be mounted, held by another device, or used by md or LVM either.
+
+use std::path::Path;
+
+use anyhow::{Error, bail, format_err};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "api-types")]
+use proxmox_schema::api;
+
+use crate::operations::disk_device_path;
+use crate::{Disk, Disks, ZFSPoolInfo, ZFSPoolVDevState, parse_zpool_status_config_tree};
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+/// The RAID level of a ZFS vdev.
+pub enum ZfsRaidLevel {
+    /// Single disk(s), striped.
+    Single,
+    /// Mirror.
+    Mirror,
+    /// Striped mirrors (pairs of disks).
+    Raid10,
+    /// RAIDZ with single parity.
+    Raidz,
+    /// RAIDZ with double parity.
+    Raidz2,
+    /// RAIDZ with triple parity.
+    Raidz3,
+    /// Distributed spare RAID with single parity.
+    Draid,
+    /// Distributed spare RAID with double parity.
+    Draid2,
+    /// Distributed spare RAID with triple parity.
+    Draid3,
+}
+
+impl ZfsRaidLevel {
+    /// The number of parity devices for RAIDZ and dRAID levels.
+    fn parity(self) -> Option<usize> {
+        match self {
+            Self::Raidz | Self::Draid => Some(1),
+            Self::Raidz2 | Self::Draid2 => Some(2),
+            Self::Raidz3 | Self::Draid3 => Some(3),
+            _ => None,
+        }
+    }
+
+    fn is_draid(self) -> bool {
+        matches!(self, Self::Draid | Self::Draid2 | Self::Draid3)
+    }
+
+    /// The number of device failures a vdev of this level with `devices` devices tolerates.
+    fn redundancy(self, devices: usize) -> usize {
+        match self {
+            Self::Single => 0,
+            Self::Mirror => devices.saturating_sub(1),
+            Self::Raid10 => 1,
+            _ => self.parity().unwrap(),
+        }
+    }
+
+    /// The layout `zpool create` compares between top-level vdevs: their type and width.
+    fn replication(self, devices: usize) -> (&'static str, usize) {
+        match self {
+            Self::Single => ("disk", 1),
+            Self::Mirror => ("mirror", devices),
+            Self::Raid10 => ("mirror", 2),
+            Self::Raidz | Self::Raidz2 | Self::Raidz3 => ("raidz", devices),
+            Self::Draid | Self::Draid2 | Self::Draid3 => ("draid", devices),
+        }
+    }
+
+    /// The minimal number of devices for this level (without dRAID spares).
+    pub fn min_devices(self) -> usize {
+        match self {
+            Self::Single => 1,
+            Self::Mirror => 2,
+            Self::Raid10 => 4,
+            // raidz with parity P needs at least P + 1 devices, we do not allow less than P + 2
+            Self::Raidz | Self::Raidz2 | Self::Raidz3 => self.parity().unwrap() + 2,
+            Self::Draid | Self::Draid2 | Self::Draid3 => self.parity().unwrap() + 1,
+        }
+    }
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+/// The allocation class of a ZFS vdev.
+pub enum ZfsVdevClass {
+    /// Regular data vdev.
+    Data,
+    /// Special allocation class for metadata and small blocks.
+    Special,
+    /// Separate intent log (SLOG).
+    Log,
+    /// L2ARC cache device.
+    Cache,
+    /// Hot spare.
+    Spare,
+}
+
+impl ZfsVdevClass {
+    fn keyword(self) -> Option<&'static str> {
+        match self {
+            Self::Data => None,
+            Self::Special => Some("special"),
+            Self::Log => Some("log"),
+            Self::Cache => Some("cache"),
+            Self::Spare => Some("spare"),
+        }
+    }
+}
+
+/// Options to create a new ZFS pool, see [`Disks::zpool_create`].
+pub struct ZpoolCreate {
+    name: String,
+    raid_level: ZfsRaidLevel,
+    devices: Vec<Disk>,
+    draid_data: Option<usize>,
+    draid_spares: usize,
+    special_level: ZfsRaidLevel,
+    special: Vec<Disk>,
+    log_level: ZfsRaidLevel,
+    log: Vec<Disk>,
+    cache: Vec<Disk>,
+    ashift: Option<u8>,
+    properties: Vec<(String, String)>,
+    force: bool,
+}
+
+impl ZpoolCreate {
+    /// Create a pool `name` with a single data vdev of the given level.
+    pub fn new(name: &str, raid_level: ZfsRaidLevel, devices: Vec<Disk>) -> Self {
+        Self {
+            name: name.to_string(),
+            raid_level,
+            devices,
+            draid_data: None,
+            draid_spares: 0,
+            special_level: ZfsRaidLevel::Single,
+            special: Vec::new(),
+            log_level: ZfsRaidLevel::Single,
+            log: Vec::new(),
+            cache: Vec::new(),
+            ashift: None,
+            properties: Vec::new(),
+            force: false,
+        }
+    }
+
+    /// Set the number of data devices per redundancy group and distributed spares for dRAID.
+    pub fn draid(mut self, data: Option<usize>, spares: usize) -> Self {
+        self.draid_data = data;
+        self.draid_spares = spares;
+        self
+    }
+
+    /// Add a special vdev, either [`Single`](ZfsRaidLevel::Single) (striped),
+    /// [`Mirror`](ZfsRaidLevel::Mirror) or [`Raid10`](ZfsRaidLevel::Raid10).
+    ///
+    /// It must tolerate at least as many device failures as the data vdev, since losing it loses
+    /// the pool. A layout different from the data vdev, like a mirror for a RAIDZ pool, needs to
+    /// be [forced](Self::force).
+    pub fn special(mut self, raid_level: ZfsRaidLevel, devices: Vec<Disk>) -> Self {
+        self.special_level = raid_level;
+        self.special = devices;
+        self
+    }
+
+    /// Add a log vdev, either [`Single`](ZfsRaidLevel::Single) (striped),
+    /// [`Mirror`](ZfsRaidLevel::Mirror) or [`Raid10`](ZfsRaidLevel::Raid10).
+    ///
+    /// It must tolerate at least as many device failures as the data vdev.
+    pub fn log(mut self, raid_level: ZfsRaidLevel, devices: Vec<Disk>) -> Self {
+        self.log_level = raid_level;
+        self.log = devices;
+        self
+    }
+
+    /// Add cache devices.
+    pub fn cache(mut self, devices: Vec<Disk>) -> Self {
+        self.cache = devices;
+        self
+    }
+
+    /// Set the pool's `ashift` property.
+    pub fn ashift(mut self, ashift: u8) -> Self {
+        self.ashift = Some(ashift);
+        self
+    }
+
+    /// Set a property of the pool's root dataset (`zpool create -O`).
+    pub fn property(mut self, property: &str, value: &str) -> Self {
+        self.properties
+            .push((property.to_string(), value.to_string()));
+        self
+    }
+
+    /// Force the creation of the pool (`zpool create -f`).
+    ///
+    /// This is required for a special vdev laid out differently than the data vdev. It also
+    /// overrides the checks of `zpool create` for devices which contain a file system or are part
+    /// of an exported pool, while the usage checks of [`Disks::zpool_create`] still apply.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// All devices used by the new pool.
+    fn all_devices(&self) -> impl Iterator<Item = &Disk> {
+        self.devices
+            .iter()
+            .chain(&self.special)
+            .chain(&self.log)
+            .chain(&self.cache)
+    }
+
+    /// Check the options without looking at the current system state.
+    fn validate(&self) -> Result<(), Error> {
+        validate_pool_name(&self.name)?;
+
+        if self.raid_level.is_draid() {
+            let parity = self.raid_level.parity().unwrap();
+            let data = self.draid_data.unwrap_or(1);
+            if data == 0 {
+                bail!("dRAID needs at least one data device per redundancy group");
+            }
+            let needed = parity + data + self.draid_spares;
+            if self.devices.len() < needed {
+                bail!(
+                    "{:?} with {data} data devices and {} spares needs at least {needed} devices",
+                    self.raid_level,
+                    self.draid_spares,
+                );
+            }
+        } else {
+            if self.draid_data.is_some() || self.draid_spares > 0 {
+                bail!("dRAID options are only valid for dRAID levels");
+            }
+            validate_vdev_devices(self.raid_level, self.devices.len())?;
+        }
+
+        let data_redundancy = self.raid_level.redundancy(self.devices.len());
+        for (class, raid_level, devices) in [
+            ("special", self.special_level, &self.special),
+            ("log", self.log_level, &self.log),
+        ] {
+            if devices.is_empty() {
+                continue;
+            }
+            if !matches!(
+                raid_level,
+                ZfsRaidLevel::Single | ZfsRaidLevel::Mirror | ZfsRaidLevel::Raid10
+            ) {
+                bail!("{class} vdev cannot use {raid_level:?}");
+            }
+            validate_vdev_devices(raid_level, devices.len())?;
+            if raid_level.redundancy(devices.len()) < data_redundancy {
+                bail!(
+                    "{class} vdev with {raid_level:?} has less redundancy than the {:?} data vdev",
+                    self.raid_level,
+                );
+            }
+        }
+
+        // `zpool create` refuses special vdevs with a different layout than the data vdevs, even
+        // if they are as redundant
+        if !self.special.is_empty()
+            && !self.force
+            && self.special_level.replication(self.special.len())
+                != self.raid_level.replication(self.devices.len())
+        {
+            bail!(
+                "special vdev with {:?} is laid out differently than the {:?} data vdev, which \
+                 needs to be forced",
+                self.special_level,
+                self.raid_level,
+            );
+        }
+
+        if let Some(ashift) = self.ashift.filter(|ashift| !(9..=16).contains(ashift)) {
+            bail!("invalid ashift {ashift}, must be between 9 and 16");
+        }
+
+        for (property, value) in &self.properties {
+            validate_property(property, value)?;
+        }
+
+        Ok(())
+    }
+
+    fn command_args(&self) -> Result<Vec<String>, Error> {
+        let mut args = vec!["create".to_string()];
+
+        if self.force {
+            args.push("-f".into());
+        }
+
+        if let Some(ashift) = self.ashift {
+            args.push("-o".into());
+            args.push(format!("ashift={ashift}"));
+        }
+        for (property, value) in &self.properties {
+            args.push("-O".into());
+            args.push(format!("{property}={value}"));
+        }
+
+        args.push(self.name.clone());
+
+        if self.raid_level.is_draid() {
+            let mut spec = match self.raid_level.parity() {
+                Some(1) | None => "draid".to_string(),
+                Some(parity) => format!("draid{parity}"),
+            };
+            if let Some(data) = self.draid_data {
+                spec.push_str(&format!(":{data}d"));
+            }
+            if self.draid_spares > 0 {
+                spec.push_str(&format!(":{}s", self.draid_spares));
+            }
+            args.push(spec);
+            args.extend(device_paths(&self.devices)?);
+        } else {
+            args.extend(vdev_args(self.raid_level, &device_paths(&self.devices)?));
+        }
+
+        for (keyword, raid_level, devices) in [
+            ("special", self.special_level, &self.special),
+            ("log", self.log_level, &self.log),
+        ] {
+            if devices.is_empty() {
+                continue;
+            }
+            args.push(keyword.into());
+            args.extend(vdev_args(raid_level, &device_paths(devices)?));
+        }
+
+        if !self.cache.is_empty() {
+            args.push("cache".into());
+            args.extend(device_paths(&self.cache)?);
+        }
+
+        Ok(args)
+    }
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+/// State of the last scrub or resilver of a pool.
+pub enum ZpoolScanState {
+    /// No scrub or resilver was run yet.
+    None,
+    /// The scan is currently running.
+    Running,
+    /// The scan is paused.
+    Paused,
+    /// The scan finished.
+    Finished,
+    /// The scan was canceled.
+    Canceled,
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// Scrub or resilver status of a pool, parsed from the `scan` field of `zpool status`.
+pub struct ZpoolScanStatus {
+    /// The kind of scan, `scrub` or `resilver`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// The state of the scan.
+    pub state: ZpoolScanState,
+    /// Progress of a running scan in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent_done: Option<f64>,
+    /// Number of errors of a finished scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<u64>,
+    /// The unparsed status text.
+    pub text: String,
+}
+
+/// Parse the `scan` field of `zpool status`.
+pub fn parse_zpool_scan_status(text: &str) -> ZpoolScanStatus {
+    let first_line = text.lines().next().unwrap_or_default().trim();
+
+    let function = ["scrub", "resilver"]
+        .into_iter()
+        .find(|function| first_line.starts_with(function))
+        .map(String::from);
+
+    let state = if function.is_none() {
+        ZpoolScanState::None
+    } else if first_line.contains("in progress") {
+        ZpoolScanState::Running
+    } else if first_line.contains("paused") {
+        ZpoolScanState::Paused
+    } else if first_line.contains("canceled") {
+        ZpoolScanState::Canceled
+    } else {
+        ZpoolScanState::Finished
+    };
+
+    // e.g. "0B repaired, 14.29% done, 02:03:04 to go"
+    let percent_done = text
+        .split([',', '\n'])
+        .map(str::trim)
+        .find_map(|part| part.strip_suffix("% done"))
+        .and_then(|percent| percent.rsplit(' ').next()?.parse().ok());
+
+    // e.g. "scrub repaired 0B in 02:11:42 with 0 errors on Sun Oct 11 02:35:43 2026"
+    let errors = first_line
+        .split_once(" with ")
+        .and_then(|(_, rest)| rest.split(' ').next()?.parse().ok());
+
+    ZpoolScanStatus {
+        function,
+        state,
+        percent_done,
+        errors,
+        text: text.to_string(),
+    }
+}
+
+#[cfg_attr(feature = "api-types", api)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+/// A ZFS snapshot.
+pub struct ZfsSnapshotInfo {
+    /// The dataset of the snapshot.
+    pub dataset: String,
+    /// The snapshot name (without the dataset).
+    pub name: String,
+    /// Space used by the snapshot in bytes.
+    pub used: u64,
+    /// Space referenced by the snapshot in bytes.
+    pub referenced: u64,
+    /// Creation time as UNIX epoch.
+    pub creation: i64,
+}
+
+/// Parse `zfs list -H -p -t snapshot -o name,used,refer,creation` output.
+fn parse_zfs_snapshot_list(output: &str) -> Result<Vec<ZfsSnapshotInfo>, Error> {
+    let mut list = Vec::new();
+
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (full_name, used, referenced, creation) = match fields[..] {
+            [full_name, used, referenced, creation] => (full_name, used, referenced, creation),
+            _ => bail!("unable to parse zfs snapshot list line '{line}'"),
+        };
+
+        let (dataset, name) = full_name
+            .split_once('@')
+            .ok_or_else(|| format_err!("not a snapshot: '{full_name}'"))?;
+
+        let parse_err = |field: &str| format_err!("invalid {field} in snapshot list line '{line}'");
+
+        list.push(ZfsSnapshotInfo {
+            dataset: dataset.to_string(),
+            name: name.to_string(),
+            used: used.parse().map_err(|_| parse_err("used"))?,
+            referenced: referenced.parse().map_err(|_| parse_err("referenced"))?,
+            creation: creation.parse().map_err(|_| parse_err("creation"))?,
+        });
+    }
+
+    Ok(list)
+}
+
+impl Disks {
+    /// Create a new ZFS pool.
+    ///
+    /// Fails if a pool with the same name exists, or a disk is mounted, has holders, is a member
+    /// of an md array, an LVM physical volume, or used by a ZFS pool.
+    pub fn zpool_create(&self, create: &ZpoolCreate) -> Result<(), Error> {
+        create.validate()?;
+
+        let pools = self.zpool_list(None, true)?;
+        if pools.iter().any(|pool| pool.name == create.name) {
+            bail!("pool '{}' already exists", create.name);
+        }
+        self.check_disks_unused(create.all_devices(), &pools)?;
+
+        let mut command = std::process::Command::new("zpool");
+        command.args(create.command_args()?);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Add a vdev of the given class to an existing pool.
+    ///
+    /// Cache and spare devices can only be added with [`ZfsRaidLevel::Single`], dRAID vdevs are
+    /// not supported here. The disks are checked like for [`zpool_create`](Self::zpool_create).
+    pub fn zpool_add(
+        &self,
+        pool: &str,
+        class: ZfsVdevClass,
+        raid_level: ZfsRaidLevel,
+        disks: &[Disk],
+    ) -> Result<(), Error> {
+        if raid_level.is_draid() {
+            bail!("adding dRAID vdevs is not supported");
+        }
+        if matches!(class, ZfsVdevClass::Cache | ZfsVdevClass::Spare)
+            && raid_level != ZfsRaidLevel::Single
+        {
+            bail!("{class:?} devices cannot use {raid_level:?}");
+        }
+        validate_vdev_devices(raid_level, disks.len())?;
+
+        let pools = self.zpool_list(None, true)?;
+        find_pool(&pools, pool)?;
+        let devices = self.check_disks_unused(disks, &pools)?;
+
+        let mut command = std::process::Command::new("zpool");
+        command.args(["add", pool]);
+        if let Some(keyword) = class.keyword() {
+            command.arg(keyword);
+        }
+        command.args(vdev_args(raid_level, &devices));
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Replace a device of a pool with a new one.
+    ///
+    /// `old_device` must be a leaf device in the pool's vdev tree, either by path or by GUID for
+    /// missing devices. The new disk is checked like for [`zpool_create`](Self::zpool_create).
+    pub fn zpool_replace(
+        &self,
+        pool: &str,
+        old_device: &str,
+        new_disk: &Disk,
+    ) -> Result<(), Error> {
+        let pools = self.zpool_list(None, true)?;
+        find_pool(&pools, pool)?;
+        let new_device = self.check_disks_unused([new_disk], &pools)?.remove(0);
+
+        let vdevs = self.zpool_vdev_list(pool)?;
+        let index = vdevs
+            .iter()
+            .position(|vdev| vdev.lvl > 0 && same_device(&vdev.name, old_device))
+            .ok_or_else(|| format_err!("device '{old_device}' is not part of pool '{pool}'"))?;
+        let is_leaf = vdevs
+            .get(index + 1)
+            .is_none_or(|next| next.lvl <= vdevs[index].lvl);
+        if !is_leaf || vdevs[index].state.is_none() {
+            bail!("'{old_device}' is not a device of pool '{pool}'");
+        }
+
+        let mut command = std::process::Command::new("zpool");
+        command.args(["replace", pool, &vdevs[index].name, &new_device]);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Start a scrub of a pool.
+    pub fn zpool_scrub(&self, pool: &str) -> Result<(), Error> {
+        self.zpool_scrub_command(pool, None)
+    }
+
+    /// Stop a running scrub of a pool.
+    pub fn zpool_scrub_stop(&self, pool: &str) -> Result<(), Error> {
+        self.zpool_scrub_command(pool, Some("-s"))
+    }
+
+    /// Pause a running scrub of a pool, start it again with [`zpool_scrub`](Self::zpool_scrub).
+    pub fn zpool_scrub_pause(&self, pool: &str) -> Result<(), Error> {
+        self.zpool_scrub_command(pool, Some("-p"))
+    }
+
+    fn zpool_scrub_command(&self, pool: &str, flag: Option<&str>) -> Result<(), Error> {
+        validate_pool_name(pool)?;
+
+        let mut command = std::process::Command::new("zpool");
+        command.arg("scrub");
+        if let Some(flag) = flag {
+            command.arg(flag);
+        }
+        command.arg(pool);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Get the state of the current or last scrub or resilver of a pool.
+    pub fn zpool_scan_status(&self, pool: &str) -> Result<ZpoolScanStatus, Error> {
+        validate_pool_name(pool)?;
+
+        let status = self.zpool_status(pool)?;
+        let text = status
+            .iter()
+            .find(|(key, _)| key == "scan")
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("none requested");
+
+        Ok(parse_zpool_scan_status(text))
+    }
+
+    fn zpool_vdev_list(&self, pool: &str) -> Result<Vec<ZFSPoolVDevState>, Error> {
+        let status = self.zpool_status(pool)?;
+        let config = status
+            .iter()
+            .find(|(key, _)| key == "config")
+            .map(|(_, value)| value.as_str())
+            .ok_or_else(|| format_err!("zpool status for '{pool}' has no config"))?;
+
+        parse_zpool_status_config_tree(config)
+    }
+
+    /// Create a dataset (file system) with the given properties.
+    pub fn zfs_create_dataset(
+        &self,
+        dataset: &str,
+        properties: &[(&str, &str)],
+    ) -> Result<(), Error> {
+        validate_dataset_name(dataset)?;
+        if !dataset.contains('/') {
+            bail!("'{dataset}' is a pool, not a dataset");
+        }
+        for (property, value) in properties {
+            validate_property(property, value)?;
+        }
+        self.check_pool_of_dataset(dataset)?;
+
+        let mut command = std::process::Command::new("zfs");
+        command.arg("create");
+        for (property, value) in properties {
+            command.arg("-o").arg(format!("{property}={value}"));
+        }
+        command.arg(dataset);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Destroy a dataset, optionally including its children and snapshots.
+    ///
+    /// The root dataset of a pool cannot be destroyed.
+    pub fn zfs_destroy_dataset(&self, dataset: &str, recursive: bool) -> Result<(), Error> {
+        validate_dataset_name(dataset)?;
+        if !dataset.contains('/') {
+            bail!("refusing to destroy the root dataset of pool '{dataset}'");
+        }
+        self.check_pool_of_dataset(dataset)?;
+
+        let mut command = std::process::Command::new("zfs");
+        command.arg("destroy");
+        if recursive {
+            command.arg("-r");
+        }
+        command.arg(dataset);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Set a property of a dataset (or the root dataset of a pool).
+    pub fn zfs_set_property(
+        &self,
+        dataset: &str,
+        property: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        validate_dataset_name(dataset)?;
+        validate_property(property, value)?;
+        self.check_pool_of_dataset(dataset)?;
+
+        let mut command = std::process::Command::new("zfs");
+        command.args(["set", &format!("{property}={value}"), dataset]);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// List the snapshots of a dataset and its children, or of all datasets.
+    pub fn zfs_list_snapshots(&self, dataset: Option<&str>) -> Result<Vec<ZfsSnapshotInfo>, Error> {
+        let mut command = std::process::Command::new("zfs");
+        command.args([
+            "list",
+            "-H",
+            "-p",
+            "-t",
+            "snapshot",
+            "-o",
+            "name,used,refer,creation",
+        ]);
+        if let Some(dataset) = dataset {
+            validate_dataset_name(dataset)?;
+            command.args(["-r", dataset]);
+        }
+
+        let output = self.command_runner().run(command, None)?;
+
+        parse_zfs_snapshot_list(&output)
+    }
+
+    /// Create a snapshot of a dataset, optionally also of all its children.
+    pub fn zfs_create_snapshot(
+        &self,
+        dataset: &str,
+        snapshot: &str,
+        recursive: bool,
+    ) -> Result<(), Error> {
+        validate_dataset_name(dataset)?;
+        validate_name_component(snapshot, "snapshot")?;
+        self.check_pool_of_dataset(dataset)?;
+
+        let mut command = std::process::Command::new("zfs");
+        command.arg("snapshot");
+        if recursive {
+            command.arg("-r");
+        }
+        command.arg(format!("{dataset}@{snapshot}"));
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    /// Destroy a snapshot of a dataset.
+    pub fn zfs_destroy_snapshot(&self, dataset: &str, snapshot: &str) -> Result<(), Error> {
+        validate_dataset_name(dataset)?;
+        validate_name_component(snapshot, "snapshot")?;
+
+        let snapshots = self.zfs_list_snapshots(Some(dataset))?;
+        if !snapshots
+            .iter()
+            .any(|snap| snap.dataset == dataset && snap.name == snapshot)
+        {
+            bail!("snapshot '{dataset}@{snapshot}' does not exist");
+        }
+
+        let mut command = std::process::Command::new("zfs");
+        command.args(["destroy", &format!("{dataset}@{snapshot}")]);
+        self.command_runner().run(command, None)?;
+
+        Ok(())
+    }
+
+    fn check_pool_of_dataset(&self, dataset: &str) -> Result<(), Error> {
+        let pool = dataset.split('/').next().unwrap_or(dataset);
+        let pools = self.zpool_list(Some(pool), false)?;
+        find_pool(&pools, pool).map(drop)
+    }
+}
+
+fn find_pool<'a>(pools: &'a [ZFSPoolInfo], name: &str) -> Result<&'a ZFSPoolInfo, Error> {
+    // 'zpool list -v' also lists special, log and cache vdevs like pools, but without usage
+    pools
+        .iter()
+        .find(|pool| pool.name == name && pool.usage.is_some())
+        .ok_or_else(|| format_err!("pool '{name}' does not exist"))
+}
+
+/// Check whether two device paths refer to the same device, resolving symlinks if possible.
+fn same_device(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Fail if `device`, or one of its partitions, is used by a pool.
+pub(crate) fn check_device_unused(pools: &[ZFSPoolInfo], device: &str) -> Result<(), Error> {
+    let canonical = std::fs::canonicalize(device).ok();
+
+    for pool in pools {
+        for used in &pool.devices {
+            if same_device(used, device) {
+                bail!("device '{device}' is already used by pool '{}'", pool.name);
+            }
+            let used = std::fs::canonicalize(used).unwrap_or_else(|_| used.into());
+            let disk = canonical.as_deref().unwrap_or(Path::new(device));
+            if is_partition_of(&used, disk) {
+                bail!(
+                    "partition '{}' of device '{device}' is already used by pool '{}'",
+                    used.display(),
+                    pool.name,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether `partition` is a partition device node of `disk` (e.g. `/dev/sda1` of
+/// `/dev/sda`, or `/dev/nvme0n1p1` of `/dev/nvme0n1`).
+fn is_partition_of(partition: &Path, disk: &Path) -> bool {
+    let (partition, disk) = match (partition.to_str(), disk.to_str()) {
+        (Some(partition), Some(disk)) => (partition, disk),
+        _ => return false,
+    };
+    let suffix = match partition.strip_prefix(disk) {
+        Some(suffix) => suffix,
+        None => return false,
+    };
+    let number = suffix
+        .strip_prefix("-part")
+        .or_else(|| suffix.strip_prefix('p'))
+        .unwrap_or(suffix);
+    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+}
+
+fn vdev_args(raid_level: ZfsRaidLevel, devices: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    match raid_level {
+        ZfsRaidLevel::Single => (),
+        ZfsRaidLevel::Raid10 => {
+            for pair in devices.chunks(2) {
+                args.push("mirror".to_string());
+                args.extend(pair.iter().cloned());
+            }
+            return args;
+        }
+        ZfsRaidLevel::Mirror => args.push("mirror".into()),
+        ZfsRaidLevel::Raidz => args.push("raidz".into()),
+        ZfsRaidLevel::Raidz2 => args.push("raidz2".into()),
+        ZfsRaidLevel::Raidz3 => args.push("raidz3".into()),
+        ZfsRaidLevel::Draid | ZfsRaidLevel::Draid2 | ZfsRaidLevel::Draid3 => {
+            unreachable!("dRAID vdevs are handled separately")
+        }
+    }
+    args.extend(devices.iter().cloned());
+    args
+}
+
+fn validate_vdev_devices(raid_level: ZfsRaidLevel, devices: usize) -> Result<(), Error> {
+    if devices < raid_level.min_devices() {
+        bail!(
+            "{raid_level:?} needs at least {} devices",
+            raid_level.min_devices()
+        );
+    }
+    if raid_level == ZfsRaidLevel::Raid10 && !devices.is_multiple_of(2) {
+        bail!("{raid_level:?} needs an even number of devices");
+    }
+    Ok(())
+}
+
+fn device_paths(disks: &[Disk]) -> Result<Vec<String>, Error> {
+    disks.iter().map(disk_device_path).collect()
+}
+
+/// Validate a pool name, see `zpool create`.
+fn validate_pool_name(name: &str) -> Result<(), Error> {
+    validate_name_component(name, "pool")?;
+
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        bail!("pool name '{name}' must begin with a letter");
+    }
+    if ["log", "cache", "special", "dedup"].contains(&name)
+        || ["mirror", "raidz", "draid", "spare"]
+            .iter()
+            .any(|reserved| name.starts_with(reserved))
+    {
+        bail!("pool name '{name}' is reserved");
+    }
+    let mut chars = name.chars();
+    if chars.next() == Some('c') && chars.next().is_some_and(|c| c.is_ascii_digit()) {
+        bail!("pool name '{name}' is reserved");
+    }
+
+    Ok(())
+}
+
+/// Validate a dataset name, which is a pool name followed by '/' separated components.
+fn validate_dataset_name(name: &str) -> Result<(), Error> {
+    let mut components = name.split('/');
+    validate_pool_name(components.next().unwrap_or_default())?;
+    for component in components {
+        validate_name_component(component, "dataset")?;
+    }
+    Ok(())
+}
+
+fn validate_name_component(name: &str, kind: &str) -> Result<(), Error> {
+    if name.is_empty() || name.len() > 255 {
+        bail!("invalid {kind} name '{name}'");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':' | '.'))
+    {
+        bail!("invalid character in {kind} name '{name}'");
+    }
+    Ok(())
+}
+
+fn validate_property(property: &str, value: &str) -> Result<(), Error> {
+    if property.is_empty()
+        || !property
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '.' | '-'))
+    {
+        bail!("invalid property name '{property}'");
+    }
+    if value.contains(['\n', '\0']) {
+        bail!("invalid value for property '{property}'");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Create a fake sysfs with the block devices `a` to `k`.
+    fn fake_disks(name: &str) -> Arc<Disks> {
+        let sysfs =
+            std::env::temp_dir().join(format!("proxmox-disks-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&sysfs);
+        for (minor, name) in ('a'..='k').enumerate() {
+            let dev_dir = sysfs.join("class/block").join(name.to_string());
+            std::fs::create_dir_all(&dev_dir).unwrap();
+            std::fs::write(
+                dev_dir.join("uevent"),
+                format!("MAJOR=8\nMINOR={}\nDEVNAME={name}\n", minor * 16),
+            )
+            .unwrap();
+        }
+        Disks::new().with_sysfs_root(sysfs).into_arc()
+    }
+
+    #[test]
+    fn test_zpool_create_args() -> Result<(), Error> {
+        let disks = fake_disks("zpool-create-args");
+        let devices = |names: &[&str]| -> Vec<Disk> {
+            names
+                .iter()
+                .map(|name| disks.partition_by_name(name).unwrap())
+                .collect()
+        };
+
+        let create = ZpoolCreate::new("tank", ZfsRaidLevel::Raid10, devices(&["a", "b", "c", "d"]))
+            .special(ZfsRaidLevel::Mirror, devices(&["e", "f"]))
+            .log(ZfsRaidLevel::Raid10, devices(&["g", "i", "j", "k"]))
+            .cache(devices(&["h"]))
+            .ashift(12)
+            .property("compression", "on");
+        create.validate()?;
+        assert_eq!(
+            create.command_args()?.join(" "),
+            "create -o ashift=12 -O compression=on tank mirror /dev/a /dev/b mirror /dev/c /dev/d \
+             special mirror /dev/e /dev/f log mirror /dev/g /dev/i mirror /dev/j /dev/k cache /dev/h"
+        );
+
+        // a special vdev laid out differently than the data vdevs needs to be forced
+        let create = ZpoolCreate::new("tank", ZfsRaidLevel::Raidz, devices(&["a", "b", "c"]))
+            .special(ZfsRaidLevel::Mirror, devices(&["d", "e"]))
+            .log(ZfsRaidLevel::Mirror, devices(&["f", "g"]));
+        assert!(create.validate().is_err());
+        let create = create.force(true);
+        create.validate()?;
+        assert_eq!(
+            create.command_args()?.join(" "),
+            "create -f tank raidz /dev/a /dev/b /dev/c special mirror /dev/d /dev/e \
+             log mirror /dev/f /dev/g"
+        );
+
+        // striped special devices for a striped pool
+        let create = ZpoolCreate::new("tank", ZfsRaidLevel::Single, devices(&["a", "b"]))
+            .special(ZfsRaidLevel::Single, devices(&["c", "d"]));
+        create.validate()?;
+        assert_eq!(
+            create.command_args()?.join(" "),
+            "create tank /dev/a /dev/b special /dev/c /dev/d"
+        );
+
+        let create = ZpoolCreate::new(
+            "tank",
+            ZfsRaidLevel::Draid2,
+            devices(&["a", "b", "c", "d", "e"]),
+        )
+        .draid(Some(2), 1);
+        create.validate()?;
+        assert_eq!(
+            create.command_args()?.join(" "),
+            "create tank draid2:2d:1s /dev/a /dev/b /dev/c /dev/d /dev/e"
+        );
+
+        std::fs::remove_dir_all(disks.sysfs_root())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zpool_create_validate() {
+        let disks = fake_disks("zpool-create-validate");
+        let devices = |names: &[&str]| -> Vec<Disk> {
+            names
+                .iter()
+                .map(|name| disks.partition_by_name(name).unwrap())
+                .collect()
+        };
+        let check = |create: ZpoolCreate| create.validate().is_ok();
+
+        assert!(check(ZpoolCreate::new(
+            "tank",
+            ZfsRaidLevel::Single,
+            devices(&["a"])
+        )));
+        assert!(!check(ZpoolCreate::new(
+            "tank",
+            ZfsRaidLevel::Mirror,
+            devices(&["a"])
+        )));
+        assert!(!check(ZpoolCreate::new(
+            "tank",
+            ZfsRaidLevel::Raid10,
+            devices(&["a", "b", "c", "d", "e"])
+        )));
+        assert!(check(ZpoolCreate::new(
+            "tank",
+            ZfsRaidLevel::Raidz,
+            devices(&["a", "b", "c"])
+        )));
+        assert!(!check(ZpoolCreate::new(
+            "tank",
+            ZfsRaidLevel::Raidz3,
+            devices(&["a", "b", "c", "d"])
+        )));
+        assert!(!check(
+            ZpoolCreate::new("tank", ZfsRaidLevel::Draid, devices(&["a", "b"])).draid(Some(1), 1)
+        ));
+        assert!(!check(
+            ZpoolCreate::new("tank", ZfsRaidLevel::Mirror, devices(&["a", "b"])).draid(None, 1)
+        ));
+
+        // special and log vdevs need at least the redundancy of the data vdev
+        let raidz2 =
+            || ZpoolCreate::new("tank", ZfsRaidLevel::Raidz2, devices(&["a", "b", "c", "d"]));
+        assert!(!check(
+            raidz2().special(ZfsRaidLevel::Single, devices(&["e"]))
+        ));
+        assert!(!check(
+            raidz2().special(ZfsRaidLevel::Mirror, devices(&["e", "f"]))
+        ));
+        assert!(!check(
+            raidz2().log(ZfsRaidLevel::Raid10, devices(&["e", "f", "g", "h"]))
+        ));
+        assert!(check(
+            raidz2()
+                .special(ZfsRaidLevel::Mirror, devices(&["e", "f", "g"]))
+                .force(true)
+        ));
+        assert!(check(
+            raidz2().log(ZfsRaidLevel::Mirror, devices(&["e", "f", "g"]))
+        ));
+        assert!(!check(
+            raidz2().log(ZfsRaidLevel::Raidz, devices(&["e", "f", "g"]))
+        ));
+        assert!(!check(
+            ZpoolCreate::new("tank", ZfsRaidLevel::Single, devices(&["a"]))
+                .special(ZfsRaidLevel::Mirror, devices(&["b"]))
+        ));
+        assert!(!check(
+            ZpoolCreate::new("tank", ZfsRaidLevel::Single, devices(&["a"])).ashift(20)
+        ));
+        assert!(!check(ZpoolCreate::new(
+            "mirror1",
+            ZfsRaidLevel::Single,
+            devices(&["a"])
+        )));
+        assert!(!check(ZpoolCreate::new(
+            "c0d0",
+            ZfsRaidLevel::Single,
+            devices(&["a"])
+        )));
+        assert!(!check(ZpoolCreate::new(
+            "1tank",
+            ZfsRaidLevel::Single,
+            devices(&["a"])
+        )));
+        assert!(!check(ZpoolCreate::new(
+            "ta/nk",
+            ZfsRaidLevel::Single,
+            devices(&["a"])
+        )));
+
+        std::fs::remove_dir_all(disks.sysfs_root()).unwrap();
+    }
+
+    #[test]
+    fn test_is_partition_of() {
+        let check = |part: &str, disk: &str| is_partition_of(Path::new(part), Path::new(disk));
+
+        assert!(check("/dev/sda1", "/dev/sda"));
+        assert!(check("/dev/nvme0n1p3", "/dev/nvme0n1"));
+        assert!(check(
+            "/dev/disk/by-id/ata-X-part1",
+            "/dev/disk/by-id/ata-X"
+        ));
+        assert!(!check("/dev/sda", "/dev/sda"));
+        assert!(!check("/dev/sdab", "/dev/sda"));
+        assert!(!check("/dev/sdb1", "/dev/sda"));
+    }
+
+    #[test]
+    fn test_parse_zpool_scan_status() {
+        let status = parse_zpool_scan_status(
+            "scrub in progress since Sun Oct 11 00:24:01 2026\n\
+             1.23T scanned at 1.10G/s, 512G issued at 400M/s, 3.50T total\n\
+             0B repaired, 14.29% done, 02:03:04 to go",
+        );
+        assert_eq!(status.function.as_deref(), Some("scrub"));
+        assert_eq!(status.state, ZpoolScanState::Running);
+        assert_eq!(status.percent_done, Some(14.29));
+        assert_eq!(status.errors, None);
+
+        let status = parse_zpool_scan_status(
+            "scrub repaired 0B in 02:11:42 with 3 errors on Sun Oct 11 02:35:43 2026",
+        );
+        assert_eq!(status.state, ZpoolScanState::Finished);
+        assert_eq!(status.errors, Some(3));
+
+        let status = parse_zpool_scan_status("resilver in progress since Mon Oct 12 10:01:49 2026");
+        assert_eq!(status.function.as_deref(), Some("resilver"));
+        assert_eq!(status.state, ZpoolScanState::Running);
+
+        let status = parse_zpool_scan_status("scrub canceled on Mon Oct 12 10:01:49 2026");
+        assert_eq!(status.state, ZpoolScanState::Canceled);
+
+        let status = parse_zpool_scan_status(
+            "scrub paused since Mon Oct 12 10:01:49 2026\n\
+             scrub started on Mon Oct 12 09:00:00 2026",
+        );
+        assert_eq!(status.state, ZpoolScanState::Paused);
+
+        let status = parse_zpool_scan_status("none requested");
+        assert_eq!(status.function, None);
+        assert_eq!(status.state, ZpoolScanState::None);
+    }
+
+    #[test]
+    fn test_parse_zfs_snapshot_list() -> Result<(), Error> {
+        let output = "tank/data@daily-1\t1048576\t8589934592\t1791763200\n\
+                      tank/data/sub@daily-1\t0\t4096\t1791763201\n";
+        let list = parse_zfs_snapshot_list(output)?;
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].dataset, "tank/data");
+        assert_eq!(list[0].name, "daily-1");
+        assert_eq!(list[0].used, 1048576);
+        assert_eq!(list[0].referenced, 8589934592);
+        assert_eq!(list[1].creation, 1791763201);
+
+        assert!(parse_zfs_snapshot_list("tank/data\t0\t0\t0\n").is_err());
+        assert!(parse_zfs_snapshot_list("tank/data@a\t-\t0\t0\n").is_err());
+        assert!(parse_zfs_snapshot_list("")?.is_empty());
+
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Error, bail};
 
+use crate::Disks;
 use crate::parse_helpers::{IResult, notspace1};
 
 use nom::{
@@ -139,26 +140,33 @@ fn parse_zpool_list(i: &str) -> Result<Vec<ZFSPoolInfo>, Error> {
 /// Devices are only included when run with verbose flags
 /// set. Without, device lists are empty.
 pub fn zpool_list(pool: Option<&str>, verbose: bool) -> Result<Vec<ZFSPoolInfo>, Error> {
-    // Note: zpools list verbose output can include entries for 'special', 'cache' and 'logs'
-    // and maybe other things.
+    Disks::new().zpool_list(pool, verbose)
+}
 
-    let mut command = std::process::Command::new("zpool");
-    command.args(["list", "-H", "-p", "-P"]);
+impl Disks {
+    /// Run zpool list and return parsed output, see [`zpool_list`].
+    pub fn zpool_list(&self, pool: Option<&str>, verbose: bool) -> Result<Vec<ZFSPoolInfo>, Error> {
+        // Note: zpools list verbose output can include entries for 'special', 'cache' and 'logs'
+        // and maybe other things.
 
-    // Note: We do not use -o to define output properties, because zpool command ignores
-    // that completely for special vdevs and devices
+        let mut command = std::process::Command::new("zpool");
+        command.args(["list", "-H", "-p", "-P"]);
 
-    if verbose {
-        command.arg("-v");
-    }
+        // Note: We do not use -o to define output properties, because zpool command ignores
+        // that completely for special vdevs and devices
 
-    if let Some(pool) = pool {
-        command.arg(pool);
-    }
+        if verbose {
+            command.arg("-v");
+        }
 
-    let output = proxmox_sys::command::run_command(command, None)?;
+        if let Some(pool) = pool {
+            command.arg(pool);
+        }
+
+        let output = self.command_runner().run(command, None)?;
 
-    parse_zpool_list(&output)
+        parse_zpool_list(&output)
+    }
 }
 
 #[test]
@@ -4,6 +4,7 @@ use anyhow::{Error, bail};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
+use crate::Disks;
 use crate::parse_helpers::{IResult, notspace1, parse_complete, parse_error, parse_failure};
 
 use nom::{
@@ -367,12 +368,19 @@ fn test_vdev_list_to_tree() {
 }
 
 pub fn zpool_status(pool: &str) -> Result<Vec<(String, String)>, Error> {
-    let mut command = std::process::Command::new("zpool");
-    command.args(["status", "-p", "-P", pool]);
+    Disks::new().zpool_status(pool)
+}
+
+impl Disks {
+    /// Run `zpool status` for a pool and return the parsed key/value pairs.
+    pub fn zpool_status(&self, pool: &str) -> Result<Vec<(String, String)>, Error> {
+        let mut command = std::process::Command::new("zpool");
+        command.args(["status", "-p", "-P", pool]);
 
-    let output = proxmox_sys::command::run_command(command, None)?;
+        let output = self.command_runner().run(command, None)?;
 
-    parse_zpool_status(&output)
+        parse_zpool_status(&output)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,909 @@
+#![cfg(all(feature = "smart", feature = "discovery", feature = "operations"))]
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Error, bail};
+
+use proxmox_disks::{
+    DEFAULT_SMART_TIMEOUT, Disk, DiskType, DiskUsageQuery, DiskUsageType, Disks, MdMemberState,
+    MdRaidLevel, MdSyncAction, RecordedCommandRunner, SmartStatus, ZfsRaidLevel, ZfsVdevClass,
+    ZpoolCreate, ZpoolScanState, parse_zpool_status_config_tree,
+};
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("failed to read {path:?} - {err}"))
+}
+
+fn disks_with(runner: RecordedCommandRunner) -> (Disks, Arc<RecordedCommandRunner>) {
+    let runner = Arc::new(runner);
+    (Disks::new().with_command_runner(runner.clone()), runner)
+}
+
+#[test]
+fn test_smart_nvme() -> Result<(), Error> {
+    let (disks, _) = disks_with(RecordedCommandRunner::new().with_output(
+        "smartctl -H -A -j /dev/nvme0n1",
+        fixture("smartctl/nvme.json"),
+    ));
+
+    let data = disks.smart_data(Path::new("/dev/nvme0n1"), false, DEFAULT_SMART_TIMEOUT)?;
+
+    assert_eq!(data.status, SmartStatus::Passed);
+    assert_eq!(data.wearout, Some(97.0));
+    let attribute = data
+        .attributes
+        .iter()
+        .find(|attr| attr.name == "media_errors")
+        .expect("missing media_errors attribute");
+    assert_eq!(attribute.raw, "0");
+    assert_eq!(attribute.id, None);
+    // arrays like 'temperature_sensors' are not attributes
+    assert!(
+        !data
+            .attributes
+            .iter()
+            .any(|attr| attr.name == "temperature_sensors")
+    );
+
+    let nvme = data.nvme.as_ref().expect("missing NVMe health log");
+    assert_eq!(nvme.percentage_used, Some(3));
+    assert_eq!(nvme.unsafe_shutdowns, Some(37));
+    assert_eq!(nvme.temperature, Some(38));
+    assert_eq!(nvme.temperature_sensors, [38, 45]);
+    assert_eq!(data.media_errors(), Some(0));
+    assert_eq!(data.reallocated_sectors(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_smart_sata() -> Result<(), Error> {
+    let (disks, _) = disks_with(RecordedCommandRunner::new().with_output(
+        "smartctl -H -A -j /dev/sda",
+        fixture("smartctl/sata-ssd.json"),
+    ));
+
+    let data = disks.smart_data(Path::new("/dev/sda"), false, DEFAULT_SMART_TIMEOUT)?;
+
+    assert_eq!(data.status, SmartStatus::Passed);
+    // 'Media_Wearout_Indicator' takes precedence over 'Wear_Leveling_Count'
+    assert_eq!(data.wearout, Some(97.0));
+    assert_eq!(data.attributes.len(), 5);
+
+    let attribute = &data.attributes[1];
+    assert_eq!(attribute.name, "Power_On_Hours");
+    assert_eq!(attribute.id, Some(9));
+    assert_eq!(attribute.raw, "24571");
+    assert_eq!(attribute.flags.as_deref(), Some("-O--CK "));
+    assert_eq!(attribute.normalized, Some(95.0));
+
+    assert_eq!(data.reallocated_sectors(), Some(0));
+    assert_eq!(data.pending_sectors(), None);
+    assert!(data.nvme.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_smart_sas() -> Result<(), Error> {
+    // exit code 4 (some SMART command failed) is not fatal
+    let (disks, _) = disks_with(RecordedCommandRunner::new().with_exit_code(
+        "smartctl -H -A -j /dev/sdb",
+        4,
+        fixture("smartctl/sas.json"),
+        "",
+    ));
+
+    let data = disks.smart_data(Path::new("/dev/sdb"), false, DEFAULT_SMART_TIMEOUT)?;
+
+    assert_eq!(data.status, SmartStatus::Passed);
+    assert_eq!(data.wearout, None);
+    assert!(data.attributes.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_smart_failing() -> Result<(), Error> {
+    // exit code 8 (disk failing) is not fatal
+    let (disks, _) = disks_with(
+        RecordedCommandRunner::new()
+            .with_exit_code(
+                "smartctl -H -j /dev/sdc",
+                8,
+                fixture("smartctl/sata-failing.json"),
+                "",
+            )
+            .with_exit_code(
+                "smartctl -H -j /dev/sdd",
+                2,
+                "",
+                "Smartctl open device failed",
+            ),
+    );
+
+    let data = disks.smart_data(Path::new("/dev/sdc"), true, DEFAULT_SMART_TIMEOUT)?;
+    assert_eq!(data.status, SmartStatus::Failed);
+
+    let err = disks
+        .smart_data(Path::new("/dev/sdd"), true, DEFAULT_SMART_TIMEOUT)
+        .unwrap_err();
+    assert!(err.to_string().contains("exit code 2"));
+
+    Ok(())
+}
+
+#[test]
+fn test_zpool_status_degraded() -> Result<(), Error> {
+    let (disks, _) = disks_with(RecordedCommandRunner::new().with_output(
+        "zpool status -p -P tank",
+        fixture("zpool/status-degraded.txt"),
+    ));
+
+    let status = disks.zpool_status("tank")?;
+
+    let get = |key: &str| {
+        status
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    assert_eq!(get("state"), Some("DEGRADED"));
+    assert!(get("action").unwrap().contains("zpool replace"));
+
+    let vdevs = parse_zpool_status_config_tree(get("config").unwrap())?;
+    let states: Vec<(&str, u64, Option<&str>)> = vdevs
+        .iter()
+        .map(|vdev| (vdev.name.as_str(), vdev.lvl, vdev.state.as_deref()))
+        .collect();
+    assert_eq!(
+        states[..4],
+        [
+            ("tank", 0, Some("DEGRADED")),
+            ("raidz2-0", 1, Some("DEGRADED")),
+            (
+                "/dev/disk/by-id/ata-ST8000VN004-2M2101_WKD0A1B2-part1",
+                2,
+                Some("ONLINE")
+            ),
+            (
+                "/dev/disk/by-id/ata-ST8000VN004-2M2101_WKD0A1B3-part1",
+                2,
+                Some("ONLINE")
+            ),
+        ]
+    );
+    let missing = &vdevs[4];
+    assert_eq!(missing.name, "1302948383829101827");
+    assert_eq!(missing.state.as_deref(), Some("UNAVAIL"));
+    assert_eq!(
+        missing.msg.as_deref(),
+        Some("was /dev/disk/by-id/ata-ST8000VN004-2M2101_WKD0A1B4-part1")
+    );
+    assert!(vdevs.iter().any(|vdev| vdev.name == "logs"));
+    assert!(vdevs.iter().any(|vdev| vdev.name == "cache"));
+
+    Ok(())
+}
+
+#[test]
+fn test_zpool_status_multipath() -> Result<(), Error> {
+    let (disks, _) = disks_with(RecordedCommandRunner::new().with_output(
+        "zpool status -p -P san",
+        fixture("zpool/status-multipath.txt"),
+    ));
+
+    let status = disks.zpool_status("san")?;
+    let config = match status.iter().find(|(k, _)| k == "config") {
+        Some((_, config)) => config,
+        None => bail!("missing config"),
+    };
+
+    let vdevs = parse_zpool_status_config_tree(config)?;
+    let devices: Vec<&str> = vdevs
+        .iter()
+        .filter(|vdev| vdev.name.starts_with("/dev/mapper/"))
+        .map(|vdev| vdev.name.as_str())
+        .collect();
+    assert_eq!(
+        devices,
+        [
+            "/dev/mapper/mpatha",
+            "/dev/mapper/mpathb",
+            "/dev/mapper/mpathc",
+            "/dev/mapper/mpathd",
+            "/dev/mapper/mpathe",
+        ]
+    );
+
+    let spare = vdevs.last().unwrap();
+    assert_eq!(spare.state.as_deref(), Some("AVAIL"));
+
+    Ok(())
+}
+
+#[test]
+fn test_zpool_list_degraded() -> Result<(), Error> {
+    let (disks, _) = disks_with(RecordedCommandRunner::new().with_output(
+        "zpool list -H -p -P -v tank",
+        fixture("zpool/list-degraded.txt"),
+    ));
+
+    let list = disks.zpool_list(Some("tank"), true)?;
+    assert_eq!(list.len(), 2);
+
+    assert_eq!(list[0].name, "tank");
+    assert_eq!(list[0].health, "DEGRADED");
+    assert_eq!(list[0].usage.as_ref().map(|usage| usage.frag), Some(12));
+    assert_eq!(
+        list[0].devices,
+        [
+            "/dev/disk/by-id/ata-ST8000VN004-2M2101_WKD0A1B2-part1",
+            "/dev/disk/by-id/ata-ST8000VN004-2M2101_WKD0A1B3-part1",
+            "/dev/disk/by-id/ata-ST8000VN004-2M2101_WKD0A1B5-part1",
+        ]
+    );
+
+    assert_eq!(list[1].name, "logs");
+    assert_eq!(
+        list[1].devices,
+        ["/dev/disk/by-id/nvme-Samsung_SSD_970_EVO_Plus_500GB_S4EVNX0N123456-part1"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_disk_usage_query_fake_sysfs() -> Result<(), Error> {
+    // fake sysfs with a SATA disk with one partition, and an iSCSI disk which is skipped
+    let sysfs = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fake-sysfs");
+    let _ = std::fs::remove_dir_all(&sysfs);
+    std::fs::create_dir_all(sysfs.join("block"))?;
+
+    let sata = "devices/pci0000:00/0000:00:1f.2/ata1/host0/target0:0:0/0:0:0:0";
+    let disk = sysfs.join(sata).join("block/sdy");
+    std::fs::create_dir_all(disk.join("queue"))?;
+    std::fs::create_dir_all(disk.join("holders"))?;
+    std::fs::create_dir_all(disk.join("device"))?;
+    std::fs::write(sysfs.join(sata).join("uevent"), "DEVTYPE=scsi_device\n")?;
+    std::fs::write(
+        disk.join("uevent"),
+        "MAJOR=65\nMINOR=128\nDEVNAME=sdy\nDEVTYPE=disk\n",
+    )?;
+    std::fs::write(disk.join("size"), "1953525168\n")?;
+    std::fs::write(disk.join("queue/rotational"), "1\n")?;
+    std::fs::write(disk.join("device/vendor"), "ATA     \n")?;
+    std::fs::create_dir_all(disk.join("sdy1/holders"))?;
+    std::fs::write(
+        disk.join("sdy1/uevent"),
+        "MAJOR=65\nMINOR=129\nDEVNAME=sdy1\nDEVTYPE=partition\nPARTN=1\n",
+    )?;
+    std::fs::write(disk.join("sdy1/partition"), "1\n")?;
+    std::fs::write(disk.join("sdy1/size"), "1953523055\n")?;
+    std::os::unix::fs::symlink(format!("../{sata}/block/sdy"), sysfs.join("block/sdy"))?;
+
+    std::os::unix::fs::symlink(
+        "../devices/platform/host3/session1/target3:0:0/3:0:0:0/block/sdx",
+        sysfs.join("block/sdx"),
+    )?;
+
+    let runner = Arc::new(
+        RecordedCommandRunner::new()
+            .with_output(
+                "lsblk --json -o path,parttype,fstype,uuid",
+                r#"{"blockdevices": []}"#,
+            )
+            .with_exit_code(
+                "zpool list -H -p -P -v",
+                127,
+                "",
+                "zpool: command not found",
+            )
+            .with_output(
+                "pvs --reportformat json --noheadings --readonly --segments -o pv_name,vg_name,lv_name",
+                r#"{"report": [{"pvseg": []}]}"#,
+            ),
+    );
+    let disks = Disks::new()
+        .with_command_runner(runner.clone())
+        .with_sysfs_root(&sysfs)
+        .into_arc();
+
+    let disk = disks.disk_by_name("sdy")?;
+    assert_eq!(disk.devnum()?, libc::makedev(65, 128));
+    assert_eq!(disk.device_path(), Some(Path::new("/dev/sdy")));
+    assert!(disk.syspath().ends_with("block/sdy"));
+    let parent = disk.parent().expect("disk without parent device");
+    assert!(parent.syspath().ends_with("0:0:0:0"));
+
+    let result = DiskUsageQuery::new()
+        .disks(disks)
+        .partitions(true)
+        .query()?;
+    assert_eq!(result.len(), 1);
+
+    let info = &result["sdy"];
+    assert_eq!(info.size, 1953525168 * 512);
+    assert_eq!(info.disk_type, DiskType::Hdd);
+    assert_eq!(info.used, DiskUsageType::Partitions);
+    assert_eq!(info.vendor.as_deref(), Some("ATA"));
+    assert_eq!(info.devpath.as_deref(), Some("/dev/sdy"));
+    let partitions = info.partitions.as_ref().expect("partitions not listed");
+    assert_eq!(partitions.len(), 1);
+    assert_eq!(partitions[0].name, "sdy1");
+    assert_eq!(partitions[0].size, Some(1953523055 * 512));
+
+    assert_eq!(
+        runner.calls(),
+        [
+            "lsblk --json -o path,parttype,fstype,uuid",
+            "zpool list -H -p -P -v",
+            "pvs --reportformat json --noheadings --readonly --segments -o pv_name,vg_name,lv_name",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_mount_operations() -> Result<(), Error> {
+    let (disks, runner) = disks_with(
+        RecordedCommandRunner::new()
+            .with_output("mount UUID=4e2c91f3 /mnt/datastore/test", "")
+            .with_output("mount --bind /mnt/datastore/test /srv/test", "")
+            .with_exit_code(
+                "umount /srv/test",
+                32,
+                "",
+                "umount: /srv/test: not mounted.",
+            ),
+    );
+
+    disks.mount_by_uuid("4e2c91f3", Path::new("/mnt/datastore/test"))?;
+    disks.bind_mount(Path::new("/mnt/datastore/test"), Path::new("/srv/test"))?;
+    let err = disks
+        .unmount_by_mountpoint(Path::new("/srv/test"))
+        .unwrap_err();
+    assert!(err.to_string().contains("not mounted"));
+
+    assert_eq!(runner.calls().len(), 3);
+
+    Ok(())
+}
+
+const VGS: &str = "vgs --reportformat json --units b --nosuffix --readonly -o vg_name,vg_size,vg_free,pv_count,lv_count";
+const PVS: &str =
+    "pvs --reportformat json --units b --nosuffix --readonly -o pv_name,vg_name,pv_size,pv_free";
+const LVS: &str = "lvs --reportformat json --units b --nosuffix --readonly -o lv_name,vg_name,lv_size,segtype,pool_lv,data_percent,metadata_percent,lv_metadata_size";
+
+fn lvs_with_vg(vg: &str) -> String {
+    format!("{LVS} {vg}")
+}
+
+#[test]
+fn test_lvm_listing() -> Result<(), Error> {
+    let (disks, _) = disks_with(
+        RecordedCommandRunner::new()
+            .with_output(VGS, fixture("lvm/vgs.json"))
+            .with_output(PVS, fixture("lvm/pvs.json"))
+            .with_output(&lvs_with_vg("pve"), fixture("lvm/lvs-pve.json")),
+    );
+
+    let vgs = disks.lvm_volume_groups()?;
+    assert_eq!(vgs.len(), 2);
+    assert_eq!(vgs[0].name, "pve");
+    assert_eq!(vgs[0].free, 16710107136);
+    assert_eq!(vgs[1].pv_count, 2);
+
+    let pvs = disks.lvm_physical_volumes()?;
+    assert_eq!(pvs[0].vg.as_deref(), Some("pve"));
+    assert_eq!(pvs[3].vg, None);
+
+    let pools = disks.lvm_thin_pools(Some("pve"))?;
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].name, "data");
+    assert_eq!(pools[0].data_percent, Some(23.41));
+    assert_eq!(pools[0].metadata_percent, Some(1.86));
+
+    let lvs = disks.lvm_logical_volumes(Some("pve"))?;
+    assert_eq!(lvs.len(), 5);
+    assert!(
+        lvs.iter()
+            .filter(|lv| lv.segment_type == "thin")
+            .all(|lv| lv.pool.as_deref() == Some("data"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lvm_thin_pool_operations() -> Result<(), Error> {
+    let (disks, runner) = disks_with(
+        RecordedCommandRunner::new()
+            .with_output(VGS, fixture("lvm/vgs.json"))
+            .with_output(&lvs_with_vg("pve"), fixture("lvm/lvs-pve.json"))
+            .with_output(&lvs_with_vg("scratch"), r#"{"report": [{"lv": []}]}"#)
+            .with_output(
+                "lvcreate --type thin-pool -L970397039763b --poolmetadatasize 9902010609b -n data scratch",
+                "",
+            )
+            .with_output("lvextend -L+10737418240b pve/data", "")
+            .with_output("lvextend -l +100%FREE pve/data", ""),
+    );
+
+    disks.lvm_create_thin_pool("scratch", "data", None)?;
+    disks.lvm_extend_thin_pool("pve", "data", Some(10 * 1024 * 1024 * 1024))?;
+    disks.lvm_extend_thin_pool("pve", "data", None)?;
+
+    let err = disks.lvm_create_thin_pool("pve", "data", None).unwrap_err();
+    assert!(err.to_string().contains("already exists"));
+    let err = disks
+        .lvm_create_thin_pool("pve", "data2", Some(20_000_000_000))
+        .unwrap_err();
+    assert!(err.to_string().contains("bytes free"));
+    let err = disks.lvm_extend_thin_pool("pve", "root", None).unwrap_err();
+    assert!(err.to_string().contains("not a thin pool"));
+    let err = disks.lvm_remove_thin_pool("pve", "data").unwrap_err();
+    assert!(err.to_string().contains("still contains 2 volume(s)"));
+    assert!(disks.lvm_remove_thin_pool("missing", "data").is_err());
+    assert!(
+        disks
+            .lvm_create_thin_pool("scratch", "-data", None)
+            .is_err()
+    );
+
+    let mutating: Vec<String> = runner
+        .calls()
+        .into_iter()
+        .filter(|call| !call.starts_with("vgs ") && !call.starts_with("lvs "))
+        .collect();
+    assert_eq!(
+        mutating,
+        [
+            "lvcreate --type thin-pool -L970397039763b --poolmetadatasize 9902010609b -n data scratch",
+            "lvextend -L+10737418240b pve/data",
+            "lvextend -l +100%FREE pve/data",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lvm_remove_volume_group() -> Result<(), Error> {
+    let (disks, runner) = disks_with(
+        RecordedCommandRunner::new()
+            .with_output(VGS, fixture("lvm/vgs.json"))
+            .with_output(PVS, fixture("lvm/pvs.json"))
+            .with_output("vgremove scratch", "")
+            .with_output("pvremove /dev/sdb /dev/sdc", ""),
+    );
+
+    let err = disks.lvm_remove_volume_group("pve").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("still contains 5 logical volume(s)")
+    );
+    assert!(disks.lvm_remove_volume_group("missing").is_err());
+
+    disks.lvm_remove_volume_group("scratch")?;
+
+    assert_eq!(
+        runner.calls()[runner.calls().len() - 2..],
+        ["vgremove scratch", "pvremove /dev/sdb /dev/sdc"]
+    );
+
+    Ok(())
+}
+
+/// Create a fake procfs and sysfs with a degraded md array `md0` rebuilding onto `sdc1`, a clean
+/// array `md1`, and some block devices to create arrays or pools from.
+fn fake_md_roots(name: &str) -> Result<(PathBuf, PathBuf), Error> {
+    let base = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&base);
+    let (procfs, sysfs) = (base.join("proc"), base.join("sys"));
+
+    std::fs::create_dir_all(&procfs)?;
+    std::fs::write(procfs.join("mdstat"), fixture("mdraid/mdstat"))?;
+
+    let md_dir = sysfs.join("block/md0/md");
+    for (member, state, slot) in [
+        ("sda1", "in_sync", "0"),
+        ("sdb1", "faulty", "none"),
+        ("sdc1", "spare", "1"),
+    ] {
+        let dev_dir = md_dir.join(format!("dev-{member}"));
+        std::fs::create_dir_all(&dev_dir)?;
+        std::fs::write(dev_dir.join("state"), format!("{state}\n"))?;
+        std::fs::write(dev_dir.join("slot"), format!("{slot}\n"))?;
+    }
+    std::fs::write(md_dir.join("array_state"), "clean\n")?;
+    std::fs::write(md_dir.join("degraded"), "1\n")?;
+
+    // sdj is held by a device mapper device
+    for (minor, name) in [
+        (1, "sda1"),
+        (48, "sdd"),
+        (81, "sdf1"),
+        (97, "sdg1"),
+        (112, "sdh"),
+        (128, "sdi"),
+        (144, "sdj"),
+        (160, "sdk"),
+        (176, "sdl"),
+        (192, "sdm"),
+    ] {
+        let dev_dir = sysfs.join("class/block").join(name);
+        std::fs::create_dir_all(dev_dir.join("holders"))?;
+        std::fs::write(
+            dev_dir.join("uevent"),
+            format!("MAJOR=65\nMINOR={minor}\nDEVNAME={name}\n"),
+        )?;
+    }
+    std::fs::write(sysfs.join("class/block/sdj/holders/dm-0"), "")?;
+
+    Ok((procfs, sysfs))
+}
+
+#[test]
+fn test_md_arrays() -> Result<(), Error> {
+    let (procfs, sysfs) = fake_md_roots("md-arrays")?;
+    let disks = Disks::new()
+        .with_procfs_root(&procfs)
+        .with_sysfs_root(&sysfs);
+
+    let arrays = disks.md_arrays()?;
+    assert_eq!(arrays.len(), 2);
+
+    let md0 = &arrays[0];
+    assert_eq!(md0.level.as_deref(), Some("raid1"));
+    assert_eq!(md0.array_state.as_deref(), Some("clean"));
+    assert!(md0.degraded);
+    assert_eq!(md0.member("sda1").unwrap().slot, Some(0));
+    assert_eq!(md0.member("sdb1").unwrap().state, MdMemberState::Faulty);
+    assert_eq!(md0.member("sdc1").unwrap().state, MdMemberState::Rebuilding);
+    let sync = md0.sync.as_ref().unwrap();
+    assert_eq!(sync.action, MdSyncAction::Recovery);
+    assert_eq!(sync.progress, Some(27.3));
+
+    // no sysfs attributes for md1, only /proc/mdstat
+    let md1 = &arrays[1];
+    assert!(!md1.degraded);
+    assert_eq!(md1.array_state, None);
+    assert_eq!(md1.member("sdb2").unwrap().state, MdMemberState::InSync);
+
+    // missing /proc/mdstat means no md driver and no arrays
+    let disks = Disks::new().with_procfs_root(sysfs.join("missing"));
+    assert!(disks.md_arrays()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_md_operations() -> Result<(), Error> {
+    let (procfs, sysfs) = fake_md_roots("md-operations")?;
+    let runner = Arc::new(
+        RecordedCommandRunner::new()
+            .with_output(
+                "mdadm --create /dev/md/data --metadata=1.2 --level=raid1 --raid-devices=2 /dev/sdh /dev/sdi",
+                "",
+            )
+            .with_output("mdadm --assemble /dev/md/old /dev/sdf1 /dev/sdg1", "")
+            .with_output("mdadm --manage /dev/md1 --fail /dev/sdb2", "")
+            .with_output("mdadm --manage /dev/md0 --remove /dev/sdb1", "")
+            .with_output(VGS, fixture("lvm/vgs.json"))
+            .with_output(PVS, fixture("lvm/pvs.json"))
+            .with_output(ZPOOL_LIST_ALL, fixture("zpool/list-degraded.txt")),
+    );
+    let disks = Disks::new()
+        .with_command_runner(runner.clone())
+        .with_procfs_root(&procfs)
+        .with_sysfs_root(&sysfs)
+        .into_arc();
+
+    let devices = |list: &[&str]| -> Vec<Disk> {
+        list.iter()
+            .map(|name| disks.partition_by_name(name).unwrap())
+            .collect()
+    };
+
+    disks.md_create("data", MdRaidLevel::Raid1, &devices(&["sdh", "sdi"]))?;
+    disks.md_assemble("old", &devices(&["sdf1", "sdg1"]))?;
+    disks.md_fail("md1", "sdb2")?;
+    disks.md_remove("md0", "/dev/sdb1")?;
+
+    // validation errors
+    let check = |list: &[&str]| {
+        disks
+            .md_create("data", MdRaidLevel::Raid1, &devices(list))
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(check(&["sda1", "sdh"]).contains("already a member of md array 'md0'"));
+    assert!(check(&["sdh", "sdd"]).contains("already an LVM physical volume"));
+    assert!(check(&["sdh", "sdj"]).contains("has holders"));
+    assert!(check(&["sdh", "sdh"]).contains("more than once"));
+    assert!(
+        disks
+            .md_assemble("old", &devices(&["sdj"]))
+            .unwrap_err()
+            .to_string()
+            .contains("has holders")
+    );
+    assert!(
+        disks
+            .md_create("data", MdRaidLevel::Raid5, &devices(&["sdh", "sdi"]))
+            .is_err()
+    );
+    // LVM checks its disks the same way
+    let err = disks
+        .lvm_create_volume_group("vg", &devices(&["sda1"])[0])
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("already a member of md array 'md0'")
+    );
+    let err = disks.md_remove("md0", "sda1").unwrap_err();
+    assert!(err.to_string().contains("fail it first"));
+    assert!(disks.md_fail("md0", "sdb1").is_err());
+    assert!(disks.md_fail("md0", "sdx1").is_err());
+    assert!(disks.md_fail("md9", "sda1").is_err());
+
+    let calls = runner.calls();
+    assert_eq!(
+        calls
+            .iter()
+            .filter(|call| call.starts_with("mdadm"))
+            .count(),
+        4
+    );
+
+    Ok(())
+}
+
+const ZPOOL_LIST_ALL: &str = "zpool list -H -p -P -v";
+
+/// Get the disks of a fake sysfs created by [`fake_md_roots`] by name.
+fn fake_disks(disks: &Arc<Disks>, names: &[&str]) -> Vec<Disk> {
+    names
+        .iter()
+        .map(|name| disks.partition_by_name(name).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_zpool_create() -> Result<(), Error> {
+    let (procfs, sysfs) = fake_md_roots("zpool-create")?;
+    let runner = Arc::new(
+        RecordedCommandRunner::new()
+            .with_output(PVS, fixture("lvm/pvs.json"))
+            .with_output(ZPOOL_LIST_ALL, fixture("zpool/list-rpool.txt"))
+            .with_output(
+                "zpool create -o ashift=12 backup mirror /dev/sdh /dev/sdi log mirror /dev/sdl /dev/sdm",
+                "",
+            ),
+    );
+    let disks = Disks::new()
+        .with_command_runner(runner.clone())
+        .with_procfs_root(&procfs)
+        .with_sysfs_root(&sysfs)
+        .into_arc();
+    let devices = |names: &[&str]| fake_disks(&disks, names);
+
+    let create = ZpoolCreate::new("backup", ZfsRaidLevel::Mirror, devices(&["sdh", "sdi"]))
+        .log(ZfsRaidLevel::Mirror, devices(&["sdl", "sdm"]))
+        .ashift(12);
+    disks.zpool_create(&create)?;
+
+    // pool name already taken
+    let create = ZpoolCreate::new("rpool", ZfsRaidLevel::Single, devices(&["sdh"]));
+    let err = disks.zpool_create(&create).unwrap_err();
+    assert_eq!(err.to_string(), "pool 'rpool' already exists");
+
+    // disks which are in use
+    let check = |names: &[&str]| {
+        let create = ZpoolCreate::new("backup", ZfsRaidLevel::Single, devices(names));
+        disks.zpool_create(&create).unwrap_err().to_string()
+    };
+    assert!(check(&["sdk"]).contains("of device '/dev/sdk' is already used by pool 'rpool'"));
+    assert!(check(&["sda1"]).contains("already a member of md array 'md0'"));
+    assert!(check(&["sdd"]).contains("already an LVM physical volume"));
+    assert!(check(&["sdj"]).contains("has holders"));
+    assert!(check(&["sdh", "sdh"]).contains("more than once"));
+
+    // invalid options fail before running any command
+    let calls = runner.calls().len();
+    let create = ZpoolCreate::new("backup", ZfsRaidLevel::Raidz2, devices(&["sdh"]));
+    assert!(disks.zpool_create(&create).is_err());
+    let create = ZpoolCreate::new(
+        "backup",
+        ZfsRaidLevel::Raidz,
+        devices(&["sdh", "sdi", "sdl"]),
+    )
+    .special(ZfsRaidLevel::Mirror, devices(&["sdm", "sdf1"]));
+    let err = disks.zpool_create(&create).unwrap_err();
+    assert!(err.to_string().contains("needs to be forced"));
+    assert_eq!(runner.calls().len(), calls);
+
+    Ok(())
+}
+
+#[test]
+fn test_zpool_add_and_replace() -> Result<(), Error> {
+    let (procfs, sysfs) = fake_md_roots("zpool-add-replace")?;
+    let runner = Arc::new(
+        RecordedCommandRunner::new()
+            .with_output(PVS, fixture("lvm/pvs.json"))
+            .with_output(ZPOOL_LIST_ALL, fixture("zpool/list-degraded.txt"))
+            .with_output(
+                "zpool status -p -P tank",
+                fixture("zpool/status-degraded.txt"),
+            )
+            .with_output("zpool add tank special mirror /dev/sdh /dev/sdi", "")
+            .with_output("zpool replace tank 1302948383829101827 /dev/sdl", ""),
+    );
+    let disks = Disks::new()
+        .with_command_runner(runner.clone())
+        .with_procfs_root(&procfs)
+        .with_sysfs_root(&sysfs)
+        .into_arc();
+    let devices = |names: &[&str]| fake_disks(&disks, names);
+    let disk = |name: &str| disks.partition_by_name(name).unwrap();
+
+    disks.zpool_add(
+        "tank",
+        ZfsVdevClass::Special,
+        ZfsRaidLevel::Mirror,
+        &devices(&["sdh", "sdi"]),
+    )?;
+    assert!(
+        disks
+            .zpool_add(
+                "tank",
+                ZfsVdevClass::Cache,
+                ZfsRaidLevel::Mirror,
+                &devices(&["sdh", "sdi"]),
+            )
+            .is_err()
+    );
+    assert!(
+        disks
+            .zpool_add(
+                "nopool",
+                ZfsVdevClass::Data,
+                ZfsRaidLevel::Single,
+                &devices(&["sdh"]),
+            )
+            .is_err()
+    );
+    let err = disks
+        .zpool_add(
+            "tank",
+            ZfsVdevClass::Log,
+            ZfsRaidLevel::Single,
+            &devices(&["sdj"]),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("has holders"));
+
+    disks.zpool_replace("tank", "1302948383829101827", &disk("sdl"))?;
+
+    // only leaf devices can be replaced
+    let err = disks
+        .zpool_replace("tank", "raidz2-0", &disk("sdl"))
+        .unwrap_err();
+    assert!(err.to_string().contains("is not a device of pool 'tank'"));
+    // new device must not be in use
+    let err = disks
+        .zpool_replace("tank", "1302948383829101827", &disk("sdd"))
+        .unwrap_err();
+    assert!(err.to_string().contains("already an LVM physical volume"));
+
+    let mutating: Vec<String> = runner
+        .calls()
+        .into_iter()
+        .filter(|call| call.starts_with("zpool add") || call.starts_with("zpool replace"))
+        .collect();
+    assert_eq!(mutating.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_zpool_scrub() -> Result<(), Error> {
+    let (disks, runner) = disks_with(
+        RecordedCommandRunner::new()
+            .with_output("zpool scrub tank", "")
+            .with_output("zpool scrub -s tank", "")
+            .with_output(
+                "zpool status -p -P tank",
+                fixture("zpool/status-degraded.txt"),
+            )
+            .with_output(
+                "zpool status -p -P san",
+                fixture("zpool/status-multipath.txt"),
+            ),
+    );
+
+    disks.zpool_scrub("tank")?;
+    disks.zpool_scrub_stop("tank")?;
+    assert!(disks.zpool_scrub("bad pool").is_err());
+    assert_eq!(runner.calls(), ["zpool scrub tank", "zpool scrub -s tank"]);
+
+    let status = disks.zpool_scan_status("tank")?;
+    assert_eq!(status.function.as_deref(), Some("scrub"));
+    assert_eq!(status.state, ZpoolScanState::Finished);
+    assert_eq!(status.errors, Some(0));
+
+    let status = disks.zpool_scan_status("san")?;
+    assert_eq!(status.function.as_deref(), Some("resilver"));
+    assert_eq!(status.state, ZpoolScanState::Finished);
+
+    Ok(())
+}
+
+#[test]
+fn test_zfs_datasets_and_snapshots() -> Result<(), Error> {
+    let (disks, runner) = disks_with(
+        RecordedCommandRunner::new()
+            .with_output(
+                "zpool list -H -p -P tank",
+                fixture("zpool/list-degraded.txt"),
+            )
+            .with_output(
+                "zfs create -o compression=zstd -o recordsize=1M tank/backup",
+                "",
+            )
+            .with_output("zfs set quota=1T tank/backup", "")
+            .with_output("zfs snapshot -r tank/backup@before-upgrade", "")
+            .with_output(
+                "zfs list -H -p -t snapshot -o name,used,refer,creation -r tank/backup",
+                "tank/backup@before-upgrade\t0\t98304\t1791763200\n",
+            )
+            .with_output("zfs destroy tank/backup@before-upgrade", "")
+            .with_output("zfs destroy -r tank/backup", ""),
+    );
+
+    disks.zfs_create_dataset(
+        "tank/backup",
+        &[("compression", "zstd"), ("recordsize", "1M")],
+    )?;
+    disks.zfs_set_property("tank/backup", "quota", "1T")?;
+    disks.zfs_create_snapshot("tank/backup", "before-upgrade", true)?;
+
+    let snapshots = disks.zfs_list_snapshots(Some("tank/backup"))?;
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].name, "before-upgrade");
+    assert_eq!(snapshots[0].referenced, 98304);
+
+    disks.zfs_destroy_snapshot("tank/backup", "before-upgrade")?;
+    assert!(
+        disks
+            .zfs_destroy_snapshot("tank/backup", "missing")
+            .is_err()
+    );
+
+    disks.zfs_destroy_dataset("tank/backup", true)?;
+
+    // validation errors
+    assert!(disks.zfs_destroy_dataset("tank", true).is_err());
+    assert!(disks.zfs_create_dataset("tank/bad@name", &[]).is_err());
+    assert!(
+        disks
+            .zfs_set_property("tank/backup", "quota", "1T\n")
+            .is_err()
+    );
+    assert!(
+        disks
+            .zfs_create_snapshot("tank/backup", "a/b", false)
+            .is_err()
+    );
+
+    assert!(!runner.calls().iter().any(|call| call.contains("bad")));
+
+    Ok(())
+}
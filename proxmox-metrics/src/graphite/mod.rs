@@ -0,0 +1,4 @@
+mod tcp;
+pub use tcp::*;
+
+pub mod utils;
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use crate::graphite::utils;
+use crate::{Metrics, MetricsData};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct GraphiteTcp {
+    address: String,
+    prefix: Option<String>,
+    conn: Option<TcpStream>,
+    mtu: u16,
+    data: String,
+    channel: mpsc::Receiver<Arc<MetricsData>>,
+}
+
+async fn connect(address: &str) -> Result<TcpStream, Error> {
+    let conn = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .with_context(|| format!("connection to '{address}' timed out"))??;
+    conn.set_nodelay(true)?;
+    Ok(conn)
+}
+
+/// Write `data` to `conn`, returning the number of bytes of all completely written lines on
+/// error.
+async fn write_lines(conn: &mut TcpStream, data: &[u8]) -> Result<(), usize> {
+    let mut written = 0;
+    while written < data.len() {
+        match conn.write(&data[written..]).await {
+            Ok(0) | Err(_) => {
+                let complete = data[..written]
+                    .iter()
+                    .rposition(|b| *b == b'\n')
+                    .map(|pos| pos + 1)
+                    .unwrap_or(0);
+                return Err(complete);
+            }
+            Ok(n) => written += n,
+        }
+    }
+    Ok(())
+}
+
+/// Tests the connection to the given graphite server.
+pub async fn test_graphite_tcp(address: &str) -> Result<(), Error> {
+    let mut conn = connect(address).await?;
+    conn.shutdown().await?;
+    Ok(())
+}
+
+/// Get a [`Metrics`] handle for a graphite server accepting the plaintext protocol via TCP.
+///
+/// `address` must be in the format of `ip_or_hostname:port`, all metric paths are prefixed with
+/// `prefix` if set. Data is sent in batches of at most `mtu` bytes (minus some overhead).
+pub fn graphite_tcp(address: &str, prefix: Option<&str>, mtu: Option<u16>) -> Metrics {
+    let (tx, rx) = mpsc::channel(1024);
+
+    let this = GraphiteTcp {
+        address: address.to_string(),
+        prefix: prefix.map(String::from),
+        conn: None,
+        // ipv6 and tcp headers need up to 80 bytes, subtract 100 for safety
+        mtu: mtu.unwrap_or(1500).saturating_sub(100).max(512),
+        data: String::new(),
+        channel: rx,
+    };
+
+    let join_handle = Some(tokio::spawn(async { this.finish().await }));
+
+    Metrics {
+        join_handle,
+        channel: Some(tx),
+    }
+}
+
+impl GraphiteTcp {
+    async fn add_data(&mut self, data: Arc<MetricsData>) -> Result<(), Error> {
+        let new_data = utils::format_graphite_lines(&data, self.prefix.as_deref())?;
+
+        if self.data.len() + new_data.len() >= (self.mtu as usize) {
+            self.flush().await?;
+        }
+
+        self.data.push_str(&new_data);
+
+        if self.data.len() >= (self.mtu as usize) {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send the buffered lines.
+    ///
+    /// Delivery is at-most-once: lines written to a connection before it failed are not sent
+    /// again, even though the server may not have received them, only the remaining lines are
+    /// sent via a new connection. A line that was only partially written is sent again in full.
+    async fn flush(&mut self) -> Result<(), Error> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+
+        let data = self.data.split_off(0);
+        let mut data = data.as_bytes();
+
+        // an existing connection may have been closed by the server in the meantime, so retry
+        // once with a new connection
+        if let Some(mut conn) = self.conn.take() {
+            match write_lines(&mut conn, data).await {
+                Ok(()) => {
+                    self.conn = Some(conn);
+                    return Ok(());
+                }
+                Err(written) => data = &data[written..],
+            }
+        }
+
+        let mut conn = connect(&self.address).await?;
+        conn.write_all(data).await?;
+        self.conn = Some(conn);
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<(), Error> {
+        while let Some(data) = self.channel.recv().await {
+            self.add_data(data).await?;
+        }
+
+        self.flush().await?;
+
+        if let Some(mut conn) = self.conn.take() {
+            conn.shutdown().await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use anyhow::{Error, bail};
+use serde_json::Value;
+
+use crate::MetricsData;
+
+/// Format the data as Graphite plaintext lines, using tags (Graphite 1.1 and newer).
+///
+/// Every value becomes a series named `<prefix>.<measurement>.<key>`, e.g.
+/// `proxmox.cpustat.cpu;host=node1 0.5 1700000000`.
+pub(crate) fn format_graphite_lines(
+    data: &MetricsData,
+    prefix: Option<&str>,
+) -> Result<String, Error> {
+    let Some(values) = data.values.as_object() else {
+        bail!("invalid data");
+    };
+
+    let mut tags: Vec<(String, String)> = data
+        .tags
+        .iter()
+        .map(|(key, value)| (escape_tag(key), escape_tag(value)))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect();
+    tags.sort();
+
+    let mut lines = String::new();
+    for (key, value) in values {
+        let value = match value {
+            Value::Object(_) => bail!("objects not supported"),
+            Value::Array(_) => bail!("arrays not supported"),
+            Value::Null | Value::String(_) => continue,
+            Value::Bool(value) => u8::from(*value).to_string(),
+            Value::Number(number) => number.to_string(),
+        };
+
+        if let Some(prefix) = prefix {
+            write!(lines, "{}.", escape_path(prefix))?;
+        }
+        write!(
+            lines,
+            "{}.{}",
+            escape_path(&data.measurement),
+            escape_path(key)
+        )?;
+        for (key, value) in &tags {
+            write!(lines, ";{key}={value}")?;
+        }
+        writeln!(lines, " {value} {}", data.ctime)?;
+    }
+
+    Ok(lines)
+}
+
+/// Path components must not contain the separator, whitespace or characters used for tags.
+fn escape_path(component: &str) -> String {
+    component
+        .chars()
+        .map(|c| match c {
+            '.' | ';' | '=' | '~' | '!' | '^' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn escape_tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| match c {
+            ';' | '=' | '~' | '!' | '^' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_format_graphite_lines() {
+        let data = MetricsData::new(
+            "cpu stat",
+            1700000000,
+            json!({ "cpu": 0.5, "online": true, "name": "skipped" }),
+        )
+        .unwrap()
+        .tag("object", "host")
+        .tag("host", "node;1");
+
+        let lines = format_graphite_lines(&data, Some("proxmox.pve")).unwrap();
+        assert_eq!(
+            lines,
+            "proxmox_pve.cpu_stat.cpu;host=node_1;object=host 0.5 1700000000\n\
+             proxmox_pve.cpu_stat.online;host=node_1;object=host 1 1700000000\n"
+        );
+
+        let data = MetricsData::new("mem", 10, json!({ "used": 1024 })).unwrap();
+        assert_eq!(
+            format_graphite_lines(&data, None).unwrap(),
+            "mem.used 1024 10\n"
+        );
+
+        let data = MetricsData::new("mem", 10, json!({ "used": { "a": 1 } })).unwrap();
+        assert!(format_graphite_lines(&data, None).is_err());
+    }
+}
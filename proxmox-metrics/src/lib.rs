@@ -13,6 +13,16 @@ mod influxdb;
 #[doc(inline)]
 pub use influxdb::{influxdb_http, influxdb_udp, test_influxdb_http, test_influxdb_udp};
 
+mod graphite;
+#[doc(inline)]
+pub use graphite::{graphite_tcp, test_graphite_tcp};
+
+mod otlp;
+#[doc(inline)]
+pub use otlp::{OtlpEncoding, otlp_http, test_otlp_http};
+
+pub mod openmetrics;
+
 #[derive(Clone)]
 /// Structured data for the metric server.
 pub struct MetricsData {
@@ -0,0 +1,75 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Error, format_err};
+use hyper::body::Incoming;
+use hyper::http::request::Parts;
+use serde_json::Value;
+
+use proxmox_http::Body;
+use proxmox_router::{ApiHandler, ApiMethod, ApiResponseFuture, Router, RpcEnvironment};
+use proxmox_schema::ObjectSchema;
+
+use super::{OPENMETRICS_CONTENT_TYPE, OpenMetricsFormatter};
+use crate::MetricsData;
+
+/// The future returned by a metrics source, see [`init_openmetrics_api`].
+pub type MetricsSourceFuture =
+    Pin<Box<dyn Future<Output = Result<Vec<Arc<MetricsData>>, Error>> + Send>>;
+
+type MetricsSourceFn = Box<dyn Fn() -> MetricsSourceFuture + Send + Sync>;
+
+struct Exporter {
+    formatter: OpenMetricsFormatter,
+    source: MetricsSourceFn,
+}
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+/// Set the formatter and the source of the metrics exported by [`API_METHOD_OPENMETRICS`].
+///
+/// The source is called on every request and should return the current metrics.
+pub fn init_openmetrics_api<F>(formatter: OpenMetricsFormatter, source: F) -> Result<(), Error>
+where
+    F: Fn() -> MetricsSourceFuture + Send + Sync + 'static,
+{
+    EXPORTER
+        .set(Exporter {
+            formatter,
+            source: Box::new(source),
+        })
+        .map_err(|_| format_err!("cannot initialize openmetrics exporter twice!"))
+}
+
+fn openmetrics_handler(
+    _parts: Parts,
+    _req_body: Incoming,
+    _param: Value,
+    _info: &ApiMethod,
+    _rpcenv: Box<dyn RpcEnvironment>,
+) -> ApiResponseFuture {
+    Box::pin(async move {
+        let exporter = EXPORTER.get().ok_or_else(|| {
+            format_err!("openmetrics exporter needs to be initialized before calling the endpoint")
+        })?;
+
+        let data = (exporter.source)().await?;
+        let text = exporter.formatter.format(&data)?;
+
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)
+            .body(Body::from(text))?)
+    })
+}
+
+/// Exports the metrics in the OpenMetrics text format, see [`init_openmetrics_api`].
+///
+/// Requires superuser privileges by default, use [`ApiMethod::access`] on a copy to change that.
+pub const API_METHOD_OPENMETRICS: ApiMethod = ApiMethod::new(
+    &ApiHandler::AsyncHttp(&openmetrics_handler),
+    &ObjectSchema::new("Export metrics in the OpenMetrics text format.", &[]),
+);
+
+pub const ROUTER: Router = Router::new().get(&API_METHOD_OPENMETRICS);
@@ -0,0 +1,312 @@
+//! OpenMetrics text exposition format, for pull based collection (e.g. by Prometheus).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use anyhow::{Error, bail};
+use serde_json::Value;
+
+use crate::MetricsData;
+
+#[cfg(feature = "api")]
+mod api;
+#[cfg(feature = "api")]
+pub use api::*;
+
+/// The content type of the OpenMetrics text exposition format.
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// The type of a metric family.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetricType {
+    /// A value which can go up and down, e.g. memory usage.
+    #[default]
+    Gauge,
+    /// A monotonically increasing value, e.g. the number of bytes read.
+    Counter,
+    /// A value of unknown type.
+    Unknown,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Gauge => "gauge",
+            MetricType::Counter => "counter",
+            MetricType::Unknown => "unknown",
+        }
+    }
+}
+
+/// Renders [`MetricsData`] in the OpenMetrics text exposition format.
+///
+/// Every value of a [`MetricsData`] entry becomes a metric family named
+/// `<prefix>_<measurement>_<key>`, its tags are used as labels. Families are gauges unless
+/// configured otherwise via [`type_hint`](Self::type_hint).
+///
+/// ```
+/// # use proxmox_metrics::MetricsData;
+/// # use proxmox_metrics::openmetrics::{MetricType, OpenMetricsFormatter};
+/// # use serde_json::json;
+/// # fn test() -> Result<(), anyhow::Error> {
+/// let data = MetricsData::new("blockstat", 1700000000, json!({ "read_ios": 10 }))?
+///     .tag("host", "node1");
+///
+/// let text = OpenMetricsFormatter::new()
+///     .prefix("pve")
+///     .type_hint("blockstat_read_ios", MetricType::Counter)
+///     .format(&[data.into()])?;
+///
+/// assert_eq!(
+///     text,
+///     concat!(
+///         "# TYPE pve_blockstat_read_ios counter\n",
+///         "pve_blockstat_read_ios_total{host=\"node1\"} 10 1700000000\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// #     Ok(())
+/// # }
+/// # test().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct OpenMetricsFormatter {
+    prefix: Option<String>,
+    types: HashMap<String, MetricType>,
+    help: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct Family {
+    metric_type: MetricType,
+    help: Option<String>,
+    /// Samples by their label set, with their value and timestamp.
+    samples: BTreeMap<String, (String, i64)>,
+}
+
+impl OpenMetricsFormatter {
+    /// Create a new formatter without prefix and type hints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a prefix for all metric names, e.g. the product name.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Set the type of a metric family, `name` is `<measurement>_<key>` without the prefix.
+    pub fn type_hint<S: Into<String>>(mut self, name: S, metric_type: MetricType) -> Self {
+        self.types.insert(name.into(), metric_type);
+        self
+    }
+
+    /// Set the help text of a metric family, `name` is `<measurement>_<key>` without the prefix.
+    pub fn help<S: Into<String>, H: Into<String>>(mut self, name: S, help: H) -> Self {
+        self.help.insert(name.into(), help.into());
+        self
+    }
+
+    /// Render the data, terminated by the final `# EOF` marker.
+    ///
+    /// If the same series (name and labels) occurs multiple times, the newest value is used.
+    /// `null` and string values are skipped, as they cannot be represented.
+    pub fn format(&self, data: &[Arc<MetricsData>]) -> Result<String, Error> {
+        let mut families: BTreeMap<String, Family> = BTreeMap::new();
+
+        for data in data {
+            let Some(values) = data.values.as_object() else {
+                bail!("invalid data");
+            };
+
+            let mut labels: Vec<(String, String)> = data
+                .tags
+                .iter()
+                .map(|(key, value)| (sanitize_name(key), escape_label_value(value)))
+                .collect();
+            labels.sort();
+            let labels = labels
+                .iter()
+                .map(|(key, value)| format!("{key}=\"{value}\""))
+                .collect::<Vec<String>>()
+                .join(",");
+
+            for (key, value) in values {
+                let value = match value {
+                    Value::Object(_) => bail!("objects not supported"),
+                    Value::Array(_) => bail!("arrays not supported"),
+                    Value::Null | Value::String(_) => continue,
+                    Value::Bool(value) => u8::from(*value).to_string(),
+                    Value::Number(number) => match number.as_f64() {
+                        Some(value) if number.is_f64() => format_float(value),
+                        _ => number.to_string(),
+                    },
+                };
+
+                let base_name = sanitize_name(&format!("{}_{key}", data.measurement));
+                let metric_type = self.types.get(&base_name).copied().unwrap_or_default();
+                // counter samples get a `_total` suffix, which is not part of the family name
+                let name = match metric_type {
+                    MetricType::Counter => base_name.strip_suffix("_total").unwrap_or(&base_name),
+                    _ => &base_name,
+                };
+                let family_name = match &self.prefix {
+                    Some(prefix) => sanitize_name(&format!("{prefix}_{name}")),
+                    None => name.to_string(),
+                };
+
+                let family = families.entry(family_name).or_insert_with(|| Family {
+                    metric_type,
+                    help: self.help.get(&base_name).cloned(),
+                    samples: BTreeMap::new(),
+                });
+
+                match family.samples.get(&labels) {
+                    Some((_, ctime)) if *ctime > data.ctime => (),
+                    _ => {
+                        family.samples.insert(labels.clone(), (value, data.ctime));
+                    }
+                }
+            }
+        }
+
+        let mut text = String::new();
+        for (name, family) in families {
+            writeln!(text, "# TYPE {name} {}", family.metric_type.as_str())?;
+            if let Some(help) = &family.help {
+                writeln!(text, "# HELP {name} {}", escape_help(help))?;
+            }
+            let suffix = match family.metric_type {
+                MetricType::Counter => "_total",
+                _ => "",
+            };
+            for (labels, (value, ctime)) in family.samples {
+                if labels.is_empty() {
+                    writeln!(text, "{name}{suffix} {value} {ctime}")?;
+                } else {
+                    writeln!(text, "{name}{suffix}{{{labels}}} {value} {ctime}")?;
+                }
+            }
+        }
+        text.push_str("# EOF\n");
+
+        Ok(text)
+    }
+}
+
+/// Replace all characters not allowed in metric and label names with underscores.
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !sanitized.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            "+Inf".to_string()
+        } else {
+            "-Inf".to_string()
+        }
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let data: Vec<Arc<MetricsData>> = vec![
+            MetricsData::new(
+                "cpustat",
+                100,
+                json!({ "cpu": 0.5, "online": true, "name": "skipped" }),
+            )
+            .unwrap()
+            .tag("host", "node\"1\"\n")
+            .tag("object", "host")
+            .into(),
+            MetricsData::new("cpustat", 200, json!({ "cpu": 0.25 }))
+                .unwrap()
+                .tag("object", "host")
+                .tag("host", "node\"1\"\n")
+                .into(),
+            MetricsData::new("cpustat", 150, json!({ "cpu": 0.75 }))
+                .unwrap()
+                .tag("object", "host")
+                .tag("host", "node\"1\"\n")
+                .into(),
+            MetricsData::new(
+                "net-stat",
+                200,
+                json!({ "netin_total": 123, "ratio": f64::NAN }),
+            )
+            .unwrap()
+            .tag("device.name", "eth0")
+            .into(),
+        ];
+
+        let text = OpenMetricsFormatter::new()
+            .prefix("pve")
+            .type_hint("net_stat_netin_total", MetricType::Counter)
+            .help("cpustat_cpu", "CPU usage\n(0-1)")
+            .format(&data)
+            .unwrap();
+
+        // NaN is serialized as null by serde_json
+        assert_eq!(
+            text,
+            "# TYPE pve_cpustat_cpu gauge\n\
+             # HELP pve_cpustat_cpu CPU usage\\n(0-1)\n\
+             pve_cpustat_cpu{host=\"node\\\"1\\\"\\n\",object=\"host\"} 0.25 200\n\
+             # TYPE pve_cpustat_online gauge\n\
+             pve_cpustat_online{host=\"node\\\"1\\\"\\n\",object=\"host\"} 1 100\n\
+             # TYPE pve_net_stat_netin counter\n\
+             pve_net_stat_netin_total{device_name=\"eth0\"} 123 200\n\
+             # EOF\n"
+        );
+
+        let invalid: Vec<Arc<MetricsData>> = vec![
+            MetricsData::new("x", 0, json!({ "a": [1] }))
+                .unwrap()
+                .into(),
+        ];
+        assert!(OpenMetricsFormatter::new().format(&invalid).is_err());
+
+        assert_eq!(OpenMetricsFormatter::new().format(&[]).unwrap(), "# EOF\n");
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(sanitize_name("1st-value.x"), "_1st_value_x");
+        assert_eq!(format_float(f64::INFINITY), "+Inf");
+        assert_eq!(format_float(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_float(1.5), "1.5");
+    }
+}
@@ -0,0 +1,402 @@
+//! Minimal encoder for OTLP `ExportMetricsServiceRequest` messages.
+//!
+//! Only the subset of the OpenTelemetry metrics data model required to represent
+//! [`MetricsData`] is implemented: every value becomes a gauge data point, tags become
+//! attributes.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Error, bail};
+use serde_json::{Value, json};
+
+use crate::MetricsData;
+
+/// Wire encoding for OTLP/HTTP.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OtlpEncoding {
+    /// Binary protobuf encoding (`application/x-protobuf`).
+    #[default]
+    Protobuf,
+    /// JSON protobuf encoding (`application/json`).
+    Json,
+}
+
+impl OtlpEncoding {
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            OtlpEncoding::Protobuf => "application/x-protobuf",
+            OtlpEncoding::Json => "application/json",
+        }
+    }
+}
+
+const SCOPE_NAME: &str = env!("CARGO_PKG_NAME");
+const SCOPE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NumberValue {
+    Int(i64),
+    Double(f64),
+}
+
+#[derive(Debug, PartialEq)]
+struct DataPoint {
+    attributes: Vec<(String, String)>,
+    time_unix_nano: u64,
+    value: NumberValue,
+}
+
+/// Upper bound of the bytes needed to frame a metric and its data points in addition to the
+/// name and the encoded data points themselves.
+const METRIC_OVERHEAD: usize = 64;
+
+/// The data points of a single [`MetricsData`], already encoded.
+pub(crate) struct EncodedData {
+    points: Vec<(String, Vec<u8>)>,
+    size: usize,
+}
+
+impl EncodedData {
+    /// Upper bound of the number of bytes these data points add to an encoded batch.
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Encoded data points grouped by metric name.
+pub(crate) struct MetricsBatch {
+    encoding: OtlpEncoding,
+    metrics: BTreeMap<String, Vec<Vec<u8>>>,
+}
+
+impl MetricsBatch {
+    pub(crate) fn new(encoding: OtlpEncoding) -> Self {
+        Self {
+            encoding,
+            metrics: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+
+    /// Encode all values of `data` as data points named `<measurement>.<key>`.
+    pub(crate) fn encode(&self, data: &MetricsData) -> Result<EncodedData, Error> {
+        let Some(values) = data.values.as_object() else {
+            bail!("invalid data");
+        };
+
+        let mut attributes: Vec<(String, String)> = data
+            .tags
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        attributes.sort();
+
+        let time_unix_nano = u64::try_from(data.ctime).unwrap_or(0) * 1_000_000_000;
+
+        let mut points = Vec::new();
+        let mut size = 0;
+        for (key, value) in values {
+            let value = match value {
+                Value::Object(_) => bail!("objects not supported"),
+                Value::Array(_) => bail!("arrays not supported"),
+                Value::Null | Value::String(_) => continue,
+                Value::Bool(value) => NumberValue::Int(i64::from(*value)),
+                Value::Number(number) => match number.as_i64() {
+                    Some(value) => NumberValue::Int(value),
+                    None => NumberValue::Double(number.as_f64().unwrap_or(f64::NAN)),
+                },
+            };
+
+            let point = DataPoint {
+                attributes: attributes.clone(),
+                time_unix_nano,
+                value,
+            };
+            let name = format!("{}.{key}", data.measurement);
+            let (encoded, name_size) = match self.encoding {
+                OtlpEncoding::Protobuf => {
+                    let mut buf = Vec::new();
+                    encode_data_point(&mut buf, &point);
+                    (buf, name.len())
+                }
+                OtlpEncoding::Json => (
+                    json_data_point(&point).to_string().into_bytes(),
+                    Value::from(name.as_str()).to_string().len(),
+                ),
+            };
+            size += name_size + encoded.len() + METRIC_OVERHEAD;
+            points.push((name, encoded));
+        }
+
+        Ok(EncodedData { points, size })
+    }
+
+    /// Add data points encoded with [`Self::encode`].
+    pub(crate) fn add(&mut self, data: EncodedData) {
+        for (name, point) in data.points {
+            self.metrics.entry(name).or_default().push(point);
+        }
+    }
+
+    /// Take the contained data points and encode them as `ExportMetricsServiceRequest`.
+    pub(crate) fn take_encoded(&mut self) -> Vec<u8> {
+        let metrics = std::mem::take(&mut self.metrics);
+        match self.encoding {
+            OtlpEncoding::Protobuf => encode_protobuf(&metrics),
+            OtlpEncoding::Json => encode_json(&metrics),
+        }
+    }
+}
+
+fn json_data_point(point: &DataPoint) -> Value {
+    let attributes: Vec<Value> = point
+        .attributes
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect();
+
+    // 64 bit integers are encoded as strings in the JSON mapping
+    let mut json = json!({
+        "attributes": attributes,
+        "timeUnixNano": point.time_unix_nano.to_string(),
+    });
+    match point.value {
+        NumberValue::Int(value) => json["asInt"] = value.to_string().into(),
+        NumberValue::Double(value) => json["asDouble"] = json_double(value),
+    }
+    json
+}
+
+fn encode_json(metrics: &BTreeMap<String, Vec<Vec<u8>>>) -> Vec<u8> {
+    if metrics.is_empty() {
+        return b"{}".to_vec();
+    }
+
+    // the data points are already encoded, so only add the surrounding objects
+    let mut buf = Vec::new();
+    buf.extend_from_slice(br#"{"resourceMetrics":[{"resource":{},"scopeMetrics":[{"scope":"#);
+    buf.extend(
+        json!({ "name": SCOPE_NAME, "version": SCOPE_VERSION })
+            .to_string()
+            .bytes(),
+    );
+    buf.extend_from_slice(br#","metrics":["#);
+    for (i, (name, points)) in metrics.iter().enumerate() {
+        if i > 0 {
+            buf.push(b',');
+        }
+        buf.extend_from_slice(br#"{"name":"#);
+        buf.extend(Value::from(name.as_str()).to_string().bytes());
+        buf.extend_from_slice(br#","gauge":{"dataPoints":["#);
+        for (j, point) in points.iter().enumerate() {
+            if j > 0 {
+                buf.push(b',');
+            }
+            buf.extend_from_slice(point);
+        }
+        buf.extend_from_slice(b"]}}");
+    }
+    buf.extend_from_slice(b"]}]}]}");
+    buf
+}
+
+/// Non-finite doubles are encoded as strings in the JSON mapping.
+fn json_double(value: f64) -> Value {
+    if value.is_nan() {
+        "NaN".into()
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            "Infinity".into()
+        } else {
+            "-Infinity".into()
+        }
+    } else {
+        value.into()
+    }
+}
+
+// protobuf wire types
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_tag(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(buf, (u64::from(field) << 3) | u64::from(wire_type));
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, data: &[u8]) {
+    put_tag(buf, field, WIRE_LEN);
+    put_varint(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+fn put_fixed64(buf: &mut Vec<u8>, field: u32, value: u64) {
+    put_tag(buf, field, WIRE_FIXED64);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_message(buf: &mut Vec<u8>, field: u32, encode: impl FnOnce(&mut Vec<u8>)) {
+    let mut message = Vec::new();
+    encode(&mut message);
+    put_bytes(buf, field, &message);
+}
+
+fn put_key_value(buf: &mut Vec<u8>, field: u32, key: &str, value: &str) {
+    // KeyValue { key = 1, value = 2 }, AnyValue { string_value = 1 }
+    put_message(buf, field, |kv| {
+        put_bytes(kv, 1, key.as_bytes());
+        put_message(kv, 2, |any| put_bytes(any, 1, value.as_bytes()));
+    });
+}
+
+fn encode_protobuf(metrics: &BTreeMap<String, Vec<Vec<u8>>>) -> Vec<u8> {
+    let mut request = Vec::new();
+    if metrics.is_empty() {
+        return request;
+    }
+
+    // ExportMetricsServiceRequest { resource_metrics = 1 }
+    put_message(&mut request, 1, |resource_metrics| {
+        // ResourceMetrics { resource = 1, scope_metrics = 2 }
+        put_message(resource_metrics, 1, |_resource| {});
+        put_message(resource_metrics, 2, |scope_metrics| {
+            // ScopeMetrics { scope = 1, metrics = 2 }, InstrumentationScope { name = 1, version = 2 }
+            put_message(scope_metrics, 1, |scope| {
+                put_bytes(scope, 1, SCOPE_NAME.as_bytes());
+                put_bytes(scope, 2, SCOPE_VERSION.as_bytes());
+            });
+            for (name, points) in metrics {
+                // Metric { name = 1, gauge = 5 }, Gauge { data_points = 1 }
+                put_message(scope_metrics, 2, |metric| {
+                    put_bytes(metric, 1, name.as_bytes());
+                    put_message(metric, 5, |gauge| {
+                        for point in points {
+                            put_bytes(gauge, 1, point);
+                        }
+                    });
+                });
+            }
+        });
+    });
+
+    request
+}
+
+fn encode_data_point(buf: &mut Vec<u8>, point: &DataPoint) {
+    // NumberDataPoint { time_unix_nano = 3, as_double = 4, as_int = 6, attributes = 7 }
+    put_fixed64(buf, 3, point.time_unix_nano);
+    match point.value {
+        NumberValue::Double(value) => put_fixed64(buf, 4, value.to_bits()),
+        NumberValue::Int(value) => put_fixed64(buf, 6, value as u64),
+    }
+    for (key, value) in &point.attributes {
+        put_key_value(buf, 7, key, value);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        let mut buf = Vec::new();
+        put_varint(&mut buf, 1);
+        put_varint(&mut buf, 300);
+        put_varint(&mut buf, u64::MAX);
+        assert_eq!(
+            buf,
+            [
+                1, 0xac, 0x02, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let mut batch = MetricsBatch::new(OtlpEncoding::Protobuf);
+        assert!(batch.is_empty());
+        assert!(batch.take_encoded().is_empty());
+
+        let mut batch = MetricsBatch::new(OtlpEncoding::Json);
+        assert_eq!(batch.take_encoded(), b"{}");
+
+        let data = MetricsData::new("cpustat", 2, json!({ "cpu": 0.5, "name": "skipped" }))
+            .unwrap()
+            .tag("host", "node1");
+        batch.add(batch.encode(&data).unwrap());
+        let data = MetricsData::new("cpustat", 3, json!({ "cpu": 1, "online": true })).unwrap();
+        let encoded = batch.encode(&data).unwrap();
+        assert_eq!(encoded.points.len(), 2);
+        batch.add(encoded);
+
+        let encoded = batch.take_encoded();
+        assert!(batch.is_empty());
+        let encoded: Value = serde_json::from_slice(&encoded).unwrap();
+        let scope_metrics = &encoded["resourceMetrics"][0]["scopeMetrics"][0];
+        assert_eq!(scope_metrics["scope"]["name"], SCOPE_NAME);
+        assert_eq!(
+            scope_metrics["metrics"],
+            json!([
+                {
+                    "name": "cpustat.cpu",
+                    "gauge": { "dataPoints": [
+                        {
+                            "attributes": [{ "key": "host", "value": { "stringValue": "node1" } }],
+                            "timeUnixNano": "2000000000",
+                            "asDouble": 0.5,
+                        },
+                        {
+                            "attributes": [],
+                            "timeUnixNano": "3000000000",
+                            "asInt": "1",
+                        },
+                    ]},
+                },
+                {
+                    "name": "cpustat.online",
+                    "gauge": { "dataPoints": [
+                        {
+                            "attributes": [],
+                            "timeUnixNano": "3000000000",
+                            "asInt": "1",
+                        },
+                    ]},
+                },
+            ])
+        );
+
+        let mut batch = MetricsBatch::new(OtlpEncoding::Protobuf);
+        let data = MetricsData::new("m", 1, json!({ "v": 2 }))
+            .unwrap()
+            .tag("k", "x");
+        let encoded = batch.encode(&data).unwrap();
+        let size = encoded.size();
+        batch.add(encoded);
+        let mut point = vec![0x19];
+        point.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+        point.push(0x31);
+        point.extend_from_slice(&2u64.to_le_bytes());
+        point.extend_from_slice(&[0x3a, 0x08, 0x0a, 0x01, b'k', 0x12, 0x03, 0x0a, 0x01, b'x']);
+
+        let encoded = batch.take_encoded();
+        assert!(encoded.len() <= size);
+        // the data point is the innermost message and at the very end
+        assert!(encoded.ends_with(&point));
+        assert!(encoded.starts_with(&[0x0a]));
+        assert_eq!(usize::from(encoded[1]), encoded.len() - 2);
+
+        let data = MetricsData::new("m", 1, json!({ "v": [1] })).unwrap();
+        assert!(batch.encode(&data).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use anyhow::{Error, bail};
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use tokio::sync::mpsc;
+
+use proxmox_http::client::Client;
+use proxmox_http::{Body, HttpOptions};
+
+use crate::otlp::encoding::{MetricsBatch, OtlpEncoding};
+use crate::{Metrics, MetricsData};
+
+struct OtlpHttp {
+    client: Client,
+    uri: http::Uri,
+    encoding: OtlpEncoding,
+    token: Option<String>,
+    max_body_size: usize,
+    batch: MetricsBatch,
+    batch_size: usize,
+    channel: mpsc::Receiver<Arc<MetricsData>>,
+}
+
+/// Tests the connection to the given OpenTelemetry collector by sending an empty request.
+pub async fn test_otlp_http(
+    uri: &str,
+    encoding: OtlpEncoding,
+    token: Option<&str>,
+    verify_tls: bool,
+) -> Result<(), Error> {
+    let (_tx, rx) = mpsc::channel(1);
+
+    let this = OtlpHttp::new(uri, encoding, token, verify_tls, 1, rx)?;
+
+    this.send(this.encoded_empty_request()).await
+}
+
+/// Get a [`Metrics`] handle for an OpenTelemetry collector accepting OTLP/HTTP.
+///
+/// `uri` is the base URI of the collector (e.g. `https://collector:4318`), data is sent to its
+/// `/v1/metrics` path. Every value is exported as gauge named `<measurement>.<key>`, with the
+/// tags as attributes.
+pub fn otlp_http(
+    uri: &str,
+    encoding: OtlpEncoding,
+    token: Option<&str>,
+    verify_tls: bool,
+    max_body_size: usize,
+) -> Result<Metrics, Error> {
+    let (tx, rx) = mpsc::channel(1024);
+
+    let this = OtlpHttp::new(uri, encoding, token, verify_tls, max_body_size, rx)?;
+
+    let join_handle = Some(tokio::spawn(this.finish()));
+
+    Ok(Metrics {
+        join_handle,
+        channel: Some(tx),
+    })
+}
+
+impl OtlpHttp {
+    fn new(
+        uri: &str,
+        encoding: OtlpEncoding,
+        token: Option<&str>,
+        verify_tls: bool,
+        max_body_size: usize,
+        channel: mpsc::Receiver<Arc<MetricsData>>,
+    ) -> Result<Self, Error> {
+        let client = if verify_tls {
+            Client::with_options(HttpOptions::default())
+        } else {
+            let mut ssl_connector = SslConnector::builder(SslMethod::tls()).unwrap();
+            ssl_connector.set_verify(SslVerifyMode::NONE);
+            Client::with_ssl_connector(ssl_connector.build(), HttpOptions::default())
+        };
+
+        Ok(OtlpHttp {
+            client,
+            uri: Self::create_uri(uri)?,
+            encoding,
+            token: token.map(String::from),
+            max_body_size,
+            batch: MetricsBatch::new(encoding),
+            batch_size: 0,
+            channel,
+        })
+    }
+
+    /// Return the metrics URI for the given base URI
+    fn create_uri(uri: &str) -> Result<http::Uri, Error> {
+        let uri: http::uri::Uri = uri.parse()?;
+        let uri_parts = uri.into_parts();
+
+        let base_path = if let Some(ref p) = uri_parts.path_and_query {
+            p.path().trim_end_matches('/')
+        } else {
+            ""
+        };
+
+        let (Some(scheme), Some(authority)) = (uri_parts.scheme, uri_parts.authority) else {
+            bail!("invalid uri, scheme and host are required");
+        };
+
+        Ok(http::uri::Builder::new()
+            .scheme(scheme)
+            .authority(authority)
+            .path_and_query(format!("{base_path}/v1/metrics"))
+            .build()?)
+    }
+
+    fn encoded_empty_request(&self) -> Vec<u8> {
+        match self.encoding {
+            OtlpEncoding::Protobuf => Vec::new(),
+            OtlpEncoding::Json => b"{}".to_vec(),
+        }
+    }
+
+    async fn send(&self, body: Vec<u8>) -> Result<(), Error> {
+        let mut request = http::Request::builder()
+            .method("POST")
+            .uri(&self.uri)
+            .header(http::header::CONTENT_TYPE, self.encoding.content_type());
+
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {token}"));
+        }
+
+        let res = self.client.request(request.body(Body::from(body))?).await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            bail!("got bad status: {}", status);
+        }
+
+        Ok(())
+    }
+
+    async fn add_data(&mut self, data: Arc<MetricsData>) -> Result<(), Error> {
+        let encoded = self.batch.encode(&data)?;
+        let new_size = encoded.size();
+
+        if self.batch_size + new_size >= self.max_body_size {
+            self.flush().await?;
+        }
+
+        self.batch.add(encoded);
+        self.batch_size += new_size;
+
+        if self.batch_size >= self.max_body_size {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        self.batch_size = 0;
+        let body = self.batch.take_encoded();
+
+        self.send(body).await
+    }
+
+    async fn finish(mut self) -> Result<(), Error> {
+        while let Some(data) = self.channel.recv().await {
+            self.add_data(data).await?;
+        }
+
+        self.flush().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OtlpHttp;
+
+    #[test]
+    fn uri_creation() {
+        let uri = OtlpHttp::create_uri("http://localhost:4318").unwrap();
+        assert_eq!(uri.to_string(), "http://localhost:4318/v1/metrics");
+
+        let uri = OtlpHttp::create_uri("https://collector/otlp/").unwrap();
+        assert_eq!(uri.to_string(), "https://collector/otlp/v1/metrics");
+
+        assert!(OtlpHttp::create_uri("collector").is_err());
+    }
+}
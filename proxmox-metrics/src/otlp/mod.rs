@@ -0,0 +1,5 @@
+mod http;
+pub use self::http::*;
+
+mod encoding;
+pub use encoding::OtlpEncoding;
@@ -0,0 +1,217 @@
+//! Aggregation of notifications into digests.
+//!
+//! Matchers with an [`AggregationConfig`] do not forward matching notifications to their
+//! targets right away. Instead, the notifications are collected in a pending batch which is
+//! persisted on disk (see [`AggregationStore`]). Once the configured time window has passed
+//! or the maximum number of collected notifications has been reached, a single digest
+//! notification listing all collected notifications is sent to the matcher's targets.
+//!
+//! The digest is rendered from the `digest` template. Its template data contains the
+//! name of the `matcher`, the `count` of collected notifications and a list of `entries`,
+//! each with the rendered `title` and `message`, the `severity`, `timestamp` and metadata
+//! `fields` of the original notification. If the digest cannot be rendered, for example
+//! because the product does not provide a `digest` template, the collected notifications are
+//! sent individually instead.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::error;
+
+use proxmox_schema::{ApiStringFormat, ApiType, Schema, StringSchema, api};
+use proxmox_uuid::Uuid;
+
+#[cfg(feature = "aggregation")]
+use crate::Error;
+use crate::renderer::{self, TemplateType};
+use crate::{Content, Metadata, Notification, Severity};
+
+#[cfg(feature = "aggregation")]
+pub(crate) mod store;
+#[cfg(feature = "aggregation")]
+pub use store::AggregationStore;
+
+/// Name of the template used for rendering digest notifications.
+pub const DIGEST_TEMPLATE_NAME: &str = "digest";
+
+/// Default maximum number of notifications collected in a single digest.
+pub const DEFAULT_MAX_ITEMS: u32 = 100;
+
+#[api(
+    properties: {
+        window: {
+            minimum: 1,
+            maximum: 604800,
+        },
+        "max-items": {
+            optional: true,
+            minimum: 1,
+            maximum: 1000,
+        },
+    }
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Aggregation settings for a matcher.
+pub struct AggregationConfig {
+    /// Time window in seconds, starting with the first collected notification, after
+    /// which the digest is sent.
+    pub window: u64,
+    /// Send the digest early once this many notifications have been collected (default: 100).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u32>,
+}
+
+pub const AGGREGATION_SCHEMA: Schema =
+    StringSchema::new("Collect matching notifications and send them as a single digest.")
+        .format(&ApiStringFormat::PropertyString(
+            &AggregationConfig::API_SCHEMA,
+        ))
+        .schema();
+
+impl AggregationConfig {
+    /// Maximum number of notifications collected in a single digest.
+    pub fn max_items(&self) -> u32 {
+        self.max_items.unwrap_or(DEFAULT_MAX_ITEMS)
+    }
+}
+
+/// Render title and message of a collected notification for the digest.
+fn render_entry(notification: &Notification) -> (String, String) {
+    match &notification.content {
+        Content::Template {
+            template_name,
+            data,
+        } => {
+            let title = renderer::render_template(TemplateType::Subject, template_name, data)
+                .unwrap_or_else(|err| {
+                    error!("could not render title of aggregated notification: {err}");
+                    template_name.clone()
+                });
+            let message =
+                renderer::render_template(TemplateType::PlaintextBody, template_name, data)
+                    .unwrap_or_else(|err| {
+                        error!("could not render body of aggregated notification: {err}");
+                        String::new()
+                    });
+
+            (title, message)
+        }
+        #[cfg(feature = "mail-forwarder")]
+        Content::ForwardedMail { title, body, .. } => (title.clone(), body.clone()),
+    }
+}
+
+/// Check that `digest` can be rendered, i.e. that the `digest` template is available.
+#[cfg(feature = "aggregation")]
+pub(crate) fn check_digest_renderable(digest: &Notification) -> Result<(), Error> {
+    match &digest.content {
+        Content::Template {
+            template_name,
+            data,
+        } => {
+            renderer::render_template(TemplateType::Subject, template_name, data)?;
+            renderer::render_template(TemplateType::PlaintextBody, template_name, data)?;
+        }
+        #[cfg(feature = "mail-forwarder")]
+        Content::ForwardedMail { .. } => (),
+    }
+
+    Ok(())
+}
+
+/// Build a digest notification for all notifications collected by `matcher`.
+///
+/// The digest has the highest severity of all collected notifications and carries
+/// those metadata fields which are identical for all of them.
+pub fn build_digest(matcher: &str, notifications: &[Notification]) -> Notification {
+    let mut severity = Severity::Info;
+    let mut common_fields: Option<HashMap<String, String>> = None;
+    let mut entries = Vec::new();
+
+    for notification in notifications {
+        let metadata = &notification.metadata;
+
+        if metadata.severity > severity {
+            severity = metadata.severity;
+        }
+
+        match &mut common_fields {
+            Some(fields) => fields.retain(|k, v| metadata.additional_fields.get(k) == Some(v)),
+            None => common_fields = Some(metadata.additional_fields.clone()),
+        }
+
+        let (title, message) = render_entry(notification);
+
+        entries.push(json!({
+            "title": title,
+            "message": message,
+            "severity": metadata.severity,
+            "timestamp": metadata.timestamp,
+            "fields": metadata.additional_fields,
+        }));
+    }
+
+    Notification {
+        content: Content::Template {
+            template_name: DIGEST_TEMPLATE_NAME.to_string(),
+            data: json!({
+                "matcher": matcher,
+                "count": entries.len(),
+                "entries": entries,
+            }),
+        },
+        metadata: Metadata {
+            severity,
+            timestamp: proxmox_time::epoch_i64(),
+            additional_fields: common_fields.unwrap_or_default(),
+        },
+        id: Uuid::generate(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_digest() {
+        let notifications: Vec<Notification> = [
+            (Severity::Warning, "store1"),
+            (Severity::Error, "store2"),
+            (Severity::Info, "store3"),
+        ]
+        .into_iter()
+        .map(|(severity, datastore)| {
+            let fields = HashMap::from([
+                ("type".to_string(), "sync".to_string()),
+                ("datastore".to_string(), datastore.to_string()),
+            ]);
+            Notification::from_template(severity, "sync", Default::default(), fields)
+        })
+        .collect();
+
+        let digest = build_digest("matcher", &notifications);
+
+        assert_eq!(digest.metadata.severity, Severity::Error);
+        assert_eq!(
+            digest.metadata.additional_fields,
+            HashMap::from([("type".to_string(), "sync".to_string())])
+        );
+
+        let (template_name, data) = match &digest.content {
+            Content::Template {
+                template_name,
+                data,
+            } => (template_name, data),
+            #[cfg(feature = "mail-forwarder")]
+            Content::ForwardedMail { .. } => panic!("digest must be a template notification"),
+        };
+
+        assert_eq!(template_name, DIGEST_TEMPLATE_NAME);
+        assert_eq!(data["count"], 3);
+        assert_eq!(data["matcher"], "matcher");
+        assert_eq!(data["entries"][1]["fields"]["datastore"], "store2");
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::AggregationConfig;
+use crate::context::context;
+use crate::state_dir::StateDir;
+use crate::{Error, Notification};
+
+/// Notifications collected by a single matcher, as stored in the aggregation directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PendingDigest {
+    /// Name of the matcher which collected the notifications.
+    pub(crate) matcher: String,
+    /// Time when the first notification was collected.
+    pub(crate) started: i64,
+    /// The collected notifications.
+    pub(crate) notifications: Vec<Notification>,
+}
+
+/// Storage for notifications collected by matchers with aggregation enabled.
+///
+/// The pending notifications of every matcher are stored in their own file, which is
+/// atomically replaced on updates. Modifications are serialized via an exclusive lock on a
+/// lock file within the aggregation directory.
+pub struct AggregationStore {
+    dir: StateDir,
+}
+
+impl AggregationStore {
+    /// Create an aggregation store using the directory `dir`.
+    ///
+    /// The directory will be created when first storing a notification.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: StateDir::new(dir.into(), "aggregation"),
+        }
+    }
+
+    /// Create an aggregation store for the directory provided by the product context.
+    ///
+    /// Returns `None` if the context does not provide an aggregation directory.
+    pub fn from_context() -> Option<Self> {
+        context().aggregation_dir().map(Self::new)
+    }
+
+    fn pending_path(&self, matcher: &str) -> PathBuf {
+        // Matcher names are restricted by `ENTITY_NAME_SCHEMA`, so they are safe to use as
+        // file names.
+        self.dir.path(matcher)
+    }
+
+    fn read(&self, matcher: &str) -> Result<Option<PendingDigest>, Error> {
+        let data = match std::fs::read(self.pending_path(matcher)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::Generic(format!(
+                    "could not read pending notifications of matcher '{matcher}': {err}"
+                )));
+            }
+        };
+
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|err| Error::ConfigDeserialization(err.into()))
+    }
+
+    fn store(&self, pending: &PendingDigest) -> Result<(), Error> {
+        let data =
+            serde_json::to_vec(pending).map_err(|err| Error::ConfigSerialization(err.into()))?;
+
+        proxmox_sys::fs::replace_file(
+            self.pending_path(&pending.matcher),
+            &data,
+            StateDir::file_options(),
+            true,
+        )
+        .map_err(|err| {
+            Error::Generic(format!(
+                "could not store pending notifications of matcher '{}': {err}",
+                pending.matcher
+            ))
+        })
+    }
+
+    fn remove(&self, matcher: &str) -> Result<(), Error> {
+        match std::fs::remove_file(self.pending_path(matcher)) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::Generic(format!(
+                "could not remove pending notifications of matcher '{matcher}': {err}"
+            ))),
+        }
+    }
+
+    /// Collect a notification for `matcher`.
+    ///
+    /// If the maximum number of notifications configured in `config` has been reached, the
+    /// pending notifications are removed from the store and returned, so that the digest can
+    /// be sent right away.
+    pub(crate) fn add(
+        &self,
+        matcher: &str,
+        config: &AggregationConfig,
+        notification: &Notification,
+    ) -> Result<Option<PendingDigest>, Error> {
+        let _lock = self.dir.lock()?;
+
+        let mut pending = self.read(matcher)?.unwrap_or_else(|| PendingDigest {
+            matcher: matcher.to_string(),
+            started: proxmox_time::epoch_i64(),
+            notifications: Vec::new(),
+        });
+
+        pending.notifications.push(notification.clone());
+
+        if pending.notifications.len() >= config.max_items() as usize {
+            self.remove(matcher)?;
+            Ok(Some(pending))
+        } else {
+            self.store(&pending)?;
+            Ok(None)
+        }
+    }
+
+    /// Remove and return the pending notifications of all matchers whose window is over.
+    ///
+    /// `window` is called with the name of each matcher that has pending notifications and
+    /// returns its aggregation window in seconds. Pending notifications of matchers for which
+    /// `window` returns `None` are always returned.
+    pub(crate) fn take_due<F>(&self, window: F) -> Result<Vec<PendingDigest>, Error>
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        let _lock = self.dir.lock()?;
+        let now = proxmox_time::epoch_i64();
+
+        let mut due = Vec::new();
+
+        for matcher in self.dir.names()? {
+            let matcher = matcher.as_str();
+
+            let pending = match self.read(matcher) {
+                Ok(Some(pending)) => pending,
+                Ok(None) => continue,
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+
+            let is_due = match window(matcher) {
+                Some(window) => pending.started.saturating_add(window as i64) <= now,
+                None => true,
+            };
+
+            if is_due {
+                self.remove(matcher)?;
+                due.push(pending);
+            }
+        }
+
+        due.sort_by_key(|pending| pending.started);
+
+        Ok(due)
+    }
+}
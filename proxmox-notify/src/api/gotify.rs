@@ -89,6 +89,7 @@ pub fn update_endpoint(
             match deletable_property {
                 DeleteableGotifyProperty::Comment => endpoint.comment = None,
                 DeleteableGotifyProperty::Disable => endpoint.disable = None,
+                DeleteableGotifyProperty::Retry => endpoint.retry = None,
             }
         }
     }
@@ -115,6 +116,10 @@ pub fn update_endpoint(
         endpoint.disable = Some(disable);
     }
 
+    if let Some(retry) = endpoint_config_updater.retry {
+        endpoint.retry = Some(retry);
+    }
+
     config
         .config
         .set_data(name, GOTIFY_TYPENAME, &endpoint)
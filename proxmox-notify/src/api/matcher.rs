@@ -1,10 +1,10 @@
 use proxmox_http_error::HttpError;
 
-use crate::Config;
 use crate::api::http_err;
 use crate::matcher::{
     DeleteableMatcherProperty, MATCHER_TYPENAME, MatcherConfig, MatcherConfigUpdater,
 };
+use crate::{Config, http_bail};
 
 /// Get a list of all matchers
 ///
@@ -29,16 +29,38 @@ pub fn get_matcher(config: &Config, name: &str) -> Result<MatcherConfig, HttpErr
         .map_err(|_| http_err!(NOT_FOUND, "matcher '{name}' not found"))
 }
 
+/// Reject matcher settings for features which are not compiled in, since they would be
+/// silently ignored otherwise.
+fn ensure_supported(matcher: &MatcherConfig) -> Result<(), HttpError> {
+    if !cfg!(feature = "aggregation") && matcher.aggregate.is_some() {
+        http_bail!(
+            BAD_REQUEST,
+            "aggregation of notifications is not supported by this build"
+        );
+    }
+
+    if !cfg!(feature = "suppression") && matcher.suppress.is_some() {
+        http_bail!(
+            BAD_REQUEST,
+            "suppression of notifications is not supported by this build"
+        );
+    }
+
+    Ok(())
+}
+
 /// Add new notification matcher.
 ///
 /// The caller is responsible for any needed permission checks.
 /// The caller also responsible for locking the configuration files.
 /// Returns a `HttpError` if:
 ///   - an entity with the same name already exists (`400 Bad request`)
+///   - a setting is not supported by this build (`400 Bad request`)
 ///   - the configuration could not be saved (`500 Internal server error`)
 pub fn add_matcher(config: &mut Config, matcher_config: MatcherConfig) -> Result<(), HttpError> {
     super::ensure_unique(config, &matcher_config.name)?;
     super::ensure_endpoints_exist(config, &matcher_config.target)?;
+    ensure_supported(&matcher_config)?;
 
     config
         .config
@@ -61,6 +83,7 @@ pub fn add_matcher(config: &mut Config, matcher_config: MatcherConfig) -> Result
 /// Returns a `HttpError` if:
 ///   - the configuration could not be saved (`500 Internal server error`)
 ///   - an invalid digest was passed (`400 Bad request`)
+///   - a setting is not supported by this build (`400 Bad request`)
 pub fn update_matcher(
     config: &mut Config,
     name: &str,
@@ -83,6 +106,8 @@ pub fn update_matcher(
                 DeleteableMatcherProperty::InvertMatch => matcher.invert_match = None,
                 DeleteableMatcherProperty::Comment => matcher.comment = None,
                 DeleteableMatcherProperty::Disable => matcher.disable = None,
+                DeleteableMatcherProperty::Aggregate => matcher.aggregate = None,
+                DeleteableMatcherProperty::Suppress => matcher.suppress = None,
             }
         }
     }
@@ -115,11 +140,21 @@ pub fn update_matcher(
         matcher.disable = Some(disable);
     }
 
+    if let Some(aggregate) = matcher_updater.aggregate {
+        matcher.aggregate = Some(aggregate);
+    }
+
+    if let Some(suppress) = matcher_updater.suppress {
+        matcher.suppress = Some(suppress);
+    }
+
     if let Some(target) = matcher_updater.target {
         super::ensure_endpoints_exist(config, target.as_slice())?;
         matcher.target = target;
     }
 
+    ensure_supported(&matcher)?;
+
     config
         .config
         .set_data(name, MATCHER_TYPENAME, &matcher)
@@ -151,7 +186,9 @@ pub fn delete_matcher(config: &mut Config, name: &str) -> Result<(), HttpError>
 #[cfg(all(test, feature = "sendmail"))]
 mod tests {
     use super::*;
+    use crate::aggregation::AggregationConfig;
     use crate::matcher::MatchModeOperator;
+    use proxmox_schema::property_string::PropertyString;
 
     fn empty_config() -> Config {
         Config::new("", "").unwrap()
@@ -236,6 +273,7 @@ matcher: matcher2
                 DeleteableMatcherProperty::MatchField,
                 DeleteableMatcherProperty::Target,
                 DeleteableMatcherProperty::Comment,
+                DeleteableMatcherProperty::Aggregate,
             ]),
             Some(&digest),
         )?;
@@ -248,6 +286,7 @@ matcher: matcher2
         assert!(matcher.target.is_empty());
         assert!(matcher.mode.is_none());
         assert_eq!(matcher.comment, None);
+        assert!(matcher.aggregate.is_none());
 
         Ok(())
     }
@@ -261,4 +300,34 @@ matcher: matcher2
 
         Ok(())
     }
+
+    #[test]
+    fn test_matcher_update_aggregate() -> Result<(), HttpError> {
+        let mut config = config_with_two_matchers();
+
+        let result = update_matcher(
+            &mut config,
+            "matcher1",
+            MatcherConfigUpdater {
+                aggregate: Some(PropertyString::new(AggregationConfig {
+                    window: 3600,
+                    max_items: Some(10),
+                })),
+                ..Default::default()
+            },
+            None,
+            None,
+        );
+
+        if cfg!(feature = "aggregation") {
+            result?;
+            let matcher = get_matcher(&config, "matcher1")?;
+            assert_eq!(matcher.aggregate.as_deref().unwrap().window, 3600);
+        } else {
+            assert!(result.is_err());
+            assert!(get_matcher(&config, "matcher1")?.aggregate.is_none());
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,289 @@
+use proxmox_http_error::HttpError;
+
+use crate::Config;
+use crate::api::http_err;
+use crate::endpoints::matrix::{
+    DeleteableMatrixProperty, MATRIX_TYPENAME, MatrixConfig, MatrixConfigUpdater,
+    MatrixPrivateConfig, MatrixPrivateConfigUpdater,
+};
+
+/// Get a list of all matrix endpoints.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a list of all matrix endpoints or a `HttpError` if the config is
+/// erroneous (`500 Internal server error`).
+pub fn get_endpoints(config: &Config) -> Result<Vec<MatrixConfig>, HttpError> {
+    config
+        .config
+        .convert_to_typed_array(MATRIX_TYPENAME)
+        .map_err(|e| http_err!(NOT_FOUND, "Could not fetch endpoints: {e}"))
+}
+
+/// Get matrix endpoint with given `name`
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns the endpoint or a `HttpError` if the endpoint was not found (`404 Not found`).
+pub fn get_endpoint(config: &Config, name: &str) -> Result<MatrixConfig, HttpError> {
+    config
+        .config
+        .lookup(MATRIX_TYPENAME, name)
+        .map_err(|_| http_err!(NOT_FOUND, "endpoint '{name}' not found"))
+}
+
+/// Add a new matrix endpoint.
+///
+/// The caller is responsible for any needed permission checks.
+/// The caller also responsible for locking the configuration files.
+/// Returns a `HttpError` if:
+///   - an entity with the same name already exists (`400 Bad request`)
+///   - the configuration could not be saved (`500 Internal server error`)
+///
+/// Panics if the names of the private config and the public config do not match.
+pub fn add_endpoint(
+    config: &mut Config,
+    endpoint_config: MatrixConfig,
+    private_endpoint_config: MatrixPrivateConfig,
+) -> Result<(), HttpError> {
+    if endpoint_config.name != private_endpoint_config.name {
+        // Programming error by the user of the crate, thus we panic
+        panic!("name for endpoint config and private config must be identical");
+    }
+
+    super::ensure_unique(config, &endpoint_config.name)?;
+
+    super::set_private_config_entry(
+        config,
+        &private_endpoint_config,
+        MATRIX_TYPENAME,
+        &endpoint_config.name,
+    )?;
+
+    config
+        .config
+        .set_data(&endpoint_config.name, MATRIX_TYPENAME, &endpoint_config)
+        .map_err(|e| {
+            http_err!(
+                INTERNAL_SERVER_ERROR,
+                "could not save endpoint '{}': {e}",
+                endpoint_config.name
+            )
+        })
+}
+
+/// Update existing matrix endpoint
+///
+/// The caller is responsible for any needed permission checks.
+/// The caller also responsible for locking the configuration files.
+/// Returns a `HttpError` if:
+///   - the passed `digest` does not match (`400 Bad request`)
+///   - the configuration could not be saved (`500 Internal server error`)
+pub fn update_endpoint(
+    config: &mut Config,
+    name: &str,
+    endpoint_config_updater: MatrixConfigUpdater,
+    private_endpoint_config_updater: MatrixPrivateConfigUpdater,
+    delete: Option<&[DeleteableMatrixProperty]>,
+    digest: Option<&[u8]>,
+) -> Result<(), HttpError> {
+    super::verify_digest(config, digest)?;
+
+    let mut endpoint = get_endpoint(config, name)?;
+
+    if let Some(delete) = delete {
+        for deletable_property in delete {
+            match deletable_property {
+                DeleteableMatrixProperty::Comment => endpoint.comment = None,
+                DeleteableMatrixProperty::Disable => endpoint.disable = None,
+                DeleteableMatrixProperty::Retry => endpoint.retry = None,
+            }
+        }
+    }
+
+    if let Some(homeserver) = endpoint_config_updater.homeserver {
+        endpoint.homeserver = homeserver;
+    }
+
+    if let Some(room) = endpoint_config_updater.room {
+        endpoint.room = room;
+    }
+
+    if let Some(access_token) = private_endpoint_config_updater.access_token {
+        super::set_private_config_entry(
+            config,
+            &MatrixPrivateConfig {
+                name: name.into(),
+                access_token,
+            },
+            MATRIX_TYPENAME,
+            name,
+        )?;
+    }
+
+    if let Some(comment) = endpoint_config_updater.comment {
+        endpoint.comment = Some(comment)
+    }
+
+    if let Some(disable) = endpoint_config_updater.disable {
+        endpoint.disable = Some(disable);
+    }
+
+    if let Some(retry) = endpoint_config_updater.retry {
+        endpoint.retry = Some(retry);
+    }
+
+    config
+        .config
+        .set_data(name, MATRIX_TYPENAME, &endpoint)
+        .map_err(|e| {
+            http_err!(
+                INTERNAL_SERVER_ERROR,
+                "could not save endpoint '{name}': {e}"
+            )
+        })
+}
+
+/// Delete existing matrix endpoint
+///
+/// The caller is responsible for any needed permission checks.
+/// The caller also responsible for locking the configuration files.
+/// Returns a `HttpError` if:
+///   - the entity does not exist (`404 Not found`)
+///   - the endpoint is still referenced by another entity (`400 Bad request`)
+pub fn delete_endpoint(config: &mut Config, name: &str) -> Result<(), HttpError> {
+    // Check if the endpoint exists
+    let _ = get_endpoint(config, name)?;
+    super::ensure_safe_to_delete(config, name)?;
+
+    super::remove_private_config_entry(config, name)?;
+    config.config.sections.remove(name);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_helpers::empty_config;
+
+    pub fn add_default_matrix_endpoint(config: &mut Config) -> Result<(), HttpError> {
+        add_endpoint(
+            config,
+            MatrixConfig {
+                name: "matrix-endpoint".into(),
+                homeserver: "https://matrix.example.com".into(),
+                room: "!abcdef:example.com".into(),
+                comment: Some("comment".into()),
+                ..Default::default()
+            },
+            MatrixPrivateConfig {
+                name: "matrix-endpoint".into(),
+                access_token: "supersecrettoken".into(),
+            },
+        )?;
+
+        assert!(get_endpoint(config, "matrix-endpoint").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_not_existing_returns_error() -> Result<(), HttpError> {
+        let mut config = empty_config();
+
+        assert!(
+            update_endpoint(
+                &mut config,
+                "test",
+                Default::default(),
+                Default::default(),
+                None,
+                None
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_invalid_digest_returns_error() -> Result<(), HttpError> {
+        let mut config = empty_config();
+        add_default_matrix_endpoint(&mut config)?;
+
+        assert!(
+            update_endpoint(
+                &mut config,
+                "matrix-endpoint",
+                Default::default(),
+                Default::default(),
+                None,
+                Some(&[0; 32])
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_matrix_update() -> Result<(), HttpError> {
+        let mut config = empty_config();
+        add_default_matrix_endpoint(&mut config)?;
+
+        let digest = config.digest;
+
+        update_endpoint(
+            &mut config,
+            "matrix-endpoint",
+            MatrixConfigUpdater {
+                room: Some("!ghijkl:example.com".into()),
+                comment: Some("newcomment".into()),
+                ..Default::default()
+            },
+            MatrixPrivateConfigUpdater {
+                access_token: Some("changedtoken".into()),
+            },
+            None,
+            Some(&digest),
+        )?;
+
+        let endpoint = get_endpoint(&config, "matrix-endpoint")?;
+
+        assert_eq!(endpoint.room, "!ghijkl:example.com".to_string());
+        assert_eq!(endpoint.comment, Some("newcomment".to_string()));
+
+        let access_token = config
+            .private_config
+            .lookup::<MatrixPrivateConfig>(MATRIX_TYPENAME, "matrix-endpoint")
+            .unwrap()
+            .access_token;
+
+        assert_eq!(access_token, "changedtoken".to_string());
+
+        // Test property deletion
+        update_endpoint(
+            &mut config,
+            "matrix-endpoint",
+            Default::default(),
+            Default::default(),
+            Some(&[DeleteableMatrixProperty::Comment]),
+            None,
+        )?;
+
+        let endpoint = get_endpoint(&config, "matrix-endpoint")?;
+        assert_eq!(endpoint.comment, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_matrix_endpoint_delete() -> Result<(), HttpError> {
+        let mut config = empty_config();
+        add_default_matrix_endpoint(&mut config)?;
+
+        delete_endpoint(&mut config, "matrix-endpoint")?;
+        assert!(delete_endpoint(&mut config, "matrix-endpoint").is_err());
+        assert_eq!(get_endpoints(&config)?.len(), 0);
+
+        Ok(())
+    }
+}
@@ -11,6 +11,12 @@ pub mod common;
 #[cfg(feature = "gotify")]
 pub mod gotify;
 pub mod matcher;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+#[cfg(feature = "ntfy")]
+pub mod ntfy;
+#[cfg(feature = "retry-queue")]
+pub mod queue;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
 #[cfg(feature = "smtp")]
@@ -59,6 +65,12 @@ pub enum EndpointType {
     /// Webhook endpoint
     #[cfg(feature = "webhook")]
     Webhook,
+    /// ntfy endpoint
+    #[cfg(feature = "ntfy")]
+    Ntfy,
+    /// Matrix endpoint
+    #[cfg(feature = "matrix")]
+    Matrix,
 }
 
 #[api]
@@ -129,6 +141,28 @@ pub fn get_targets(config: &Config) -> Result<Vec<Target>, HttpError> {
         })
     }
 
+    #[cfg(feature = "ntfy")]
+    for endpoint in ntfy::get_endpoints(config)? {
+        targets.push(Target {
+            name: endpoint.name,
+            origin: endpoint.origin.unwrap_or(Origin::UserCreated),
+            endpoint_type: EndpointType::Ntfy,
+            disable: endpoint.disable,
+            comment: endpoint.comment,
+        })
+    }
+
+    #[cfg(feature = "matrix")]
+    for endpoint in matrix::get_endpoints(config)? {
+        targets.push(Target {
+            name: endpoint.name,
+            origin: endpoint.origin.unwrap_or(Origin::UserCreated),
+            endpoint_type: EndpointType::Matrix,
+            disable: endpoint.disable,
+            comment: endpoint.comment,
+        })
+    }
+
     Ok(targets)
 }
 
@@ -165,6 +199,14 @@ fn ensure_endpoint_exists(#[allow(unused)] config: &Config, name: &str) -> Resul
     {
         exists = exists || webhook::get_endpoint(config, name).is_ok();
     }
+    #[cfg(feature = "ntfy")]
+    {
+        exists = exists || ntfy::get_endpoint(config, name).is_ok();
+    }
+    #[cfg(feature = "matrix")]
+    {
+        exists = exists || matrix::get_endpoint(config, name).is_ok();
+    }
 
     if !exists {
         http_bail!(NOT_FOUND, "endpoint '{name}' does not exist")
@@ -0,0 +1,96 @@
+//! API for the queue of notifications which could not be delivered.
+//!
+//! All functions assume that the caller has already done any required permission checks.
+
+use proxmox_http_error::HttpError;
+
+use crate::api::http_err;
+use crate::queue::{QueuedNotificationInfo, RetryQueue};
+use crate::{Bus, Config, http_bail};
+
+fn retry_queue() -> Result<RetryQueue, HttpError> {
+    RetryQueue::from_context()
+        .ok_or_else(|| http_err!(NOT_IMPLEMENTED, "notification retry queue is not available"))
+}
+
+/// Get a list of all queued notifications.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a `HttpError` if the queue could not be read (`500 Internal server error`).
+pub fn get_queued_notifications() -> Result<Vec<QueuedNotificationInfo>, HttpError> {
+    retry_queue()?
+        .list()
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "could not read queue: {err}"))
+}
+
+/// Immediately retry sending the queued notification with the given `id`.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a `HttpError` if:
+///   - the queue entry does not exist (`404 Not found`)
+///   - sending the notification failed again (`500 Internal server error`)
+pub fn retry_queued_notification(config: &Config, id: &str) -> Result<(), HttpError> {
+    let queue = retry_queue()?;
+
+    let bus = Bus::from_config(config).map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not instantiate notification bus: {err}"
+        )
+    })?;
+
+    // The lock is only needed for reading the entry, see `Bus::redeliver`.
+    let entry = {
+        let _lock = queue
+            .lock()
+            .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "{err}"))?;
+
+        queue
+            .lookup(id)
+            .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "could not read queue: {err}"))?
+            .ok_or_else(|| http_err!(NOT_FOUND, "queued notification '{id}' does not exist"))?
+    };
+
+    bus.redeliver(&queue, entry).map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "could not send queued notification: {err}"
+        )
+    })
+}
+
+/// Send all queued notifications which are due for a retry.
+///
+/// The caller is responsible for any needed permission checks.
+/// Errors of individual notifications are only logged.
+/// Returns a `HttpError` if the queue could not be processed (`500 Internal server error`).
+pub fn process_queued_notifications(config: &Config) -> Result<(), HttpError> {
+    let bus = Bus::from_config(config).map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not instantiate notification bus: {err}"
+        )
+    })?;
+
+    bus.process_retry_queue()
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "could not process queue: {err}"))
+}
+
+/// Drop the queued notification with the given `id`, or all queued notifications if `id`
+/// is `None`.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a `HttpError` if:
+///   - the queue entry does not exist (`404 Not found`)
+///   - the queue could not be modified (`500 Internal server error`)
+pub fn purge_queued_notifications(id: Option<&str>) -> Result<(), HttpError> {
+    let found = retry_queue()?
+        .purge(id)
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "could not purge queue: {err}"))?;
+
+    if let (false, Some(id)) = (found, id) {
+        http_bail!(NOT_FOUND, "queued notification '{id}' does not exist");
+    }
+
+    Ok(())
+}
@@ -86,6 +86,7 @@ pub fn update_endpoint(
                 DeleteableSendmailProperty::Mailto => endpoint.mailto.clear(),
                 DeleteableSendmailProperty::MailtoUser => endpoint.mailto_user.clear(),
                 DeleteableSendmailProperty::Disable => endpoint.disable = None,
+                DeleteableSendmailProperty::Retry => endpoint.retry = None,
             }
         }
     }
@@ -114,6 +115,10 @@ pub fn update_endpoint(
         endpoint.disable = Some(disable);
     }
 
+    if let Some(retry) = updater.retry {
+        endpoint.retry = Some(retry);
+    }
+
     if endpoint.mailto.is_empty() && endpoint.mailto_user.is_empty() {
         http_bail!(
             BAD_REQUEST,
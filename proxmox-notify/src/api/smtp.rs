@@ -113,6 +113,7 @@ pub fn update_endpoint(
                     name,
                 )?,
                 DeleteableSmtpProperty::Port => endpoint.port = None,
+                DeleteableSmtpProperty::Retry => endpoint.retry = None,
                 DeleteableSmtpProperty::Username => endpoint.username = None,
             }
         }
@@ -163,6 +164,10 @@ pub fn update_endpoint(
         endpoint.disable = Some(disable);
     }
 
+    if let Some(retry) = updater.retry {
+        endpoint.retry = Some(retry);
+    }
+
     if endpoint.mailto.is_empty() && endpoint.mailto_user.is_empty() {
         http_bail!(
             BAD_REQUEST,
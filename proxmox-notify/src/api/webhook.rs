@@ -154,6 +154,7 @@ pub fn update_endpoint(
             match deleteable_property {
                 DeleteableWebhookProperty::Comment => endpoint.comment = None,
                 DeleteableWebhookProperty::Disable => endpoint.disable = None,
+                DeleteableWebhookProperty::Retry => endpoint.retry = None,
                 DeleteableWebhookProperty::Header => endpoint.header = Vec::new(),
                 DeleteableWebhookProperty::Body => endpoint.body = None,
                 DeleteableWebhookProperty::Secret => {
@@ -180,6 +181,7 @@ pub fn update_endpoint(
         disable,
         comment,
         secret,
+        retry,
     } = config_updater;
 
     if let Some(url) = url {
@@ -214,6 +216,10 @@ pub fn update_endpoint(
         endpoint.disable = Some(disable);
     }
 
+    if let Some(retry) = retry {
+        endpoint.retry = Some(retry);
+    }
+
     if let Some(comment) = comment {
         endpoint.comment = Some(comment);
     }
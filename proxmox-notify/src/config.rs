@@ -67,6 +67,29 @@ fn config_init() -> SectionConfig {
         ));
     }
 
+    #[cfg(feature = "ntfy")]
+    {
+        use crate::endpoints::ntfy::{NTFY_TYPENAME, NtfyConfig};
+
+        const NTFY_SCHEMA: &ObjectSchema = NtfyConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            NTFY_TYPENAME.to_string(),
+            Some(String::from("name")),
+            NTFY_SCHEMA,
+        ));
+    }
+    #[cfg(feature = "matrix")]
+    {
+        use crate::endpoints::matrix::{MATRIX_TYPENAME, MatrixConfig};
+
+        const MATRIX_SCHEMA: &ObjectSchema = MatrixConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            MATRIX_TYPENAME.to_string(),
+            Some(String::from("name")),
+            MATRIX_SCHEMA,
+        ));
+    }
+
     const MATCHER_SCHEMA: &ObjectSchema = MatcherConfig::API_SCHEMA.unwrap_object_schema();
     config.register_plugin(SectionConfigPlugin::new(
         MATCHER_TYPENAME.to_string(),
@@ -117,6 +140,30 @@ fn private_config_init() -> SectionConfig {
             WEBHOOK_SCHEMA,
         ));
     }
+
+    #[cfg(feature = "ntfy")]
+    {
+        use crate::endpoints::ntfy::{NTFY_TYPENAME, NtfyPrivateConfig};
+
+        const NTFY_SCHEMA: &ObjectSchema = NtfyPrivateConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            NTFY_TYPENAME.to_string(),
+            Some(String::from("name")),
+            NTFY_SCHEMA,
+        ));
+    }
+
+    #[cfg(feature = "matrix")]
+    {
+        use crate::endpoints::matrix::{MATRIX_TYPENAME, MatrixPrivateConfig};
+
+        const MATRIX_SCHEMA: &ObjectSchema = MatrixPrivateConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            MATRIX_TYPENAME.to_string(),
+            Some(String::from("name")),
+            MATRIX_SCHEMA,
+        ));
+    }
     config
 }
 
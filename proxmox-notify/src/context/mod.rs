@@ -32,6 +32,21 @@ pub trait Context: Send + Sync + Debug {
         namespace: Option<&str>,
         source: TemplateSource,
     ) -> Result<Option<String>, Error>;
+    /// Spool directory for notifications which could not be delivered and are queued for
+    /// a retry. Returns `None` if failed notifications should not be queued.
+    fn retry_queue_dir(&self) -> Option<&'static str> {
+        None
+    }
+    /// Directory for notifications collected by matchers with aggregation enabled.
+    /// Returns `None` if notifications should always be sent immediately.
+    fn aggregation_dir(&self) -> Option<&'static str> {
+        None
+    }
+    /// Directory for the state of matchers with suppression of repeated notifications
+    /// enabled. Returns `None` if repeated notifications should never be suppressed.
+    fn suppression_dir(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 #[cfg(not(test))]
@@ -12,6 +12,9 @@ use crate::renderer::TemplateSource;
 
 const PBS_USER_CFG_FILENAME: &str = "/etc/proxmox-backup/user.cfg";
 const PBS_NODE_CFG_FILENAME: &str = "/etc/proxmox-backup/node.cfg";
+const PBS_RETRY_QUEUE_DIR: &str = "/var/lib/proxmox-backup/notification-retry-queue";
+const PBS_AGGREGATION_DIR: &str = "/var/lib/proxmox-backup/notification-aggregation";
+const PBS_SUPPRESSION_DIR: &str = "/var/lib/proxmox-backup/notification-suppression";
 
 // FIXME: Switch to the actual schema when possible in terms of dependency.
 // It's safe to assume that the config was written with the actual schema restrictions, so parsing
@@ -125,6 +128,18 @@ impl Context for PBSContext {
             .map_err(|err| Error::Generic(format!("could not load template: {err}")))?;
         Ok(template_string)
     }
+
+    fn retry_queue_dir(&self) -> Option<&'static str> {
+        Some(PBS_RETRY_QUEUE_DIR)
+    }
+
+    fn aggregation_dir(&self) -> Option<&'static str> {
+        Some(PBS_AGGREGATION_DIR)
+    }
+
+    fn suppression_dir(&self) -> Option<&'static str> {
+        Some(PBS_SUPPRESSION_DIR)
+    }
 }
 
 #[cfg(test)]
@@ -14,6 +14,10 @@ fn lookup_mail_address(content: &str, user: &str) -> Option<String> {
     }))
 }
 
+const PVE_RETRY_QUEUE_DIR: &str = "/var/lib/pve-manager/notification-retry-queue";
+const PVE_AGGREGATION_DIR: &str = "/var/lib/pve-manager/notification-aggregation";
+const PVE_SUPPRESSION_DIR: &str = "/var/lib/pve-manager/notification-suppression";
+
 const DEFAULT_CONFIG: &str = "\
 sendmail: mail-to-root
 	comment Send mails to root@pam's email address
@@ -74,6 +78,18 @@ impl Context for PVEContext {
             .map_err(|err| Error::Generic(format!("could not load template: {err}")))?;
         Ok(template_string)
     }
+
+    fn retry_queue_dir(&self) -> Option<&'static str> {
+        Some(PVE_RETRY_QUEUE_DIR)
+    }
+
+    fn aggregation_dir(&self) -> Option<&'static str> {
+        Some(PVE_AGGREGATION_DIR)
+    }
+
+    fn suppression_dir(&self) -> Option<&'static str> {
+        Some(PVE_SUPPRESSION_DIR)
+    }
 }
 
 pub static PVE_CONTEXT: PVEContext = PVEContext;
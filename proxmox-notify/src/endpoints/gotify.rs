@@ -7,9 +7,11 @@ use serde_json::json;
 use proxmox_http::client::sync::Client;
 use proxmox_http::{HttpClient, HttpOptions, ProxyConfig};
 use proxmox_schema::api_types::COMMENT_SCHEMA;
+use proxmox_schema::property_string::PropertyString;
 use proxmox_schema::{Updater, api};
 
 use crate::context::context;
+use crate::queue::{RETRY_POLICY_SCHEMA, RetryPolicy};
 use crate::renderer::TemplateType;
 use crate::schema::ENTITY_NAME_SCHEMA;
 use crate::{Content, Endpoint, Error, Notification, Origin, Severity, renderer};
@@ -37,6 +39,10 @@ pub(crate) const GOTIFY_TYPENAME: &str = "gotify";
             optional: true,
             schema: COMMENT_SCHEMA,
         },
+        retry: {
+            optional: true,
+            schema: RETRY_POLICY_SCHEMA,
+        },
     }
 )]
 #[derive(Serialize, Deserialize, Updater, Default)]
@@ -58,6 +64,9 @@ pub struct GotifyConfig {
     /// Disable this target.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable: Option<bool>,
+    /// Retry policy for notifications which could not be delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PropertyString<RetryPolicy>>,
     /// Origin of this config entry.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[updater(skip)]
@@ -93,6 +102,8 @@ pub enum DeleteableGotifyProperty {
     Comment,
     /// Delete `disable`
     Disable,
+    /// Delete `retry`
+    Retry,
 }
 
 impl Endpoint for GotifyEndpoint {
@@ -176,4 +187,8 @@ impl Endpoint for GotifyEndpoint {
     fn disabled(&self) -> bool {
         self.config.disable.unwrap_or_default()
     }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry.as_deref().copied().unwrap_or_default()
+    }
 }
@@ -0,0 +1,302 @@
+//! This endpoint sends notifications as messages to a [Matrix](https://matrix.org) room.
+//!
+//! Messages are sent via the client-server API of the configured homeserver, authenticated
+//! with the access token of an account which has already joined the room. The access token
+//! is stored in the private configuration file.
+use std::time::Duration;
+
+use http::Request;
+use percent_encoding::NON_ALPHANUMERIC;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use proxmox_http::client::sync::Client;
+use proxmox_http::{HttpClient, HttpOptions, ProxyConfig};
+use proxmox_schema::api_types::{COMMENT_SCHEMA, HTTP_URL_SCHEMA};
+use proxmox_schema::property_string::PropertyString;
+use proxmox_schema::{ApiStringFormat, Schema, StringSchema, Updater, api, const_regex};
+
+use crate::context::context;
+use crate::queue::{RETRY_POLICY_SCHEMA, RetryPolicy};
+use crate::renderer::TemplateType;
+use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::{Content, Endpoint, Error, Notification, Origin, renderer};
+
+/// This will be used as a section type in the public/private configuration file.
+pub(crate) const MATRIX_TYPENAME: &str = "matrix";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const_regex! {
+    MATRIX_ROOM_ID_REGEX = r"^![^\s:]+:\S+$";
+}
+
+pub const MATRIX_ROOM_ID_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&MATRIX_ROOM_ID_REGEX);
+
+pub const MATRIX_ROOM_ID_SCHEMA: Schema =
+    StringSchema::new("Matrix room ID, e.g. '!abcdefg:example.org'.")
+        .format(&MATRIX_ROOM_ID_FORMAT)
+        .min_length(3)
+        .max_length(255)
+        .schema();
+
+#[api(
+    properties: {
+        name: {
+            schema: ENTITY_NAME_SCHEMA,
+        },
+        homeserver: {
+            schema: HTTP_URL_SCHEMA,
+        },
+        room: {
+            schema: MATRIX_ROOM_ID_SCHEMA,
+        },
+        comment: {
+            optional: true,
+            schema: COMMENT_SCHEMA,
+        },
+        retry: {
+            optional: true,
+            schema: RETRY_POLICY_SCHEMA,
+        },
+    }
+)]
+#[derive(Serialize, Deserialize, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Config for Matrix notification endpoints
+pub struct MatrixConfig {
+    /// Name of the endpoint.
+    #[updater(skip)]
+    pub name: String,
+    /// URL of the Matrix homeserver, e.g. https://matrix.example.org
+    pub homeserver: String,
+    /// ID of the room to send notifications to.
+    pub room: String,
+    /// Comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Disable this target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable: Option<bool>,
+    /// Retry policy for notifications which could not be delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PropertyString<RetryPolicy>>,
+    /// Origin of this config entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[updater(skip)]
+    pub origin: Option<Origin>,
+}
+
+#[api()]
+#[derive(Serialize, Deserialize, Clone, Updater)]
+#[serde(rename_all = "kebab-case")]
+/// Private configuration for Matrix notification endpoints.
+/// This config will be saved to a separate configuration file with stricter
+/// permissions (root:root 0600)
+pub struct MatrixPrivateConfig {
+    /// Name of the endpoint
+    #[updater(skip)]
+    pub name: String,
+    /// Access token of the Matrix account used for sending messages.
+    pub access_token: String,
+}
+
+/// A Matrix notification endpoint.
+pub struct MatrixEndpoint {
+    pub config: MatrixConfig,
+    pub private_config: MatrixPrivateConfig,
+}
+
+#[api]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// The set of properties that can be deleted from a Matrix endpoint configuration.
+pub enum DeleteableMatrixProperty {
+    /// Delete `comment`
+    Comment,
+    /// Delete `disable`
+    Disable,
+    /// Delete `retry`
+    Retry,
+}
+
+impl Endpoint for MatrixEndpoint {
+    fn send(&self, notification: &Notification) -> Result<(), Error> {
+        let request = self.build_request(notification)?;
+
+        let proxy_config = context()
+            .http_proxy_config()
+            .map(|url| ProxyConfig::parse_proxy_url(&url))
+            .transpose()
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        let options = HttpOptions {
+            proxy_config,
+            ..Default::default()
+        };
+
+        Client::new_with_timeout(options, HTTP_TIMEOUT)
+            .request(request)
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Check if the endpoint is disabled
+    fn disabled(&self) -> bool {
+        self.config.disable.unwrap_or_default()
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry.as_deref().copied().unwrap_or_default()
+    }
+}
+
+impl MatrixEndpoint {
+    fn build_request(&self, notification: &Notification) -> Result<Request<String>, Error> {
+        let (title, message) = match &notification.content {
+            Content::Template {
+                template_name,
+                data,
+            } => {
+                let rendered_title =
+                    renderer::render_template(TemplateType::Subject, template_name, data)?;
+                let rendered_message =
+                    renderer::render_template(TemplateType::PlaintextBody, template_name, data)?;
+
+                (rendered_title, rendered_message)
+            }
+            #[cfg(feature = "mail-forwarder")]
+            Content::ForwardedMail { title, body, .. } => (title.clone(), body.clone()),
+        };
+
+        // Plaintext bodies contain tables etc., so keep them pre-formatted
+        let formatted_body = format!(
+            "<strong>{}</strong><pre>{}</pre>",
+            handlebars::html_escape(&title),
+            handlebars::html_escape(&message)
+        );
+
+        let body = json!({
+            "msgtype": "m.text",
+            "body": format!("{title}\n\n{message}"),
+            "format": "org.matrix.custom.html",
+            "formatted_body": formatted_body,
+        });
+
+        let body = serde_json::to_string(&body)
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        // The homeserver deduplicates messages by transaction ID per access token. Derive it
+        // from the notification ID, the target and the room, so that sending the same
+        // notification more than once is deduplicated, while other targets and rooms sharing
+        // the access token still get it.
+        let txn = format!(
+            "{}-{}-{}",
+            notification.id(),
+            self.config.name,
+            self.config.room
+        );
+        let uri = format!(
+            "{homeserver}/_matrix/client/v3/rooms/{room}/send/m.room.message/{txn}",
+            homeserver = self.config.homeserver.trim_end_matches('/'),
+            room = percent_encoding::utf8_percent_encode(&self.config.room, NON_ALPHANUMERIC),
+            txn = percent_encoding::utf8_percent_encode(&txn, NON_ALPHANUMERIC),
+        );
+
+        Request::builder()
+            .method("PUT")
+            .uri(uri)
+            .header(
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", self.private_config.access_token),
+            )
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::CONTENT_LENGTH, body.len())
+            .body(body)
+            .map_err(|err| Error::Generic(format!("failed to build http request: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request() -> Result<(), Error> {
+        let endpoint = MatrixEndpoint {
+            config: MatrixConfig {
+                name: "matrix".into(),
+                homeserver: "https://matrix.example.org/".into(),
+                room: "!abcdef:example.org".into(),
+                ..Default::default()
+            },
+            private_config: MatrixPrivateConfig {
+                name: "matrix".into(),
+                access_token: "secret".into(),
+            },
+        };
+
+        let notification = Notification::from_template(
+            crate::Severity::Info,
+            "test",
+            Default::default(),
+            Default::default(),
+        );
+
+        let request = endpoint.build_request(&notification)?;
+
+        assert_eq!(request.method(), "PUT");
+        assert_eq!(
+            request.uri().to_string(),
+            format!(
+                "https://matrix.example.org/_matrix/client/v3/rooms/%21abcdef%3Aexample%2Eorg/send/m.room.message/{}",
+                notification.id().to_string().replace('-', "%2D")
+                    + "%2Dmatrix%2D%21abcdef%3Aexample%2Eorg"
+            )
+        );
+
+        // Another target sharing the access token must not use the same transaction ID
+        let other = MatrixEndpoint {
+            config: MatrixConfig {
+                name: "matrix-ops".into(),
+                homeserver: "https://matrix.example.org/".into(),
+                room: "!abcdef:example.org".into(),
+                ..Default::default()
+            },
+            private_config: endpoint.private_config.clone(),
+        };
+        let txn = |request: &Request<String>| {
+            request.uri().path().rsplit_once('/').unwrap().1.to_string()
+        };
+        assert_ne!(txn(&request), txn(&other.build_request(&notification)?));
+        assert_eq!(
+            request.headers().get(http::header::AUTHORIZATION).unwrap(),
+            "Bearer secret"
+        );
+
+        let body: serde_json::Value = serde_json::from_str(request.body()).unwrap();
+        assert_eq!(body["msgtype"], "m.text");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_room_id_schema() {
+        assert!(
+            MATRIX_ROOM_ID_SCHEMA
+                .parse_simple_value("!abc:example.org")
+                .is_ok()
+        );
+        assert!(
+            MATRIX_ROOM_ID_SCHEMA
+                .parse_simple_value("#alias:example.org")
+                .is_err()
+        );
+        assert!(MATRIX_ROOM_ID_SCHEMA.parse_simple_value("!abc").is_err());
+    }
+}
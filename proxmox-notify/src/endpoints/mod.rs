@@ -1,5 +1,9 @@
 #[cfg(feature = "gotify")]
 pub mod gotify;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+#[cfg(feature = "ntfy")]
+pub mod ntfy;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
 #[cfg(feature = "smtp")]
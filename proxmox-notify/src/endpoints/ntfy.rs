@@ -0,0 +1,263 @@
+//! This endpoint publishes notifications to a topic on a [ntfy](https://ntfy.sh) server.
+//!
+//! The priority and the tags of the published message are derived from the severity
+//! of the notification. An optional access token can be stored in the private
+//! configuration file for topics which require authentication.
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use proxmox_http::client::sync::Client;
+use proxmox_http::{HttpClient, HttpOptions, ProxyConfig};
+use proxmox_schema::api_types::{COMMENT_SCHEMA, HTTP_URL_SCHEMA};
+use proxmox_schema::property_string::PropertyString;
+use proxmox_schema::{ApiStringFormat, Schema, StringSchema, Updater, api, const_regex};
+
+use crate::context::context;
+use crate::queue::{RETRY_POLICY_SCHEMA, RetryPolicy};
+use crate::renderer::TemplateType;
+use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::{Content, Endpoint, Error, Notification, Origin, Severity, renderer};
+
+/// This will be used as a section type in the public/private configuration file.
+pub(crate) const NTFY_TYPENAME: &str = "ntfy";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const_regex! {
+    NTFY_TOPIC_REGEX = r"^[-_A-Za-z0-9]{1,64}$";
+}
+
+pub const NTFY_TOPIC_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&NTFY_TOPIC_REGEX);
+
+pub const NTFY_TOPIC_SCHEMA: Schema = StringSchema::new("ntfy topic.")
+    .format(&NTFY_TOPIC_FORMAT)
+    .min_length(1)
+    .max_length(64)
+    .schema();
+
+/// Map the severity to one of ntfy's message priorities (1 = min, 5 = max).
+fn severity_to_priority(level: Severity) -> u8 {
+    match level {
+        Severity::Info => 2,
+        Severity::Notice => 3,
+        Severity::Warning => 4,
+        Severity::Error => 5,
+        Severity::Unknown => 3,
+    }
+}
+
+/// Map the severity to tags. Tags matching an emoji short code are displayed as emojis
+/// by ntfy clients.
+fn severity_to_tags(level: Severity) -> Vec<String> {
+    let emoji = match level {
+        Severity::Info => "information_source",
+        Severity::Notice => "memo",
+        Severity::Warning => "warning",
+        Severity::Error => "rotating_light",
+        Severity::Unknown => "grey_question",
+    };
+
+    vec![emoji.to_string()]
+}
+
+#[api(
+    properties: {
+        name: {
+            schema: ENTITY_NAME_SCHEMA,
+        },
+        server: {
+            schema: HTTP_URL_SCHEMA,
+        },
+        topic: {
+            schema: NTFY_TOPIC_SCHEMA,
+        },
+        comment: {
+            optional: true,
+            schema: COMMENT_SCHEMA,
+        },
+        retry: {
+            optional: true,
+            schema: RETRY_POLICY_SCHEMA,
+        },
+    }
+)]
+#[derive(Serialize, Deserialize, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Config for ntfy notification endpoints
+pub struct NtfyConfig {
+    /// Name of the endpoint.
+    #[updater(skip)]
+    pub name: String,
+    /// ntfy server URL, e.g. https://ntfy.sh
+    pub server: String,
+    /// Topic to publish notifications to.
+    pub topic: String,
+    /// Comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Disable this target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable: Option<bool>,
+    /// Retry policy for notifications which could not be delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PropertyString<RetryPolicy>>,
+    /// Origin of this config entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[updater(skip)]
+    pub origin: Option<Origin>,
+}
+
+#[api()]
+#[derive(Serialize, Deserialize, Clone, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Private configuration for ntfy notification endpoints.
+/// This config will be saved to a separate configuration file with stricter
+/// permissions (root:root 0600)
+pub struct NtfyPrivateConfig {
+    /// Name of the endpoint
+    #[updater(skip)]
+    pub name: String,
+    /// Access token, required if the topic is protected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// A ntfy notification endpoint.
+pub struct NtfyEndpoint {
+    pub config: NtfyConfig,
+    pub private_config: NtfyPrivateConfig,
+}
+
+#[api]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// The set of properties that can be deleted from a ntfy endpoint configuration.
+pub enum DeleteableNtfyProperty {
+    /// Delete `comment`
+    Comment,
+    /// Delete `disable`
+    Disable,
+    /// Delete `retry`
+    Retry,
+    /// Delete `token`
+    Token,
+}
+
+impl Endpoint for NtfyEndpoint {
+    fn send(&self, notification: &Notification) -> Result<(), Error> {
+        let (title, message) = match &notification.content {
+            Content::Template {
+                template_name,
+                data,
+            } => {
+                let rendered_title =
+                    renderer::render_template(TemplateType::Subject, template_name, data)?;
+                let rendered_message =
+                    renderer::render_template(TemplateType::PlaintextBody, template_name, data)?;
+
+                (rendered_title, rendered_message)
+            }
+            #[cfg(feature = "mail-forwarder")]
+            Content::ForwardedMail { title, body, .. } => (title.clone(), body.clone()),
+        };
+
+        let severity = notification.metadata.severity;
+
+        let body = json!({
+            "topic": &self.config.topic,
+            "title": &title,
+            "message": &message,
+            "priority": severity_to_priority(severity),
+            "tags": severity_to_tags(severity),
+        });
+
+        let body = serde_json::to_string(&body)
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        let mut extra_headers = HashMap::from([(
+            http::header::CONTENT_LENGTH.to_string(),
+            body.len().to_string(),
+        )]);
+
+        if let Some(token) = &self.private_config.token {
+            extra_headers.insert(
+                http::header::AUTHORIZATION.to_string(),
+                format!("Bearer {token}"),
+            );
+        }
+
+        let proxy_config = context()
+            .http_proxy_config()
+            .map(|url| ProxyConfig::parse_proxy_url(&url))
+            .transpose()
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        let options = HttpOptions {
+            proxy_config,
+            ..Default::default()
+        };
+
+        let client = Client::new_with_timeout(options, HTTP_TIMEOUT);
+
+        // Messages are published as JSON by posting to the root URL of the server
+        let uri = format!("{}/", self.config.server.trim_end_matches('/'));
+
+        client
+            .post(
+                &uri,
+                Some(body),
+                Some("application/json"),
+                Some(&extra_headers),
+            )
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Check if the endpoint is disabled
+    fn disabled(&self) -> bool {
+        self.config.disable.unwrap_or_default()
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry.as_deref().copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_schema() {
+        assert!(
+            NTFY_TOPIC_SCHEMA
+                .parse_simple_value("backups_node-1")
+                .is_ok()
+        );
+        assert!(NTFY_TOPIC_SCHEMA.parse_simple_value("").is_err());
+        assert!(NTFY_TOPIC_SCHEMA.parse_simple_value("foo/bar").is_err());
+        assert!(
+            NTFY_TOPIC_SCHEMA
+                .parse_simple_value(&"a".repeat(65))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_severity_mapping() {
+        assert_eq!(severity_to_priority(Severity::Info), 2);
+        assert_eq!(severity_to_priority(Severity::Error), 5);
+        assert_eq!(
+            severity_to_tags(Severity::Warning),
+            vec!["warning".to_string()]
+        );
+    }
+}
@@ -2,10 +2,12 @@ use proxmox_sendmail::Mail;
 use serde::{Deserialize, Serialize};
 
 use proxmox_schema::api_types::COMMENT_SCHEMA;
+use proxmox_schema::property_string::PropertyString;
 use proxmox_schema::{Updater, api};
 
 use crate::context;
 use crate::endpoints::common::mail;
+use crate::queue::{RETRY_POLICY_SCHEMA, RetryPolicy};
 use crate::renderer::TemplateType;
 use crate::schema::{EMAIL_SCHEMA, ENTITY_NAME_SCHEMA, USER_SCHEMA};
 use crate::{Content, Endpoint, Error, Notification, Origin, renderer};
@@ -35,6 +37,10 @@ pub(crate) const SENDMAIL_TYPENAME: &str = "sendmail";
             optional: true,
             schema: COMMENT_SCHEMA,
         },
+        retry: {
+            optional: true,
+            schema: RETRY_POLICY_SCHEMA,
+        },
     },
 )]
 #[derive(Debug, Serialize, Deserialize, Updater, Default)]
@@ -73,6 +79,9 @@ pub struct SendmailConfig {
     /// Disable this target.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable: Option<bool>,
+    /// Retry policy for notifications which could not be delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PropertyString<RetryPolicy>>,
     /// Origin of this config entry.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[updater(skip)]
@@ -96,6 +105,8 @@ pub enum DeleteableSendmailProperty {
     Mailto,
     /// Delete `mailto-user`
     MailtoUser,
+    /// Delete `retry`
+    Retry,
 }
 
 /// A sendmail notification endpoint.
@@ -160,4 +171,8 @@ impl Endpoint for SendmailEndpoint {
     fn disabled(&self) -> bool {
         self.config.disable.unwrap_or_default()
     }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry.as_deref().copied().unwrap_or_default()
+    }
 }
@@ -8,10 +8,12 @@ use lettre::{Message, SmtpTransport, Transport, message::header::ContentType};
 use serde::{Deserialize, Serialize};
 
 use proxmox_schema::api_types::COMMENT_SCHEMA;
+use proxmox_schema::property_string::PropertyString;
 use proxmox_schema::{Updater, api};
 
 use crate::context::context;
 use crate::endpoints::common::mail;
+use crate::queue::{RETRY_POLICY_SCHEMA, RetryPolicy};
 use crate::renderer::TemplateType;
 use crate::schema::{EMAIL_SCHEMA, ENTITY_NAME_SCHEMA, USER_SCHEMA};
 use crate::{Content, Endpoint, Error, Notification, Origin, renderer};
@@ -61,6 +63,10 @@ pub enum SmtpMode {
             optional: true,
             schema: COMMENT_SCHEMA,
         },
+        retry: {
+            optional: true,
+            schema: RETRY_POLICY_SCHEMA,
+        },
     },
 )]
 #[derive(Debug, Serialize, Deserialize, Updater, Default)]
@@ -107,6 +113,9 @@ pub struct SmtpConfig {
     /// Disable this target.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable: Option<bool>,
+    /// Retry policy for notifications which could not be delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PropertyString<RetryPolicy>>,
     /// Origin of this config entry.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[updater(skip)]
@@ -132,6 +141,8 @@ pub enum DeleteableSmtpProperty {
     Password,
     /// Delete `port`
     Port,
+    /// Delete `retry`
+    Retry,
     /// Delete `username`
     Username,
 }
@@ -283,6 +294,10 @@ impl Endpoint for SmtpEndpoint {
     fn disabled(&self) -> bool {
         self.config.disable.unwrap_or_default()
     }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry.as_deref().copied().unwrap_or_default()
+    }
 }
 
 /// Construct a lettre `Message` from a raw email message.
@@ -25,6 +25,7 @@ use proxmox_schema::api_types::{COMMENT_SCHEMA, HTTP_URL_SCHEMA};
 use proxmox_schema::{ApiStringFormat, ApiType, Schema, StringSchema, Updater, api};
 
 use crate::context::context;
+use crate::queue::{RETRY_POLICY_SCHEMA, RetryPolicy};
 use crate::renderer::TemplateType;
 use crate::schema::ENTITY_NAME_SCHEMA;
 use crate::{Content, Endpoint, Error, Notification, Origin, renderer};
@@ -72,6 +73,10 @@ impl From<HttpMethod> for &str {
             optional: true,
             schema: COMMENT_SCHEMA,
         },
+        retry: {
+            optional: true,
+            schema: RETRY_POLICY_SCHEMA,
+        },
         header: {
             type: Array,
             items: {
@@ -115,6 +120,9 @@ pub struct WebhookConfig {
     /// Disable this target.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable: Option<bool>,
+    /// Retry policy for notifications which could not be delivered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PropertyString<RetryPolicy>>,
     /// Origin of this config entry.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[updater(skip)]
@@ -183,6 +191,8 @@ pub enum DeleteableWebhookProperty {
     Body,
     /// Delete `secret`.
     Secret,
+    /// Delete `retry`.
+    Retry,
 }
 
 #[api]
@@ -259,6 +269,10 @@ impl Endpoint for WebhookEndpoint {
     fn disabled(&self) -> bool {
         self.config.disable.unwrap_or_default()
     }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry.as_deref().copied().unwrap_or_default()
+    }
 }
 
 impl WebhookEndpoint {
@@ -1,6 +1,10 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+#[cfg(any(feature = "aggregation", feature = "suppression"))]
+use std::collections::HashSet;
+use std::collections::hash_map::Entry;
 use std::error::Error as StdError;
 use std::fmt::Display;
 use std::str::FromStr;
@@ -18,12 +22,21 @@ use proxmox_uuid::Uuid;
 pub mod matcher;
 use matcher::{MATCHER_TYPENAME, MatcherConfig};
 
+pub mod aggregation;
 pub mod api;
 pub mod config;
 pub mod context;
 pub mod endpoints;
+pub mod queue;
 pub mod renderer;
 pub mod schema;
+#[cfg(any(
+    feature = "retry-queue",
+    feature = "aggregation",
+    feature = "suppression"
+))]
+mod state_dir;
+pub mod suppression;
 
 #[derive(Debug)]
 pub enum Error {
@@ -157,6 +170,11 @@ pub trait Endpoint {
 
     /// Check if the endpoint is disabled
     fn disabled(&self) -> bool;
+
+    /// Retry policy for notifications which could not be sent
+    fn retry_policy(&self) -> queue::RetryPolicy {
+        queue::RetryPolicy::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -376,6 +394,12 @@ impl Config {
 pub struct Bus {
     endpoints: HashMap<String, Box<dyn Endpoint>>,
     matchers: Vec<MatcherConfig>,
+    #[cfg(feature = "retry-queue")]
+    retry_queue: Option<queue::RetryQueue>,
+    #[cfg(feature = "aggregation")]
+    aggregation_store: Option<aggregation::AggregationStore>,
+    #[cfg(feature = "suppression")]
+    suppression_store: Option<suppression::SuppressionStore>,
 }
 
 #[allow(unused_macros)]
@@ -513,6 +537,40 @@ impl Bus {
             );
         }
 
+        #[cfg(feature = "ntfy")]
+        {
+            use endpoints::ntfy::NTFY_TYPENAME;
+            use endpoints::ntfy::{NtfyConfig, NtfyEndpoint, NtfyPrivateConfig};
+            endpoints.extend(
+                parse_endpoints_with_private_config!(
+                    config,
+                    NtfyConfig,
+                    NtfyPrivateConfig,
+                    NtfyEndpoint,
+                    NTFY_TYPENAME
+                )?
+                .into_iter()
+                .map(|e| (e.name().into(), e)),
+            );
+        }
+
+        #[cfg(feature = "matrix")]
+        {
+            use endpoints::matrix::MATRIX_TYPENAME;
+            use endpoints::matrix::{MatrixConfig, MatrixEndpoint, MatrixPrivateConfig};
+            endpoints.extend(
+                parse_endpoints_with_private_config!(
+                    config,
+                    MatrixConfig,
+                    MatrixPrivateConfig,
+                    MatrixEndpoint,
+                    MATRIX_TYPENAME
+                )?
+                .into_iter()
+                .map(|e| (e.name().into(), e)),
+            );
+        }
+
         let matchers = config
             .config
             .convert_to_typed_array(MATCHER_TYPENAME)
@@ -521,6 +579,12 @@ impl Bus {
         Ok(Bus {
             endpoints,
             matchers,
+            #[cfg(feature = "retry-queue")]
+            retry_queue: queue::RetryQueue::from_context(),
+            #[cfg(feature = "aggregation")]
+            aggregation_store: aggregation::AggregationStore::from_context(),
+            #[cfg(feature = "suppression")]
+            suppression_store: suppression::SuppressionStore::from_context(),
         })
     }
 
@@ -534,36 +598,101 @@ impl Bus {
         self.matchers.push(filter)
     }
 
+    #[cfg(all(test, feature = "retry-queue"))]
+    pub fn set_retry_queue(&mut self, retry_queue: queue::RetryQueue) {
+        self.retry_queue = Some(retry_queue);
+    }
+
+    #[cfg(all(test, feature = "aggregation"))]
+    pub fn set_aggregation_store(&mut self, store: aggregation::AggregationStore) {
+        self.aggregation_store = Some(store);
+    }
+
+    #[cfg(all(test, feature = "suppression"))]
+    pub fn set_suppression_store(&mut self, store: suppression::SuppressionStore) {
+        self.suppression_store = Some(store);
+    }
+
     /// Send a notification. Notification matchers will determine which targets will receive
     /// the notification.
     ///
-    /// Any errors will not be returned but only logged.
+    /// Any errors will not be returned but only logged. If a retry queue is available, the
+    /// notification will be queued for any target that failed to send it. Matchers with
+    /// aggregation enabled collect the notification for a later digest instead, matchers with
+    /// suppression enabled drop repetitions of the notification within their hold-down window.
     pub fn send(&self, notification: &Notification) {
-        let targets = matcher::check_matches(self.matchers.as_slice(), notification);
-
-        for target in targets {
-            if let Some(endpoint) = self.endpoints.get(target) {
-                let name = endpoint.name();
-
-                if endpoint.disabled() {
-                    // Skip this target if it is disabled
-                    info!("skipping disabled target '{name}'");
+        let mut targets: HashMap<&str, Cow<Notification>> = HashMap::new();
+
+        for matcher in matcher::matching_matchers(self.matchers.as_slice(), notification) {
+            #[cfg(feature = "suppression")]
+            let notification = match matcher.suppress.as_deref() {
+                Some(suppress) => {
+                    match self.check_suppression(matcher, suppress, Cow::Borrowed(notification)) {
+                        Some(checked) => checked,
+                        None => continue,
+                    }
+                }
+                None => Cow::Borrowed(notification),
+            };
+            #[cfg(not(feature = "suppression"))]
+            let notification = Cow::Borrowed(notification);
+
+            #[cfg(feature = "aggregation")]
+            if let Some(aggregate) = matcher.aggregate.as_deref() {
+                if self.collect_for_digest(matcher, aggregate, &notification) {
                     continue;
                 }
+            }
 
-                match endpoint.send(notification) {
-                    Ok(_) => {
-                        info!("notified via target `{name}`");
+            // If several matchers route the notification to the same target, prefer the one
+            // reporting the most suppressed repetitions, independent of the matcher order.
+            for target in &matcher.target {
+                match targets.entry(target.as_str()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(notification.clone());
                     }
-                    Err(e) => {
-                        // Only log on errors, do not propagate fail to the caller.
-                        error!("could not notify via target `{name}`: {e}");
+                    Entry::Occupied(mut entry) => {
+                        if suppression::repeat_count(&notification)
+                            > suppression::repeat_count(entry.get())
+                        {
+                            entry.insert(notification.clone());
+                        }
                     }
                 }
-            } else {
-                error!("could not notify via target '{target}', it does not exist");
             }
         }
+
+        for (target, notification) in targets {
+            self.send_via_target(target, &notification);
+        }
+    }
+
+    /// Send a notification via a single target, logging any errors.
+    fn send_via_target(&self, target: &str, notification: &Notification) {
+        if let Some(endpoint) = self.endpoints.get(target) {
+            let name = endpoint.name();
+
+            if endpoint.disabled() {
+                // Skip this target if it is disabled
+                info!("skipping disabled target '{name}'");
+                return;
+            }
+
+            match endpoint.send(notification) {
+                Ok(_) => {
+                    info!("notified via target `{name}`");
+                }
+                Err(e) => {
+                    // Only log on errors, do not propagate fail to the caller.
+                    error!("could not notify via target `{name}`: {e}");
+
+                    #[cfg(feature = "retry-queue")]
+                    self.queue_for_retry(endpoint.as_ref(), notification, &e);
+                }
+            }
+        } else {
+            error!("could not notify via target '{target}', it does not exist");
+        }
     }
 
     /// Send a test notification to a target (endpoint or group).
@@ -593,12 +722,316 @@ impl Bus {
 
         Ok(())
     }
+
+    /// Check if `notification` is a repetition which must be suppressed by `matcher`.
+    ///
+    /// Returns `None` if the notification must not be sent, otherwise the notification to
+    /// send, including the number of previously suppressed repetitions.
+    #[cfg(feature = "suppression")]
+    fn check_suppression<'a>(
+        &self,
+        matcher: &MatcherConfig,
+        suppress: &suppression::SuppressionConfig,
+        notification: Cow<'a, Notification>,
+    ) -> Option<Cow<'a, Notification>> {
+        let Some(store) = &self.suppression_store else {
+            return Some(notification);
+        };
+
+        let name = &matcher.name;
+
+        match store.check(name, suppress, &notification) {
+            Ok(suppression::store::Verdict::Suppressed) => {
+                info!("suppressed repeated notification for matcher '{name}'");
+                None
+            }
+            Ok(suppression::store::Verdict::Deliver(Some(count))) => Some(Cow::Owned(
+                suppression::with_repeat_count(&notification, count),
+            )),
+            Ok(suppression::store::Verdict::Deliver(None)) => Some(notification),
+            Err(err) => {
+                error!(
+                    "could not check suppression for matcher '{name}', sending notification: {err}"
+                );
+                Some(notification)
+            }
+        }
+    }
+
+    /// Send suppressed repetitions of notifications whose hold-down window is over, including
+    /// the number of suppressed repetitions.
+    ///
+    /// Individual delivery errors are only logged.
+    ///
+    /// This should be called periodically by the product, e.g. from a timer.
+    #[cfg(feature = "suppression")]
+    pub fn flush_suppressed(&self) -> Result<(), Error> {
+        let Some(store) = &self.suppression_store else {
+            return Ok(());
+        };
+
+        let find_matcher = |name: &str| self.matchers.iter().find(|m| m.name == name);
+
+        let expired = store.take_expired(|name| {
+            find_matcher(name)
+                .and_then(|matcher| matcher.suppress.as_deref())
+                .map(|suppress| suppress.window)
+        })?;
+
+        for entry in expired {
+            let Some(matcher) = find_matcher(&entry.matcher) else {
+                error!(
+                    "dropping {count} suppressed notifications of matcher '{name}', it does not exist",
+                    count = entry.count,
+                    name = entry.matcher,
+                );
+                continue;
+            };
+
+            let notification = suppression::with_repeat_count(&entry.notification, entry.count);
+
+            #[cfg(feature = "aggregation")]
+            if let Some(aggregate) = matcher.aggregate.as_deref() {
+                if self.collect_for_digest(matcher, aggregate, &notification) {
+                    continue;
+                }
+            }
+
+            let targets: HashSet<&str> = matcher.target.iter().map(|s| s.as_str()).collect();
+            for target in targets {
+                self.send_via_target(target, &notification);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect a notification for the digest of `matcher`.
+    ///
+    /// Returns `false` if the notification could not be collected and should be sent
+    /// immediately instead.
+    #[cfg(feature = "aggregation")]
+    fn collect_for_digest(
+        &self,
+        matcher: &MatcherConfig,
+        aggregate: &aggregation::AggregationConfig,
+        notification: &Notification,
+    ) -> bool {
+        let Some(store) = &self.aggregation_store else {
+            return false;
+        };
+
+        let name = &matcher.name;
+
+        match store.add(name, aggregate, notification) {
+            Ok(Some(pending)) => {
+                self.send_digest(matcher, &pending.notifications);
+                true
+            }
+            Ok(None) => {
+                info!("collected notification for digest of matcher '{name}'");
+                true
+            }
+            Err(err) => {
+                error!(
+                    "could not collect notification for matcher '{name}', sending it directly: {err}"
+                );
+                false
+            }
+        }
+    }
+
+    /// Send a digest of `notifications` to all targets of `matcher`.
+    ///
+    /// If the digest cannot be rendered, the notifications are sent individually instead, so
+    /// that they do not get lost.
+    #[cfg(feature = "aggregation")]
+    fn send_digest(&self, matcher: &MatcherConfig, notifications: &[Notification]) {
+        let digest = aggregation::build_digest(&matcher.name, notifications);
+        let targets: HashSet<&str> = matcher.target.iter().map(|s| s.as_str()).collect();
+
+        if let Err(err) = aggregation::check_digest_renderable(&digest) {
+            error!(
+                "could not render digest of matcher '{name}', sending {count} collected notifications individually: {err}",
+                name = matcher.name,
+                count = notifications.len(),
+            );
+
+            for notification in notifications {
+                for target in &targets {
+                    self.send_via_target(target, notification);
+                }
+            }
+            return;
+        }
+
+        for target in targets {
+            self.send_via_target(target, &digest);
+        }
+    }
+
+    /// Send digests for all matchers whose aggregation window is over.
+    ///
+    /// Notifications collected by matchers which no longer have aggregation enabled are sent
+    /// right away, those of matchers which no longer exist are dropped. Individual delivery
+    /// errors are only logged.
+    ///
+    /// This should be called periodically by the product, e.g. from a timer.
+    #[cfg(feature = "aggregation")]
+    pub fn flush_digests(&self) -> Result<(), Error> {
+        let Some(store) = &self.aggregation_store else {
+            return Ok(());
+        };
+
+        let find_matcher = |name: &str| self.matchers.iter().find(|m| m.name == name);
+
+        let due = store.take_due(|name| {
+            find_matcher(name)
+                .and_then(|matcher| matcher.aggregate.as_deref())
+                .map(|aggregate| aggregate.window)
+        })?;
+
+        for pending in due {
+            match find_matcher(&pending.matcher) {
+                Some(matcher) => self.send_digest(matcher, &pending.notifications),
+                None => error!(
+                    "dropping {count} collected notifications of matcher '{name}', it does not exist",
+                    count = pending.notifications.len(),
+                    name = pending.matcher,
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "retry-queue")]
+    fn queue_for_retry(&self, endpoint: &dyn Endpoint, notification: &Notification, err: &Error) {
+        let Some(retry_queue) = &self.retry_queue else {
+            return;
+        };
+
+        let name = endpoint.name();
+        let policy = endpoint.retry_policy();
+
+        if policy.max_retries() == 0 {
+            return;
+        }
+
+        match retry_queue.enqueue(name, notification, &policy, err) {
+            Ok(()) => info!("queued notification for target `{name}` for a later retry"),
+            Err(err) => error!("could not queue notification for target `{name}`: {err}"),
+        }
+    }
+
+    /// Retry sending all queued notifications which are due.
+    ///
+    /// Successfully delivered notifications are removed from the queue. Notifications
+    /// that still fail are rescheduled, or dropped once the retry policy of their target
+    /// is exhausted. Individual delivery errors are only logged.
+    ///
+    /// This should be called periodically by the product, e.g. from a timer.
+    #[cfg(feature = "retry-queue")]
+    pub fn process_retry_queue(&self) -> Result<(), Error> {
+        let Some(retry_queue) = &self.retry_queue else {
+            return Ok(());
+        };
+
+        let now = proxmox_time::epoch_i64();
+
+        // Only hold the lock while reading the queue, sending may take a while and failing
+        // notifications need to be queued in the meantime.
+        let due: Vec<_> = {
+            let _lock = retry_queue.lock()?;
+            retry_queue
+                .entries()?
+                .into_iter()
+                .filter(|entry| entry.next_attempt <= now)
+                .collect()
+        };
+
+        for entry in due {
+            if let Err(err) = self.redeliver(retry_queue, entry) {
+                error!("{err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Immediately retry sending the queued notification with the given `id`, regardless
+    /// of when the next attempt was scheduled.
+    ///
+    /// In contrast to `process_retry_queue`, errors are returned to the caller.
+    #[cfg(feature = "retry-queue")]
+    pub fn retry_queued(&self, id: &str) -> Result<(), Error> {
+        let retry_queue = self
+            .retry_queue
+            .as_ref()
+            .ok_or_else(|| Error::Generic("no notification retry queue available".into()))?;
+
+        let entry = {
+            let _lock = retry_queue.lock()?;
+            retry_queue.entry(id)?
+        };
+
+        self.redeliver(retry_queue, entry)
+    }
+
+    /// Attempt to redeliver a queued notification and update the queue accordingly.
+    ///
+    /// The caller must not hold the queue lock, as sending can take a while. The queue entry is
+    /// only updated if it was not removed or retried by someone else in the meantime.
+    #[cfg(feature = "retry-queue")]
+    pub(crate) fn redeliver(
+        &self,
+        retry_queue: &queue::RetryQueue,
+        entry: queue::spool::QueuedNotification,
+    ) -> Result<(), Error> {
+        let target = entry.target.clone();
+
+        let Some(endpoint) = self.endpoints.get(&target) else {
+            retry_queue.replace(&entry, None)?;
+            return Err(Error::TargetDoesNotExist(target));
+        };
+
+        if endpoint.disabled() {
+            info!("dropping queued notification for disabled target '{target}'");
+            retry_queue.replace(&entry, None)?;
+            return Ok(());
+        }
+
+        match endpoint.send(&entry.notification) {
+            Ok(()) => {
+                info!("notified via target `{target}` after queued retry");
+                retry_queue.replace(&entry, None)?;
+                Ok(())
+            }
+            Err(err) => {
+                let mut updated = entry.clone();
+                if updated.record_failure(&endpoint.retry_policy(), &err) {
+                    retry_queue.replace(&entry, Some(&updated))?;
+                } else {
+                    error!(
+                        "giving up on queued notification for target `{target}` after {} attempts",
+                        updated.attempts
+                    );
+                    retry_queue.replace(&entry, None)?;
+                }
+
+                Err(err)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::{cell::RefCell, rc::Rc};
 
+    #[cfg(any(feature = "aggregation", feature = "suppression"))]
+    use proxmox_schema::property_string::PropertyString;
+
     use super::*;
 
     #[derive(Default, Clone)]
@@ -607,10 +1040,23 @@ mod tests {
         // Needs to be an Rc so that we can clone MockEndpoint before
         // passing it to Bus, while still retaining a handle to the Vec
         messages: Rc<RefCell<Vec<Notification>>>,
+        // Simulate an unreachable target
+        failing: Rc<RefCell<bool>>,
+        // Called before sending, to simulate concurrent actions during a slow send
+        #[allow(clippy::type_complexity)]
+        before_send: Rc<RefCell<Option<Box<dyn Fn()>>>>,
     }
 
     impl Endpoint for MockEndpoint {
         fn send(&self, message: &Notification) -> Result<(), Error> {
+            if let Some(before_send) = self.before_send.borrow().as_ref() {
+                before_send();
+            }
+
+            if *self.failing.borrow() {
+                return Err(Error::Generic("target unreachable".into()));
+            }
+
             self.messages.borrow_mut().push(message.clone());
 
             Ok(())
@@ -636,6 +1082,11 @@ mod tests {
         fn messages(&self) -> Vec<Notification> {
             self.messages.borrow().clone()
         }
+
+        #[cfg(feature = "retry-queue")]
+        fn set_failing(&self, failing: bool) {
+            *self.failing.borrow_mut() = failing;
+        }
     }
 
     #[test]
@@ -714,4 +1165,224 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(feature = "retry-queue")]
+    #[test]
+    fn test_failed_notification_is_queued() -> Result<(), Error> {
+        let dir = proxmox_sys::fs::make_tmp_dir("/tmp", None)
+            .map_err(|err| Error::Generic(err.to_string()))?;
+
+        let endpoint = MockEndpoint::new("mock");
+        endpoint.set_failing(true);
+
+        let mut bus = Bus::default();
+        bus.add_endpoint(Box::new(endpoint.clone()));
+        bus.add_matcher(MatcherConfig {
+            target: vec!["mock".into()],
+            ..Default::default()
+        });
+        bus.set_retry_queue(queue::RetryQueue::new(&dir));
+
+        bus.send(&Notification::from_template(
+            Severity::Error,
+            "test",
+            Default::default(),
+            Default::default(),
+        ));
+
+        let queue = queue::RetryQueue::new(&dir);
+        let queued = queue.list()?;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].target, "mock");
+        assert_eq!(queued[0].attempts, 1);
+        assert!(queued[0].next_attempt > proxmox_time::epoch_i64());
+
+        // Not due yet, so nothing is sent
+        endpoint.set_failing(false);
+        bus.process_retry_queue()?;
+        assert_eq!(endpoint.messages().len(), 0);
+        assert_eq!(queue.list()?.len(), 1);
+
+        // Still failing, the attempt is recorded
+        endpoint.set_failing(true);
+        assert!(bus.retry_queued(&queued[0].id).is_err());
+        assert_eq!(queue.list()?[0].attempts, 2);
+
+        endpoint.set_failing(false);
+        bus.retry_queued(&queued[0].id)?;
+        assert_eq!(endpoint.messages().len(), 1);
+        assert!(queue.list()?.is_empty());
+        assert!(!queue.purge(Some(&queued[0].id))?);
+
+        bus.send(&Notification::from_template(
+            Severity::Error,
+            "test",
+            Default::default(),
+            Default::default(),
+        ));
+        assert!(queue.list()?.is_empty());
+
+        // The queue is not locked while sending: an entry purged meanwhile is not stored
+        // again, and other notifications can still be queued.
+        bus.set_retry_queue(queue::RetryQueue::new(&dir));
+        endpoint.set_failing(true);
+        bus.send(&Notification::from_template(
+            Severity::Error,
+            "test",
+            Default::default(),
+            Default::default(),
+        ));
+        let id = queue.list()?[0].id.clone();
+        *endpoint.before_send.borrow_mut() = Some(Box::new({
+            let dir = dir.clone();
+            move || {
+                let queue = queue::RetryQueue::new(&dir);
+                queue.purge(None).unwrap();
+                queue
+                    .enqueue(
+                        "mock",
+                        &Notification::from_template(
+                            Severity::Info,
+                            "other",
+                            Default::default(),
+                            Default::default(),
+                        ),
+                        &queue::RetryPolicy::default(),
+                        &Error::Generic("target unreachable".into()),
+                    )
+                    .unwrap();
+            }
+        }));
+        assert!(bus.retry_queued(&id).is_err());
+        let queued = queue.list()?;
+        assert_eq!(queued.len(), 1);
+        assert_ne!(queued[0].id, id);
+        assert_eq!(queued[0].attempts, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "aggregation")]
+    #[test]
+    fn test_aggregated_notifications() -> Result<(), Error> {
+        let dir = proxmox_sys::fs::make_tmp_dir("/tmp", None)
+            .map_err(|err| Error::Generic(err.to_string()))?;
+
+        let endpoint = MockEndpoint::new("mock");
+
+        let mut bus = Bus::default();
+        bus.add_endpoint(Box::new(endpoint.clone()));
+        bus.add_matcher(MatcherConfig {
+            name: "digest".into(),
+            target: vec!["mock".into()],
+            aggregate: Some(PropertyString::new(aggregation::AggregationConfig {
+                window: 3600,
+                max_items: Some(2),
+            })),
+            ..Default::default()
+        });
+        bus.set_aggregation_store(aggregation::AggregationStore::new(&dir));
+
+        let send_with_severity = |bus: &Bus, severity| {
+            bus.send(&Notification::from_template(
+                severity,
+                "test",
+                Default::default(),
+                Default::default(),
+            ));
+        };
+
+        send_with_severity(&bus, Severity::Info);
+        assert!(endpoint.messages().is_empty());
+
+        // The window is not over yet
+        bus.flush_digests()?;
+        assert!(endpoint.messages().is_empty());
+
+        // Maximum number of items reached, digest is sent right away
+        send_with_severity(&bus, Severity::Warning);
+        let messages = endpoint.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].metadata.severity, Severity::Warning);
+
+        // Notifications collected before aggregation was disabled are sent on the next flush
+        send_with_severity(&bus, Severity::Info);
+        assert_eq!(endpoint.messages().len(), 1);
+        bus.matchers[0].aggregate = None;
+        bus.flush_digests()?;
+        assert_eq!(endpoint.messages().len(), 2);
+
+        send_with_severity(&bus, Severity::Info);
+        assert_eq!(endpoint.messages().len(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "suppression")]
+    #[test]
+    fn test_repeated_notifications_are_suppressed() -> Result<(), Error> {
+        let dir = proxmox_sys::fs::make_tmp_dir("/tmp", None)
+            .map_err(|err| Error::Generic(err.to_string()))?;
+
+        let endpoint = MockEndpoint::new("mock");
+
+        let mut bus = Bus::default();
+        bus.add_endpoint(Box::new(endpoint.clone()));
+        bus.add_matcher(MatcherConfig {
+            name: "dedup".into(),
+            target: vec!["mock".into()],
+            suppress: Some(PropertyString::new(suppression::SuppressionConfig {
+                window: 3600,
+                field: vec!["hostname".into()],
+            })),
+            ..Default::default()
+        });
+        bus.set_suppression_store(suppression::SuppressionStore::new(&dir));
+
+        let send_for_host = |bus: &Bus, hostname: &str| {
+            let fields = HashMap::from([("hostname".to_string(), hostname.to_string())]);
+            bus.send(&Notification::from_template(
+                Severity::Error,
+                "test",
+                json!({}),
+                fields,
+            ));
+        };
+
+        send_for_host(&bus, "pve1");
+        send_for_host(&bus, "pve1");
+        send_for_host(&bus, "pve1");
+        send_for_host(&bus, "pve2");
+        assert_eq!(endpoint.messages().len(), 2);
+
+        // The hold-down window is not over yet
+        bus.flush_suppressed()?;
+        assert_eq!(endpoint.messages().len(), 2);
+
+        // Once suppression is disabled, the last repetition is sent with the repeat count
+        bus.matchers[0].suppress = None;
+        bus.flush_suppressed()?;
+
+        let messages = endpoint.messages();
+        assert_eq!(messages.len(), 3);
+        match &messages[2].content {
+            Content::Template { data, .. } => {
+                assert_eq!(data[suppression::REPEAT_COUNT_KEY], 2)
+            }
+            #[cfg(feature = "mail-forwarder")]
+            _ => panic!("expected a template notification"),
+        }
+
+        // Nothing left to flush
+        bus.flush_suppressed()?;
+        assert_eq!(endpoint.messages().len(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        Ok(())
+    }
 }
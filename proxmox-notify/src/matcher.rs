@@ -9,10 +9,13 @@ use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
 use proxmox_schema::api_types::{COMMENT_SCHEMA, SAFE_ID_REGEX_STR};
+use proxmox_schema::property_string::PropertyString;
 use proxmox_schema::{ApiStringFormat, Schema, StringSchema, Updater, api, const_regex};
 use proxmox_time::{DailyDuration, parse_daily_duration};
 
+use crate::aggregation::{AGGREGATION_SCHEMA, AggregationConfig};
 use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::suppression::{SUPPRESSION_SCHEMA, SuppressionConfig};
 use crate::{Error, Notification, Origin, Severity};
 
 pub const MATCHER_TYPENAME: &str = "matcher";
@@ -105,6 +108,14 @@ pub const MATCH_FIELD_ENTRY_SCHEMA: Schema = StringSchema::new("Match metadata f
             },
             optional: true,
         },
+        aggregate: {
+            schema: AGGREGATION_SCHEMA,
+            optional: true,
+        },
+        suppress: {
+            schema: SUPPRESSION_SCHEMA,
+            optional: true,
+        },
     })]
 #[derive(Debug, Serialize, Deserialize, Updater, Default)]
 #[serde(rename_all = "kebab-case")]
@@ -149,6 +160,14 @@ pub struct MatcherConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disable: Option<bool>,
 
+    /// Collect matching notifications and send them as a single digest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<PropertyString<AggregationConfig>>,
+
+    /// Suppress repeated notifications within a hold-down window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress: Option<PropertyString<SuppressionConfig>>,
+
     /// Origin of this config entry.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[updater(skip)]
@@ -419,6 +438,8 @@ impl FromStr for CalendarMatcher {
 #[serde(rename_all = "kebab-case")]
 /// The set of matcher properties that can be deleted.
 pub enum DeleteableMatcherProperty {
+    /// Delete `aggregate`
+    Aggregate,
     /// Delete `comment`
     Comment,
     /// Delete `disable`
@@ -433,15 +454,18 @@ pub enum DeleteableMatcherProperty {
     MatchSeverity,
     /// Delete `mode`
     Mode,
+    /// Delete `suppress`
+    Suppress,
     /// Delete `target`
     Target,
 }
 
-pub fn check_matches<'a>(
+/// Return all enabled matchers which match a notification.
+pub fn matching_matchers<'a>(
     matchers: &'a [MatcherConfig],
     notification: &Notification,
-) -> HashSet<&'a str> {
-    let mut targets = HashSet::new();
+) -> Vec<&'a MatcherConfig> {
+    let mut matching = Vec::new();
 
     for matcher in matchers {
         if matcher.disable.unwrap_or_default() {
@@ -451,15 +475,23 @@ pub fn check_matches<'a>(
         }
 
         match matcher.matches(notification) {
-            Ok(t) => {
-                let t = t.unwrap_or_default();
-                targets.extend(t.iter().map(|s| s.as_str()));
-            }
+            Ok(Some(_)) => matching.push(matcher),
+            Ok(None) => {}
             Err(err) => error!("matcher '{matcher}' failed: {err}", matcher = matcher.name),
         }
     }
 
-    targets
+    matching
+}
+
+pub fn check_matches<'a>(
+    matchers: &'a [MatcherConfig],
+    notification: &Notification,
+) -> HashSet<&'a str> {
+    matching_matchers(matchers, notification)
+        .into_iter()
+        .flat_map(|matcher| matcher.target.iter().map(|s| s.as_str()))
+        .collect()
 }
 
 #[cfg(test)]
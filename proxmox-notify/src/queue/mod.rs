@@ -0,0 +1,143 @@
+//! Retry handling for notifications which could not be delivered.
+//!
+//! If a target fails to send a notification, the notification can be stored in a spool
+//! directory together with the name of the target (see [`RetryQueue`]). Queued notifications
+//! are then retried with an exponential backoff, as configured by the target's
+//! [`RetryPolicy`], until they are either delivered or the maximum number of retries has been
+//! reached.
+
+use serde::{Deserialize, Serialize};
+
+use proxmox_schema::{ApiStringFormat, ApiType, Schema, StringSchema, api};
+
+use crate::Severity;
+use crate::schema::ENTITY_NAME_SCHEMA;
+
+#[cfg(feature = "retry-queue")]
+pub(crate) mod spool;
+#[cfg(feature = "retry-queue")]
+pub use spool::RetryQueue;
+
+/// Default number of retries for a failed notification.
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+/// Default delay before the first retry, in seconds.
+pub const DEFAULT_RETRY_DELAY: u64 = 60;
+/// Default upper bound for the delay between two retries, in seconds.
+pub const DEFAULT_MAX_RETRY_DELAY: u64 = 3600;
+
+#[api(
+    properties: {
+        "max-retries": {
+            optional: true,
+            maximum: 100,
+        },
+        delay: {
+            optional: true,
+            minimum: 1,
+        },
+        "max-delay": {
+            optional: true,
+            minimum: 1,
+        },
+    }
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Retry policy for notifications which could not be delivered by a target.
+pub struct RetryPolicy {
+    /// Number of delivery retries before a notification is dropped (default: 5).
+    /// Setting this to 0 disables retrying.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Delay before the first retry in seconds (default: 60). The delay is doubled
+    /// for every subsequent retry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<u64>,
+    /// Upper bound for the delay between two retries in seconds (default: 3600).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_delay: Option<u64>,
+}
+
+pub const RETRY_POLICY_SCHEMA: Schema =
+    StringSchema::new("Retry policy for notifications which could not be delivered.")
+        .format(&ApiStringFormat::PropertyString(&RetryPolicy::API_SCHEMA))
+        .schema();
+
+impl RetryPolicy {
+    /// Number of retries before a queued notification is dropped.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES)
+    }
+
+    /// Delay in seconds before retry number `retry` (starting at 1).
+    pub fn delay_for_retry(&self, retry: u32) -> u64 {
+        let delay = self.delay.unwrap_or(DEFAULT_RETRY_DELAY);
+        let max_delay = self.max_delay.unwrap_or(DEFAULT_MAX_RETRY_DELAY);
+
+        let factor = 1u64
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+
+        delay.saturating_mul(factor).min(max_delay)
+    }
+}
+
+#[api(
+    properties: {
+        target: {
+            schema: ENTITY_NAME_SCHEMA,
+        },
+    }
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Information about a queued notification.
+pub struct QueuedNotificationInfo {
+    /// Unique ID of the queue entry.
+    pub id: String,
+    /// Name of the target which failed to deliver the notification.
+    pub target: String,
+    /// Severity of the notification.
+    pub severity: Severity,
+    /// Timestamp of the notification as a UNIX epoch.
+    pub timestamp: i64,
+    /// Template used by the notification, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Number of failed delivery attempts.
+    pub attempts: u32,
+    /// Error message of the last failed delivery attempt.
+    pub last_error: String,
+    /// Time when the notification was queued, as a UNIX epoch.
+    pub queued: i64,
+    /// Time of the next scheduled delivery attempt, as a UNIX epoch.
+    pub next_attempt: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.max_retries(), DEFAULT_MAX_RETRIES);
+        assert_eq!(policy.delay_for_retry(1), 60);
+        assert_eq!(policy.delay_for_retry(2), 120);
+        assert_eq!(policy.delay_for_retry(3), 240);
+        assert_eq!(policy.delay_for_retry(10), 3600);
+        assert_eq!(policy.delay_for_retry(100), 3600);
+
+        let policy = RetryPolicy {
+            max_retries: Some(0),
+            delay: Some(10),
+            max_delay: Some(25),
+        };
+
+        assert_eq!(policy.max_retries(), 0);
+        assert_eq!(policy.delay_for_retry(1), 10);
+        assert_eq!(policy.delay_for_retry(2), 20);
+        assert_eq!(policy.delay_for_retry(3), 25);
+    }
+}
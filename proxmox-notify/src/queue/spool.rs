@@ -0,0 +1,260 @@
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use proxmox_uuid::Uuid;
+
+use super::{QueuedNotificationInfo, RetryPolicy};
+use crate::context::context;
+use crate::state_dir::StateDir;
+use crate::{Content, Error, Notification};
+
+/// A notification waiting for redelivery, as stored in the spool directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct QueuedNotification {
+    /// Unique ID of the queue entry, also used as the file name.
+    pub(crate) id: String,
+    /// Name of the target which failed to deliver the notification.
+    pub(crate) target: String,
+    /// The queued notification.
+    pub(crate) notification: Notification,
+    /// Number of failed delivery attempts, including the initial one.
+    pub(crate) attempts: u32,
+    /// Error message of the last failed attempt.
+    pub(crate) last_error: String,
+    /// Time when the notification was queued.
+    pub(crate) queued: i64,
+    /// Time of the next scheduled delivery attempt.
+    pub(crate) next_attempt: i64,
+}
+
+impl QueuedNotification {
+    /// Record a failed delivery attempt.
+    ///
+    /// Returns `false` if the retry policy does not allow any further attempts.
+    pub(crate) fn record_failure(&mut self, policy: &RetryPolicy, err: &Error) -> bool {
+        self.attempts += 1;
+        self.last_error = err.to_string();
+
+        let retries = self.attempts - 1;
+        if retries >= policy.max_retries() {
+            return false;
+        }
+
+        self.next_attempt =
+            proxmox_time::epoch_i64() + policy.delay_for_retry(self.attempts) as i64;
+        true
+    }
+
+    fn info(&self) -> QueuedNotificationInfo {
+        let template = match &self.notification.content {
+            Content::Template { template_name, .. } => Some(template_name.clone()),
+            #[cfg(feature = "mail-forwarder")]
+            Content::ForwardedMail { .. } => None,
+        };
+
+        QueuedNotificationInfo {
+            id: self.id.clone(),
+            target: self.target.clone(),
+            severity: self.notification.metadata.severity,
+            timestamp: self.notification.metadata.timestamp,
+            template,
+            attempts: self.attempts,
+            last_error: self.last_error.clone(),
+            queued: self.queued,
+            next_attempt: self.next_attempt,
+        }
+    }
+}
+
+/// Spool directory for notifications which could not be delivered.
+///
+/// Every queued notification is stored in its own file, which is atomically replaced on
+/// updates. Modifications and redelivery attempts are serialized via an exclusive lock on a
+/// lock file within the spool directory.
+pub struct RetryQueue {
+    dir: StateDir,
+}
+
+impl RetryQueue {
+    /// Create a retry queue using the spool directory `dir`.
+    ///
+    /// The directory will be created when first storing a notification.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: StateDir::new(dir.into(), "queue"),
+        }
+    }
+
+    /// Create a retry queue for the spool directory provided by the product context.
+    ///
+    /// Returns `None` if the context does not provide a spool directory.
+    pub fn from_context() -> Option<Self> {
+        context().retry_queue_dir().map(Self::new)
+    }
+
+    /// Lock the queue. The lock is held until the returned file handle is dropped.
+    pub(crate) fn lock(&self) -> Result<File, Error> {
+        self.dir.lock()
+    }
+
+    fn entry_path(&self, id: &str) -> Result<PathBuf, Error> {
+        // Make sure that `id` cannot be used to escape the spool directory.
+        Uuid::parse_str(id)
+            .map_err(|_| Error::Generic(format!("invalid queue entry id '{id}'")))?;
+
+        Ok(self.dir.path(id))
+    }
+
+    /// Queue a notification which could not be delivered via `target` for a later retry.
+    pub(crate) fn enqueue(
+        &self,
+        target: &str,
+        notification: &Notification,
+        policy: &RetryPolicy,
+        err: &Error,
+    ) -> Result<(), Error> {
+        let _lock = self.lock()?;
+        let now = proxmox_time::epoch_i64();
+
+        let entry = QueuedNotification {
+            id: Uuid::generate().to_string(),
+            target: target.to_string(),
+            notification: notification.clone(),
+            attempts: 1,
+            last_error: err.to_string(),
+            queued: now,
+            next_attempt: now + policy.delay_for_retry(1) as i64,
+        };
+
+        self.store(&entry)
+    }
+
+    /// Write a queue entry. The caller must hold the queue lock.
+    pub(crate) fn store(&self, entry: &QueuedNotification) -> Result<(), Error> {
+        let data =
+            serde_json::to_vec(entry).map_err(|err| Error::ConfigSerialization(err.into()))?;
+
+        proxmox_sys::fs::replace_file(
+            self.entry_path(&entry.id)?,
+            &data,
+            StateDir::file_options(),
+            true,
+        )
+        .map_err(|err| Error::Generic(format!("could not write queue entry '{}': {err}", entry.id)))
+    }
+
+    /// Replace the queue entry `current` was read from with `updated`, or remove it if `updated`
+    /// is `None`, unless it was removed or retried by someone else in the meantime.
+    ///
+    /// This takes the queue lock, so that the entry can be read without holding it while
+    /// trying to deliver the notification. Returns `false` if the entry was left alone.
+    pub(crate) fn replace(
+        &self,
+        current: &QueuedNotification,
+        updated: Option<&QueuedNotification>,
+    ) -> Result<bool, Error> {
+        let _lock = self.lock()?;
+
+        match self.lookup(&current.id)? {
+            Some(entry)
+                if entry.attempts == current.attempts
+                    && entry.next_attempt == current.next_attempt => {}
+            _ => return Ok(false),
+        }
+
+        match updated {
+            Some(updated) => self.store(updated)?,
+            None => {
+                self.try_remove(&current.id)?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Remove a queue entry, returning `false` if it does not exist. The caller must hold the
+    /// queue lock.
+    fn try_remove(&self, id: &str) -> Result<bool, Error> {
+        match std::fs::remove_file(self.entry_path(id)?) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(Error::Generic(format!(
+                "could not remove queue entry '{id}': {err}"
+            ))),
+        }
+    }
+
+    /// Read all queue entries, sorted by the time they were queued.
+    ///
+    /// Entries which cannot be parsed are logged and skipped.
+    pub(crate) fn entries(&self) -> Result<Vec<QueuedNotification>, Error> {
+        let mut entries = Vec::new();
+
+        for id in self.dir.names()? {
+            match self.lookup(&id) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(err) => error!("could not parse notification queue entry '{id}': {err}"),
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.queued);
+
+        Ok(entries)
+    }
+
+    /// Look up a single queue entry. The caller must hold the queue lock.
+    pub(crate) fn entry(&self, id: &str) -> Result<QueuedNotification, Error> {
+        self.lookup(id)?
+            .ok_or_else(|| Error::Generic(format!("queue entry '{id}' does not exist")))
+    }
+
+    /// Look up a single queue entry, returning `None` if it does not exist. The caller must
+    /// hold the queue lock.
+    pub(crate) fn lookup(&self, id: &str) -> Result<Option<QueuedNotification>, Error> {
+        let data = match std::fs::read(self.entry_path(id)?) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::Generic(format!(
+                    "could not read queue entry '{id}': {err}"
+                )));
+            }
+        };
+
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|err| Error::ConfigDeserialization(err.into()))
+    }
+
+    /// List all queued notifications.
+    pub fn list(&self) -> Result<Vec<QueuedNotificationInfo>, Error> {
+        Ok(self
+            .entries()?
+            .iter()
+            .map(QueuedNotification::info)
+            .collect())
+    }
+
+    /// Drop a queued notification, or all of them if `id` is `None`.
+    ///
+    /// Returns `false` if the queued notification `id` does not exist.
+    pub fn purge(&self, id: Option<&str>) -> Result<bool, Error> {
+        let _lock = self.lock()?;
+
+        match id {
+            Some(id) => self.try_remove(id),
+            None => {
+                for entry in self.entries()? {
+                    self.try_remove(&entry.id)?;
+                }
+                Ok(true)
+            }
+        }
+    }
+}
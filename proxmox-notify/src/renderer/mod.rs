@@ -12,6 +12,7 @@ use tracing::error;
 use proxmox_human_byte::HumanByte;
 use proxmox_time::TimeSpan;
 
+use crate::suppression::REPEAT_COUNT_KEY;
 use crate::{Error, context};
 
 mod html;
@@ -233,6 +234,29 @@ impl TemplateType {
         rendered
     }
 
+    /// Append the number of suppressed repetitions of a notification.
+    fn append_repeat_count(&self, mut rendered: String, count: u64) -> String {
+        let note = format!("This notification was repeated {count} times.");
+
+        match self {
+            TemplateType::Subject => {
+                rendered = format!("{} (repeated {count} times)", rendered.trim_end());
+            }
+            TemplateType::PlaintextBody | TemplateType::HtmlBodyFromPlaintext => {
+                rendered = format!("{}\n\n{note}\n", rendered.trim_end());
+            }
+            TemplateType::HtmlBody => {
+                let note = format!("<p>{note}</p>");
+                match rendered.rfind("</body>") {
+                    Some(pos) => rendered.insert_str(pos, &note),
+                    None => rendered.push_str(&note),
+                }
+            }
+        }
+
+        rendered
+    }
+
     fn block_render_fns(&self) -> BlockRenderFunctions {
         match self {
             TemplateType::HtmlBody => html::block_render_functions(),
@@ -292,6 +316,11 @@ fn render_template_impl(
             .render_template(&template_string, data)
             .map_err(|err| Error::RenderError(err.into()))?;
 
+        let rendered_template = match data.get(REPEAT_COUNT_KEY).and_then(Value::as_u64) {
+            Some(count) if count > 0 => renderer.append_repeat_count(rendered_template, count),
+            _ => rendered_template,
+        };
+
         let rendered_template = renderer.postprocess(rendered_template);
 
         Ok(Some(rendered_template))
@@ -0,0 +1,110 @@
+//! Directories for persistent notification state.
+//!
+//! The retry queue, the aggregation store and the suppression store all keep their state as
+//! JSON files in a directory of their own, which is created on first use. Modifications are
+//! serialized via an exclusive lock on a lock file within the directory.
+
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use nix::sys::stat::Mode;
+use tracing::error;
+
+use proxmox_sys::fs::CreateOptions;
+
+use crate::Error;
+
+const LOCK_FILE_NAME: &str = ".lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A directory holding notification state in JSON files.
+pub(crate) struct StateDir {
+    dir: PathBuf,
+    /// What the state is used for, e.g. `queue`, used in error messages.
+    kind: &'static str,
+}
+
+impl StateDir {
+    pub(crate) fn new(dir: PathBuf, kind: &'static str) -> Self {
+        Self { dir, kind }
+    }
+
+    /// Options for the state files, which are only accessible by the owner.
+    pub(crate) fn file_options() -> CreateOptions {
+        CreateOptions::new().perm(Mode::from_bits_truncate(0o600))
+    }
+
+    fn dir_options() -> CreateOptions {
+        CreateOptions::new().perm(Mode::from_bits_truncate(0o700))
+    }
+
+    /// Get the path of the state file `name`.
+    pub(crate) fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.json"))
+    }
+
+    /// Create the directory if needed and lock it. The lock is held until the returned file
+    /// handle is dropped.
+    pub(crate) fn lock(&self) -> Result<File, Error> {
+        proxmox_sys::fs::create_path(&self.dir, None, Some(Self::dir_options())).map_err(
+            |err| {
+                Error::Generic(format!(
+                    "could not create notification {} directory {:?}: {err}",
+                    self.kind, self.dir
+                ))
+            },
+        )?;
+
+        proxmox_sys::fs::open_file_locked(
+            self.dir.join(LOCK_FILE_NAME),
+            LOCK_TIMEOUT,
+            true,
+            Self::file_options(),
+        )
+        .map_err(|err| Error::Generic(format!("could not lock notification {}: {err}", self.kind)))
+    }
+
+    /// List the names of all state files, without the `.json` extension.
+    ///
+    /// Entries which cannot be read are logged and skipped. A missing directory is treated as
+    /// empty.
+    pub(crate) fn names(&self) -> Result<Vec<String>, Error> {
+        let read_dir = match std::fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(Error::Generic(format!(
+                    "could not read notification {} directory {:?}: {err}",
+                    self.kind, self.dir
+                )));
+            }
+        };
+
+        let mut names = Vec::new();
+
+        for dir_entry in read_dir {
+            let path = match dir_entry {
+                Ok(dir_entry) => dir_entry.path(),
+                Err(err) => {
+                    error!("could not read notification {} entry: {err}", self.kind);
+                    continue;
+                }
+            };
+
+            if let Some(name) = json_file_stem(&path) {
+                names.push(name.to_string());
+            }
+        }
+
+        Ok(names)
+    }
+}
+
+fn json_file_stem(path: &Path) -> Option<&str> {
+    if path.extension()? != "json" {
+        return None;
+    }
+    path.file_stem()?.to_str()
+}
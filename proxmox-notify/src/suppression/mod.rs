@@ -0,0 +1,188 @@
+//! Suppression of repeated notifications.
+//!
+//! Matchers with a [`SuppressionConfig`] only forward the first of several identical
+//! notifications within a hold-down window. Two notifications are considered identical if
+//! they were created from the same template and have the same values for the configured
+//! metadata fields (or for all metadata fields, if none are configured).
+//!
+//! Repeated notifications are counted instead of being sent. Once the hold-down window is
+//! over, the number of suppressed repetitions is included in the next delivered message,
+//! either with the next identical notification or when [`Bus::flush_suppressed`] is called.
+//!
+//! [`Bus::flush_suppressed`]: crate::Bus::flush_suppressed
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use proxmox_schema::api_types::SAFE_ID_FORMAT;
+use proxmox_schema::{ApiStringFormat, ApiType, Schema, StringSchema, api};
+
+use crate::{Content, Notification};
+
+#[cfg(feature = "suppression")]
+pub(crate) mod store;
+#[cfg(feature = "suppression")]
+pub use store::SuppressionStore;
+
+/// Key in the template data which holds the number of suppressed repetitions of a
+/// notification.
+pub const REPEAT_COUNT_KEY: &str = "repeat-count";
+
+pub const SUPPRESSION_FIELD_SCHEMA: Schema = StringSchema::new("Metadata field name.")
+    .format(&SAFE_ID_FORMAT)
+    .schema();
+
+#[api(
+    properties: {
+        window: {
+            minimum: 1,
+            maximum: 604800,
+        },
+        field: {
+            type: Array,
+            items: {
+                schema: SUPPRESSION_FIELD_SCHEMA,
+            },
+            optional: true,
+        },
+    }
+)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Suppression settings for a matcher.
+pub struct SuppressionConfig {
+    /// Hold-down window in seconds during which repetitions of a notification are suppressed.
+    pub window: u64,
+    /// Metadata fields used to identify repeated notifications. If not set, all metadata
+    /// fields are compared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub field: Vec<String>,
+}
+
+pub const SUPPRESSION_SCHEMA: Schema =
+    StringSchema::new("Suppress repeated notifications within a hold-down window.")
+        .format(&ApiStringFormat::PropertyString(
+            &SuppressionConfig::API_SCHEMA,
+        ))
+        .schema();
+
+impl SuppressionConfig {
+    /// Compute the key identifying repetitions of `notification`.
+    pub fn key(&self, notification: &Notification) -> String {
+        let additional_fields = &notification.metadata.additional_fields;
+
+        let fields: BTreeMap<&str, Option<&str>> = if self.field.is_empty() {
+            additional_fields
+                .iter()
+                .map(|(k, v)| (k.as_str(), Some(v.as_str())))
+                .collect()
+        } else {
+            self.field
+                .iter()
+                .map(|k| (k.as_str(), additional_fields.get(k).map(String::as_str)))
+                .collect()
+        };
+
+        let origin = match &notification.content {
+            Content::Template { template_name, .. } => template_name.as_str(),
+            #[cfg(feature = "mail-forwarder")]
+            Content::ForwardedMail { title, .. } => title.as_str(),
+        };
+
+        // Serializing a string and a map of strings cannot fail.
+        let data = serde_json::to_vec(&(origin, fields)).unwrap_or_default();
+
+        hex::encode(openssl::sha::sha256(&data))
+    }
+}
+
+/// Include the number of suppressed repetitions in a notification.
+///
+/// For template based notifications, the count is stored in the template data under
+/// [`REPEAT_COUNT_KEY`] and appended to the rendered title and body.
+pub fn with_repeat_count(notification: &Notification, count: u32) -> Notification {
+    let mut notification = notification.clone();
+
+    match &mut notification.content {
+        Content::Template { data, .. } => match data {
+            Value::Object(map) => {
+                map.insert(REPEAT_COUNT_KEY.to_string(), count.into());
+            }
+            Value::Null => {
+                *data = serde_json::json!({ REPEAT_COUNT_KEY: count });
+            }
+            _ => {}
+        },
+        #[cfg(feature = "mail-forwarder")]
+        Content::ForwardedMail { .. } => {}
+    }
+
+    notification
+}
+
+/// Get the number of suppressed repetitions included in a notification, if any.
+pub fn repeat_count(notification: &Notification) -> Option<u64> {
+    match &notification.content {
+        Content::Template { data, .. } => data.get(REPEAT_COUNT_KEY).and_then(Value::as_u64),
+        #[cfg(feature = "mail-forwarder")]
+        Content::ForwardedMail { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::Severity;
+
+    fn notification(fields: &[(&str, &str)]) -> Notification {
+        let fields: HashMap<String, String> = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        Notification::from_template(Severity::Error, "test", Value::Null, fields)
+    }
+
+    #[test]
+    fn test_suppression_key() {
+        let all_fields = SuppressionConfig {
+            window: 60,
+            field: Vec::new(),
+        };
+        let hostname_only = SuppressionConfig {
+            window: 60,
+            field: vec!["hostname".into()],
+        };
+
+        let a = notification(&[("hostname", "pve1"), ("job-id", "1")]);
+        let b = notification(&[("hostname", "pve1"), ("job-id", "2")]);
+        let c = notification(&[("hostname", "pve2"), ("job-id", "1")]);
+
+        assert_ne!(all_fields.key(&a), all_fields.key(&b));
+        assert_eq!(hostname_only.key(&a), hostname_only.key(&b));
+        assert_ne!(hostname_only.key(&a), hostname_only.key(&c));
+
+        let repeated = with_repeat_count(&a, 3);
+        assert_eq!(all_fields.key(&a), all_fields.key(&repeated));
+        assert_eq!(repeat_count(&a), None);
+        assert_eq!(repeat_count(&repeated), Some(3));
+    }
+
+    #[test]
+    fn test_property_string() {
+        let config: SuppressionConfig =
+            proxmox_schema::property_string::parse(r#"window=300,field="hostname,job-id""#)
+                .unwrap();
+
+        assert_eq!(config.window, 300);
+        assert_eq!(config.field, vec!["hostname", "job-id"]);
+        assert_eq!(
+            proxmox_schema::property_string::print(&config).unwrap(),
+            r#"window=300,field="hostname,job-id""#
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::SuppressionConfig;
+use crate::context::context;
+use crate::state_dir::StateDir;
+use crate::{Error, Notification};
+
+/// State of a single suppressed notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct SuppressionEntry {
+    /// Time when the hold-down window started.
+    pub(crate) since: i64,
+    /// Number of repetitions suppressed since then.
+    pub(crate) count: u32,
+    /// The most recently suppressed repetition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last: Option<Notification>,
+}
+
+/// Result of checking a notification against the suppression state of a matcher.
+pub(crate) enum Verdict {
+    /// The notification is a repetition within the hold-down window and must not be sent.
+    Suppressed,
+    /// The notification must be sent, including the number of previously suppressed
+    /// repetitions, if any.
+    Deliver(Option<u32>),
+}
+
+/// A suppressed notification whose hold-down window is over.
+pub(crate) struct ExpiredSuppression {
+    /// Name of the matcher which suppressed the notification.
+    pub(crate) matcher: String,
+    /// The most recently suppressed repetition.
+    pub(crate) notification: Notification,
+    /// Number of suppressed repetitions.
+    pub(crate) count: u32,
+}
+
+/// Storage for the suppression state of matchers.
+///
+/// The state of every matcher is stored in its own file, which is atomically replaced on
+/// updates. Modifications are serialized via an exclusive lock on a lock file within the
+/// suppression directory.
+pub struct SuppressionStore {
+    dir: StateDir,
+}
+
+impl SuppressionStore {
+    /// Create a suppression store using the directory `dir`.
+    ///
+    /// The directory will be created when first storing any state.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: StateDir::new(dir.into(), "suppression"),
+        }
+    }
+
+    /// Create a suppression store for the directory provided by the product context.
+    ///
+    /// Returns `None` if the context does not provide a suppression directory.
+    pub fn from_context() -> Option<Self> {
+        context().suppression_dir().map(Self::new)
+    }
+
+    fn state_path(&self, matcher: &str) -> PathBuf {
+        // Matcher names are restricted by `ENTITY_NAME_SCHEMA`, so they are safe to use as
+        // file names.
+        self.dir.path(matcher)
+    }
+
+    fn read(&self, matcher: &str) -> Result<HashMap<String, SuppressionEntry>, Error> {
+        let data = match std::fs::read(self.state_path(matcher)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => {
+                return Err(Error::Generic(format!(
+                    "could not read suppression state of matcher '{matcher}': {err}"
+                )));
+            }
+        };
+
+        serde_json::from_slice(&data).map_err(|err| Error::ConfigDeserialization(err.into()))
+    }
+
+    fn store(&self, matcher: &str, state: &HashMap<String, SuppressionEntry>) -> Result<(), Error> {
+        if state.is_empty() {
+            return match std::fs::remove_file(self.state_path(matcher)) {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(Error::Generic(format!(
+                    "could not remove suppression state of matcher '{matcher}': {err}"
+                ))),
+            };
+        }
+
+        let data =
+            serde_json::to_vec(state).map_err(|err| Error::ConfigSerialization(err.into()))?;
+
+        proxmox_sys::fs::replace_file(
+            self.state_path(matcher),
+            &data,
+            StateDir::file_options(),
+            true,
+        )
+        .map_err(|err| {
+            Error::Generic(format!(
+                "could not store suppression state of matcher '{matcher}': {err}"
+            ))
+        })
+    }
+
+    /// Check whether `notification` is a repetition that must be suppressed by `matcher`,
+    /// and update the suppression state accordingly.
+    pub(crate) fn check(
+        &self,
+        matcher: &str,
+        config: &SuppressionConfig,
+        notification: &Notification,
+    ) -> Result<Verdict, Error> {
+        let _lock = self.dir.lock()?;
+        let now = proxmox_time::epoch_i64();
+        let key = config.key(notification);
+
+        let mut state = self.read(matcher)?;
+
+        let verdict = match state.get_mut(&key) {
+            Some(entry) if entry.since.saturating_add(config.window as i64) > now => {
+                entry.count += 1;
+                entry.last = Some(notification.clone());
+                Verdict::Suppressed
+            }
+            previous => {
+                let count = previous.map(|entry| entry.count).filter(|count| *count > 0);
+                state.insert(
+                    key,
+                    SuppressionEntry {
+                        since: now,
+                        count: 0,
+                        last: None,
+                    },
+                );
+                Verdict::Deliver(count)
+            }
+        };
+
+        self.store(matcher, &state)?;
+
+        Ok(verdict)
+    }
+
+    /// Take all suppressed notifications whose hold-down window is over.
+    ///
+    /// `window` is called with the name of each matcher that has suppression state and
+    /// returns its hold-down window in seconds. The state of matchers for which `window`
+    /// returns `None` is considered expired.
+    ///
+    /// Expired entries without suppressed repetitions are removed. For the others, a new
+    /// hold-down window is started, since the returned notifications are about to be sent.
+    pub(crate) fn take_expired<F>(&self, window: F) -> Result<Vec<ExpiredSuppression>, Error>
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        let _lock = self.dir.lock()?;
+        let now = proxmox_time::epoch_i64();
+
+        let mut expired = Vec::new();
+
+        for matcher in self.dir.names()? {
+            let matcher = matcher.as_str();
+
+            let mut state = match self.read(matcher) {
+                Ok(state) => state,
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+
+            let window = window(matcher);
+
+            state.retain(|_, entry| {
+                let is_expired = match window {
+                    Some(window) => entry.since.saturating_add(window as i64) <= now,
+                    None => true,
+                };
+
+                if !is_expired {
+                    return true;
+                }
+
+                match entry.last.take() {
+                    Some(notification) if entry.count > 0 => {
+                        expired.push(ExpiredSuppression {
+                            matcher: matcher.to_string(),
+                            notification,
+                            count: entry.count,
+                        });
+
+                        entry.since = now;
+                        entry.count = 0;
+                        window.is_some()
+                    }
+                    _ => false,
+                }
+            });
+
+            self.store(matcher, &state)?;
+        }
+
+        Ok(expired)
+    }
+}
@@ -115,6 +115,18 @@ impl ApiConfig {
         self.auth_handler(AuthHandler::from_fn(func))
     }
 
+    /// Set the authentication handler from a function which also gets the [RestEnvironment],
+    /// e.g. to map a TLS client certificate to a user.
+    pub fn auth_handler_env_func<Func>(self, func: Func) -> Self
+    where
+        Func: for<'a> Fn(&'a HeaderMap, &'a Method, &'a RestEnvironment) -> CheckAuthFuture<'a>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.auth_handler(AuthHandler::from_env_fn(func))
+    }
+
     pub fn auth_cookie_name(mut self, auth_cookie_name: String) -> Self {
         self.auth_cookie_name = Some(auth_cookie_name);
         self
@@ -167,9 +179,10 @@ impl ApiConfig {
         &self,
         headers: &HeaderMap,
         method: &Method,
+        rpcenv: &RestEnvironment,
     ) -> Result<(String, Box<dyn UserInformation + Sync + Send>), AuthError> {
         match self.auth_handler.as_ref() {
-            Some(handler) => (handler.func)(headers, method).await,
+            Some(handler) => (handler.func)(headers, method, rpcenv).await,
             None => Err(AuthError::NoData),
         }
     }
@@ -512,12 +525,24 @@ pub type CheckAuthFuture<'a> = Pin<Box<dyn Future<Output = CheckAuthOutput> + Se
 pub type CheckAuthFunc =
     Box<dyn for<'a> Fn(&'a HeaderMap, &'a Method) -> CheckAuthFuture<'a> + Send + Sync>;
 
+pub type CheckAuthEnvFunc = Box<
+    dyn for<'a> Fn(&'a HeaderMap, &'a Method, &'a RestEnvironment) -> CheckAuthFuture<'a>
+        + Send
+        + Sync,
+>;
+
 pub struct AuthHandler {
-    func: CheckAuthFunc,
+    func: CheckAuthEnvFunc,
 }
 
 impl From<CheckAuthFunc> for AuthHandler {
     fn from(func: CheckAuthFunc) -> Self {
+        Self::from_env_fn(move |headers, method, _rpcenv| func(headers, method))
+    }
+}
+
+impl From<CheckAuthEnvFunc> for AuthHandler {
+    fn from(func: CheckAuthEnvFunc) -> Self {
         Self { func }
     }
 }
@@ -529,6 +554,18 @@ impl AuthHandler {
     {
         Self::from(Box::new(func) as CheckAuthFunc)
     }
+
+    /// Create a handler which also gets access to the [RestEnvironment], for example to check the
+    /// peer's [TLS client certificate](RestEnvironment::peer_certificate).
+    pub fn from_env_fn<Func>(func: Func) -> Self
+    where
+        Func: for<'a> Fn(&'a HeaderMap, &'a Method, &'a RestEnvironment) -> CheckAuthFuture<'a>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self::from(Box::new(func) as CheckAuthEnvFunc)
+    }
 }
 
 /// Authentication Error
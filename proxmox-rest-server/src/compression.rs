@@ -2,11 +2,11 @@ use anyhow::{Error, bail};
 use hyper::header;
 
 /// Possible Compression Methods, order determines preference (later is preferred)
-#[derive(Eq, Ord, PartialEq, PartialOrd, Debug)]
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Debug)]
 pub enum CompressionMethod {
     Deflate,
-    //    Gzip,
-    //    Brotli,
+    Gzip,
+    Brotli,
 }
 
 impl CompressionMethod {
@@ -14,26 +14,119 @@ impl CompressionMethod {
         header::HeaderValue::from_static(self.extension())
     }
 
+    /// Content coding name as used in http headers
     pub fn extension(&self) -> &'static str {
         match *self {
-            //            CompressionMethod::Brotli => "br",
-            //            CompressionMethod::Gzip => "gzip",
+            CompressionMethod::Brotli => "br",
+            CompressionMethod::Gzip => "gzip",
             CompressionMethod::Deflate => "deflate",
         }
     }
+
+    /// File name extension of precompressed files, if supported
+    pub fn file_extension(&self) -> Option<&'static str> {
+        match *self {
+            CompressionMethod::Brotli => Some("br"),
+            CompressionMethod::Gzip => Some("gz"),
+            CompressionMethod::Deflate => None,
+        }
+    }
+
+    /// Returns the methods accepted by an `Accept-Encoding` header value, most preferred first
+    ///
+    /// Methods are ordered by their quality value (`;q=`), then by our own preference. Codings
+    /// with a quality value of zero are not acceptable, a `*` applies to all methods not listed
+    /// explicitly.
+    pub fn from_accept_encoding(value: &str) -> Vec<CompressionMethod> {
+        let mut listed: Vec<(CompressionMethod, f32)> = Vec::new();
+        let mut wildcard = None;
+
+        'entries: for entry in value.split(',') {
+            let mut params = entry.split(';');
+            let coding = params.next().unwrap_or_default().trim();
+
+            let mut quality = 1.0;
+            for param in params {
+                if let Some((name, value)) = param.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("q") {
+                        match value.trim().parse::<f32>() {
+                            Ok(value) if (0.0..=1.0).contains(&value) => quality = value,
+                            _ => continue 'entries, // ignore invalid entries
+                        }
+                    }
+                }
+            }
+
+            if coding == "*" {
+                wildcard = Some(quality);
+            } else if let Ok(method) = coding.parse() {
+                listed.push((method, quality));
+            }
+        }
+
+        let mut methods: Vec<(CompressionMethod, f32)> = [
+            CompressionMethod::Brotli,
+            CompressionMethod::Gzip,
+            CompressionMethod::Deflate,
+        ]
+        .into_iter()
+        .filter_map(|method| {
+            let quality = listed
+                .iter()
+                .find(|(listed, _)| *listed == method)
+                .map(|(_, quality)| *quality)
+                .or(wildcard)?;
+            (quality > 0.0).then_some((method, quality))
+        })
+        .collect();
+
+        // stable sort, so equal quality keeps our preference
+        methods.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        methods.into_iter().map(|(method, _)| method).collect()
+    }
 }
 
 impl std::str::FromStr for CompressionMethod {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            //            "br" => Ok(CompressionMethod::Brotli),
-            //            "gzip" => Ok(CompressionMethod::Gzip),
+        match s.to_ascii_lowercase().as_str() {
+            "br" => Ok(CompressionMethod::Brotli),
+            "gzip" | "x-gzip" => Ok(CompressionMethod::Gzip),
             "deflate" => Ok(CompressionMethod::Deflate),
-            // http accept-encoding allows to give weights with ';q='
-            other if other.starts_with("deflate;q=") => Ok(CompressionMethod::Deflate),
             _ => bail!("unknown compression format"),
         }
     }
 }
+
+#[test]
+fn test_accept_encoding() {
+    use CompressionMethod::*;
+
+    assert_eq!(
+        CompressionMethod::from_accept_encoding("gzip, deflate, br"),
+        [Brotli, Gzip, Deflate]
+    );
+    assert_eq!(
+        CompressionMethod::from_accept_encoding("deflate;q=0.5, gzip;q=0.8"),
+        [Gzip, Deflate]
+    );
+    assert_eq!(
+        CompressionMethod::from_accept_encoding("br;q=0, GZIP, *;q=0.1"),
+        [Gzip, Deflate]
+    );
+    assert_eq!(
+        CompressionMethod::from_accept_encoding("gzip;q=0.5, deflate;q=1.0"),
+        [Deflate, Gzip]
+    );
+    assert_eq!(
+        CompressionMethod::from_accept_encoding("deflate;q=2, gzip;q=abc, identity"),
+        []
+    );
+    assert_eq!(
+        CompressionMethod::from_accept_encoding("*"),
+        [Brotli, Gzip, Deflate]
+    );
+    assert_eq!(CompressionMethod::from_accept_encoding(""), []);
+}
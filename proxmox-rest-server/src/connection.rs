@@ -11,13 +11,14 @@ use std::pin::{Pin, pin};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use anyhow::{Context, Error, format_err};
+use anyhow::{Context, Error, bail, format_err};
 use futures::FutureExt;
 use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private};
-use openssl::ssl::{SslAcceptor, SslMethod};
-use openssl::x509::X509;
+use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+use openssl::x509::{GeneralNameRef, X509, X509Name, X509NameRef, X509VerifyResult};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::mpsc;
 use tokio_openssl::SslStream;
@@ -45,6 +46,8 @@ pub struct TlsAcceptorBuilder {
     tls: Option<Tls>,
     cipher_suites: Option<String>,
     cipher_list: Option<String>,
+    client_ca: Option<PathBuf>,
+    client_cert_required: bool,
 }
 
 impl TlsAcceptorBuilder {
@@ -76,6 +79,23 @@ impl TlsAcceptorBuilder {
         self
     }
 
+    /// Verify client certificates against the CA certificates in the given PEM file.
+    ///
+    /// Clients without a certificate are still accepted unless
+    /// [`require_client_certificate`](Self::require_client_certificate) is set, clients with an
+    /// invalid certificate are always rejected. The verified certificate is available via
+    /// [`RestEnvironment::peer_certificate`](crate::RestEnvironment::peer_certificate).
+    pub fn client_ca_path_pem(mut self, ca: impl Into<PathBuf>) -> Self {
+        self.client_ca = Some(ca.into());
+        self
+    }
+
+    /// Reject clients which do not present a certificate, requires a client CA to be set.
+    pub fn require_client_certificate(mut self, required: bool) -> Self {
+        self.client_cert_required = required;
+        self
+    }
+
     pub fn build(self) -> Result<SslAcceptor, Error> {
         let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())
             .context("failed to create TLS acceptor")?;
@@ -154,6 +174,28 @@ impl TlsAcceptorBuilder {
                     .context("failed to set tls acceptor certificate")?;
             }
         }
+
+        if let Some(ca) = self.client_ca {
+            acceptor
+                .set_ca_file(&ca)
+                .with_context(|| format!("failed to load client CA file {ca:?}"))?;
+            let ca_names = X509Name::load_client_ca_file(&ca)
+                .with_context(|| format!("failed to load client CA names from {ca:?}"))?;
+            acceptor.set_client_ca_list(ca_names);
+
+            let mut mode = SslVerifyMode::PEER;
+            if self.client_cert_required {
+                mode |= SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+            }
+            acceptor.set_verify(mode);
+            // session resumption fails without a context once peer verification is enabled
+            acceptor
+                .set_session_id_context(b"proxmox-rest-server")
+                .context("failed to set tls session id context")?;
+        } else if self.client_cert_required {
+            bail!("client certificates can only be required with a client CA");
+        }
+
         acceptor.set_options(openssl::ssl::SslOptions::NO_RENEGOTIATION);
         acceptor
             .check_private_key()
@@ -163,6 +205,103 @@ impl TlsAcceptorBuilder {
     }
 }
 
+/// A verified TLS client certificate.
+#[derive(Clone, Debug)]
+pub struct PeerCertificate {
+    subject: String,
+    subject_alt_names: Vec<String>,
+    fingerprint: String,
+    certificate: X509,
+}
+
+impl PeerCertificate {
+    pub fn from_x509(certificate: X509) -> Result<Self, Error> {
+        let subject = x509_name_to_string(certificate.subject_name());
+
+        let subject_alt_names = certificate
+            .subject_alt_names()
+            .map(|names| names.iter().filter_map(general_name_to_string).collect())
+            .unwrap_or_default();
+
+        let fingerprint = certificate
+            .digest(MessageDigest::sha256())
+            .context("failed to calculate certificate fingerprint")?
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<String>>()
+            .join(":");
+
+        Ok(Self {
+            subject,
+            subject_alt_names,
+            fingerprint,
+            certificate,
+        })
+    }
+
+    /// The subject name, e.g. `C=AT, O=Example, CN=client`.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// The subject alternative names (DNS names, IP addresses, emails and URIs).
+    pub fn subject_alt_names(&self) -> &[String] {
+        &self.subject_alt_names
+    }
+
+    /// The SHA256 fingerprint as colon separated hex string.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// The full certificate.
+    pub fn certificate(&self) -> &X509 {
+        &self.certificate
+    }
+}
+
+fn x509_name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("UNKNOWN");
+            let value = entry.data().to_string().unwrap_or_default();
+            format!("{key}={value}")
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn general_name_to_string(name: &GeneralNameRef) -> Option<String> {
+    if let Some(dns) = name.dnsname() {
+        Some(dns.to_string())
+    } else if let Some(ip) = name.ipaddress() {
+        match ip.len() {
+            4 => Some(std::net::Ipv4Addr::from(<[u8; 4]>::try_from(ip).ok()?).to_string()),
+            16 => Some(std::net::Ipv6Addr::from(<[u8; 16]>::try_from(ip).ok()?).to_string()),
+            _ => None,
+        }
+    } else if let Some(email) = name.email() {
+        Some(email.to_string())
+    } else {
+        name.uri().map(|uri| uri.to_string())
+    }
+}
+
+/// Returns the client certificate of an established TLS connection, if it was verified.
+pub(crate) fn verified_peer_certificate(ssl: &openssl::ssl::SslRef) -> Option<PeerCertificate> {
+    let certificate = ssl.peer_certificate()?;
+    if ssl.verify_result() != X509VerifyResult::OK {
+        return None;
+    }
+    match PeerCertificate::from_x509(certificate) {
+        Ok(certificate) => Some(certificate),
+        Err(err) => {
+            log::error!("failed to parse client certificate: {err:#}");
+            None
+        }
+    }
+}
+
 #[cfg(not(feature = "rate-limited-stream"))]
 type InsecureClientStream = TcpStream;
 #[cfg(feature = "rate-limited-stream")]
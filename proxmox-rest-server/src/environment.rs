@@ -6,6 +6,7 @@ use serde_json::{Value, json};
 use proxmox_router::{RpcEnvironment, RpcEnvironmentType};
 
 use crate::ApiConfig;
+use crate::connection::PeerCertificate;
 
 /// Encapsulates information about the runtime environment
 pub struct RestEnvironment {
@@ -13,6 +14,7 @@ pub struct RestEnvironment {
     result_attributes: Value,
     auth_id: Option<String>,
     client_ip: Option<SocketAddr>,
+    peer_certificate: Option<Arc<PeerCertificate>>,
     api: Arc<ApiConfig>,
 }
 
@@ -22,6 +24,7 @@ impl RestEnvironment {
             result_attributes: json!({}),
             auth_id: None,
             client_ip: None,
+            peer_certificate: None,
             env_type,
             api,
         }
@@ -31,6 +34,16 @@ impl RestEnvironment {
         &self.api
     }
 
+    /// Set the verified TLS client certificate of the peer.
+    pub fn set_peer_certificate(&mut self, peer_certificate: Option<Arc<PeerCertificate>>) {
+        self.peer_certificate = peer_certificate;
+    }
+
+    /// The verified TLS client certificate of the peer, if the connection used mutual TLS.
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer_certificate.as_deref()
+    }
+
     pub fn log_auth(&self, auth_id: &str) {
         let msg = format!("successful auth for user '{auth_id}'");
         log::debug!("{}", msg); // avoid noisy syslog, admins can already check the auth log
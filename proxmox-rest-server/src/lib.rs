@@ -13,6 +13,7 @@
 //!   - logfile rotation
 //!   - worker task management
 //! * generic interface to authenticate user
+//! * optional TLS client certificate verification (mutual TLS)
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
@@ -42,8 +42,11 @@ use proxmox_schema::{ObjectSchemaType, ParameterSchema};
 
 use proxmox_async::stream::AsyncReaderStream;
 use proxmox_compression::DeflateEncoder;
+use proxmox_compression::brotli::BrotliEncoder;
+use proxmox_compression::gzip::GzipEncoder;
 use proxmox_log::FileLogger;
 
+use crate::connection::{PeerCertificate, verified_peer_certificate};
 use crate::{
     ApiConfig, AuthError, CompressionMethod, RestEnvironment, formatter::*, normalize_path,
 };
@@ -97,6 +100,7 @@ impl RestServer {
     {
         Ok(ApiService {
             peer: peer.peer_addr()?,
+            peer_certificate: peer.peer_certificate().map(Arc::new),
             api_config: Arc::clone(&self.api_config),
         })
     }
@@ -108,6 +112,7 @@ impl RestServer {
     {
         Ok(ApiService {
             peer: peer.peer_addr()?,
+            peer_certificate: peer.peer_certificate().map(Arc::new),
             api_config: Arc::clone(&self.api_config),
             rate_limit_tags: peer.rate_limiter_tag_handle(),
         })
@@ -203,6 +208,11 @@ impl Service<Request<Incoming>> for RedirectService {
 
 pub trait PeerAddress {
     fn peer_addr(&self) -> Result<std::net::SocketAddr, Error>;
+
+    /// The verified TLS client certificate of the peer, if any.
+    fn peer_certificate(&self) -> Option<PeerCertificate> {
+        None
+    }
 }
 
 #[cfg(feature = "rate-limited-stream")]
@@ -217,12 +227,20 @@ impl<T: PeerAddress> PeerAddress for Pin<Box<T>> {
     fn peer_addr(&self) -> Result<std::net::SocketAddr, Error> {
         T::peer_addr(&**self)
     }
+
+    fn peer_certificate(&self) -> Option<PeerCertificate> {
+        T::peer_certificate(&**self)
+    }
 }
 
 impl<T: PeerAddress> PeerAddress for tokio_openssl::SslStream<T> {
     fn peer_addr(&self) -> Result<std::net::SocketAddr, Error> {
         self.get_ref().peer_addr()
     }
+
+    fn peer_certificate(&self) -> Option<PeerCertificate> {
+        verified_peer_certificate(self.ssl())
+    }
 }
 
 impl PeerAddress for tokio::net::TcpStream {
@@ -292,12 +310,18 @@ impl<T> PeerRateLimitTags for proxmox_http::RateLimitedStream<T> {
 #[derive(Clone)]
 pub struct ApiService {
     pub peer: std::net::SocketAddr,
+    peer_certificate: Option<Arc<PeerCertificate>>,
     pub api_config: Arc<ApiConfig>,
     #[cfg(feature = "rate-limited-stream")]
     pub rate_limit_tags: Option<RateLimiterTagsHandle>,
 }
 
 impl ApiService {
+    /// The verified TLS client certificate of the peer, if any.
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer_certificate.as_deref()
+    }
+
     pub async fn serve<S>(
         self,
         conn: S,
@@ -423,7 +447,11 @@ impl Service<Request<Incoming>> for ApiService {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Request<Incoming>) -> Self::Future {
+    fn call(&mut self, mut req: Request<Incoming>) -> Self::Future {
+        if let Some(certificate) = &self.peer_certificate {
+            req.extensions_mut().insert(Arc::clone(certificate));
+        }
+
         let path = req
             .uri()
             .path_and_query()
@@ -688,7 +716,9 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
 ) -> Result<Response<Body>, Error> {
     let formatter = formatter.unwrap_or(crate::formatter::DIRECT_JSON_FORMATTER);
 
-    let compression = extract_compression_method(&parts.headers);
+    let compression = extract_compression_methods(&parts.headers)
+        .into_iter()
+        .next();
 
     let accept_json_seq = parts.headers.get_all(http::header::ACCEPT).iter().any(|h| {
         h.as_ref()
@@ -779,20 +809,16 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
             .is_some_and(|h| h.as_ref().starts_with(b"application/json-seq"));
 
     let resp = match compression {
-        Some(CompressionMethod::Deflate) => {
-            resp.headers_mut().insert(
-                header::CONTENT_ENCODING,
-                CompressionMethod::Deflate.content_encoding(),
-            );
+        Some(method) => {
+            resp.headers_mut()
+                .insert(header::CONTENT_ENCODING, method.content_encoding());
             resp.map(|body| {
-                Body::wrap_stream(
-                    DeflateEncoder::builder(TryStreamExt::map_err(
-                        BodyDataStream::new(body),
-                        |err| proxmox_lang::io_format_err!("error during compression: {}", err),
-                    ))
-                    .zlib(true)
-                    .flush_window(is_streaming.then_some(64 * 1024))
-                    .build(),
+                compressed_body(
+                    TryStreamExt::map_err(BodyDataStream::new(body), |err| {
+                        proxmox_lang::io_format_err!("error during compression: {}", err)
+                    }),
+                    method,
+                    is_streaming.then_some(64 * 1024),
                 )
             })
         }
@@ -808,6 +834,28 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
     Ok(resp)
 }
 
+/// Compress a stream on-the-fly, see [DeflateEncoder] for the meaning of `flush_window`.
+fn compressed_body<S, O>(stream: S, method: CompressionMethod, flush_window: Option<usize>) -> Body
+where
+    S: futures::Stream<Item = Result<O, io::Error>> + Send + Unpin + 'static,
+    O: Into<hyper::body::Bytes> + Send + 'static,
+{
+    match method {
+        CompressionMethod::Deflate => Body::wrap_stream(
+            DeflateEncoder::builder(stream)
+                .zlib(true)
+                .flush_window(flush_window)
+                .build(),
+        ),
+        CompressionMethod::Gzip => {
+            Body::wrap_stream(GzipEncoder::new(stream).flush_window(flush_window))
+        }
+        CompressionMethod::Brotli => {
+            Body::wrap_stream(BrotliEncoder::new(stream).flush_window(flush_window))
+        }
+    }
+}
+
 fn extension_to_content_type(filename: &Path) -> (&'static str, bool) {
     if let Some(ext) = filename.extension().and_then(|osstr| osstr.to_str()) {
         return match ext {
@@ -837,29 +885,43 @@ fn extension_to_content_type(filename: &Path) -> (&'static str, bool) {
     ("application/octet-stream", false)
 }
 
-async fn simple_static_file_download(
-    mut file: File,
+async fn simple_static_file_download<R>(
+    mut reader: R,
     content_type: &'static str,
     compression: Option<CompressionMethod>,
-) -> Result<Response<Body>, Error> {
+) -> Result<Response<Body>, Error>
+where
+    R: AsyncRead + Unpin,
+{
     use tokio::io::AsyncReadExt;
 
     let mut data: Vec<u8> = Vec::new();
 
+    let size_hint = CHUNK_SIZE_LIMIT as usize;
     let mut response = match compression {
-        Some(CompressionMethod::Deflate) => {
-            let mut enc = DeflateEncoder::builder(data).zlib(true).build();
-            enc.compress_vec(&mut file, CHUNK_SIZE_LIMIT as usize)
-                .await?;
-            let mut response = Response::new(enc.into_inner().into());
-            response.headers_mut().insert(
-                header::CONTENT_ENCODING,
-                CompressionMethod::Deflate.content_encoding(),
-            );
+        Some(method) => {
+            let data = match method {
+                CompressionMethod::Deflate => {
+                    let mut enc = DeflateEncoder::builder(data).zlib(true).build();
+                    enc.compress_vec(&mut reader, size_hint).await?;
+                    enc.into_inner()
+                }
+                CompressionMethod::Gzip => {
+                    proxmox_compression::gzip::compress_vec(&mut reader, size_hint).await?
+                }
+                CompressionMethod::Brotli => {
+                    proxmox_compression::brotli::compress_vec(&mut reader, size_hint).await?
+                }
+            };
+            let mut response = Response::new(data.into());
+            response
+                .headers_mut()
+                .insert(header::CONTENT_ENCODING, method.content_encoding());
             response
         }
         None => {
-            file.read_to_end(&mut data)
+            reader
+                .read_to_end(&mut data)
                 .await
                 .map_err(|err| http_err!(BAD_REQUEST, "File read failed: {}", err))?;
             Response::new(data.into())
@@ -874,38 +936,147 @@ async fn simple_static_file_download(
     Ok(response)
 }
 
-async fn chunked_static_file_download(
-    file: File,
+async fn chunked_static_file_download<R>(
+    reader: R,
     content_type: &'static str,
     compression: Option<CompressionMethod>,
-) -> Result<Response<Body>, Error> {
+) -> Result<Response<Body>, Error>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
     let mut resp = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, content_type);
 
     let body = match compression {
-        Some(CompressionMethod::Deflate) => {
-            resp = resp.header(
-                header::CONTENT_ENCODING,
-                CompressionMethod::Deflate.content_encoding(),
-            );
-            Body::wrap_stream(
-                DeflateEncoder::builder(AsyncReaderStream::new(file))
-                    .zlib(true)
-                    .build(),
-            )
+        Some(method) => {
+            resp = resp.header(header::CONTENT_ENCODING, method.content_encoding());
+            compressed_body(AsyncReaderStream::new(reader), method, None)
         }
-        None => Body::wrap_stream(AsyncReaderStream::new(file)),
+        None => Body::wrap_stream(AsyncReaderStream::new(reader)),
     };
 
     Ok(resp.body(body).unwrap())
 }
 
+/// Look for a precompressed variant of `filename` (e.g. `file.js.gz`) for any of the accepted
+/// compression methods, most preferred first.
+///
+/// Variants older than the file itself are outdated and skipped.
+async fn find_precompressed_file(
+    filename: &Path,
+    source: &std::fs::Metadata,
+    compression: &[CompressionMethod],
+) -> Option<(PathBuf, std::fs::Metadata, CompressionMethod)> {
+    let source_mtime = source.modified().ok();
+    for method in compression {
+        let Some(extension) = method.file_extension() else {
+            continue;
+        };
+        let mut path = filename.as_os_str().to_owned();
+        path.push(".");
+        path.push(extension);
+        let path = PathBuf::from(path);
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => {
+                if metadata.modified().ok() < source_mtime {
+                    log::debug!("ignoring outdated precompressed file {path:?}");
+                    continue;
+                }
+                return Some((path, metadata, *method));
+            }
+            _ => continue,
+        }
+    }
+    None
+}
+
+/// Entity tag of a static file, derived from inode, size and modification time.
+///
+/// The content encoding is part of the tag, as each encoding is a different representation.
+fn static_file_etag(metadata: &std::fs::Metadata, encoding: Option<CompressionMethod>) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    let mtime = metadata.mtime() as u64 * 1_000_000_000 + metadata.mtime_nsec() as u64;
+    match encoding {
+        Some(method) => format!(
+            "\"{:x}-{:x}-{:x}-{}\"",
+            metadata.ino(),
+            metadata.len(),
+            mtime,
+            method.extension()
+        ),
+        None => format!("\"{:x}-{:x}-{:x}\"", metadata.ino(), metadata.len(), mtime),
+    }
+}
+
+/// Check an `If-None-Match` header value against our entity tag (weak comparison).
+fn etag_list_matches(value: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// A single byte range requested via the `Range` header.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// First and last byte (inclusive) to send.
+    Satisfiable(u64, u64),
+    /// The range does not overlap with the file's content.
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header value for a file of the given `size`.
+///
+/// Returns `None` if the header should be ignored, which is the case for invalid values and for
+/// multiple ranges, which we do not support.
+fn parse_byte_range(value: &str, size: u64) -> Option<ByteRange> {
+    let (unit, range) = value.trim().split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") || range.contains(',') {
+        return None;
+    }
+
+    let (first, last) = range.trim().split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // suffix range, the last N bytes
+        let length: u64 = last.parse().ok()?;
+        if length == 0 || size == 0 {
+            return Some(ByteRange::Unsatisfiable);
+        }
+        return Some(ByteRange::Satisfiable(
+            size.saturating_sub(length),
+            size - 1,
+        ));
+    }
+
+    let first: u64 = first.parse().ok()?;
+    let last: u64 = if last.is_empty() {
+        u64::MAX
+    } else {
+        last.parse().ok()?
+    };
+
+    if last < first {
+        return None;
+    }
+    if first >= size {
+        return Some(ByteRange::Unsatisfiable);
+    }
+
+    Some(ByteRange::Satisfiable(first, last.min(size - 1)))
+}
+
 async fn handle_static_file_download(
     components: &[&str],
     filename: PathBuf,
-    compression: Option<CompressionMethod>,
+    headers: &HeaderMap,
 ) -> Result<Response<Body>, Error> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
     let metadata = match tokio::fs::metadata(filename.clone()).await {
         Ok(metadata) => metadata,
         Err(err) if err.kind() == io::ErrorKind::NotFound => {
@@ -920,9 +1091,95 @@ async fn handle_static_file_download(
     };
 
     let (content_type, nocomp) = extension_to_content_type(&filename);
-    let compression = if nocomp { None } else { compression };
+    let compression = extract_compression_methods(headers);
+
+    // serve precompressed files as-is, only the content encoding differs
+    let (filename, metadata, precompressed) =
+        match find_precompressed_file(&filename, &metadata, &compression).await {
+            Some((path, metadata, method)) => (path, metadata, Some(method)),
+            None => (filename, metadata, None),
+        };
+
+    // the length of on-the-fly compressed content is unknown, so ranges are served uncompressed
+    let range_header = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let compression = if nocomp || precompressed.is_some() || range_header.is_some() {
+        None
+    } else {
+        compression.into_iter().next()
+    };
+
+    let etag = static_file_etag(&metadata, precompressed.or(compression));
+    let last_modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|time| proxmox_time::epoch_to_http_date(time.as_secs() as i64).ok());
+
+    let mut cache_headers = HeaderMap::new();
+    cache_headers.insert(header::ETAG, header::HeaderValue::from_str(&etag)?);
+    if let Some(last_modified) = &last_modified {
+        cache_headers.insert(
+            header::LAST_MODIFIED,
+            header::HeaderValue::from_str(last_modified)?,
+        );
+    }
+    if !nocomp || precompressed.is_some() {
+        cache_headers.insert(
+            header::VARY,
+            header::HeaderValue::from_static("accept-encoding"),
+        );
+    }
 
-    let file = File::open(filename).await.map_err(|err| {
+    // If-Modified-Since is only evaluated without If-None-Match, see RFC 9110, 13.1.3
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(value) => value
+            .to_str()
+            .is_ok_and(|value| etag_list_matches(value, &etag)),
+        // we only support exact matches, as sent back by clients
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| Some(value) == last_modified.as_deref()),
+    };
+    if not_modified {
+        let mut response = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+        response.headers_mut().extend(cache_headers);
+        return Ok(response);
+    }
+
+    // a range is only valid for the representation the client already has (strong comparison)
+    let if_range_matches = match headers.get(header::IF_RANGE) {
+        Some(value) => value
+            .to_str()
+            .is_ok_and(|value| value == etag || Some(value) == last_modified.as_deref()),
+        None => true,
+    };
+    let size = metadata.len();
+    let range = match range_header {
+        Some(value) if if_range_matches => parse_byte_range(value, size),
+        _ => None,
+    };
+
+    let (first, length) = match range {
+        Some(ByteRange::Satisfiable(first, last)) => (first, last - first + 1),
+        Some(ByteRange::Unsatisfiable) => {
+            let mut response = Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+                .body(Body::empty())
+                .unwrap();
+            response.headers_mut().extend(cache_headers);
+            return Ok(response);
+        }
+        None => (0, size),
+    };
+
+    let mut file = File::open(filename).await.map_err(|err| {
         http_err!(
             BAD_REQUEST,
             "File open failed for '{}': {}",
@@ -930,24 +1187,52 @@ async fn handle_static_file_download(
             err.kind()
         )
     })?;
+    if first > 0 {
+        file.seek(io::SeekFrom::Start(first))
+            .await
+            .map_err(|err| http_err!(BAD_REQUEST, "File seek failed: {}", err))?;
+    }
+    let reader = file.take(length);
 
-    if metadata.len() < CHUNK_SIZE_LIMIT {
-        simple_static_file_download(file, content_type, compression).await
+    let mut response = if length < CHUNK_SIZE_LIMIT {
+        simple_static_file_download(reader, content_type, compression).await?
     } else {
-        chunked_static_file_download(file, content_type, compression).await
+        chunked_static_file_download(reader, content_type, compression).await?
+    };
+
+    let response_headers = response.headers_mut();
+    if let Some(method) = precompressed {
+        response_headers.insert(header::CONTENT_ENCODING, method.content_encoding());
+    }
+    response_headers.extend(cache_headers);
+    response_headers.insert(
+        header::ACCEPT_RANGES,
+        header::HeaderValue::from_static("bytes"),
+    );
+    if compression.is_none() {
+        response_headers.insert(header::CONTENT_LENGTH, length.into());
+    }
+    if let Some(ByteRange::Satisfiable(first, last)) = range {
+        response_headers.insert(
+            header::CONTENT_RANGE,
+            header::HeaderValue::from_str(&format!("bytes {first}-{last}/{size}"))?,
+        );
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
     }
+
+    Ok(response)
 }
 
-// FIXME: support handling multiple compression methods
-fn extract_compression_method(headers: &http::HeaderMap) -> Option<CompressionMethod> {
-    if let Some(Ok(encodings)) = headers.get(header::ACCEPT_ENCODING).map(|v| v.to_str()) {
-        for encoding in encodings.split(&[',', ' '][..]) {
-            if let Ok(method) = encoding.parse() {
-                return Some(method);
-            }
-        }
-    }
-    None
+/// Returns the compression methods accepted by the client, most preferred first
+fn extract_compression_methods(headers: &http::HeaderMap) -> Vec<CompressionMethod> {
+    // multiple header fields are equivalent to a single comma separated list
+    let value = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<&str>>()
+        .join(",");
+    CompressionMethod::from_accept_encoding(&value)
 }
 
 impl ApiConfig {
@@ -975,6 +1260,7 @@ impl ApiConfig {
         let mut rpcenv = RestEnvironment::new(env_type, Arc::clone(&self));
 
         rpcenv.set_client_ip(Some(*peer));
+        rpcenv.set_peer_certificate(parts.extensions.get::<Arc<PeerCertificate>>().cloned());
 
         if let Some(handler) = self.find_handler(&components) {
             let relative_path_components = &components[handler.prefix.len()..];
@@ -998,7 +1284,7 @@ impl ApiConfig {
         }
 
         if components.is_empty() {
-            match self.check_auth(&parts.headers, &method).await {
+            match self.check_auth(&parts.headers, &method, &rpcenv).await {
                 Ok((auth_id, _user_info)) => {
                     rpcenv.set_auth_id(Some(auth_id.clone()));
                     #[cfg(feature = "rate-limited-stream")]
@@ -1025,8 +1311,7 @@ impl ApiConfig {
             Ok(self.get_index(rpcenv, parts).await)
         } else {
             let filename = self.find_alias(&components);
-            let compression = extract_compression_method(&parts.headers);
-            handle_static_file_download(&components, filename, compression).await
+            handle_static_file_download(&components, filename, &parts.headers).await
         }
     }
 }
@@ -1141,7 +1426,10 @@ impl Formatted {
             Box::new(EmptyUserInformation {});
 
         if auth_required {
-            match config.check_auth(&parts.headers, &parts.method).await {
+            match config
+                .check_auth(&parts.headers, &parts.method, &rpcenv)
+                .await
+            {
                 Ok((authid, info)) => {
                     #[cfg(feature = "rate-limited-stream")]
                     if let Some(handle) = rate_limit_tags.as_ref() {
@@ -1262,7 +1550,10 @@ impl Unformatted {
         let user_info: Box<dyn UserInformation + Send + Sync>;
 
         if auth_required {
-            match config.check_auth(&parts.headers, &parts.method).await {
+            match config
+                .check_auth(&parts.headers, &parts.method, &rpcenv)
+                .await
+            {
                 Ok((authid, info)) => {
                     #[cfg(feature = "rate-limited-stream")]
                     if let Some(handle) = rate_limit_tags.as_ref() {
@@ -1338,3 +1629,89 @@ impl Unformatted {
         }
     }
 }
+
+#[test]
+fn test_parse_byte_range() {
+    use ByteRange::*;
+
+    assert_eq!(
+        parse_byte_range("bytes=0-99", 1000),
+        Some(Satisfiable(0, 99))
+    );
+    assert_eq!(
+        parse_byte_range("bytes=500-", 1000),
+        Some(Satisfiable(500, 999))
+    );
+    assert_eq!(
+        parse_byte_range("bytes=-100", 1000),
+        Some(Satisfiable(900, 999))
+    );
+    assert_eq!(
+        parse_byte_range("bytes=-2000", 1000),
+        Some(Satisfiable(0, 999))
+    );
+    assert_eq!(
+        parse_byte_range("bytes=900-2000", 1000),
+        Some(Satisfiable(900, 999))
+    );
+    assert_eq!(parse_byte_range("bytes=1000-", 1000), Some(Unsatisfiable));
+    assert_eq!(parse_byte_range("bytes=-0", 1000), Some(Unsatisfiable));
+    assert_eq!(parse_byte_range("bytes=0-", 0), Some(Unsatisfiable));
+
+    // ignored: invalid, other units and multiple ranges
+    assert_eq!(parse_byte_range("bytes=100-50", 1000), None);
+    assert_eq!(parse_byte_range("bytes=a-b", 1000), None);
+    assert_eq!(parse_byte_range("items=0-1", 1000), None);
+    assert_eq!(parse_byte_range("bytes=0-1,5-6", 1000), None);
+}
+
+#[test]
+fn test_etag_list_matches() {
+    let etag = "\"1-2-3\"";
+    assert!(etag_list_matches("\"1-2-3\"", etag));
+    assert!(etag_list_matches("\"a\", W/\"1-2-3\"", etag));
+    assert!(etag_list_matches("*", etag));
+    assert!(!etag_list_matches("\"1-2-3-gzip\"", etag));
+}
+
+#[tokio::test]
+async fn test_find_precompressed_file() -> Result<(), Error> {
+    use std::time::{Duration, SystemTime};
+
+    let dir = std::env::temp_dir().join(format!("rest-precompressed-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let filename = dir.join("file.js");
+    let now = SystemTime::now();
+    let write = |path: &Path, mtime: SystemTime| -> Result<(), Error> {
+        std::fs::write(path, b"content")?;
+        std::fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(mtime)?;
+        Ok(())
+    };
+    write(&filename, now)?;
+    write(&dir.join("file.js.br"), now - Duration::from_secs(60))?;
+    write(&dir.join("file.js.gz"), now)?;
+
+    let source = std::fs::metadata(&filename)?;
+    let compression = [CompressionMethod::Brotli, CompressionMethod::Gzip];
+
+    // the outdated brotli variant is skipped, even though it is preferred
+    let (path, _, method) = find_precompressed_file(&filename, &source, &compression)
+        .await
+        .unwrap();
+    assert_eq!(path, dir.join("file.js.gz"));
+    assert_eq!(method, CompressionMethod::Gzip);
+
+    assert!(
+        find_precompressed_file(&filename, &source, &compression[..1])
+            .await
+            .is_none()
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use anyhow::{Error, format_err};
+use http_body_util::BodyExt;
+use hyper::Request;
+use hyper_util::rt::TokioIo;
+use openssl::asn1::Asn1Time;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{Ssl, SslConnector, SslMethod, SslVerifyMode};
+use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+use openssl::x509::{X509, X509Name};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_openssl::SslStream;
+
+use proxmox_router::{RpcEnvironment, RpcEnvironmentType, UserInformation};
+
+use proxmox_rest_server::connection::TlsAcceptorBuilder;
+use proxmox_rest_server::{ApiConfig, AuthError, RestServer};
+
+struct DummyUserInfo;
+
+impl UserInformation for DummyUserInfo {
+    fn is_superuser(&self, _userid: &str) -> bool {
+        false
+    }
+    fn is_group_member(&self, _userid: &str, _group: &str) -> bool {
+        false
+    }
+    fn lookup_privs(&self, _userid: &str, _path: &[&str]) -> u64 {
+        0
+    }
+}
+
+fn generate_key() -> Result<PKey<Private>, Error> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+}
+
+/// Create a certificate for `key`, self-signed if no issuer is given.
+fn generate_cert(
+    common_name: &str,
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+) -> Result<X509, Error> {
+    let mut name = X509Name::builder()?;
+    name.append_entry_by_text("CN", common_name)?;
+    let name = name.build();
+
+    let mut cert = X509::builder()?;
+    cert.set_version(2)?;
+    cert.set_pubkey(key)?;
+    cert.set_not_before(Asn1Time::days_from_now(0)?.as_ref())?;
+    cert.set_not_after(Asn1Time::days_from_now(1)?.as_ref())?;
+    cert.set_subject_name(&name)?;
+
+    match issuer {
+        Some((issuer_cert, issuer_key)) => {
+            cert.set_issuer_name(issuer_cert.subject_name())?;
+            let san = SubjectAlternativeName::new()
+                .dns(&format!("{common_name}.example.com"))
+                .build(&cert.x509v3_context(Some(issuer_cert), None))?;
+            cert.append_extension(san)?;
+            cert.sign(issuer_key, MessageDigest::sha256())?;
+        }
+        None => {
+            cert.set_issuer_name(&name)?;
+            cert.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+            cert.sign(key, MessageDigest::sha256())?;
+        }
+    }
+
+    Ok(cert.build())
+}
+
+struct TestCa {
+    cert: X509,
+    key: PKey<Private>,
+    path: PathBuf,
+}
+
+impl TestCa {
+    fn new(name: &str) -> Result<Self, Error> {
+        let key = generate_key()?;
+        let cert = generate_cert(name, &key, None)?;
+        let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.pem"));
+        std::fs::write(&path, cert.to_pem()?)?;
+        Ok(Self { cert, key, path })
+    }
+
+    fn issue(&self, common_name: &str) -> Result<(X509, PKey<Private>), Error> {
+        let key = generate_key()?;
+        let cert = generate_cert(common_name, &key, Some((&self.cert, &self.key)))?;
+        Ok((cert, key))
+    }
+}
+
+/// Outcome of a single request over a TLS connection.
+struct Outcome {
+    /// Subject and alternative names of the client certificate the server saw, if any.
+    peer_certificate: Option<(String, Vec<String>)>,
+    /// Response body, containing the authenticated user.
+    body: String,
+}
+
+/// Serve a single TLS connection with an auth handler which maps the client certificate to a
+/// user, and request the index page.
+async fn request_index(
+    ca: &TestCa,
+    required: bool,
+    client_cert: Option<(X509, PKey<Private>)>,
+) -> Result<Outcome, Error> {
+    let acceptor = TlsAcceptorBuilder::new()
+        .client_ca_path_pem(&ca.path)
+        .require_client_certificate(required)
+        .build()?;
+
+    let config = ApiConfig::new("/var/empty", RpcEnvironmentType::PUBLIC)
+        .auth_handler_env_func(|_headers, _method, rpcenv| {
+            Box::pin(async move {
+                let userinfo: Box<dyn UserInformation + Send + Sync> = Box::new(DummyUserInfo);
+                match rpcenv.peer_certificate() {
+                    Some(certificate) => Ok((certificate.subject().to_string(), userinfo)),
+                    None => Err(AuthError::NoData),
+                }
+            })
+        })
+        .index_handler_func(|rpcenv, _parts| {
+            let user = rpcenv
+                .get_auth_id()
+                .unwrap_or_else(|| "anonymous".to_string());
+            Box::pin(async move { hyper::Response::new(user.into_bytes().into()) })
+        });
+    let server = RestServer::new(config);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    let server_task = tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await?;
+        let mut stream = SslStream::new(Ssl::new(acceptor.context())?, tcp)?;
+        Pin::new(&mut stream).accept().await?;
+
+        let service = server.api_service(&stream)?;
+        let peer_certificate = service.peer_certificate().map(|certificate| {
+            (
+                certificate.subject().to_string(),
+                certificate.subject_alt_names().to_vec(),
+            )
+        });
+        service.serve(stream, None).await?;
+
+        Ok::<_, Error>(peer_certificate)
+    });
+
+    let mut connector = SslConnector::builder(SslMethod::tls())?;
+    connector.set_verify(SslVerifyMode::NONE);
+    if let Some((cert, key)) = &client_cert {
+        connector.set_certificate(cert)?;
+        connector.set_private_key(key)?;
+    }
+    let ssl = connector.build().configure()?.into_ssl("localhost")?;
+    let mut stream = SslStream::new(ssl, TcpStream::connect(addr).await?)?;
+
+    let client_result = async {
+        Pin::new(&mut stream).connect().await?;
+        let (mut sender, conn) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(conn);
+
+        let request = Request::get("/")
+            .header(hyper::header::HOST, "localhost")
+            .body(http_body_util::Empty::<hyper::body::Bytes>::new())?;
+        let response = sender.send_request(request).await?;
+        let body = response.into_body().collect().await?.to_bytes();
+        Ok::<_, Error>(String::from_utf8(body.to_vec())?)
+    }
+    .await;
+
+    // With TLS 1.3, the client considers the handshake complete before the server verified its
+    // certificate, so check the server side first.
+    let peer_certificate = server_task.await??;
+    let body = client_result?;
+
+    Ok(Outcome {
+        peer_certificate,
+        body,
+    })
+}
+
+#[tokio::test]
+async fn client_certificate_auth() -> Result<(), Error> {
+    let ca = TestCa::new("rest-server-test-ca")?;
+
+    let outcome = request_index(&ca, false, Some(ca.issue("client")?)).await?;
+    assert_eq!(
+        outcome.peer_certificate,
+        Some((
+            "CN=client".to_string(),
+            vec!["client.example.com".to_string()]
+        ))
+    );
+    assert_eq!(outcome.body, "CN=client");
+
+    // optional client certificates
+    let outcome = request_index(&ca, false, None).await?;
+    assert_eq!(outcome.peer_certificate, None);
+    assert_eq!(outcome.body, "anonymous");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_certificate_rejected() -> Result<(), Error> {
+    let ca = TestCa::new("rest-server-test-ca-required")?;
+    let other_ca = TestCa::new("rest-server-test-ca-other")?;
+
+    let outcome = request_index(&ca, true, Some(ca.issue("client")?)).await?;
+    assert_eq!(outcome.body, "CN=client");
+
+    request_index(&ca, true, None)
+        .await
+        .err()
+        .ok_or_else(|| format_err!("connection without required client certificate accepted"))?;
+
+    // certificates from other CAs are rejected even if they are optional
+    request_index(&ca, false, Some(other_ca.issue("client")?))
+        .await
+        .err()
+        .ok_or_else(|| format_err!("client certificate from unknown CA accepted"))?;
+
+    Ok(())
+}
+
+#[test]
+fn client_certificate_requires_ca() {
+    assert!(
+        TlsAcceptorBuilder::new()
+            .require_client_certificate(true)
+            .build()
+            .is_err()
+    );
+}
@@ -34,6 +34,19 @@ pub struct RRAConfig {
     pub cf: AggregationFn,
     /// Number of data points
     pub n: u64,
+    /// Percentile rank, only used with aggregation function 'percentile'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p: Option<f64>,
+}
+
+impl RRAConfig {
+    fn to_archive(&self) -> Result<Archive, Error> {
+        let rra = Archive::new(self.cf, self.r, self.n as usize);
+        match self.p {
+            Some(percentile) => rra.with_percentile(percentile),
+            None => Ok(rra),
+        }
+    }
 }
 
 #[api(
@@ -70,12 +83,17 @@ pub fn rrd_info(path: String) -> Result<(), Error> {
 
     for (i, rra) in rrd.rra_list.iter().enumerate() {
         // use RRAConfig property string format
+        let percentile = match rra.percentile {
+            Some(percentile) => format!(",p={percentile}"),
+            None => String::new(),
+        };
         println!(
-            "RRA[{}]: {:?},r={},n={}",
+            "RRA[{}]: {:?},r={},n={}{}",
             i,
             rra.cf,
             rra.resolution,
-            rra.data.len()
+            rra.data.len(),
+            percentile,
         );
     }
 
@@ -249,7 +267,7 @@ pub fn create_rrd(dst: DataSourceType, path: String, rra: Vec<String>) -> Result
         let rra: RRAConfig =
             serde_json::from_value(RRAConfig::API_SCHEMA.parse_property_string(item)?)?;
         println!("GOT {rra:?}");
-        rra_list.push(Archive::new(rra.cf, rra.r, rra.n as usize));
+        rra_list.push(rra.to_archive()?);
     }
 
     let path = PathBuf::from(path);
@@ -307,6 +325,8 @@ pub fn resize_rrd(path: String, rra_index: usize, slots: i64) -> Result<(), Erro
 
     let mut new_rra = Archive::new(rra.cf, rra.resolution, new_slots as usize);
     new_rra.last_count = rra.last_count;
+    new_rra.percentile = rra.percentile;
+    new_rra.reservoir = rra.reservoir.clone();
 
     new_rra.insert_data(start, reso, data)?;
 
@@ -317,6 +337,33 @@ pub fn resize_rrd(path: String, rra_index: usize, slots: i64) -> Result<(), Erro
     Ok(())
 }
 
+#[api(
+   input: {
+       properties: {
+           path: {
+               description: "The filename."
+           },
+           rra: {
+               schema: RRA_CONFIG_STRING_SCHEMA,
+           },
+       },
+   },
+)]
+/// Add a new RRA, backfilled from the data of the existing RRAs.
+pub fn add_rra(path: String, rra: String) -> Result<(), Error> {
+    let path = PathBuf::from(&path);
+
+    let mut rrd = Database::load(&path, false)?;
+
+    let rra: RRAConfig =
+        serde_json::from_value(RRAConfig::API_SCHEMA.parse_property_string(&rra)?)?;
+    rrd.add_archive(rra.to_archive()?)?;
+
+    rrd.save(&path, CreateOptions::new(), false)?;
+
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
     let uid = nix::unistd::Uid::current();
 
@@ -326,6 +373,12 @@ fn main() -> Result<(), Error> {
     };
 
     let cmd_def = CliCommandMap::new()
+        .insert(
+            "add-rra",
+            CliCommand::new(&API_METHOD_ADD_RRA)
+                .arg_param(&["path", "rra"])
+                .completion_cb("path", complete_file_name),
+        )
         .insert(
             "create",
             CliCommand::new(&API_METHOD_CREATE_RRD)
@@ -0,0 +1,241 @@
+//! RRD export/import tool - move proxmox RRD files between hosts and merge histories
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{Error, bail, format_err};
+use serde::{Deserialize, Serialize};
+
+use proxmox_router::RpcEnvironment;
+use proxmox_router::cli::{
+    CliCommand, CliCommandMap, CliEnvironment, complete_file_name, run_cli_command,
+};
+use proxmox_schema::api;
+use proxmox_sys::fs::CreateOptions;
+
+use proxmox_rrd::rrd::{Database, DatabaseExport};
+
+#[api()]
+#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// Export file format
+pub enum ExportFormat {
+    /// JSON
+    #[default]
+    Json,
+    /// CSV, missing values are empty fields
+    Csv,
+}
+
+fn open_output(output: Option<String>) -> Result<Box<dyn Write>, Error> {
+    match output {
+        Some(path) => {
+            let file = File::create(&path)
+                .map_err(|err| format_err!("unable to create '{path}' - {err}"))?;
+            Ok(Box::new(BufWriter::new(file)))
+        }
+        None => Ok(Box::new(std::io::stdout().lock())),
+    }
+}
+
+fn save_rrd(rrd: &Database, path: &str, force: bool) -> Result<(), Error> {
+    let path = PathBuf::from(path);
+    if !force && path.exists() {
+        bail!(
+            "file '{}' already exists (use --force to overwrite)",
+            path.display()
+        );
+    }
+    rrd.save(&path, CreateOptions::new(), false)
+}
+
+#[api(
+   input: {
+       properties: {
+          path: {
+              description: "The RRD filename (v1 or v2 format)."
+          },
+          format: {
+              type: ExportFormat,
+              optional: true,
+          },
+          output: {
+              description: "Output filename. Default is to write to stdout.",
+              optional: true,
+          },
+       },
+   },
+)]
+/// Export the RRD file contents
+pub fn export_rrd(
+    path: String,
+    format: Option<ExportFormat>,
+    output: Option<String>,
+) -> Result<(), Error> {
+    let rrd = Database::load(&PathBuf::from(path), false)?;
+
+    let mut writer = open_output(output)?;
+    match format.unwrap_or_default() {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &rrd.export())?;
+            writeln!(writer)?;
+            writer.flush()?;
+        }
+        ExportFormat::Csv => rrd.export_csv(writer)?,
+    }
+
+    Ok(())
+}
+
+#[api(
+   input: {
+       properties: {
+          input: {
+              description: "The exported data."
+          },
+          path: {
+              description: "The RRD filename to create."
+          },
+          format: {
+              type: ExportFormat,
+              optional: true,
+          },
+          force: {
+              description: "Overwrite existing files.",
+              optional: true,
+              default: false,
+          },
+       },
+   },
+)]
+/// Create a RRD file from exported data
+pub fn import_rrd(
+    input: String,
+    path: String,
+    format: Option<ExportFormat>,
+    force: bool,
+) -> Result<(), Error> {
+    let file = File::open(&input).map_err(|err| format_err!("unable to open '{input}' - {err}"))?;
+    let reader = BufReader::new(file);
+
+    let rrd = match format.unwrap_or_default() {
+        ExportFormat::Json => {
+            let export: DatabaseExport = serde_json::from_reader(reader)?;
+            Database::import(&export)?
+        }
+        ExportFormat::Csv => Database::import_csv(reader)?,
+    };
+
+    save_rrd(&rrd, &path, force)
+}
+
+#[api(
+   input: {
+       properties: {
+          path: {
+              description: "The RRD filename to merge into."
+          },
+          source: {
+              description: "RRD files to merge (v1 or v2 format).",
+              type: Array,
+              items: {
+                  description: "The RRD filename.",
+                  type: String,
+              },
+          },
+       },
+   },
+)]
+/// Merge RRD files
+///
+/// Empty data slots are filled from the source files, existing values
+/// are kept. Archives only present in a source file are added.
+pub fn merge_rrd(path: String, source: Vec<String>) -> Result<(), Error> {
+    let path = PathBuf::from(path);
+    let mut rrd = Database::load(&path, false)?;
+
+    for source in source {
+        let other = Database::load(&PathBuf::from(&source), false)?;
+        rrd.merge(&other)
+            .map_err(|err| format_err!("unable to merge '{source}' - {err}"))?;
+    }
+
+    rrd.save(&path, CreateOptions::new(), false)?;
+
+    Ok(())
+}
+
+#[api(
+   input: {
+       properties: {
+          path: {
+              description: "The RRD filename (v1 or v2 format)."
+          },
+          output: {
+              description: "Output filename. Default is to replace the file in place.",
+              optional: true,
+          },
+          force: {
+              description: "Overwrite existing output files.",
+              optional: true,
+              default: false,
+          },
+       },
+   },
+)]
+/// Convert a RRD file to the current (v2) format
+pub fn convert_rrd(path: String, output: Option<String>, force: bool) -> Result<(), Error> {
+    let rrd = Database::load(&PathBuf::from(&path), false)?;
+
+    match output {
+        Some(output) => save_rrd(&rrd, &output, force),
+        None => save_rrd(&rrd, &path, true),
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let uid = nix::unistd::Uid::current();
+
+    let username = match nix::unistd::User::from_uid(uid)? {
+        Some(user) => user.name,
+        None => bail!("unable to get user name"),
+    };
+
+    let cmd_def = CliCommandMap::new()
+        .insert(
+            "convert",
+            CliCommand::new(&API_METHOD_CONVERT_RRD)
+                .arg_param(&["path"])
+                .completion_cb("path", complete_file_name)
+                .completion_cb("output", complete_file_name),
+        )
+        .insert(
+            "export",
+            CliCommand::new(&API_METHOD_EXPORT_RRD)
+                .arg_param(&["path"])
+                .completion_cb("path", complete_file_name)
+                .completion_cb("output", complete_file_name),
+        )
+        .insert(
+            "import",
+            CliCommand::new(&API_METHOD_IMPORT_RRD)
+                .arg_param(&["input", "path"])
+                .completion_cb("input", complete_file_name)
+                .completion_cb("path", complete_file_name),
+        )
+        .insert(
+            "merge",
+            CliCommand::new(&API_METHOD_MERGE_RRD)
+                .arg_param(&["path", "source"])
+                .completion_cb("path", complete_file_name)
+                .completion_cb("source", complete_file_name),
+        );
+
+    let mut rpcenv = CliEnvironment::new();
+    rpcenv.set_auth_id(Some(format!("{username}@pam")));
+
+    run_cli_command(cmd_def, rpcenv, None);
+
+    Ok(())
+}
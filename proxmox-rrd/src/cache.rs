@@ -21,6 +21,8 @@ use journal::*;
 mod rrd_map;
 use rrd_map::*;
 
+mod series;
+
 /// RRD cache - keep RRD data in RAM, but write updates to disk
 ///
 /// This cache is designed to run as single instance (no concurrent
@@ -268,6 +270,69 @@ impl Cache {
             }
         }
     }
+
+    /// Extract aligned data for multiple series from cached RRDs
+    ///
+    /// `series`: List of `(base, name)` pairs, see [Self::extract_cached_data].
+    ///
+    /// `start`: Start time. If not specified, we simply extract 10 data points.
+    ///
+    /// `end`: End time. Default is to use the current time.
+    ///
+    /// `points`: Maximum number of data points per series. If
+    /// specified, data is consolidated to a coarser resolution as
+    /// required.
+    ///
+    /// All returned entries use the same start time (aligned to the
+    /// resolution), resolution and number of data points. Series
+    /// stored with a finer resolution are consolidated using `cf`.
+    /// `None` is returned for series without RRD file.
+    pub fn extract_cached_series(
+        &self,
+        series: &[(&str, &str)],
+        cf: AggregationFn,
+        resolution: u64,
+        start: Option<u64>,
+        end: Option<u64>,
+        points: Option<usize>,
+    ) -> Result<Vec<Option<Entry>>, Error> {
+        if resolution == 0 {
+            bail!("invalid resolution 0");
+        }
+
+        let end = end.unwrap_or_else(|| proxmox_time::epoch_f64() as u64);
+        let start = start.unwrap_or_else(|| end.saturating_sub(10 * resolution));
+
+        let mut entries = {
+            let map = self.rrd_map.read().unwrap();
+            series
+                .iter()
+                .map(|(base, name)| {
+                    map.extract_cached_data(base, name, cf, resolution, Some(start), Some(end))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+
+        if entries.iter().any(Option::is_none) {
+            let mut map = self.rrd_map.write().unwrap();
+            for ((base, name), entry) in series.iter().zip(entries.iter_mut()) {
+                if entry.is_none() && map.load(&format!("{base}/{name}"))? {
+                    *entry = map.extract_cached_data(
+                        base,
+                        name,
+                        cf,
+                        resolution,
+                        Some(start),
+                        Some(end),
+                    )?;
+                }
+            }
+        }
+
+        Ok(series::align_series(
+            entries, cf, resolution, start, end, points,
+        ))
+    }
 }
 
 fn apply_and_commit_journal_thread(
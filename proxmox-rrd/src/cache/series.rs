@@ -0,0 +1,149 @@
+use crate::Entry;
+use crate::rrd::AggregationFn;
+
+/// Consolidate the values of finer slots into a single value.
+///
+/// Percentiles cannot be computed exactly from the percentiles of
+/// smaller time spans, so we use the maximum, which is an upper bound.
+fn consolidate(cf: AggregationFn, values: &[f64]) -> f64 {
+    match cf {
+        AggregationFn::Average => values.iter().sum::<f64>() / (values.len() as f64),
+        AggregationFn::Maximum | AggregationFn::Percentile => {
+            values.iter().copied().fold(f64::NAN, f64::max)
+        }
+        AggregationFn::Minimum => values.iter().copied().fold(f64::NAN, f64::min),
+        AggregationFn::Last => values.last().copied().unwrap_or(f64::NAN),
+        AggregationFn::Sum => values.iter().sum(),
+    }
+}
+
+/// Align entries extracted from different RRDs to a common time grid.
+///
+/// The grid uses the coarsest resolution of all entries, or the
+/// requested `resolution` if there is no data at all. If `points` is
+/// set, the resolution is further reduced until the grid has no more
+/// than `points` slots. Values of finer slots are consolidated using
+/// `cf`.
+pub(crate) fn align_series(
+    entries: Vec<Option<Entry>>,
+    cf: AggregationFn,
+    resolution: u64,
+    start: u64,
+    end: u64,
+    points: Option<usize>,
+) -> Vec<Option<Entry>> {
+    let base_reso = entries
+        .iter()
+        .flatten()
+        .map(|entry| entry.resolution)
+        .max()
+        .unwrap_or(resolution)
+        .max(1);
+
+    let slot_count = |reso: u64| {
+        let grid_start = start - (start % reso);
+        if end < start {
+            (grid_start, 0)
+        } else {
+            (grid_start, ((end - grid_start) / reso + 1) as usize)
+        }
+    };
+
+    let mut reso = base_reso;
+    let (mut grid_start, mut count) = slot_count(reso);
+
+    if let Some(points) = points.filter(|points| *points > 0) {
+        let mut factor = count.div_ceil(points).max(1) as u64;
+        while count > points {
+            reso = base_reso * factor;
+            (grid_start, count) = slot_count(reso);
+            factor += 1;
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let entry = entry?;
+
+            let mut slots = vec![Vec::new(); count];
+            for (index, value) in entry.data.iter().enumerate() {
+                let Some(value) = value else {
+                    continue;
+                };
+                let time = entry.start + (index as u64) * entry.resolution;
+                let time = time - (time % entry.resolution);
+                if time < grid_start {
+                    continue;
+                }
+                if let Some(slot) = slots.get_mut(((time - grid_start) / reso) as usize) {
+                    slot.push(*value);
+                }
+            }
+
+            let data = slots
+                .iter()
+                .map(|values| (!values.is_empty()).then(|| consolidate(cf, values)))
+                .collect();
+
+            Some(Entry::new(grid_start, reso, data))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_series_test() {
+        let entries = vec![
+            Some(Entry::new(
+                90,
+                60,
+                vec![Some(1.0), Some(2.0), None, Some(4.0)],
+            )),
+            None,
+            Some(Entry::new(90, 120, vec![Some(10.0), Some(20.0)])),
+        ];
+
+        let aligned = align_series(entries.clone(), AggregationFn::Average, 60, 90, 270, None);
+        let aligned: Vec<_> = aligned
+            .into_iter()
+            .map(|entry| entry.map(|entry| (entry.start, entry.resolution, entry.data)))
+            .collect();
+        assert_eq!(
+            aligned,
+            [
+                Some((0, 120, vec![Some(1.0), Some(2.0), Some(4.0)])),
+                None,
+                Some((0, 120, vec![Some(10.0), Some(20.0), None])),
+            ]
+        );
+
+        let aligned = align_series(entries, AggregationFn::Maximum, 60, 90, 270, Some(2));
+        let aligned: Vec<_> = aligned
+            .into_iter()
+            .map(|entry| entry.map(|entry| (entry.start, entry.resolution, entry.data)))
+            .collect();
+        assert_eq!(
+            aligned,
+            [
+                Some((0, 240, vec![Some(2.0), Some(4.0)])),
+                None,
+                Some((0, 240, vec![Some(20.0), None])),
+            ]
+        );
+
+        // sums are kept when consolidating
+        let entries = vec![Some(Entry::new(0, 60, vec![Some(1.0); 10]))];
+        let aligned = align_series(entries, AggregationFn::Sum, 60, 0, 540, Some(3));
+        let entry = aligned[0].as_ref().unwrap();
+        assert_eq!(entry.resolution, 240);
+        assert_eq!(entry.data, [Some(4.0), Some(4.0), Some(2.0)]);
+
+        // series without RRD file stay empty
+        let aligned = align_series(vec![None], AggregationFn::Average, 60, 0, 600, None);
+        assert!(aligned[0].is_none());
+    }
+}
@@ -5,6 +5,7 @@
 //! * One file stores a single data source
 //! * Stores data for different time resolution
 //! * Simple cache implementation with journal support
+//! * Export/import (JSON, CSV) and merging of databases
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
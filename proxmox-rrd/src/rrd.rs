@@ -10,6 +10,14 @@
 //! * Well defined data format [CBOR](https://datatracker.ietf.org/doc/html/rfc8949)
 //! * Platform independent (big endian f64, hopefully a standard format?)
 //! * Arbitrary number of RRAs (dynamically changeable)
+//!
+//! ## Version 2.1
+//!
+//! Files containing archives using the [AggregationFn::Sum] or
+//! [AggregationFn::Percentile] aggregation functions cannot be decoded
+//! by readers only supporting version 2.0, so they are marked with a
+//! separate magic number. All other files are still written as
+//! version 2.0.
 
 use std::io::{Read, Write};
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
@@ -21,10 +29,23 @@ use serde::{Deserialize, Serialize};
 use proxmox_schema::api;
 use proxmox_sys::fs::{CreateOptions, make_tmp_file};
 
+mod export;
+pub use export::{ArchiveExport, DatabaseExport};
+
 /// Proxmox RRD v2 file magic number
 // openssl::sha::sha256(b"Proxmox Round Robin Database file v2.0")[0..8];
 pub const PROXMOX_RRD_MAGIC_2_0: [u8; 8] = [224, 200, 228, 27, 239, 112, 122, 159];
 
+/// Proxmox RRD v2.1 file magic number
+// openssl::sha::sha256(b"Proxmox Round Robin Database file v2.1")[0..8];
+pub const PROXMOX_RRD_MAGIC_2_1: [u8; 8] = [136, 205, 190, 198, 125, 248, 38, 242];
+
+/// Default percentile rank computed by archives using [AggregationFn::Percentile]
+pub const DEFAULT_PERCENTILE: f64 = 95.0;
+
+/// Maximum number of values sampled per slot to compute percentiles
+pub const PERCENTILE_RESERVOIR_SIZE: usize = 256;
+
 #[api()]
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
@@ -52,6 +73,18 @@ pub enum AggregationFn {
     Minimum,
     /// Use the last value
     Last,
+    /// Sum of all values
+    ///
+    /// For derive and counter data sources, the computed rate is
+    /// multiplied by the time since the last update, so that a slot
+    /// stores the total increase within its time span.
+    Sum,
+    /// Percentile of the values (see [Archive::with_percentile])
+    ///
+    /// Computed from a bounded sample of the values inside the
+    /// current slot, so the result is approximate for slots with
+    /// more than [PERCENTILE_RESERVOIR_SIZE] updates.
+    Percentile,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -170,17 +203,49 @@ pub struct Archive {
     pub last_count: u64,
     /// The actual data entries.
     pub data: Vec<f64>,
+    /// Percentile rank (0-100), only used with [AggregationFn::Percentile].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentile: Option<f64>,
+    /// Sampled values of the current slot, only used with
+    /// [AggregationFn::Percentile].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reservoir: Vec<f64>,
 }
 
 impl Archive {
     /// Creates a new instance
     pub fn new(cf: AggregationFn, resolution: u64, points: usize) -> Self {
+        let percentile = (cf == AggregationFn::Percentile).then_some(DEFAULT_PERCENTILE);
         Self {
             cf,
             resolution,
             last_count: 0,
             data: vec![f64::NAN; points],
+            percentile,
+            reservoir: Vec::new(),
+        }
+    }
+
+    /// Set the percentile rank (0-100) computed by this archive.
+    ///
+    /// Only valid for archives using [AggregationFn::Percentile].
+    pub fn with_percentile(mut self, percentile: f64) -> Result<Self, Error> {
+        if self.cf != AggregationFn::Percentile {
+            bail!("percentile rank requires aggregation function 'percentile'");
         }
+        if !(0.0..=100.0).contains(&percentile) {
+            bail!("percentile rank {percentile} out of range (0 - 100)");
+        }
+        self.percentile = Some(percentile);
+        Ok(self)
+    }
+
+    /// Check if both archives use the same consolidation function,
+    /// resolution and percentile rank.
+    fn same_kind(&self, other: &Archive) -> bool {
+        self.cf == other.cf
+            && self.resolution == other.resolution
+            && self.percentile == other.percentile
     }
 
     /// Data slot end time
@@ -273,6 +338,10 @@ impl Archive {
         if self.last_count == 0 {
             self.data[index] = value;
             self.last_count = 1;
+            if self.cf == AggregationFn::Percentile {
+                self.reservoir.clear();
+                self.reservoir.push(value);
+            }
         } else {
             let new_value = match self.cf {
                 AggregationFn::Maximum => {
@@ -294,12 +363,69 @@ impl Archive {
                     (last_value * (self.last_count as f64)) / (new_count as f64)
                         + value / (new_count as f64)
                 }
+                AggregationFn::Sum => last_value + value,
+                AggregationFn::Percentile => {
+                    self.sample_value(epoch, new_count, value);
+                    percentile(&self.reservoir, self.percentile_rank())
+                }
             };
             self.data[index] = new_value;
             self.last_count = new_count;
         }
     }
 
+    fn percentile_rank(&self) -> f64 {
+        self.percentile.unwrap_or(DEFAULT_PERCENTILE)
+    }
+
+    // Reservoir sampling (algorithm R), keeping a uniform sample of the slot values
+    fn sample_value(&mut self, time: u64, count: u64, value: f64) {
+        if self.reservoir.len() < PERCENTILE_RESERVOIR_SIZE {
+            self.reservoir.push(value);
+            return;
+        }
+        // Deterministic pseudo random index, derived via splitmix64
+        let mut z = (time ^ count.rotate_left(32)).wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        let index = (z % count) as usize;
+        if index < self.reservoir.len() {
+            self.reservoir[index] = value;
+        }
+    }
+
+    /// Aggregate the given values of finer slots into a single value,
+    /// using the consolidation function of this archive.
+    fn aggregate(&self, values: &[f64]) -> f64 {
+        match self.cf {
+            AggregationFn::Average => values.iter().sum::<f64>() / (values.len() as f64),
+            AggregationFn::Maximum => values.iter().copied().fold(f64::NAN, f64::max),
+            AggregationFn::Minimum => values.iter().copied().fold(f64::NAN, f64::min),
+            AggregationFn::Last => values.last().copied().unwrap_or(f64::NAN),
+            AggregationFn::Sum => values.iter().sum(),
+            AggregationFn::Percentile => percentile(values, self.percentile_rank()),
+        }
+    }
+
+    /// Check if this archive can be computed from the data of the
+    /// `other` archive.
+    fn can_backfill_from(&self, other: &Archive) -> bool {
+        if other.resolution >= self.resolution || !self.resolution.is_multiple_of(other.resolution)
+        {
+            return false;
+        }
+        match self.cf {
+            // Approximate percentiles from the finer values
+            AggregationFn::Percentile => {
+                other.cf == AggregationFn::Average
+                    || (other.cf == AggregationFn::Percentile
+                        && other.percentile_rank() == self.percentile_rank())
+            }
+            cf => other.cf == cf,
+        }
+    }
+
     /// Extract data
     ///
     /// Extract data from `start` to `end`. The RRA itself does not
@@ -342,6 +468,22 @@ impl Archive {
     }
 }
 
+/// Percentile with linear interpolation between the closest ranks.
+fn percentile(values: &[f64], rank: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let position = (rank / 100.0).clamp(0.0, 1.0) * ((sorted.len() - 1) as f64);
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - (lower as f64);
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
 #[derive(Serialize, Deserialize)]
 /// Round Robin Database
 pub struct Database {
@@ -371,8 +513,10 @@ impl Database {
                 v1.to_rrd_v2()
                     .map_err(|err| format_err!("unable to convert from old V1 format - {err}"))?
             }
-            magic if magic == PROXMOX_RRD_MAGIC_2_0 => serde_cbor::from_slice(&raw[8..])
-                .map_err(|err| format_err!("unable to decode RRD file - {err}"))?,
+            magic if magic == PROXMOX_RRD_MAGIC_2_0 || magic == PROXMOX_RRD_MAGIC_2_1 => {
+                serde_cbor::from_slice(&raw[8..])
+                    .map_err(|err| format_err!("unable to decode RRD file - {err}"))?
+            }
             _ => bail!("not an rrd file - unknown magic number"),
         };
 
@@ -383,6 +527,27 @@ impl Database {
         Ok(rrd)
     }
 
+    fn to_raw(&self) -> Result<Vec<u8>, Error> {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend(self.magic());
+        serde_cbor::to_writer(&mut data, self)?;
+        Ok(data)
+    }
+
+    /// Magic number of the oldest format version able to store this database.
+    fn magic(&self) -> [u8; 8] {
+        let needs_v2_1 = self
+            .rra_list
+            .iter()
+            .any(|rra| matches!(rra.cf, AggregationFn::Sum | AggregationFn::Percentile));
+
+        if needs_v2_1 {
+            PROXMOX_RRD_MAGIC_2_1
+        } else {
+            PROXMOX_RRD_MAGIC_2_0
+        }
+    }
+
     /// Load data from a file
     ///
     /// Setting `avoid_page_cache` uses
@@ -425,9 +590,7 @@ impl Database {
         let mut file = unsafe { std::fs::File::from_raw_fd(fd.into_raw_fd()) };
 
         let mut try_block = || -> Result<(), Error> {
-            let mut data: Vec<u8> = Vec::new();
-            data.extend(PROXMOX_RRD_MAGIC_2_0);
-            serde_cbor::to_writer(&mut data, self)?;
+            let data = self.to_raw()?;
             file.write_all(&data)?;
 
             if avoid_page_cache {
@@ -478,10 +641,176 @@ impl Database {
         let last_update = self.source.last_update;
         self.source.last_update = time;
 
+        // total increase since the last update, for sum archives
+        let increase = match self.source.dst {
+            DataSourceType::Gauge => value,
+            DataSourceType::Derive | DataSourceType::Counter => value * (time - last_update),
+        };
+
         for rra in self.rra_list.iter_mut() {
             rra.delete_old_slots(time, last_update);
-            rra.compute_new_value(time, last_update, value);
+            if rra.cf == AggregationFn::Sum {
+                rra.compute_new_value(time, last_update, increase);
+            } else {
+                rra.compute_new_value(time, last_update, value);
+            }
+        }
+    }
+
+    /// Add a new archive and backfill it from the existing archives
+    ///
+    /// Slots of the new archive are computed from the data of finer
+    /// archives with a compatible consolidation function, whose
+    /// resolution evenly divides the resolution of the new archive.
+    /// The finest archive covering a slot is used, so history is kept
+    /// as far as available instead of restarting from empty. Archives
+    /// with [AggregationFn::Percentile] can also be backfilled from
+    /// averages, which only approximates the percentile.
+    ///
+    /// Note: This does not call [Self::save].
+    pub fn add_archive(&mut self, mut archive: Archive) -> Result<(), Error> {
+        if archive.resolution == 0 || archive.data.is_empty() {
+            bail!("invalid archive, resolution and number of slots must not be zero");
+        }
+        if self.rra_list.iter().any(|rra| rra.same_kind(&archive)) {
+            bail!(
+                "archive ({:?}:{}) already exists",
+                archive.cf,
+                archive.resolution
+            );
+        }
+
+        let last_update = self.source.last_update;
+        if last_update > 0.0 {
+            let mut sources: Vec<&Archive> = self
+                .rra_list
+                .iter()
+                .filter(|rra| archive.can_backfill_from(rra))
+                .collect();
+            // prefer an exactly matching consolidation function, then finer resolution
+            sources.sort_by_key(|rra| (rra.cf != archive.cf, rra.resolution));
+
+            let reso = archive.resolution;
+            let rra_end = archive.slot_end_time(last_update as u64);
+            let rra_start = rra_end.saturating_sub(reso * (archive.data.len() as u64));
+            let last_slot_start = archive.slot_start_time(last_update as u64);
+
+            let mut t = archive.slot_start_time(rra_start);
+            while t < rra_end {
+                for source in sources.iter() {
+                    let source_end = source.slot_end_time(last_update as u64);
+                    let source_start =
+                        source_end.saturating_sub(source.resolution * (source.data.len() as u64));
+                    if t < source_start {
+                        continue;
+                    }
+
+                    let values: Vec<f64> = source
+                        .extract_data(t, t + reso - 1, last_update)
+                        .data
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                    if values.is_empty() {
+                        continue;
+                    }
+
+                    let index = archive.slot(t);
+                    archive.data[index] = archive.aggregate(&values);
+                    if t == last_slot_start {
+                        // allow further updates within the current slot to aggregate
+                        archive.last_count = values.len() as u64;
+                        if archive.cf == AggregationFn::Percentile {
+                            archive.reservoir = values;
+                            archive.reservoir.truncate(PERCENTILE_RESERVOIR_SIZE);
+                        }
+                    }
+                    break;
+                }
+                t += reso;
+            }
         }
+
+        self.rra_list.push(archive);
+
+        Ok(())
+    }
+
+    /// Merge the data of another database into this one
+    ///
+    /// Archives are matched by consolidation function, resolution and
+    /// percentile rank. Empty slots are filled from the matching
+    /// archive of `other`, so existing values always take precedence.
+    /// Archives only present in `other` are added. If `other` has a
+    /// newer last update, its data source state is taken over.
+    ///
+    /// Note: This does not call [Self::save].
+    pub fn merge(&mut self, other: &Database) -> Result<(), Error> {
+        if self.source.dst != other.source.dst {
+            bail!(
+                "unable to merge databases with different data source types ({:?} != {:?})",
+                self.source.dst,
+                other.source.dst
+            );
+        }
+
+        let own_last_update = self.source.last_update;
+        let other_last_update = other.source.last_update;
+        let last_update = own_last_update.max(other_last_update);
+
+        for other_rra in other.rra_list.iter() {
+            if !self.rra_list.iter().any(|rra| rra.same_kind(other_rra)) {
+                let mut rra =
+                    Archive::new(other_rra.cf, other_rra.resolution, other_rra.data.len());
+                rra.percentile = other_rra.percentile;
+                self.rra_list.push(rra);
+            }
+        }
+
+        // rewrite all archives, so that slots outdated by a newer last
+        // update are cleared as well
+        for rra in self.rra_list.iter_mut() {
+            let other_rra = other.rra_list.iter().find(|other| rra.same_kind(other));
+
+            let reso = rra.resolution;
+            let rra_end = rra.slot_end_time(last_update as u64);
+            let rra_start = rra_end.saturating_sub(reso * (rra.data.len() as u64));
+
+            let own = rra.extract_data(rra_start, rra_end, own_last_update);
+            let theirs = match other_rra {
+                Some(other_rra) => other_rra.extract_data(rra_start, rra_end, other_last_update),
+                None => Entry::new(rra_start, reso, Vec::new()),
+            };
+
+            // keep the aggregation state of whoever provides the current slot
+            let last_slot = ((rra.slot_start_time(last_update as u64) - own.start) / reso) as usize;
+            if own.get(last_slot).is_none() {
+                if let (Some(other_rra), Some(_)) = (other_rra, theirs.get(last_slot)) {
+                    rra.last_count = other_rra.last_count;
+                    rra.reservoir = other_rra.reservoir.clone();
+                } else {
+                    rra.last_count = 0;
+                    rra.reservoir.clear();
+                }
+            }
+
+            let data = own
+                .data
+                .iter()
+                .enumerate()
+                .map(|(index, value)| value.or(theirs.get(index)))
+                .collect();
+
+            rra.data.fill(f64::NAN);
+            rra.insert_data(rra_start, reso, data)?;
+        }
+
+        if other_last_update > own_last_update {
+            self.source.last_update = other_last_update;
+            self.source.last_value = other.source.last_value;
+        }
+
+        Ok(())
     }
 
     /// Extract data from the archive
@@ -692,4 +1021,250 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn basic_rra_sum_gauge_test() -> Result<(), Error> {
+        let rra = Archive::new(AggregationFn::Sum, 60, 5);
+        let mut rrd = Database::new(DataSourceType::Gauge, vec![rra]);
+
+        for i in 2..10 {
+            rrd.update((i as f64) * 30.0, i as f64);
+        }
+
+        let Entry {
+            start,
+            resolution,
+            data,
+        } = rrd.extract_data(AggregationFn::Sum, 60, Some(0), Some(5 * 60))?;
+        assert_eq!(start, 0);
+        assert_eq!(resolution, 60);
+        assert_eq!(data, [None, Some(5.0), Some(9.0), Some(13.0), Some(17.0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn basic_rra_sum_counter_test() -> Result<(), Error> {
+        let rra = Archive::new(AggregationFn::Sum, 60, 5);
+        let mut rrd = Database::new(DataSourceType::Counter, vec![rra]);
+
+        // counter increases by 100 every 30 seconds
+        for i in 2..10 {
+            rrd.update((i as f64) * 30.0, (i * 100) as f64);
+        }
+
+        let Entry {
+            start,
+            resolution,
+            data,
+        } = rrd.extract_data(AggregationFn::Sum, 60, Some(60), Some(5 * 60))?;
+        assert_eq!(start, 60);
+        assert_eq!(resolution, 60);
+        assert_eq!(
+            data,
+            [Some(100.0), Some(200.0), Some(200.0), Some(200.0), None]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn basic_rra_percentile_gauge_test() -> Result<(), Error> {
+        let rra = Archive::new(AggregationFn::Percentile, 1000, 5).with_percentile(90.0)?;
+        let mut rrd = Database::new(DataSourceType::Gauge, vec![rra]);
+
+        // 0.0 to 100.0 within the first slot, in reverse order
+        for i in 0..=100 {
+            rrd.update((i + 1) as f64, (100 - i) as f64);
+        }
+
+        let Entry { data, .. } =
+            rrd.extract_data(AggregationFn::Percentile, 1000, Some(0), Some(1000))?;
+        assert_eq!(data[0], Some(90.0));
+
+        // more values than fit into the reservoir
+        let rra = Archive::new(AggregationFn::Percentile, 10_000, 5).with_percentile(50.0)?;
+        let mut rrd = Database::new(DataSourceType::Gauge, vec![rra]);
+        for i in 1..2000 {
+            rrd.update(i as f64, (i % 100) as f64);
+        }
+        assert_eq!(rrd.rra_list[0].reservoir.len(), PERCENTILE_RESERVOIR_SIZE);
+        let median = rrd.rra_list[0].data[0];
+        assert!(
+            (30.0..70.0).contains(&median),
+            "median {median} out of range"
+        );
+
+        assert!(
+            Archive::new(AggregationFn::Average, 60, 5)
+                .with_percentile(95.0)
+                .is_err()
+        );
+        assert!(
+            Archive::new(AggregationFn::Percentile, 60, 5)
+                .with_percentile(101.0)
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_version_test() -> Result<(), Error> {
+        let mut rrd = Database::new(
+            DataSourceType::Gauge,
+            vec![Archive::new(AggregationFn::Average, 60, 5)],
+        );
+        rrd.update(60.0, 1.0);
+        let raw = rrd.to_raw()?;
+        assert_eq!(raw[0..8], PROXMOX_RRD_MAGIC_2_0);
+        assert_eq!(Database::from_raw(&raw)?.rra_list.len(), 1);
+
+        // archives not supported by version 2.0 readers
+        rrd.add_archive(Archive::new(AggregationFn::Percentile, 60, 5))?;
+        let raw = rrd.to_raw()?;
+        assert_eq!(raw[0..8], PROXMOX_RRD_MAGIC_2_1);
+        let rrd = Database::from_raw(&raw)?;
+        assert_eq!(rrd.rra_list[1].percentile, Some(DEFAULT_PERCENTILE));
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_archive_backfill_test() -> Result<(), Error> {
+        let mut rrd = Database::new(
+            DataSourceType::Gauge,
+            vec![
+                Archive::new(AggregationFn::Average, 60, 10),
+                Archive::new(AggregationFn::Maximum, 60, 10),
+                Archive::new(AggregationFn::Average, 300, 10),
+            ],
+        );
+
+        for i in 1..50 {
+            rrd.update((i * 60) as f64, i as f64);
+        }
+
+        rrd.add_archive(Archive::new(AggregationFn::Maximum, 120, 10))?;
+        rrd.add_archive(Archive::new(AggregationFn::Average, 600, 5))?;
+        assert!(
+            rrd.add_archive(Archive::new(AggregationFn::Average, 600, 5))
+                .is_err()
+        );
+
+        // only the last 10 minutes are available from the maximum archive
+        let Entry { start, data, .. } =
+            rrd.extract_data(AggregationFn::Maximum, 120, Some(30 * 60), Some(48 * 60))?;
+        assert_eq!(start, 30 * 60);
+        assert_eq!(
+            data,
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(41.0),
+                Some(43.0),
+                Some(45.0),
+                Some(47.0),
+                Some(49.0),
+            ]
+        );
+
+        // recent slots use the finest archive, older ones the coarser archive (the first
+        // slot only has partial data)
+        let Entry { start, data, .. } =
+            rrd.extract_data(AggregationFn::Average, 600, Some(0), Some(40 * 60))?;
+        assert_eq!(start, 0);
+        assert_eq!(
+            data,
+            [Some(4.75), Some(14.5), Some(24.5), Some(34.5), Some(44.5)]
+        );
+
+        // further updates continue aggregating the current slot
+        rrd.update((50 * 60) as f64, 50.0);
+        let Entry { data, .. } =
+            rrd.extract_data(AggregationFn::Maximum, 120, Some(48 * 60), Some(50 * 60))?;
+        assert_eq!(data, [Some(49.0), Some(50.0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_test() -> Result<(), Error> {
+        // history of the old node, up to the reinstall
+        let mut old = Database::new(
+            DataSourceType::Gauge,
+            vec![
+                Archive::new(AggregationFn::Average, 60, 10),
+                Archive::new(AggregationFn::Maximum, 60, 10),
+            ],
+        );
+        for i in 1..6 {
+            old.update((i * 60) as f64, i as f64);
+        }
+
+        // new node, starting a few minutes later
+        let mut rrd = Database::new(
+            DataSourceType::Gauge,
+            vec![
+                Archive::new(AggregationFn::Average, 60, 10),
+                Archive::new(AggregationFn::Minimum, 60, 10),
+            ],
+        );
+        for i in 7..10 {
+            rrd.update((i * 60) as f64, (i * 10) as f64);
+        }
+        // overlapping slot, the existing value takes precedence
+        old.update((7 * 60) as f64, 1000.0);
+
+        rrd.merge(&old)?;
+        assert_eq!(rrd.rra_list.len(), 3);
+        assert_eq!(rrd.last_update(), (9 * 60) as f64);
+
+        let Entry { data, .. } =
+            rrd.extract_data(AggregationFn::Average, 60, Some(0), Some(9 * 60))?;
+        assert_eq!(
+            data,
+            [
+                None,
+                Some(1.0),
+                Some(2.0),
+                Some(3.0),
+                Some(4.0),
+                Some(5.0),
+                None,
+                Some(70.0),
+                Some(80.0),
+                Some(90.0),
+            ]
+        );
+
+        let Entry { data, .. } =
+            rrd.extract_data(AggregationFn::Maximum, 60, Some(4 * 60), Some(9 * 60))?;
+        assert_eq!(data, [Some(4.0), Some(5.0), None, Some(1000.0), None, None]);
+
+        // merging into the older database takes over the newer source state
+        let Entry { data, .. } =
+            old.extract_data(AggregationFn::Average, 60, Some(0), Some(9 * 60))?;
+        assert_eq!(data[7], Some(1000.0));
+        old.merge(&rrd)?;
+        assert_eq!(old.last_update(), (9 * 60) as f64);
+        assert_eq!(old.source.last_value, 90.0);
+        let Entry { data, .. } =
+            old.extract_data(AggregationFn::Average, 60, Some(7 * 60), Some(9 * 60))?;
+        assert_eq!(data, [Some(1000.0), Some(80.0), Some(90.0)]);
+
+        // further updates aggregate into the current slot of the merged data
+        old.update((9 * 60 + 30) as f64, 100.0);
+        let Entry { data, .. } =
+            old.extract_data(AggregationFn::Average, 60, Some(9 * 60), Some(9 * 60))?;
+        assert_eq!(data, [Some(95.0)]);
+
+        let mut other = Database::new(DataSourceType::Derive, Vec::new());
+        assert!(other.merge(&rrd).is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,434 @@
+//! Export and import of [Database] contents
+//!
+//! The CBOR storage format is a direct image of the in-memory ring
+//! buffers. For exchanging data with other tools or moving databases
+//! between hosts, we use a portable representation instead, where
+//! archive data is ordered by time and missing values are `None`.
+//! This can be serialized as JSON, or written as CSV with
+//! [Database::export_csv].
+
+use std::io::{BufRead, Write};
+
+use anyhow::{Error, bail, format_err};
+use serde::{Deserialize, Serialize};
+
+use super::{AggregationFn, Archive, DataSource, DataSourceType, Database};
+
+/// Header line of the CSV data rows
+const CSV_HEADER: &str = "rra,cf,resolution,time,value";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Portable representation of a [Database]
+pub struct DatabaseExport {
+    /// Data source type
+    pub dst: DataSourceType,
+    /// Last update time (epoch)
+    pub last_update: f64,
+    /// Last value, used to compute differential value for
+    /// derive/counters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_value: Option<f64>,
+    /// List of round robin archives
+    pub archives: Vec<ArchiveExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Portable representation of an [Archive]
+pub struct ArchiveExport {
+    /// Consolidation function.
+    pub cf: AggregationFn,
+    /// Number of seconds spanned by a single data entry.
+    pub resolution: u64,
+    /// Percentile rank, only used with [AggregationFn::Percentile].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentile: Option<f64>,
+    /// Count values computed inside the current slot.
+    #[serde(default)]
+    pub last_count: u64,
+    /// Sampled values of the current slot, only used with
+    /// [AggregationFn::Percentile].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reservoir: Vec<f64>,
+    /// Start time of the first data slot.
+    pub start: u64,
+    /// Data slots in time order, the length defines the number of
+    /// slots of the archive.
+    pub data: Vec<Option<f64>>,
+}
+
+impl ArchiveExport {
+    fn from_archive(rra: &Archive, last_update: f64) -> Self {
+        let rra_end = rra.slot_end_time(last_update as u64);
+        let rra_start = rra_end.saturating_sub(rra.resolution * (rra.data.len() as u64));
+        let entry = rra.extract_data(rra_start, rra_end, last_update);
+
+        Self {
+            cf: rra.cf,
+            resolution: rra.resolution,
+            percentile: rra.percentile,
+            last_count: rra.last_count,
+            reservoir: rra.reservoir.clone(),
+            start: entry.start,
+            data: entry.data,
+        }
+    }
+
+    fn to_archive(&self, last_update: f64) -> Result<Archive, Error> {
+        if self.resolution == 0 || self.data.is_empty() {
+            bail!("invalid archive, resolution and number of slots must not be zero");
+        }
+        if !self.start.is_multiple_of(self.resolution) {
+            bail!(
+                "archive start time {} is not aligned to resolution {}",
+                self.start,
+                self.resolution
+            );
+        }
+
+        let mut rra = Archive::new(self.cf, self.resolution, self.data.len());
+        if let Some(percentile) = self.percentile {
+            rra = rra.with_percentile(percentile)?;
+        }
+
+        let rra_end = rra.slot_end_time(last_update as u64);
+        let rra_start = rra_end.saturating_sub(rra.resolution * (rra.data.len() as u64));
+        let mut time = self.start;
+        for value in self.data.iter() {
+            if value.is_some() && (time < rra_start || time >= rra_end) {
+                bail!(
+                    "archive ({:?}:{}) has data outside of its time range ({time})",
+                    self.cf,
+                    self.resolution
+                );
+            }
+            time += self.resolution;
+        }
+
+        rra.insert_data(self.start, self.resolution, self.data.clone())?;
+        rra.last_count = self.last_count;
+        rra.reservoir = self.reservoir.clone();
+
+        Ok(rra)
+    }
+}
+
+impl Database {
+    /// Export the database into its portable representation
+    pub fn export(&self) -> DatabaseExport {
+        let last_update = self.source.last_update;
+        let last_value = self.source.last_value;
+
+        DatabaseExport {
+            dst: self.source.dst,
+            last_update,
+            last_value: (!last_value.is_nan()).then_some(last_value),
+            archives: self
+                .rra_list
+                .iter()
+                .map(|rra| ArchiveExport::from_archive(rra, last_update))
+                .collect(),
+        }
+    }
+
+    /// Create a database from its portable representation
+    pub fn import(export: &DatabaseExport) -> Result<Self, Error> {
+        if export.last_update < 0.0 {
+            bail!("got negative last_update time");
+        }
+
+        let mut rra_list = Vec::new();
+        for archive in export.archives.iter() {
+            let rra = archive.to_archive(export.last_update)?;
+            if rra_list.iter().any(|other| rra.same_kind(other)) {
+                bail!("duplicate archive ({:?}:{})", rra.cf, rra.resolution);
+            }
+            rra_list.push(rra);
+        }
+
+        let source = DataSource {
+            dst: export.dst,
+            last_update: export.last_update,
+            last_value: export.last_value.unwrap_or(f64::NAN),
+        };
+
+        Ok(Database { source, rra_list })
+    }
+
+    /// Write the database contents as CSV
+    ///
+    /// The data source and archive definitions are written as comment
+    /// lines (starting with `#`), followed by one row per data slot.
+    /// Missing values are written as empty field. The percentile
+    /// sample of the current slot is not included.
+    pub fn export_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let export = self.export();
+
+        writeln!(writer, "# dst={}", serde_plain::to_string(&export.dst)?)?;
+        writeln!(writer, "# last-update={}", export.last_update)?;
+        if let Some(last_value) = export.last_value {
+            writeln!(writer, "# last-value={last_value}")?;
+        }
+        for archive in export.archives.iter() {
+            write!(
+                writer,
+                "# rra={},r={},n={},count={}",
+                serde_plain::to_string(&archive.cf)?,
+                archive.resolution,
+                archive.data.len(),
+                archive.last_count,
+            )?;
+            if let Some(percentile) = archive.percentile {
+                write!(writer, ",p={percentile}")?;
+            }
+            writeln!(writer)?;
+        }
+
+        writeln!(writer, "{CSV_HEADER}")?;
+        for (index, archive) in export.archives.iter().enumerate() {
+            let cf = serde_plain::to_string(&archive.cf)?;
+            let mut time = archive.start;
+            for value in archive.data.iter() {
+                match value {
+                    Some(value) => {
+                        writeln!(writer, "{index},{cf},{},{time},{value}", archive.resolution)?
+                    }
+                    None => writeln!(writer, "{index},{cf},{},{time},", archive.resolution)?,
+                }
+                time += archive.resolution;
+            }
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Read database contents written by [Self::export_csv]
+    ///
+    /// Data rows may be omitted, missing slots are left empty.
+    pub fn import_csv<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut dst = None;
+        let mut last_update = None;
+        let mut last_value = None;
+        let mut archives: Vec<ArchiveExport> = Vec::new();
+        let mut rows = Vec::new();
+
+        for (line_nr, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            let line_nr = line_nr + 1;
+
+            if line.is_empty() || line == CSV_HEADER {
+                continue;
+            }
+
+            if let Some(comment) = line.strip_prefix('#') {
+                let (key, value) = match comment.trim().split_once('=') {
+                    Some(property) => property,
+                    None => continue,
+                };
+                let result = match key {
+                    "dst" => serde_plain::from_str(value)
+                        .map(|value| dst = Some(value))
+                        .map_err(Error::from),
+                    "last-update" => value
+                        .parse()
+                        .map(|value| last_update = Some(value))
+                        .map_err(Error::from),
+                    "last-value" => value
+                        .parse()
+                        .map(|value| last_value = Some(value))
+                        .map_err(Error::from),
+                    "rra" => parse_csv_archive(value).map(|archive| archives.push(archive)),
+                    _ => Ok(()),
+                };
+                result.map_err(|err| format_err!("line {line_nr}: invalid '{key}' - {err}"))?;
+                continue;
+            }
+
+            let row = parse_csv_row(line).map_err(|err| format_err!("line {line_nr}: {err}"))?;
+            rows.push((line_nr, row));
+        }
+
+        let last_update: f64 = last_update.ok_or_else(|| format_err!("missing 'last-update'"))?;
+
+        for archive in archives.iter_mut() {
+            if archive.resolution == 0 {
+                bail!("invalid archive, resolution must not be zero");
+            }
+            let rra_end = archive.resolution * ((last_update as u64) / archive.resolution + 1);
+            archive.start =
+                rra_end.saturating_sub(archive.resolution * (archive.data.len() as u64));
+        }
+
+        for (line_nr, (index, cf, resolution, time, value)) in rows {
+            let archive = archives
+                .get_mut(index)
+                .ok_or_else(|| format_err!("line {line_nr}: unknown rra index {index}"))?;
+            if archive.cf != cf || archive.resolution != resolution {
+                bail!("line {line_nr}: row does not match rra definition {index}");
+            }
+
+            let slot = time
+                .checked_sub(archive.start)
+                .filter(|offset| offset.is_multiple_of(resolution))
+                .map(|offset| (offset / resolution) as usize)
+                .filter(|slot| *slot < archive.data.len())
+                .ok_or_else(|| format_err!("line {line_nr}: time {time} outside of rra"))?;
+            archive.data[slot] = value;
+        }
+
+        Self::import(&DatabaseExport {
+            dst: dst.ok_or_else(|| format_err!("missing 'dst'"))?,
+            last_update,
+            last_value,
+            archives,
+        })
+    }
+}
+
+// parse an archive definition ("<cf>,r=<resolution>,n=<slots>[,count=<count>][,p=<rank>]")
+fn parse_csv_archive(value: &str) -> Result<ArchiveExport, Error> {
+    let mut parts = value.split(',');
+    let cf: AggregationFn = serde_plain::from_str(parts.next().unwrap_or_default())?;
+
+    let mut resolution = None;
+    let mut slots = None;
+    let mut last_count = 0;
+    let mut percentile = None;
+
+    for part in parts {
+        match part.split_once('=') {
+            Some(("r", value)) => resolution = Some(value.parse::<u64>()?),
+            Some(("n", value)) => slots = Some(value.parse::<usize>()?),
+            Some(("count", value)) => last_count = value.parse()?,
+            Some(("p", value)) => percentile = Some(value.parse()?),
+            _ => bail!("unknown property '{part}'"),
+        }
+    }
+
+    let resolution = resolution.ok_or_else(|| format_err!("missing resolution"))?;
+    let slots = slots.ok_or_else(|| format_err!("missing number of slots"))?;
+
+    Ok(ArchiveExport {
+        cf,
+        resolution,
+        percentile,
+        last_count,
+        reservoir: Vec::new(),
+        start: 0,
+        data: vec![None; slots],
+    })
+}
+
+type CsvRow = (usize, AggregationFn, u64, u64, Option<f64>);
+
+fn parse_csv_row(line: &str) -> Result<CsvRow, Error> {
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() != 5 {
+        bail!("expected 5 fields, got {}", fields.len());
+    }
+
+    let value = match fields[4] {
+        "" => None,
+        value => Some(value.parse()?),
+    };
+
+    Ok((
+        fields[0].parse()?,
+        serde_plain::from_str(fields[1])?,
+        fields[2].parse()?,
+        fields[3].parse()?,
+        value,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_database() -> Database {
+        let mut rrd = Database::new(
+            DataSourceType::Gauge,
+            vec![
+                Archive::new(AggregationFn::Average, 60, 10),
+                Archive::new(AggregationFn::Percentile, 300, 5),
+            ],
+        );
+        for i in 1..30 {
+            rrd.update((i * 45) as f64, i as f64);
+        }
+        rrd
+    }
+
+    #[test]
+    fn json_export_import_test() -> Result<(), Error> {
+        let rrd = test_database();
+
+        let export = rrd.export();
+        assert_eq!(export.last_value, Some(29.0));
+        assert_eq!(export.archives[0].start, 1320 - 600);
+        assert_eq!(export.archives[0].data.len(), 10);
+        assert_eq!(export.archives[1].percentile, Some(95.0));
+
+        let json = serde_json::to_string(&export)?;
+        let imported = Database::import(&serde_json::from_str(&json)?)?;
+        assert_eq!(imported.export(), export);
+
+        let mut invalid = export.clone();
+        invalid.archives[0].start += 600;
+        assert!(Database::import(&invalid).is_err());
+
+        let mut invalid = export;
+        invalid.archives.push(invalid.archives[0].clone());
+        assert!(Database::import(&invalid).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn csv_export_import_test() -> Result<(), Error> {
+        let rrd = test_database();
+
+        let mut csv = Vec::new();
+        rrd.export_csv(&mut csv)?;
+        let csv = String::from_utf8(csv)?;
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("# dst=gauge"));
+        assert_eq!(lines.next(), Some("# last-update=1305"));
+        assert_eq!(lines.next(), Some("# last-value=29"));
+        assert_eq!(lines.next(), Some("# rra=average,r=60,n=10,count=2"));
+        assert_eq!(
+            lines.next(),
+            Some("# rra=percentile,r=300,n=5,count=3,p=95")
+        );
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        assert_eq!(lines.next(), Some("0,average,60,720,16.5"));
+        assert_eq!(lines.next(), Some("0,average,60,780,18"));
+
+        let imported = Database::import_csv(csv.as_bytes())?;
+        let mut expected = rrd.export();
+        // the percentile sample is not part of the CSV export
+        expected.archives[1].reservoir.clear();
+        assert_eq!(imported.export(), expected);
+
+        // rows without value may be omitted
+        let csv = "# dst=derive\n# last-update=600\n# rra=maximum,r=60,n=5\n0,maximum,60,480,2.5\n";
+        let imported = Database::import_csv(csv.as_bytes())?;
+        assert_eq!(imported.source.dst, DataSourceType::Derive);
+        assert_eq!(
+            imported.export().archives[0].data,
+            [None, None, Some(2.5), None, None]
+        );
+
+        let csv = "# dst=gauge\n# last-update=600\n# rra=maximum,r=60,n=5\n0,maximum,60,120,1\n";
+        assert!(Database::import_csv(csv.as_bytes()).is_err());
+        let csv = "# dst=gauge\n# last-update=600\n0,maximum,60,480,1\n";
+        assert!(Database::import_csv(csv.as_bytes()).is_err());
+
+        Ok(())
+    }
+}
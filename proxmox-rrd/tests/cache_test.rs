@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Error;
+
+use proxmox_rrd::Cache;
+use proxmox_rrd::rrd::{AggregationFn, Archive, DataSourceType, Database};
+
+static LOAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn load_rrd(path: &Path, _rel_path: &str) -> Option<Database> {
+    LOAD_COUNT.fetch_add(1, Ordering::SeqCst);
+    Database::load(path, true).ok()
+}
+
+fn create_rrd(dst: DataSourceType) -> Database {
+    Database::new(dst, vec![Archive::new(AggregationFn::Average, 60, 20)])
+}
+
+fn write_rrd(basedir: &Path, rel_path: &str, resolution: u64, values: &[f64]) -> Result<(), Error> {
+    let mut rrd = Database::new(
+        DataSourceType::Gauge,
+        vec![Archive::new(AggregationFn::Average, resolution, 20)],
+    );
+    for (i, value) in values.iter().enumerate() {
+        rrd.update(((i as u64 + 1) * resolution) as f64, *value);
+    }
+
+    let path = basedir.join(rel_path);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    rrd.save(&path, Default::default(), true)
+}
+
+#[test]
+fn extract_cached_series() -> Result<(), Error> {
+    let basedir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("rrd-cache-series-test");
+    let _ = std::fs::remove_dir_all(&basedir);
+
+    write_rrd(&basedir, "host/cpu", 60, &[1.0, 2.0, 3.0, 4.0])?;
+    write_rrd(&basedir, "host/mem", 120, &[10.0, 20.0])?;
+
+    let cache = Cache::new(&basedir, None, None, 1800.0, load_rrd, create_rrd)?;
+
+    let series = [("host", "cpu"), ("host", "missing"), ("host", "mem")];
+    let entries: Vec<_> = cache
+        .extract_cached_series(
+            &series,
+            AggregationFn::Average,
+            120,
+            Some(0),
+            Some(240),
+            None,
+        )?
+        .into_iter()
+        .map(|entry| entry.map(|entry| (entry.start, entry.resolution, entry.data)))
+        .collect();
+
+    // the coarser series determines the resolution of all entries
+    assert_eq!(
+        entries,
+        [
+            Some((0, 120, vec![Some(1.0), Some(2.5), Some(4.0)])),
+            None,
+            Some((0, 120, vec![None, Some(10.0), Some(20.0)])),
+        ]
+    );
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 3);
+
+    // loaded files are not looked up again, missing ones are retried
+    let entries = cache.extract_cached_series(
+        &series,
+        AggregationFn::Average,
+        120,
+        Some(0),
+        Some(240),
+        None,
+    )?;
+    assert_eq!(entries.len(), 3);
+    assert!(entries[1].is_none());
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 4);
+
+    // a file created later is picked up
+    write_rrd(&basedir, "host/missing", 120, &[5.0, 6.0])?;
+    let entry = cache
+        .extract_cached_data(
+            "host",
+            "missing",
+            AggregationFn::Average,
+            120,
+            Some(0),
+            Some(240),
+        )?
+        .unwrap();
+    assert_eq!(entry.data, [None, Some(5.0), Some(6.0)]);
+    assert_eq!(LOAD_COUNT.load(Ordering::SeqCst), 5);
+
+    std::fs::remove_dir_all(&basedir)?;
+
+    Ok(())
+}
@@ -55,3 +55,27 @@ fn load_and_save_rrd_v2() -> Result<(), Error> {
 
     Ok(())
 }
+
+// make sure exported data can be imported again, including converted RRD v1 files
+#[test]
+fn export_and_import_rrd() -> Result<(), Error> {
+    let rrd = Database::load(Path::new(RRD_V2_FN), true)?;
+    let export = rrd.export();
+
+    let json = serde_json::to_string(&export)?;
+    let imported = Database::import(&serde_json::from_str(&json)?)?;
+    assert_eq!(imported.export(), export);
+
+    let mut csv = Vec::new();
+    rrd.export_csv(&mut csv)?;
+    let imported = Database::import_csv(&csv[..])?;
+    assert_eq!(imported.export(), export);
+
+    #[cfg(feature = "rrd_v1")]
+    {
+        let rrd = Database::load(Path::new("./tests/testdata/cpu.rrd_v1"), true)?;
+        assert_eq!(rrd.export(), export);
+    }
+
+    Ok(())
+}
@@ -6,7 +6,9 @@ fn main() {
 }
 
 #[cfg(feature = "impl")]
-use proxmox_s3_client::{S3Client, S3ClientOptions, S3ObjectKey, S3PathPrefix};
+use proxmox_s3_client::{
+    MultipartUploadOptions, S3Client, S3ClientOptions, S3ObjectKey, S3PathPrefix,
+};
 
 #[cfg(feature = "impl")]
 fn main() -> Result<(), anyhow::Error> {
@@ -39,6 +41,10 @@ async fn run() -> Result<(), anyhow::Error> {
         fingerprint: Some("<s3-api-fingerprint>".to_string()),
         put_rate_limit: None,
         provider_quirks: Vec::new(),
+        // Server-side encryption, e.g. `Some(ServerSideEncryptionMode::SseS3)`
+        encryption: None,
+        kms_key_id: None,
+        sse_customer_key: None,
         rate_limiter_config: None,
         proxy_config: None,
         request_counter_config: None,
@@ -59,6 +65,24 @@ async fn run() -> Result<(), anyhow::Error> {
         .put_object(rel_object_key, body, request_timeout, replace_existing_key)
         .await?;
 
+    // Upload larger objects via a multipart upload. The data is split into parts of 16 MiB by
+    // default, up to 4 of which are uploaded in parallel.
+    let rel_object_key = S3ObjectKey::try_from("large-object.bin")?;
+    let data = vec![0u8; 64 * 1024 * 1024];
+    let _response = s3_client
+        .put_object_multipart(
+            rel_object_key,
+            data.as_slice(),
+            &MultipartUploadOptions::default(),
+        )
+        .await?;
+
+    // Clean up multipart uploads left behind by interrupted uploads older than one day
+    let prefix = S3PathPrefix::Some("/teststore/".to_string());
+    let _aborted = s3_client
+        .abort_stale_multipart_uploads(&prefix, std::time::Duration::from_secs(24 * 60 * 60))
+        .await?;
+
     // List object, limiting to ones matching the given prefix. Since the api limits the response
     // to 1000 entries, the following contents might be fetched using a continuation token, being
     // part of the previouis response.
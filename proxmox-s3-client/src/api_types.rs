@@ -25,12 +25,22 @@ const_regex! {
     pub S3_ENDPOINT_REGEX = concatcp!(r"^(?:", S3_ENDPOINT_NAME_STR, "|",  IPRE_STR, r")$");
     /// Regex to match S3 regions, similar to SAFE_ID_REGEX but only lower case and without dot.
     pub S3_REGION_REGEX = r"^[_a-z\d][-_a-z\d]+$";
+    /// Regex to match KMS key identifiers, given as key id, key ARN, alias name or alias ARN.
+    pub S3_KMS_KEY_ID_REGEX = r"^[A-Za-z0-9:/_\-]+$";
+    /// Regex to match base64 encoded 256-bit keys for server-side encryption with customer
+    /// provided keys.
+    pub S3_SSE_CUSTOMER_KEY_REGEX = r"^[A-Za-z0-9+/]{43}=$";
 }
 
 /// S3 REST API endpoint format.
 pub const S3_ENDPOINT_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&S3_ENDPOINT_REGEX);
 /// S3 region format.
 pub const S3_REGION_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&S3_REGION_REGEX);
+/// KMS key identifier format.
+pub const S3_KMS_KEY_ID_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&S3_KMS_KEY_ID_REGEX);
+/// Server-side encryption customer key format.
+pub const S3_SSE_CUSTOMER_KEY_FORMAT: ApiStringFormat =
+    ApiStringFormat::Pattern(&S3_SSE_CUSTOMER_KEY_REGEX);
 
 /// ID to uniquely identify an S3 client config.
 pub const S3_CLIENT_ID_SCHEMA: Schema =
@@ -51,6 +61,20 @@ pub const S3_REGION_SCHEMA: Schema = StringSchema::new("Region to access S3 obje
     .max_length(32)
     .schema();
 
+/// KMS key used for server-side encryption.
+pub const S3_KMS_KEY_ID_SCHEMA: Schema = StringSchema::new(
+    "KMS key used for server-side encryption, the default key of the provider is used if not set.",
+)
+.format(&S3_KMS_KEY_ID_FORMAT)
+.max_length(2048)
+.schema();
+
+/// Customer provided key used for server-side encryption.
+pub const S3_SSE_CUSTOMER_KEY_SCHEMA: Schema =
+    StringSchema::new("Base64 encoded 256-bit key used for server-side encryption (SSE-C).")
+        .format(&S3_SSE_CUSTOMER_KEY_FORMAT)
+        .schema();
+
 /// Bucket to access S3 object store.
 pub const S3_BUCKET_NAME_SCHEMA: Schema = StringSchema::new("Bucket name for S3 object store.")
     .format(&ApiStringFormat::VerifyFn(|bucket_name| {
@@ -89,10 +113,30 @@ pub enum ProviderQuirks {
     SkipIfNoneMatchHeader,
     /// Prvider does not support DeleteObjects API endpoint, use delete object calls instead
     DeleteObjectsViaDeleteObject,
+    /// Provider does not support object versioning, version aware requests fail early
+    NoObjectVersioning,
+    /// Provider does not support object lock, requests setting retention or legal hold fail early
+    NoObjectLock,
 }
 serde_plain::derive_display_from_serialize!(ProviderQuirks);
 serde_plain::derive_fromstr_from_deserialize!(ProviderQuirks);
 
+#[api]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// Server-side encryption mode for objects stored by the client.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/userguide/serv-side-encryption.html
+pub enum ServerSideEncryptionMode {
+    /// Encryption with keys managed by the object store (SSE-S3)
+    SseS3,
+    /// Encryption with keys managed by the key management service (SSE-KMS)
+    SseKms,
+    /// Encryption with a customer provided key (SSE-C)
+    SseC,
+}
+serde_plain::derive_display_from_serialize!(ServerSideEncryptionMode);
+serde_plain::derive_fromstr_from_deserialize!(ServerSideEncryptionMode);
+
 #[api(
     properties: {
         endpoint: {
@@ -145,6 +189,14 @@ serde_plain::derive_fromstr_from_deserialize!(ProviderQuirks);
             type: HumanByte,
             optional: true,
         },
+        encryption: {
+            type: ServerSideEncryptionMode,
+            optional: true,
+        },
+        "kms-key-id": {
+            schema: S3_KMS_KEY_ID_SCHEMA,
+            optional: true,
+        },
     },
 )]
 #[derive(Serialize, Deserialize, Updater, Clone, PartialEq)]
@@ -185,6 +237,12 @@ pub struct S3ClientConfig {
     /// Upload burst
     #[serde(skip_serializing_if = "Option::is_none")]
     pub burst_out: Option<HumanByte>,
+    /// Server-side encryption mode for stored objects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<ServerSideEncryptionMode>,
+    /// KMS key used for server-side encryption with mode `sse-kms`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kms_key_id: Option<String>,
 }
 
 impl S3ClientConfig {
@@ -206,6 +264,10 @@ impl S3ClientConfig {
         "secret-key": {
             type: String,
         },
+        "sse-customer-key": {
+            schema: S3_SSE_CUSTOMER_KEY_SCHEMA,
+            optional: true,
+        },
     },
 )]
 #[derive(Serialize, Deserialize, Updater, Clone, PartialEq)]
@@ -220,6 +282,9 @@ pub struct S3ClientConf {
     pub config: S3ClientConfig,
     /// Secret key for S3 object store.
     pub secret_key: String,
+    /// Customer provided key for server-side encryption with mode `sse-c`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sse_customer_key: Option<String>,
 }
 
 #[api(
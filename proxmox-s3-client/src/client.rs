@@ -5,11 +5,14 @@ use std::sync::{Arc, LazyLock, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Error, bail, format_err};
+use futures::future::{self, Either};
+use futures::stream::{FuturesUnordered, StreamExt};
 use hyper::body::{Bytes, Incoming};
 use hyper::http::method::Method;
+use hyper::http::request::Builder;
 use hyper::http::uri::{Authority, Parts, PathAndQuery, Scheme};
 use hyper::http::{HeaderValue, StatusCode, Uri, header};
-use hyper::{Request, Response};
+use hyper::{HeaderMap, Request, Response};
 use hyper_util::client::legacy::Client;
 use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::rt::TokioExecutor;
@@ -18,6 +21,7 @@ use openssl::hash::MessageDigest;
 use openssl::sha::Sha256;
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
 use openssl::x509::X509StoreContextRef;
+use tokio::io::AsyncRead;
 use tracing::error;
 
 use proxmox_http::client::HttpsConnector;
@@ -25,14 +29,27 @@ use proxmox_http::{Body, ProxyConfig};
 use proxmox_rate_limiter::{RateLimit, RateLimiter, SharedRateLimiter};
 use proxmox_schema::api_types::CERT_FINGERPRINT_SHA256_SCHEMA;
 
-use crate::api_types::{ProviderQuirks, S3ClientConfig};
+use crate::api_types::{ProviderQuirks, S3ClientConf, S3ClientConfig, ServerSideEncryptionMode};
 use crate::aws_sign_v4::AWS_SIGN_V4_DATETIME_FORMAT;
-use crate::aws_sign_v4::{aws_sign_v4_signature, aws_sign_v4_uri_encode};
+use crate::aws_sign_v4::{
+    aws_sign_v4_presigned_url, aws_sign_v4_signature, aws_sign_v4_uri_encode,
+};
+use crate::encryption::ServerSideEncryption;
+use crate::multipart::{
+    CompletedPart, MultipartUploadOptions, MultipartUploadState, S3_MULTIPART_MAX_PARTS,
+    complete_multipart_upload_body, read_part,
+};
 use crate::object_key::S3ObjectKey;
+use crate::object_lock::{
+    BYPASS_GOVERNANCE_RETENTION_HEADER, LegalHoldStatus, OBJECT_LOCK_LEGAL_HOLD_HEADER,
+    ObjectLockRetention,
+};
+use crate::object_reader::GetObjectOptions;
 use crate::response_reader::{
-    CopyObjectResponse, DeleteError, DeleteObjectError, DeleteObjectsResponse, DeletedObject,
-    GetObjectResponse, HeadObjectResponse, ListBucketsResponse, ListObjectsV2Response,
-    PutObjectResponse, ResponseReader,
+    CompleteMultipartUploadResponse, ConditionalGetObjectResponse, CopyObjectResponse, DeleteError,
+    DeleteObjectError, DeleteObjectsResponse, DeletedObject, GetObjectResponse, HeadObjectResponse,
+    ListBucketsResponse, ListMultipartUploadsResponse, ListObjectVersionsResponse,
+    ListObjectsV2Response, PutObjectResponse, ResponseReader,
 };
 use crate::shared_request_counters::{
     MmapFlusher, SharedRequestCounters, ThresholdExceededCallback,
@@ -40,6 +57,9 @@ use crate::shared_request_counters::{
 
 /// Default timeout for s3 api requests.
 pub const S3_HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// Maximum validity period of presigned URLs.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/API/sigv4-query-string-auth.html
+pub const S3_PRESIGNED_URL_MAX_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 
 const S3_HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 const S3_TCP_KEEPIDLE_TIME: u32 = 120;
@@ -92,6 +112,28 @@ pub struct S3RequestCounterConfig {
     pub user: User,
 }
 
+#[derive(Clone, Debug, Default)]
+/// Options for put object requests.
+pub struct PutObjectOptions {
+    /// Replace an object with matching key if it already exists in the bucket.
+    pub replace: bool,
+    /// Object lock retention to apply to the uploaded object version.
+    pub object_lock_retention: Option<ObjectLockRetention>,
+    /// Place an object lock legal hold on the uploaded object version.
+    pub object_lock_legal_hold: bool,
+}
+
+/// Presigned request granting time-limited access to an object without further credentials.
+pub struct PresignedRequest {
+    /// Presigned URL, including the authentication query parameters.
+    pub url: String,
+    /// Headers covered by the signature, which must be sent along with the request unchanged,
+    /// e.g. for server-side encryption with keys managed by the object store or KMS.
+    pub headers: HeaderMap,
+    /// Time the presigned URL expires at, as seconds since the UNIX epoch.
+    pub expires_at: i64,
+}
+
 /// Configuration options for client
 pub struct S3ClientOptions {
     /// Endpoint to access S3 object store.
@@ -116,6 +158,12 @@ pub struct S3ClientOptions {
     pub put_rate_limit: Option<u64>,
     /// Provider implementation specific features and limitations
     pub provider_quirks: Vec<ProviderQuirks>,
+    /// Server-side encryption mode for stored objects.
+    pub encryption: Option<ServerSideEncryptionMode>,
+    /// KMS key used for server-side encryption with mode `sse-kms`.
+    pub kms_key_id: Option<String>,
+    /// Customer provided key for server-side encryption with mode `sse-c`, base64 encoded.
+    pub sse_customer_key: Option<String>,
     /// Configuration options for the shared rate limiter.
     pub rate_limiter_config: Option<S3RateLimiterConfig>,
     /// Proxy configuration to be used by the client.
@@ -128,6 +176,14 @@ pub struct S3ClientOptions {
 
 impl S3ClientOptions {
     /// Construct options for the S3 client give the provided configuration parameters.
+    ///
+    /// The configuration does not contain the customer provided key required for server-side
+    /// encryption with mode `sse-c`, set it via [`with_sse_customer_key`] or construct the
+    /// options from the full client configuration via [`from_conf`] instead. Creating the client
+    /// fails if it is missing.
+    ///
+    /// [`with_sse_customer_key`]: Self::with_sse_customer_key
+    /// [`from_conf`]: Self::from_conf
     pub fn from_config(
         config: S3ClientConfig,
         secret_key: String,
@@ -156,12 +212,45 @@ impl S3ClientOptions {
             secret_key,
             put_rate_limit: config.put_rate_limit,
             provider_quirks: config.provider_quirks.unwrap_or_default(),
+            encryption: config.encryption,
+            kms_key_id: config.kms_key_id,
+            sse_customer_key: None,
             rate_limiter_config,
             proxy_config,
             request_counter_config,
             threshold_callback: None,
         }
     }
+
+    /// Construct options for the S3 client given the full client configuration, including the
+    /// secret key and the customer provided key for server-side encryption.
+    pub fn from_conf(
+        conf: S3ClientConf,
+        bucket: Option<String>,
+        common_prefix: String,
+        rate_limiter_options: Option<S3RateLimiterOptions>,
+        proxy_config: Option<ProxyConfig>,
+        request_counter_config: Option<S3RequestCounterConfig>,
+    ) -> Self {
+        Self::from_config(
+            conf.config,
+            conf.secret_key,
+            bucket,
+            common_prefix,
+            rate_limiter_options,
+            proxy_config,
+            request_counter_config,
+        )
+        .with_sse_customer_key(conf.sse_customer_key)
+    }
+
+    /// Set the customer provided key for server-side encryption with mode `sse-c`.
+    ///
+    /// Like the secret key, the customer key is not part of the [`S3ClientConfig`].
+    pub fn with_sse_customer_key(mut self, sse_customer_key: Option<String>) -> Self {
+        self.sse_customer_key = sse_customer_key;
+        self
+    }
 }
 
 /// S3 client for object stores compatible with the AWS S3 API
@@ -171,6 +260,7 @@ pub struct S3Client {
     authority: Authority,
     put_rate_limiter: Option<Arc<Mutex<RateLimiter>>>,
     request_counters: Option<Arc<SharedRequestCounters>>,
+    encryption: Option<ServerSideEncryption>,
 }
 
 impl Drop for S3Client {
@@ -201,6 +291,12 @@ impl S3Client {
         } else {
             None
         };
+        let encryption = ServerSideEncryption::new(
+            options.encryption,
+            options.kms_key_id.as_deref(),
+            options.sse_customer_key.as_deref(),
+        )
+        .context("invalid server-side encryption options")?;
         let verified_fingerprint = Arc::new(Mutex::new(None));
         let trust_openssl_valid = Arc::new(Mutex::new(true));
         let mut ssl_connector_builder = SslConnector::builder(SslMethod::tls())?;
@@ -311,6 +407,7 @@ impl S3Client {
             authority,
             put_rate_limiter,
             request_counters,
+            encryption,
         })
     }
 
@@ -530,11 +627,39 @@ impl S3Client {
     pub async fn head_object(
         &self,
         object_key: S3ObjectKey,
+    ) -> Result<Option<HeadObjectResponse>, Error> {
+        self.head_object_impl(object_key, None).await
+    }
+
+    /// Fetch metadata from the given version of an object without returning the object itself.
+    ///
+    /// The response includes the object lock retention and legal hold status of the version,
+    /// if any.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_HeadObject.html
+    pub async fn head_object_version(
+        &self,
+        object_key: S3ObjectKey,
+        version_id: &str,
+    ) -> Result<Option<HeadObjectResponse>, Error> {
+        self.head_object_impl(object_key, Some(version_id)).await
+    }
+
+    async fn head_object_impl(
+        &self,
+        object_key: S3ObjectKey,
+        version_id: Option<&str>,
     ) -> Result<Option<HeadObjectResponse>, Error> {
         let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let mut query = Vec::new();
+        if let Some(version_id) = version_id {
+            self.check_versioning_support()?;
+            query.push(("versionId", version_id));
+        }
         let request = Request::builder()
             .method(Method::HEAD)
-            .uri(self.build_uri(&object_key, &[])?)
+            .uri(self.build_uri(&object_key, &query)?);
+        let request = self
+            .with_customer_key_headers(request)
             .body(Body::empty())?;
         let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
         let response_reader = ResponseReader::new(response, self.request_counters.clone());
@@ -550,7 +675,9 @@ impl S3Client {
         let object_key = object_key.to_full_key(&self.options.common_prefix);
         let request = Request::builder()
             .method(Method::GET)
-            .uri(self.build_uri(&object_key, &[])?)
+            .uri(self.build_uri(&object_key, &[])?);
+        let request = self
+            .with_customer_key_headers(request)
             .body(Body::empty())?;
 
         let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
@@ -558,6 +685,129 @@ impl S3Client {
         response_reader.get_object_response().await
     }
 
+    /// Fetch an object or a byte range of it from object store, subject to the pre-conditions
+    /// given by the options.
+    ///
+    /// Returns `None` if the object does not exist. Failed pre-conditions are returned as
+    /// [`ConditionalGetObjectResponse::NotModified`] or
+    /// [`ConditionalGetObjectResponse::PreconditionFailed`], respectively.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_GetObject.html
+    pub async fn get_object_with_options(
+        &self,
+        object_key: S3ObjectKey,
+        options: &GetObjectOptions,
+    ) -> Result<Option<ConditionalGetObjectResponse>, Error> {
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let mut query = Vec::new();
+        if let Some(version_id) = &options.version_id {
+            self.check_versioning_support()?;
+            query.push(("versionId", version_id.as_str()));
+        }
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(self.build_uri(&object_key, &query)?);
+        let mut request = self.with_customer_key_headers(request);
+
+        if let Some(range) = &options.range {
+            request = request.header(header::RANGE, range.header_value()?);
+        }
+        if let Some(e_tag) = &options.if_match {
+            request = request.header(header::IF_MATCH, HeaderValue::from_str(e_tag)?);
+        }
+        if let Some(e_tag) = &options.if_none_match {
+            request = request.header(header::IF_NONE_MATCH, HeaderValue::from_str(e_tag)?);
+        }
+        if let Some(date) = &options.if_modified_since {
+            request = request.header(header::IF_MODIFIED_SINCE, date.to_string());
+        }
+        if let Some(date) = &options.if_unmodified_since {
+            request = request.header(header::IF_UNMODIFIED_SINCE, date.to_string());
+        }
+
+        let request = request.body(Body::empty())?;
+
+        let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        response_reader.conditional_get_object_response().await
+    }
+
+    /// Generate a presigned URL to fetch an object, valid for the given time period.
+    ///
+    /// The URL can be handed out to grant direct, time-limited download access to the object
+    /// without sharing the credentials. No request is sent to the object store. Fails if the
+    /// client uses server-side encryption with a customer provided key.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/userguide/ShareObjectPreSignedURL.html
+    pub fn presign_get_object(
+        &self,
+        object_key: S3ObjectKey,
+        expires_in: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(self.build_uri(&object_key, &[])?);
+        self.presign(request, expires_in)
+    }
+
+    /// Generate a presigned URL to upload an object, valid for the given time period.
+    ///
+    /// The URL can be handed out to grant direct, time-limited upload access without sharing
+    /// the credentials. Existing objects are replaced. No request is sent to the object store.
+    /// Fails if the client uses server-side encryption with a customer provided key.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/userguide/PresignedUrlUploadObject.html
+    pub fn presign_put_object(
+        &self,
+        object_key: S3ObjectKey,
+        expires_in: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let request = Request::builder()
+            .method(Method::PUT)
+            .uri(self.build_uri(&object_key, &[])?);
+        let request = self.with_encryption_headers(request);
+        self.presign(request, expires_in)
+    }
+
+    /// Presign the given request.
+    ///
+    /// Fails for server-side encryption with a customer provided key, since the key would have
+    /// to be handed out together with the URL.
+    fn presign(&self, request: Builder, expires_in: Duration) -> Result<PresignedRequest, Error> {
+        if let Some(ServerSideEncryption::Customer { .. }) = self.encryption {
+            bail!(
+                "refusing to presign request, server-side encryption with a customer key would \
+                require sharing the key"
+            );
+        }
+        if expires_in.is_zero() || expires_in > S3_PRESIGNED_URL_MAX_EXPIRY {
+            bail!(
+                "presigned URL expiry must be between 1 and {} seconds",
+                S3_PRESIGNED_URL_MAX_EXPIRY.as_secs(),
+            );
+        }
+        let expires = expires_in.as_secs().max(1);
+
+        let mut request = request.body(Body::empty())?;
+        let headers = request.headers().clone();
+        let host_header = request
+            .uri()
+            .authority()
+            .ok_or_else(|| format_err!("request missing authority"))?
+            .to_string();
+        request
+            .headers_mut()
+            .insert("host", HeaderValue::from_str(&host_header)?);
+
+        let epoch = proxmox_time::epoch_i64();
+        let url = aws_sign_v4_presigned_url(&request, &self.options, epoch, expires)?;
+
+        Ok(PresignedRequest {
+            url,
+            headers,
+            expires_at: epoch + expires as i64,
+        })
+    }
+
     /// Returns some or all (up to 1,000) of the objects in a bucket with each request.
     /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_GetObjectTagging.html
     pub async fn list_objects_v2(
@@ -588,6 +838,42 @@ impl S3Client {
         response_reader.list_objects_v2_response().await
     }
 
+    /// Returns metadata about all versions of the objects in a bucket, up to 1,000 versions and
+    /// delete markers with each request.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_ListObjectVersions.html
+    pub async fn list_object_versions(
+        &self,
+        prefix: &S3PathPrefix,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+    ) -> Result<ListObjectVersionsResponse, Error> {
+        self.check_versioning_support()?;
+        let mut query = vec![("versions", "")];
+        let abs_prefix: String;
+        if let S3PathPrefix::Some(prefix) = prefix {
+            abs_prefix = if prefix.starts_with("/") {
+                format!("{}{prefix}", self.options.common_prefix)
+            } else {
+                format!("{}/{prefix}", self.options.common_prefix)
+            };
+            query.push(("prefix", &abs_prefix));
+        }
+        if let Some(key_marker) = key_marker {
+            query.push(("key-marker", key_marker));
+        }
+        if let Some(version_id_marker) = version_id_marker {
+            query.push(("version-id-marker", version_id_marker));
+        }
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(self.build_uri("/", &query)?)
+            .body(Body::empty())?;
+
+        let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        response_reader.list_object_versions_response().await
+    }
+
     /// Add a new object to a bucket.
     ///
     /// Do not reupload if an object with matching key already exists in the bucket if the replace
@@ -599,14 +885,48 @@ impl S3Client {
         object_data: Body,
         timeout: Option<Duration>,
         replace: bool,
+    ) -> Result<PutObjectResponse, Error> {
+        let options = PutObjectOptions {
+            replace,
+            ..Default::default()
+        };
+        self.put_object_with_options(object_key, object_data, timeout, &options)
+            .await
+    }
+
+    /// Add a new object to a bucket, given the provided options.
+    ///
+    /// Allows to protect the uploaded object version against deletion and overwrites via object
+    /// lock retention and legal hold, which requires a bucket with object lock enabled.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutObject.html
+    pub async fn put_object_with_options(
+        &self,
+        object_key: S3ObjectKey,
+        object_data: Body,
+        timeout: Option<Duration>,
+        options: &PutObjectOptions,
     ) -> Result<PutObjectResponse, Error> {
         let object_key = object_key.to_full_key(&self.options.common_prefix);
-        let mut request = Request::builder()
+        let request = Request::builder()
             .method(Method::PUT)
             .uri(self.build_uri(&object_key, &[])?)
             .header(header::CONTENT_TYPE, "application/octet-stream");
+        let mut request = self.with_encryption_headers(request);
+
+        if options.object_lock_retention.is_some() || options.object_lock_legal_hold {
+            self.check_object_lock_support()?;
+        }
+        if let Some(retention) = &options.object_lock_retention {
+            request = retention.add_headers(request)?;
+        }
+        if options.object_lock_legal_hold {
+            request = request.header(
+                OBJECT_LOCK_LEGAL_HOLD_HEADER,
+                LegalHoldStatus::On.to_string(),
+            );
+        }
 
-        if !replace {
+        if !options.replace {
             // Some providers not implement this and fails with error if the header is set,
             // see https://forum.proxmox.com/threads/168834/post-786278
             if !self
@@ -628,7 +948,24 @@ impl S3Client {
     /// Removes an object from a bucket.
     /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_DeleteObject.html
     pub async fn delete_object(&self, object_key: S3ObjectKey) -> Result<DeletedObject, Error> {
-        self.delete_object_impl(object_key)
+        self.delete_object_impl(object_key, None, false)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Permanently removes the given version of an object from a bucket.
+    ///
+    /// Versions protected by an object lock retention in governance mode can only be removed if
+    /// `bypass_governance_retention` is set and the user has the permission to do so.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_DeleteObject.html
+    pub async fn delete_object_version(
+        &self,
+        object_key: S3ObjectKey,
+        version_id: &str,
+        bypass_governance_retention: bool,
+    ) -> Result<DeletedObject, Error> {
+        self.check_versioning_support()?;
+        self.delete_object_impl(object_key, Some(version_id), bypass_governance_retention)
             .await
             .map_err(Into::into)
     }
@@ -636,14 +973,22 @@ impl S3Client {
     async fn delete_object_impl(
         &self,
         object_key: S3ObjectKey,
+        version_id: Option<&str>,
+        bypass_governance_retention: bool,
     ) -> Result<DeletedObject, DeleteError> {
         let object_key = object_key.to_full_key(&self.options.common_prefix);
-        let request = Request::builder()
-            .method(Method::DELETE)
-            .uri(
-                self.build_uri(&object_key, &[])
-                    .map_err(DeleteError::Parsing)?,
-            )
+        let mut query = Vec::new();
+        if let Some(version_id) = version_id {
+            query.push(("versionId", version_id));
+        }
+        let mut request = Request::builder().method(Method::DELETE).uri(
+            self.build_uri(&object_key, &query)
+                .map_err(DeleteError::Parsing)?,
+        );
+        if bypass_governance_retention {
+            request = request.header(BYPASS_GOVERNANCE_RETENTION_HEADER, "true");
+        }
+        let request = request
             .body(Body::empty())
             .map_err(|err| DeleteError::Parsing(err.into()))?;
 
@@ -652,7 +997,9 @@ impl S3Client {
             .await
             .map_err(DeleteError::Parsing)?;
         let response_reader = ResponseReader::new(response, self.request_counters.clone());
-        response_reader.delete_object_response(object_key).await
+        response_reader
+            .delete_object_response(object_key, version_id.map(str::to_string))
+            .await
     }
 
     /// Delete multiple objects from a bucket using a single HTTP request.
@@ -674,7 +1021,10 @@ impl S3Client {
             response.deleted = Some(Vec::with_capacity(object_keys.len()));
 
             for object_key in object_keys {
-                match self.delete_object_impl(object_key.clone()).await {
+                match self
+                    .delete_object_impl(object_key.clone(), None, false)
+                    .await
+                {
                     Ok(deleted_object) => {
                         let deleted = response.deleted.get_or_insert(Vec::new());
                         deleted.push(deleted_object);
@@ -738,8 +1088,13 @@ impl S3Client {
             .header(
                 "x-amz-metadata-directive",
                 HeaderValue::from_str("REPLACE")?,
-            )
-            .body(Body::empty())?;
+            );
+        let request = self.with_encryption_headers(request);
+        let request = match &self.encryption {
+            Some(encryption) => encryption.add_copy_source_headers(request),
+            None => request,
+        };
+        let request = request.body(Body::empty())?;
 
         let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
         let response_reader = ResponseReader::new(response, self.request_counters.clone());
@@ -901,11 +1256,7 @@ impl S3Client {
         object_data: Bytes,
         replace: bool,
     ) -> Result<bool, Error> {
-        let content_size = object_data.len() as u64;
-        let timeout_secs = content_size
-            .div_ceil(S3_MIN_ASSUMED_UPLOAD_RATE)
-            .max(S3_HTTP_REQUEST_TIMEOUT.as_secs());
-        let timeout = Some(Duration::from_secs(timeout_secs));
+        let timeout = Self::upload_timeout(object_data.len() as u64);
         for retry in 0..MAX_S3_UPLOAD_RETRY {
             let body = Body::from(object_data.clone());
             match self
@@ -929,6 +1280,371 @@ impl S3Client {
         Ok(false)
     }
 
+    /// Calculate the request timeout for uploading the given number of bytes, based on the
+    /// assumed minimum upload rate.
+    fn upload_timeout(content_size: u64) -> Option<Duration> {
+        let timeout_secs = content_size
+            .div_ceil(S3_MIN_ASSUMED_UPLOAD_RATE)
+            .max(S3_HTTP_REQUEST_TIMEOUT.as_secs());
+        Some(Duration::from_secs(timeout_secs))
+    }
+
+    /// Initiate a multipart upload for the given object.
+    ///
+    /// The returned state has to be used for all further requests of this upload and can be
+    /// persisted in order to resume the upload later on.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_CreateMultipartUpload.html
+    pub async fn create_multipart_upload(
+        &self,
+        object_key: S3ObjectKey,
+        part_size: u64,
+    ) -> Result<MultipartUploadState, Error> {
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.build_uri(&object_key, &[("uploads", "")])?)
+            .header(header::CONTENT_TYPE, "application/octet-stream");
+        let request = self.with_encryption_headers(request).body(Body::empty())?;
+
+        let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        let response = response_reader.create_multipart_upload_response().await?;
+
+        Ok(MultipartUploadState {
+            object_key,
+            upload_id: response.upload_id,
+            part_size,
+            completed_parts: Vec::new(),
+        })
+    }
+
+    /// Upload a single part of a multipart upload, returning the entity tag of the part.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_UploadPart.html
+    pub async fn upload_part(
+        &self,
+        object_key: S3ObjectKey,
+        upload_id: &str,
+        part_number: u32,
+        part_data: Body,
+        timeout: Option<Duration>,
+    ) -> Result<String, Error> {
+        if !(1..=S3_MULTIPART_MAX_PARTS).contains(&part_number) {
+            bail!("part number {part_number} out of range (1 - {S3_MULTIPART_MAX_PARTS})");
+        }
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let part_number = part_number.to_string();
+        let request = Request::builder().method(Method::PUT).uri(self.build_uri(
+            &object_key,
+            &[("partNumber", &part_number), ("uploadId", upload_id)],
+        )?);
+        let request = self.with_customer_key_headers(request).body(part_data)?;
+
+        let response = self.send(request, timeout).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        response_reader.upload_part_response().await
+    }
+
+    /// Complete a multipart upload by assembling the given, previously uploaded parts.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_CompleteMultipartUpload.html
+    pub async fn complete_multipart_upload(
+        &self,
+        object_key: S3ObjectKey,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<CompleteMultipartUploadResponse, Error> {
+        if parts.is_empty() {
+            bail!("cannot complete multipart upload without parts");
+        }
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.build_uri(&object_key, &[("uploadId", upload_id)])?);
+        let request = self
+            .with_customer_key_headers(request)
+            .body(Body::from(complete_multipart_upload_body(parts)))?;
+
+        let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        response_reader.complete_multipart_upload_response().await
+    }
+
+    /// Abort a multipart upload, freeing the storage used by already uploaded parts.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_AbortMultipartUpload.html
+    pub async fn abort_multipart_upload(
+        &self,
+        object_key: S3ObjectKey,
+        upload_id: &str,
+    ) -> Result<(), Error> {
+        let object_key = object_key.to_full_key(&self.options.common_prefix);
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri(self.build_uri(&object_key, &[("uploadId", upload_id)])?)
+            .body(Body::empty())?;
+
+        let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        response_reader.abort_multipart_upload_response().await
+    }
+
+    /// Returns some or all (up to 1,000) of the in-progress multipart uploads in a bucket.
+    /// See reference docs: https://docs.aws.amazon.com/AmazonS3/latest/API/API_ListMultipartUploads.html
+    pub async fn list_multipart_uploads(
+        &self,
+        prefix: &S3PathPrefix,
+        key_marker: Option<&str>,
+        upload_id_marker: Option<&str>,
+    ) -> Result<ListMultipartUploadsResponse, Error> {
+        let mut query = vec![("uploads", "")];
+        let abs_prefix: String;
+        if let S3PathPrefix::Some(prefix) = prefix {
+            abs_prefix = if prefix.starts_with("/") {
+                format!("{}{prefix}", self.options.common_prefix)
+            } else {
+                format!("{}/{prefix}", self.options.common_prefix)
+            };
+            query.push(("prefix", &abs_prefix));
+        }
+        if let Some(key_marker) = key_marker {
+            query.push(("key-marker", key_marker));
+        }
+        if let Some(upload_id_marker) = upload_id_marker {
+            query.push(("upload-id-marker", upload_id_marker));
+        }
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(self.build_uri("/", &query)?)
+            .body(Body::empty())?;
+
+        let response = self.send(request, Some(S3_HTTP_REQUEST_TIMEOUT)).await?;
+        let response_reader = ResponseReader::new(response, self.request_counters.clone());
+        response_reader.list_multipart_uploads_response().await
+    }
+
+    /// Upload the data provided by the reader as object via a multipart upload, aborting the
+    /// multipart upload on failure.
+    ///
+    /// The data is split into parts of the configured size, which are uploaded in parallel,
+    /// buffering at most `part_size * concurrency` bytes in memory.
+    pub async fn put_object_multipart<R: AsyncRead + Unpin>(
+        &self,
+        object_key: S3ObjectKey,
+        reader: R,
+        options: &MultipartUploadOptions,
+    ) -> Result<CompleteMultipartUploadResponse, Error> {
+        options.check()?;
+
+        let mut state = self
+            .create_multipart_upload(object_key, options.part_size)
+            .await?;
+
+        match self
+            .upload_multipart(&mut state, reader, options.concurrency)
+            .await
+        {
+            Ok(response) => Ok(response),
+            Err(err) => {
+                if let Err(abort_err) = self
+                    .abort_multipart_upload(state.object_key.clone(), &state.upload_id)
+                    .await
+                {
+                    error!("failed to abort multipart upload: {abort_err:#}");
+                }
+                Err(err.context("multipart upload failed"))
+            }
+        }
+    }
+
+    /// Upload the data provided by the reader as parts of the multipart upload given by its state
+    /// and complete the upload.
+    ///
+    /// The reader must be positioned at [`MultipartUploadState::resume_offset`], parts already
+    /// contained in the state are skipped. The state is updated for every successfully uploaded
+    /// part, so on failure it can be persisted by the caller to resume the upload later on. At
+    /// most `concurrency` parts are uploaded in parallel, each retried up to 3 times in case of
+    /// error.
+    pub async fn upload_multipart<R: AsyncRead + Unpin>(
+        &self,
+        state: &mut MultipartUploadState,
+        mut reader: R,
+        concurrency: usize,
+    ) -> Result<CompleteMultipartUploadResponse, Error> {
+        let concurrency = concurrency.max(1);
+        let mut part_number = state.first_missing_part();
+        let mut in_flight = FuturesUnordered::new();
+        let mut end_of_data = false;
+
+        loop {
+            while !end_of_data && in_flight.len() < concurrency {
+                // Keep driving the uploads in flight while reading the next part, so that they
+                // are not stalled by a slow reader.
+                let read = read_part(&mut reader, state.part_size);
+                futures::pin_mut!(read);
+                let part_data = loop {
+                    if in_flight.is_empty() {
+                        break read.await?;
+                    }
+                    match future::select(read.as_mut(), in_flight.next()).await {
+                        Either::Left((part_data, _)) => break part_data?,
+                        Either::Right((Some(result), _)) => state.add_completed(result?),
+                        Either::Right((None, _)) => unreachable!("no uploads in flight"),
+                    }
+                };
+                if part_data.is_empty() && part_number > 1 {
+                    end_of_data = true;
+                    break;
+                }
+                if (part_data.len() as u64) < state.part_size {
+                    end_of_data = true;
+                }
+                if part_number > S3_MULTIPART_MAX_PARTS {
+                    bail!("object data exceeds maximum number of {S3_MULTIPART_MAX_PARTS} parts");
+                }
+
+                if !state.is_completed(part_number) {
+                    in_flight.push(self.upload_part_with_retry(
+                        state.object_key.clone(),
+                        state.upload_id.clone(),
+                        part_number,
+                        part_data,
+                    ));
+                }
+                part_number += 1;
+            }
+
+            match in_flight.next().await {
+                Some(result) => state.add_completed(result?),
+                None => break,
+            }
+        }
+
+        self.complete_multipart_upload(
+            state.object_key.clone(),
+            &state.upload_id,
+            &state.completed_parts,
+        )
+        .await
+    }
+
+    /// Helper to upload a part of a multipart upload, retrying up to 3 times in case of error.
+    async fn upload_part_with_retry(
+        &self,
+        object_key: S3ObjectKey,
+        upload_id: String,
+        part_number: u32,
+        part_data: Bytes,
+    ) -> Result<CompletedPart, Error> {
+        let timeout = Self::upload_timeout(part_data.len() as u64);
+        for retry in 0..MAX_S3_UPLOAD_RETRY {
+            let body = Body::from(part_data.clone());
+            match self
+                .upload_part(object_key.clone(), &upload_id, part_number, body, timeout)
+                .await
+            {
+                Ok(e_tag) => return Ok(CompletedPart { part_number, e_tag }),
+                Err(err) => {
+                    if retry >= MAX_S3_UPLOAD_RETRY - 1 {
+                        return Err(err.context(format!("upload of part {part_number} failed")));
+                    }
+                }
+            }
+        }
+        bail!("upload of part {part_number} failed exceeding retries");
+    }
+
+    /// Abort all multipart uploads matching the given prefix which were initiated more than
+    /// `max_age` ago, e.g. left behind by interrupted uploads.
+    ///
+    /// Returns the number of aborted uploads. Failing to abort individual uploads is logged, but
+    /// not considered fatal.
+    pub async fn abort_stale_multipart_uploads(
+        &self,
+        prefix: &S3PathPrefix,
+        max_age: Duration,
+    ) -> Result<usize, Error> {
+        let cutoff = proxmox_time::epoch_i64() - max_age.as_secs() as i64;
+        let mut key_marker: Option<String> = None;
+        let mut upload_id_marker: Option<String> = None;
+        let mut aborted = 0;
+        loop {
+            let response = self
+                .list_multipart_uploads(prefix, key_marker.as_deref(), upload_id_marker.as_deref())
+                .await?;
+
+            for upload in response.upload {
+                let initiated = match upload.initiated.to_epoch() {
+                    Ok(initiated) => initiated,
+                    Err(err) => {
+                        error!("skipping multipart upload {}: {err:#}", upload.upload_id);
+                        continue;
+                    }
+                };
+                if initiated >= cutoff {
+                    continue;
+                }
+                match self
+                    .abort_multipart_upload(upload.key, &upload.upload_id)
+                    .await
+                {
+                    Ok(()) => aborted += 1,
+                    Err(err) => error!(
+                        "failed to abort stale multipart upload {}: {err:#}",
+                        upload.upload_id
+                    ),
+                }
+            }
+
+            if response.is_truncated {
+                key_marker = response.next_key_marker;
+                upload_id_marker = response.next_upload_id_marker;
+                continue;
+            }
+            break;
+        }
+        Ok(aborted)
+    }
+
+    /// Fail early if the provider does not support object versioning.
+    fn check_versioning_support(&self) -> Result<(), Error> {
+        if self
+            .options
+            .provider_quirks
+            .contains(&ProviderQuirks::NoObjectVersioning)
+        {
+            bail!("object versioning is not supported by the provider");
+        }
+        Ok(())
+    }
+
+    /// Fail early if the provider does not support object lock, instead of possibly storing
+    /// objects without the requested protection.
+    fn check_object_lock_support(&self) -> Result<(), Error> {
+        if self
+            .options
+            .provider_quirks
+            .contains(&ProviderQuirks::NoObjectLock)
+        {
+            bail!("object lock is not supported by the provider");
+        }
+        Ok(())
+    }
+
+    /// Add the server-side encryption headers for requests storing object data, if configured.
+    fn with_encryption_headers(&self, request: Builder) -> Builder {
+        match &self.encryption {
+            Some(encryption) => encryption.add_store_headers(request),
+            None => request,
+        }
+    }
+
+    /// Add the customer key headers for requests accessing object data, if server-side
+    /// encryption with customer provided keys is configured.
+    fn with_customer_key_headers(&self, request: Builder) -> Builder {
+        match &self.encryption {
+            Some(encryption) => encryption.add_customer_key_headers(request),
+            None => request,
+        }
+    }
+
     #[inline(always)]
     /// Helper to generate [`Uri`] instance with common properties based on given path and query.
     fn build_uri(&self, mut path: &str, query: &[(&str, &str)]) -> Result<Uri, Error> {
@@ -975,3 +1691,45 @@ impl S3Client {
         Uri::from_parts(uri_parts).context("failed to build uri")
     }
 }
+
+#[test]
+fn client_options_from_conf_test() {
+    let conf: S3ClientConf = serde_json::from_value(serde_json::json!({
+        "id": "test",
+        "endpoint": "localhost",
+        "access-key": "access",
+        "secret-key": "secret",
+        "encryption": "sse-c",
+        "sse-customer-key": proxmox_base64::encode([0u8; 32]),
+    }))
+    .unwrap();
+
+    let options = S3ClientOptions::from_conf(conf.clone(), None, String::new(), None, None, None);
+    assert_eq!(options.sse_customer_key, conf.sse_customer_key);
+    let client = S3Client::new(options).unwrap();
+
+    // Presigning would leak the customer key to whoever gets the URL
+    let object_key = S3ObjectKey::try_from("object").unwrap();
+    assert!(
+        client
+            .presign_get_object(object_key.clone(), Duration::from_secs(60))
+            .is_err()
+    );
+    assert!(
+        client
+            .presign_put_object(object_key, Duration::from_secs(60))
+            .is_err()
+    );
+
+    // The configuration alone lacks the customer key
+    let options = S3ClientOptions::from_config(
+        conf.config,
+        conf.secret_key,
+        None,
+        String::new(),
+        None,
+        None,
+        None,
+    );
+    assert!(S3Client::new(options).is_err());
+}
@@ -0,0 +1,230 @@
+use anyhow::{Context, Error, bail};
+use hyper::http::HeaderValue;
+use hyper::http::request::Builder;
+
+use crate::api_types::ServerSideEncryptionMode;
+
+const SSE_HEADER: &str = "x-amz-server-side-encryption";
+const SSE_KMS_KEY_ID_HEADER: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+const SSE_CUSTOMER_ALGORITHM_HEADER: &str = "x-amz-server-side-encryption-customer-algorithm";
+const SSE_CUSTOMER_KEY_HEADER: &str = "x-amz-server-side-encryption-customer-key";
+const SSE_CUSTOMER_KEY_MD5_HEADER: &str = "x-amz-server-side-encryption-customer-key-md5";
+const COPY_SOURCE_SSE_CUSTOMER_ALGORITHM_HEADER: &str =
+    "x-amz-copy-source-server-side-encryption-customer-algorithm";
+const COPY_SOURCE_SSE_CUSTOMER_KEY_HEADER: &str =
+    "x-amz-copy-source-server-side-encryption-customer-key";
+const COPY_SOURCE_SSE_CUSTOMER_KEY_MD5_HEADER: &str =
+    "x-amz-copy-source-server-side-encryption-customer-key-md5";
+
+const SSE_ALGORITHM_AES256: &str = "AES256";
+const SSE_ALGORITHM_KMS: &str = "aws:kms";
+const SSE_CUSTOMER_KEY_LENGTH: usize = 32;
+
+/// Server-side encryption parameters, sent as http headers along with the requests.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/userguide/serv-side-encryption.html
+pub(crate) enum ServerSideEncryption {
+    /// Encryption with keys managed by the object store.
+    S3,
+    /// Encryption with the given or the default key of the key management service.
+    Kms { key_id: Option<HeaderValue> },
+    /// Encryption with a customer provided key, given base64 encoded together with its MD5
+    /// digest for integrity checking.
+    Customer {
+        key: HeaderValue,
+        key_md5: HeaderValue,
+    },
+}
+
+impl ServerSideEncryption {
+    /// Check the encryption parameters for consistency and prepare the header values.
+    ///
+    /// Returns `None` if no server-side encryption mode is set.
+    pub(crate) fn new(
+        mode: Option<ServerSideEncryptionMode>,
+        kms_key_id: Option<&str>,
+        customer_key: Option<&str>,
+    ) -> Result<Option<Self>, Error> {
+        if kms_key_id.is_some() && mode != Some(ServerSideEncryptionMode::SseKms) {
+            bail!("KMS key id requires server-side encryption mode 'sse-kms'");
+        }
+        if customer_key.is_some() && mode != Some(ServerSideEncryptionMode::SseC) {
+            bail!("customer provided key requires server-side encryption mode 'sse-c'");
+        }
+
+        let encryption = match mode {
+            None => return Ok(None),
+            Some(ServerSideEncryptionMode::SseS3) => Self::S3,
+            Some(ServerSideEncryptionMode::SseKms) => Self::Kms {
+                key_id: kms_key_id.map(HeaderValue::from_str).transpose()?,
+            },
+            Some(ServerSideEncryptionMode::SseC) => {
+                let encoded = match customer_key {
+                    Some(key) => key,
+                    None => bail!("server-side encryption mode 'sse-c' requires a customer key"),
+                };
+                let key = proxmox_base64::decode(encoded)
+                    .context("invalid customer key for server-side encryption")?;
+                if key.len() != SSE_CUSTOMER_KEY_LENGTH {
+                    bail!(
+                        "invalid customer key for server-side encryption, expected {} bits, got {}",
+                        SSE_CUSTOMER_KEY_LENGTH * 8,
+                        key.len() * 8,
+                    );
+                }
+                let key_md5 = proxmox_base64::encode(*md5::compute(&key));
+                let mut key = HeaderValue::from_str(encoded)?;
+                key.set_sensitive(true);
+                Self::Customer {
+                    key,
+                    key_md5: HeaderValue::from_str(&key_md5)?,
+                }
+            }
+        };
+
+        Ok(Some(encryption))
+    }
+
+    /// Add the headers to requests storing object data, e.g. put object, copy object or create
+    /// multipart upload requests.
+    pub(crate) fn add_store_headers(&self, request: Builder) -> Builder {
+        match self {
+            Self::S3 => request.header(SSE_HEADER, SSE_ALGORITHM_AES256),
+            Self::Kms { key_id } => {
+                let request = request.header(SSE_HEADER, SSE_ALGORITHM_KMS);
+                match key_id {
+                    Some(key_id) => request.header(SSE_KMS_KEY_ID_HEADER, key_id.clone()),
+                    None => request,
+                }
+            }
+            Self::Customer { .. } => self.add_customer_key_headers(request),
+        }
+    }
+
+    /// Add the headers required to access data encrypted with a customer provided key, e.g. for
+    /// get object, head object or upload part requests. No-op for other encryption modes, for
+    /// which the object store handles decryption transparently.
+    pub(crate) fn add_customer_key_headers(&self, request: Builder) -> Builder {
+        match self {
+            Self::Customer { key, key_md5 } => request
+                .header(SSE_CUSTOMER_ALGORITHM_HEADER, SSE_ALGORITHM_AES256)
+                .header(SSE_CUSTOMER_KEY_HEADER, key.clone())
+                .header(SSE_CUSTOMER_KEY_MD5_HEADER, key_md5.clone()),
+            _ => request,
+        }
+    }
+
+    /// Add the headers required to read the source object of copy object requests if it is
+    /// encrypted with a customer provided key.
+    pub(crate) fn add_copy_source_headers(&self, request: Builder) -> Builder {
+        match self {
+            Self::Customer { key, key_md5 } => request
+                .header(
+                    COPY_SOURCE_SSE_CUSTOMER_ALGORITHM_HEADER,
+                    SSE_ALGORITHM_AES256,
+                )
+                .header(COPY_SOURCE_SSE_CUSTOMER_KEY_HEADER, key.clone())
+                .header(COPY_SOURCE_SSE_CUSTOMER_KEY_MD5_HEADER, key_md5.clone()),
+            _ => request,
+        }
+    }
+}
+
+#[test]
+fn server_side_encryption_headers_test() {
+    use hyper::Request;
+
+    let headers = |encryption: &ServerSideEncryption| {
+        let request = encryption.add_store_headers(Request::builder());
+        let request = encryption.add_copy_source_headers(request);
+        let request = request.body(()).unwrap();
+        let mut headers: Vec<(String, String)> = request
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_str().to_string(),
+                    value.to_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        headers.sort();
+        headers
+    };
+
+    assert!(
+        ServerSideEncryption::new(None, None, None)
+            .unwrap()
+            .is_none()
+    );
+
+    let encryption = ServerSideEncryption::new(Some(ServerSideEncryptionMode::SseS3), None, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        headers(&encryption),
+        vec![(SSE_HEADER.to_string(), "AES256".to_string())],
+    );
+
+    let encryption = ServerSideEncryption::new(
+        Some(ServerSideEncryptionMode::SseKms),
+        Some("alias/backup"),
+        None,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        headers(&encryption),
+        vec![
+            (SSE_HEADER.to_string(), "aws:kms".to_string()),
+            (
+                SSE_KMS_KEY_ID_HEADER.to_string(),
+                "alias/backup".to_string()
+            ),
+        ],
+    );
+
+    // 32 zero bytes
+    let key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+    let encryption =
+        ServerSideEncryption::new(Some(ServerSideEncryptionMode::SseC), None, Some(key))
+            .unwrap()
+            .unwrap();
+    let key_md5 = "cLyPS3KoaSFGi/joRB3OUQ==";
+    assert_eq!(
+        headers(&encryption),
+        vec![
+            (
+                COPY_SOURCE_SSE_CUSTOMER_ALGORITHM_HEADER.to_string(),
+                "AES256".to_string()
+            ),
+            (
+                COPY_SOURCE_SSE_CUSTOMER_KEY_HEADER.to_string(),
+                key.to_string()
+            ),
+            (
+                COPY_SOURCE_SSE_CUSTOMER_KEY_MD5_HEADER.to_string(),
+                key_md5.to_string()
+            ),
+            (
+                SSE_CUSTOMER_ALGORITHM_HEADER.to_string(),
+                "AES256".to_string()
+            ),
+            (SSE_CUSTOMER_KEY_HEADER.to_string(), key.to_string()),
+            (SSE_CUSTOMER_KEY_MD5_HEADER.to_string(), key_md5.to_string()),
+        ],
+    );
+
+    assert!(ServerSideEncryption::new(Some(ServerSideEncryptionMode::SseC), None, None).is_err());
+    assert!(
+        ServerSideEncryption::new(Some(ServerSideEncryptionMode::SseC), None, Some("AAAA"))
+            .is_err()
+    );
+    assert!(
+        ServerSideEncryption::new(
+            Some(ServerSideEncryptionMode::SseS3),
+            Some("alias/backup"),
+            None
+        )
+        .is_err()
+    );
+}
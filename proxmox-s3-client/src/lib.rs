@@ -21,21 +21,48 @@ pub use aws_sign_v4::uri_decode;
 mod client;
 #[cfg(feature = "impl")]
 pub use client::{
-    S3_HTTP_REQUEST_TIMEOUT, S3Client, S3ClientOptions, S3PathPrefix, S3RateLimiterOptions,
-    S3RequestCounterConfig,
+    PresignedRequest, PutObjectOptions, S3_HTTP_REQUEST_TIMEOUT, S3_PRESIGNED_URL_MAX_EXPIRY,
+    S3Client, S3ClientOptions, S3PathPrefix, S3RateLimiterOptions, S3RequestCounterConfig,
 };
 #[cfg(feature = "impl")]
+mod encryption;
+#[cfg(feature = "impl")]
 mod timestamps;
 #[cfg(feature = "impl")]
 pub use timestamps::*;
+#[cfg(feature = "mock-server")]
+mod mock_server;
+#[cfg(feature = "mock-server")]
+pub use mock_server::{
+    MOCK_S3_ACCESS_KEY, MOCK_S3_REGION, MOCK_S3_SECRET_KEY, MockFault, MockS3Server,
+};
+#[cfg(feature = "impl")]
+mod multipart;
+#[cfg(feature = "impl")]
+pub use multipart::{
+    CompletedPart, MultipartUploadOptions, MultipartUploadState, S3_MULTIPART_MAX_PART_SIZE,
+    S3_MULTIPART_MAX_PARTS, S3_MULTIPART_MIN_PART_SIZE,
+};
 #[cfg(feature = "impl")]
 mod object_key;
 #[cfg(feature = "impl")]
 pub use object_key::S3ObjectKey;
 #[cfg(feature = "impl")]
+mod object_lock;
+#[cfg(feature = "impl")]
+pub use object_lock::{ObjectLockMode, ObjectLockRetention};
+#[cfg(feature = "impl")]
+mod object_reader;
+#[cfg(feature = "impl")]
+pub use object_reader::{GetObjectOptions, S3ByteRange, S3ObjectReader};
+#[cfg(feature = "impl")]
 mod response_reader;
 #[cfg(feature = "impl")]
-pub use response_reader::DeleteObjectError;
+pub use response_reader::{
+    CompleteMultipartUploadResponse, ConditionalGetObjectResponse, ContentRange,
+    CreateMultipartUploadResponse, DeleteMarkerEntry, DeleteObjectError,
+    ListMultipartUploadsResponse, ListObjectVersionsResponse, MultipartUpload, ObjectVersion,
+};
 #[cfg(feature = "impl")]
 mod shared_request_counters;
 #[cfg(feature = "impl")]
@@ -0,0 +1,241 @@
+use anyhow::{Error, bail};
+use hyper::body::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::S3ObjectKey;
+
+/// Minimum size of all but the last part of a multipart upload.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/userguide/qfacts.html
+pub const S3_MULTIPART_MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+/// Maximum size of a single part of a multipart upload.
+pub const S3_MULTIPART_MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Maximum number of parts of a multipart upload.
+pub const S3_MULTIPART_MAX_PARTS: u32 = 10_000;
+
+const S3_MULTIPART_DEFAULT_PART_SIZE: u64 = 16 * 1024 * 1024;
+const S3_MULTIPART_DEFAULT_CONCURRENCY: usize = 4;
+
+/// Options for streaming multipart uploads.
+///
+/// At most `part_size * concurrency` bytes are buffered in memory at any time.
+pub struct MultipartUploadOptions {
+    /// Size of each part in bytes, except for the last one.
+    pub part_size: u64,
+    /// Maximum number of parts uploaded in parallel.
+    pub concurrency: usize,
+}
+
+impl Default for MultipartUploadOptions {
+    fn default() -> Self {
+        Self {
+            part_size: S3_MULTIPART_DEFAULT_PART_SIZE,
+            concurrency: S3_MULTIPART_DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+impl MultipartUploadOptions {
+    /// Check the options against the limits imposed by the S3 api.
+    pub fn check(&self) -> Result<(), Error> {
+        if !(S3_MULTIPART_MIN_PART_SIZE..=S3_MULTIPART_MAX_PART_SIZE).contains(&self.part_size) {
+            bail!(
+                "part size {} out of range ({S3_MULTIPART_MIN_PART_SIZE} - {S3_MULTIPART_MAX_PART_SIZE})",
+                self.part_size,
+            );
+        }
+        if self.concurrency == 0 {
+            bail!("concurrency must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Part of a multipart upload which was uploaded successfully.
+pub struct CompletedPart {
+    /// Part number, starting from 1.
+    pub part_number: u32,
+    /// Entity tag returned for the uploaded part.
+    pub e_tag: String,
+}
+
+/// (De)serialize object keys preserving the variant, in the form accepted by
+/// `S3ObjectKey::try_from`, with full keys being prefixed by a slash.
+///
+/// The `Deserialize` implementation of `S3ObjectKey` is meant for keys as returned by the S3 api
+/// and always yields full keys.
+mod object_key_variant {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::S3ObjectKey;
+
+    pub(super) fn serialize<S: Serializer>(
+        object_key: &S3ObjectKey,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match object_key {
+            S3ObjectKey::Full(key) => serializer.serialize_str(&format!("/{key}")),
+            S3ObjectKey::Relative(key) => serializer.serialize_str(key),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<S3ObjectKey, D::Error> {
+        let object_key = std::borrow::Cow::<'de, str>::deserialize(deserializer)?;
+        S3ObjectKey::try_from(object_key.as_ref()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+/// State of an in-progress multipart upload.
+///
+/// The state is updated as parts are uploaded and can be persisted by the caller, e.g. after a
+/// failed upload, to resume the upload later on via [`S3Client::upload_multipart`].
+///
+/// [`S3Client::upload_multipart`]: crate::S3Client::upload_multipart
+pub struct MultipartUploadState {
+    /// Full object key of the object being uploaded.
+    #[serde(with = "object_key_variant")]
+    pub object_key: S3ObjectKey,
+    /// ID of the multipart upload.
+    pub upload_id: String,
+    /// Size of each part in bytes, except for the last one.
+    pub part_size: u64,
+    /// Parts uploaded so far, sorted by part number.
+    pub completed_parts: Vec<CompletedPart>,
+}
+
+impl MultipartUploadState {
+    /// Check if the part with given number has already been uploaded.
+    pub fn is_completed(&self, part_number: u32) -> bool {
+        self.completed_parts
+            .binary_search_by_key(&part_number, |part| part.part_number)
+            .is_ok()
+    }
+
+    /// Number of the first part which has not been uploaded yet.
+    pub fn first_missing_part(&self) -> u32 {
+        let mut part_number = 1;
+        for part in &self.completed_parts {
+            if part.part_number != part_number {
+                break;
+            }
+            part_number += 1;
+        }
+        part_number
+    }
+
+    /// Offset in the object data at which a resumed upload has to continue reading.
+    pub fn resume_offset(&self) -> u64 {
+        u64::from(self.first_missing_part() - 1) * self.part_size
+    }
+
+    /// Record a successfully uploaded part, replacing a previous upload of the same part.
+    pub(crate) fn add_completed(&mut self, part: CompletedPart) {
+        match self
+            .completed_parts
+            .binary_search_by_key(&part.part_number, |part| part.part_number)
+        {
+            Ok(pos) => self.completed_parts[pos] = part,
+            Err(pos) => self.completed_parts.insert(pos, part),
+        }
+    }
+}
+
+/// Generate the request body for the complete multipart upload api call.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/API/API_CompleteMultipartUpload.html#API_CompleteMultipartUpload_RequestSyntax
+pub(crate) fn complete_multipart_upload_body(parts: &[CompletedPart]) -> String {
+    let mut body = String::from(
+        r#"<CompleteMultipartUpload xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+    );
+    for part in parts {
+        body.push_str("<Part><PartNumber>");
+        body.push_str(&part.part_number.to_string());
+        body.push_str("</PartNumber><ETag>");
+        body.push_str(&part.e_tag);
+        body.push_str("</ETag></Part>");
+    }
+    body.push_str("</CompleteMultipartUpload>");
+    body
+}
+
+/// Read the next part of up to `part_size` bytes from the reader.
+///
+/// Returns less than `part_size` bytes only if the end of the reader was reached.
+pub(crate) async fn read_part<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    part_size: u64,
+) -> Result<Bytes, Error> {
+    let mut buffer = Vec::with_capacity(part_size as usize);
+    reader.take(part_size).read_to_end(&mut buffer).await?;
+    Ok(Bytes::from(buffer))
+}
+
+#[test]
+fn multipart_upload_state_test() {
+    let mut state = MultipartUploadState {
+        object_key: S3ObjectKey::try_from("/teststore/object0").unwrap(),
+        upload_id: "upload0".to_string(),
+        part_size: S3_MULTIPART_MIN_PART_SIZE,
+        completed_parts: Vec::new(),
+    };
+    assert_eq!(state.first_missing_part(), 1);
+    assert_eq!(state.resume_offset(), 0);
+
+    for part_number in [3, 1, 2, 5] {
+        state.add_completed(CompletedPart {
+            part_number,
+            e_tag: format!("\"etag{part_number}\""),
+        });
+    }
+    state.add_completed(CompletedPart {
+        part_number: 2,
+        e_tag: "\"etag2-retry\"".to_string(),
+    });
+
+    let part_numbers: Vec<u32> = state
+        .completed_parts
+        .iter()
+        .map(|p| p.part_number)
+        .collect();
+    assert_eq!(part_numbers, vec![1, 2, 3, 5]);
+    assert_eq!(state.completed_parts[1].e_tag, "\"etag2-retry\"");
+    assert!(state.is_completed(5));
+    assert!(!state.is_completed(4));
+    assert_eq!(state.first_missing_part(), 4);
+    assert_eq!(state.resume_offset(), 3 * S3_MULTIPART_MIN_PART_SIZE);
+
+    let serialized = serde_json::to_string(&state).unwrap();
+    let deserialized: MultipartUploadState = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.object_key, state.object_key);
+    assert_eq!(deserialized.completed_parts, state.completed_parts);
+
+    state.object_key = S3ObjectKey::try_from("object0").unwrap();
+    let serialized = serde_json::to_string(&state).unwrap();
+    let deserialized: MultipartUploadState = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.object_key, state.object_key);
+
+    assert_eq!(
+        complete_multipart_upload_body(&state.completed_parts[..1]),
+        r#"<CompleteMultipartUpload xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Part><PartNumber>1</PartNumber><ETag>"etag1"</ETag></Part></CompleteMultipartUpload>"#
+    );
+}
+
+#[test]
+fn multipart_options_check_test() {
+    assert!(MultipartUploadOptions::default().check().is_ok());
+    let options = MultipartUploadOptions {
+        part_size: 1024,
+        concurrency: 1,
+    };
+    assert!(options.check().is_err());
+    let options = MultipartUploadOptions {
+        concurrency: 0,
+        ..Default::default()
+    };
+    assert!(options.check().is_err());
+}
@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use anyhow::Error;
+use hyper::http::request::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::LastModifiedTimestamp;
+
+pub(crate) const OBJECT_LOCK_MODE_HEADER: &str = "x-amz-object-lock-mode";
+pub(crate) const OBJECT_LOCK_RETAIN_UNTIL_DATE_HEADER: &str = "x-amz-object-lock-retain-until-date";
+pub(crate) const OBJECT_LOCK_LEGAL_HOLD_HEADER: &str = "x-amz-object-lock-legal-hold";
+pub(crate) const BYPASS_GOVERNANCE_RETENTION_HEADER: &str = "x-amz-bypass-governance-retention";
+
+const RETAIN_UNTIL_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+/// Object lock retention mode.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/userguide/object-lock.html#object-lock-retention-modes
+pub enum ObjectLockMode {
+    /// Object version can be overwritten or deleted by users with special permissions only.
+    Governance,
+    /// Object version cannot be overwritten or deleted by any user until the retention period
+    /// expired.
+    Compliance,
+}
+serde_plain::derive_display_from_serialize!(ObjectLockMode);
+serde_plain::derive_fromstr_from_deserialize!(ObjectLockMode);
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+/// Object lock legal hold status as used in http headers.
+pub(crate) enum LegalHoldStatus {
+    On,
+    Off,
+}
+serde_plain::derive_display_from_serialize!(LegalHoldStatus);
+serde_plain::derive_fromstr_from_deserialize!(LegalHoldStatus);
+
+#[derive(Clone, Debug, PartialEq)]
+/// Object lock retention of an object version.
+pub struct ObjectLockRetention {
+    /// Retention mode.
+    pub mode: ObjectLockMode,
+    /// End of the retention period, as seconds since the UNIX epoch.
+    pub retain_until: i64,
+}
+
+impl ObjectLockRetention {
+    /// Add the object lock retention headers to the request.
+    pub(crate) fn add_headers(&self, request: Builder) -> Result<Builder, Error> {
+        let retain_until = proxmox_time::strftime_utc(RETAIN_UNTIL_DATE_FORMAT, self.retain_until)?;
+        Ok(request
+            .header(OBJECT_LOCK_MODE_HEADER, self.mode.to_string())
+            .header(OBJECT_LOCK_RETAIN_UNTIL_DATE_HEADER, retain_until))
+    }
+}
+
+/// Object lock retain until date as obtained from API response http headers.
+pub(crate) struct RetainUntilDate(pub(crate) i64);
+
+impl FromStr for RetainUntilDate {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self(LastModifiedTimestamp::from_str(value)?.to_epoch()?))
+    }
+}
+
+#[test]
+fn object_lock_retention_headers_test() {
+    use hyper::Request;
+
+    let retention = ObjectLockRetention {
+        mode: ObjectLockMode::Compliance,
+        retain_until: 1298685380,
+    };
+    let request = retention
+        .add_headers(Request::builder())
+        .unwrap()
+        .body(())
+        .unwrap();
+    assert_eq!(
+        request.headers().get(OBJECT_LOCK_MODE_HEADER).unwrap(),
+        "COMPLIANCE"
+    );
+    assert_eq!(
+        request
+            .headers()
+            .get(OBJECT_LOCK_RETAIN_UNTIL_DATE_HEADER)
+            .unwrap(),
+        "2011-02-26T01:56:20Z"
+    );
+
+    let retain_until = RetainUntilDate::from_str("2011-02-26T01:56:20.000Z").unwrap();
+    assert_eq!(retain_until.0, retention.retain_until);
+    assert_eq!(
+        ObjectLockMode::from_str("GOVERNANCE").unwrap(),
+        ObjectLockMode::Governance
+    );
+    assert_eq!(LegalHoldStatus::On.to_string(), "ON");
+}
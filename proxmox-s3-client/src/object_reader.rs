@@ -0,0 +1,283 @@
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use anyhow::{Error, bail};
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+use crate::response_reader::ConditionalGetObjectResponse;
+use crate::{HttpDate, S3Client, S3ObjectKey};
+
+const S3_OBJECT_READER_DEFAULT_READ_AHEAD: usize = 4 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+/// Byte range of an object to fetch.
+/// See https://www.rfc-editor.org/rfc/rfc9110#name-byte-ranges
+pub enum S3ByteRange {
+    /// Bytes within the given range, end exclusive.
+    Range(Range<u64>),
+    /// All bytes starting at the given offset.
+    From(u64),
+    /// The given number of bytes at the end of the object.
+    Suffix(u64),
+}
+
+impl S3ByteRange {
+    /// Range header value requesting this byte range.
+    pub(crate) fn header_value(&self) -> Result<String, Error> {
+        match self {
+            Self::Range(range) => {
+                if range.is_empty() {
+                    bail!("empty byte range {range:?}");
+                }
+                Ok(format!("bytes={}-{}", range.start, range.end - 1))
+            }
+            Self::From(start) => Ok(format!("bytes={start}-")),
+            Self::Suffix(0) => bail!("empty suffix byte range"),
+            Self::Suffix(length) => Ok(format!("bytes=-{length}")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Options for ranged and conditional get object requests.
+/// See https://docs.aws.amazon.com/AmazonS3/latest/API/API_GetObject.html#API_GetObject_RequestSyntax
+pub struct GetObjectOptions {
+    /// Fetch the given version of the object instead of the latest one.
+    pub version_id: Option<String>,
+    /// Only fetch the given byte range of the object.
+    pub range: Option<S3ByteRange>,
+    /// Only return the object if its entity tag matches the given one.
+    pub if_match: Option<String>,
+    /// Only return the object if its entity tag does not match the given one.
+    pub if_none_match: Option<String>,
+    /// Only return the object if it has been modified since the given time.
+    pub if_modified_since: Option<HttpDate>,
+    /// Only return the object if it has not been modified since the given time.
+    pub if_unmodified_since: Option<HttpDate>,
+}
+
+type FetchFuture = Pin<Box<dyn Future<Output = Result<Bytes, Error>> + Send>>;
+
+/// Seekable reader for the contents of an object.
+///
+/// Data is fetched on demand via ranged get object requests, reading ahead by a configurable
+/// number of bytes. All requests are conditional on the entity tag the object had when the
+/// reader was created, so modifications of the object while reading are detected and reported
+/// as errors instead of returning inconsistent data.
+///
+/// Only the async [`AsyncRead`] and [`AsyncSeek`] interfaces are provided, since blocking on the
+/// requests from within the async runtime the client is used in would stall or deadlock it.
+/// For the same reason this is not built on `proxmox_io::RangeReader`, which requires a blocking
+/// `Read + Seek` source. To read a part of the object, seek to its start and limit the reader via
+/// [`AsyncReadExt::take`](tokio::io::AsyncReadExt::take).
+pub struct S3ObjectReader {
+    client: Arc<S3Client>,
+    object_key: S3ObjectKey,
+    size: u64,
+    e_tag: String,
+    read_ahead: usize,
+    position: u64,
+    buffer: Bytes,
+    buffer_start: u64,
+    pending: Option<(u64, FetchFuture)>,
+}
+
+impl S3ObjectReader {
+    /// Create a reader for the object with given key.
+    ///
+    /// Returns `None` if the object does not exist.
+    pub async fn new(
+        client: Arc<S3Client>,
+        object_key: S3ObjectKey,
+    ) -> Result<Option<Self>, Error> {
+        let head = match client.head_object(object_key.clone()).await? {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            client,
+            object_key,
+            size: head.content_length,
+            e_tag: head.e_tag,
+            read_ahead: S3_OBJECT_READER_DEFAULT_READ_AHEAD,
+            position: 0,
+            buffer: Bytes::new(),
+            buffer_start: 0,
+            pending: None,
+        }))
+    }
+
+    /// Set the minimum number of bytes fetched per request.
+    pub fn with_read_ahead(mut self, read_ahead: usize) -> Self {
+        self.read_ahead = read_ahead.max(1);
+        self
+    }
+
+    /// Size of the object in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Entity tag of the object being read.
+    pub fn e_tag(&self) -> &str {
+        &self.e_tag
+    }
+
+    /// Object key of the object being read.
+    pub fn object_key(&self) -> &S3ObjectKey {
+        &self.object_key
+    }
+
+    async fn fetch(
+        client: Arc<S3Client>,
+        object_key: S3ObjectKey,
+        e_tag: String,
+        range: Range<u64>,
+    ) -> Result<Bytes, Error> {
+        let options = GetObjectOptions {
+            range: Some(S3ByteRange::Range(range.clone())),
+            if_match: Some(e_tag),
+            ..Default::default()
+        };
+
+        let response = match client.get_object_with_options(object_key, &options).await? {
+            Some(ConditionalGetObjectResponse::Success(response)) => response,
+            Some(ConditionalGetObjectResponse::PreconditionFailed) => {
+                bail!("object was modified while reading")
+            }
+            Some(ConditionalGetObjectResponse::NotModified) => {
+                bail!("unexpected not modified response")
+            }
+            None => bail!("object was removed while reading"),
+        };
+
+        // Check the range before reading the body, to not download unrequested data
+        match &response.content_range {
+            Some(content_range) if content_range.range.start == range.start => (),
+            Some(content_range) => bail!(
+                "got unexpected content range {:?}, expected {range:?}",
+                content_range.range,
+            ),
+            // A server ignoring the range returns the full object, which is only acceptable if
+            // that was requested anyway, as it would otherwise be fetched again for every read.
+            None if range.start == 0 && response.content_length == range.end => (),
+            None => bail!("server ignored the range request for {range:?}"),
+        }
+
+        Ok(response.content.collect().await?.to_bytes())
+    }
+}
+
+impl AsyncRead for S3ObjectReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.position >= this.size || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let buffer_end = this.buffer_start + this.buffer.len() as u64;
+            if (this.buffer_start..buffer_end).contains(&this.position) {
+                let offset = (this.position - this.buffer_start) as usize;
+                let count = buf.remaining().min(this.buffer.len() - offset);
+                buf.put_slice(&this.buffer[offset..offset + count]);
+                this.position += count as u64;
+                return Poll::Ready(Ok(()));
+            }
+
+            // Discard requests for an outdated position, e.g. after seeking
+            if this
+                .pending
+                .as_ref()
+                .is_none_or(|(start, _)| *start != this.position)
+            {
+                let length = this.read_ahead.max(buf.remaining()) as u64;
+                let end = this.position.saturating_add(length).min(this.size);
+                let future = Self::fetch(
+                    Arc::clone(&this.client),
+                    this.object_key.clone(),
+                    this.e_tag.clone(),
+                    this.position..end,
+                );
+                this.pending = Some((this.position, Box::pin(future)));
+            }
+
+            let (start, future) = this.pending.as_mut().unwrap();
+            let result = match future.as_mut().poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            let start = *start;
+            this.pending = None;
+
+            let data = result.map_err(io::Error::other)?;
+            if data.is_empty() {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "got empty response for object range",
+                )));
+            }
+            this.buffer_start = start;
+            this.buffer = data;
+        }
+    }
+}
+
+impl AsyncSeek for S3ObjectReader {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => this.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.position.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) => {
+                this.position = position;
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+#[test]
+fn byte_range_header_value_test() {
+    assert_eq!(
+        S3ByteRange::Range(0..500).header_value().unwrap(),
+        "bytes=0-499"
+    );
+    assert_eq!(
+        S3ByteRange::Range(500..501).header_value().unwrap(),
+        "bytes=500-500"
+    );
+    assert_eq!(
+        S3ByteRange::From(9500).header_value().unwrap(),
+        "bytes=9500-"
+    );
+    assert_eq!(
+        S3ByteRange::Suffix(500).header_value().unwrap(),
+        "bytes=-500"
+    );
+    assert!(S3ByteRange::Range(10..10).header_value().is_err());
+    assert!(S3ByteRange::Suffix(0).header_value().is_err());
+}
@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -13,8 +14,14 @@ use hyper::http::header;
 use hyper::{HeaderMap, Response};
 use serde::Deserialize;
 
+use crate::object_lock::{
+    LegalHoldStatus, OBJECT_LOCK_LEGAL_HOLD_HEADER, OBJECT_LOCK_MODE_HEADER,
+    OBJECT_LOCK_RETAIN_UNTIL_DATE_HEADER, ObjectLockMode, ObjectLockRetention, RetainUntilDate,
+};
 use crate::{HttpDate, LastModifiedTimestamp, S3ObjectKey, SharedRequestCounters};
 
+const VERSION_ID_HEADER: &str = "x-amz-version-id";
+
 /// Response reader to check S3 api response status codes and parse response body, if any.
 pub(crate) struct ResponseReader {
     response: Response<Incoming>,
@@ -94,6 +101,12 @@ pub struct HeadObjectResponse {
     pub e_tag: String,
     /// Last modified http header.
     pub last_modified: HttpDate,
+    /// Version ID of the object, if versioning is enabled for the bucket.
+    pub version_id: Option<String>,
+    /// Object lock retention of the object version, if any.
+    pub object_lock_retention: Option<ObjectLockRetention>,
+    /// Object lock legal hold status of the object version, if reported.
+    pub object_lock_legal_hold: Option<bool>,
 }
 
 /// Response contents of the get object api call.
@@ -109,10 +122,197 @@ pub struct GetObjectResponse {
     pub e_tag: String,
     /// Last modified http header.
     pub last_modified: HttpDate,
+    /// Version ID of the object, if versioning is enabled for the bucket.
+    pub version_id: Option<String>,
+    /// Content range header, only present for partial content responses to ranged requests.
+    pub content_range: Option<ContentRange>,
     /// Object content in http response body.
     pub content: Content,
 }
 
+/// Variants to distinguish object fetch response states for conditional requests.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_GetObject.html#API_GetObject_RequestSyntax
+pub enum ConditionalGetObjectResponse {
+    /// Object was not returned because it was not modified according to the provided
+    /// pre-condition (If-None-Match or If-Modified-Since header).
+    NotModified,
+    /// Object was not returned because the provided pre-condition (If-Match or
+    /// If-Unmodified-Since header) failed.
+    PreconditionFailed,
+    /// Object (or the requested range of it) was fetched with success.
+    Success(GetObjectResponse),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Parsed content range header of a partial content response.
+/// https://www.rfc-editor.org/rfc/rfc9110#name-content-range
+pub struct ContentRange {
+    /// Byte range of the object contained in the response body, end exclusive.
+    pub range: Range<u64>,
+    /// Total size of the object, if known.
+    pub size: Option<u64>,
+}
+
+impl FromStr for ContentRange {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value
+            .strip_prefix("bytes ")
+            .ok_or_else(|| format_err!("unsupported range unit"))?;
+        let (range, size) = value
+            .split_once('/')
+            .ok_or_else(|| format_err!("missing complete length"))?;
+        let (start, last) = range
+            .split_once('-')
+            .ok_or_else(|| format_err!("invalid byte range '{range}'"))?;
+        let start: u64 = start.parse()?;
+        let last: u64 = last.parse()?;
+        if last < start {
+            bail!("invalid byte range '{range}'");
+        }
+        let size = match size {
+            "*" => None,
+            size => {
+                let size: u64 = size.parse()?;
+                if last >= size {
+                    bail!("byte range '{range}' exceeds complete length {size}");
+                }
+                Some(size)
+            }
+        };
+
+        Ok(Self {
+            range: start..last + 1,
+            size,
+        })
+    }
+}
+
+#[derive(Debug)]
+/// Response contents of list object versions api calls.
+pub struct ListObjectVersionsResponse {
+    /// Parsed http date header from response.
+    pub date: Option<HttpDate>,
+    /// Flag indication if response was truncated because of key limits.
+    pub is_truncated: bool,
+    /// Key marker to fetch the next set of versions for truncated responses.
+    pub next_key_marker: Option<String>,
+    /// Version ID marker to fetch the next set of versions for truncated responses.
+    pub next_version_id_marker: Option<String>,
+    /// List of object versions.
+    pub versions: Vec<ObjectVersion>,
+    /// List of delete markers.
+    pub delete_markers: Vec<DeleteMarkerEntry>,
+}
+
+#[derive(Debug, Default)]
+/// Subset of items used to deserialize a list object versions response.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_ListObjectVersions.html#API_ListObjectVersions_ResponseSyntax
+struct ListObjectVersionsResponseBody {
+    /// Flag indication if response was truncated because of key limits.
+    pub is_truncated: Option<bool>,
+    /// Key marker to fetch the next set of versions for truncated responses.
+    pub next_key_marker: Option<String>,
+    /// Version ID marker to fetch the next set of versions for truncated responses.
+    pub next_version_id_marker: Option<String>,
+    /// List of object versions, interleaved with delete markers in the response.
+    pub version: Vec<ObjectVersion>,
+    /// List of delete markers, interleaved with object versions in the response.
+    pub delete_marker: Vec<DeleteMarkerEntry>,
+}
+
+// Versions and delete markers are interleaved in the response, which the derived implementation
+// rejects as duplicate fields, so collect the elements manually.
+impl<'de> Deserialize<'de> for ListObjectVersionsResponseBody {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BodyVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BodyVisitor {
+            type Value = ListObjectVersionsResponseBody;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a list object versions response")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut body = ListObjectVersionsResponseBody::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "IsTruncated" => body.is_truncated = Some(map.next_value()?),
+                        "NextKeyMarker" => body.next_key_marker = Some(map.next_value()?),
+                        "NextVersionIdMarker" => {
+                            body.next_version_id_marker = Some(map.next_value()?)
+                        }
+                        "Version" => body.version.push(map.next_value()?),
+                        "DeleteMarker" => body.delete_marker.push(map.next_value()?),
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(body)
+            }
+        }
+
+        deserializer.deserialize_map(BodyVisitor)
+    }
+}
+
+impl ListObjectVersionsResponseBody {
+    fn with_optional_date(self, date: Option<HttpDate>) -> ListObjectVersionsResponse {
+        ListObjectVersionsResponse {
+            date,
+            is_truncated: self.is_truncated.unwrap_or_default(),
+            next_key_marker: self.next_key_marker.filter(|marker| !marker.is_empty()),
+            next_version_id_marker: self
+                .next_version_id_marker
+                .filter(|marker| !marker.is_empty()),
+            versions: self.version,
+            delete_markers: self.delete_marker,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+/// Subset of contents used to deserialize the object versions of a list object versions response.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_ObjectVersion.html
+pub struct ObjectVersion {
+    /// Object key.
+    pub key: S3ObjectKey,
+    /// Version ID of the object.
+    pub version_id: String,
+    /// Flag indicating if this is the latest version of the object.
+    pub is_latest: bool,
+    /// Object version last modified timestamp.
+    pub last_modified: LastModifiedTimestamp,
+    /// Entity tag for the object version.
+    pub e_tag: String,
+    /// Content size of the object version.
+    pub size: u64,
+    /// Storage class the object version is stored on.
+    pub storage_class: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+/// Subset of contents used to deserialize the delete markers of a list object versions response.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_DeleteMarkerEntry.html
+pub struct DeleteMarkerEntry {
+    /// Object key.
+    pub key: S3ObjectKey,
+    /// Version ID of the delete marker.
+    pub version_id: String,
+    /// Flag indicating if the delete marker is the latest version of the object.
+    pub is_latest: bool,
+    /// Delete marker creation timestamp.
+    pub last_modified: LastModifiedTimestamp,
+}
+
 #[derive(Debug)]
 /// Variants to distinguish object upload response states.
 /// https://docs.aws.amazon.com/AmazonS3/latest/API/API_PutObject.html#API_PutObject_ResponseSyntax
@@ -231,6 +431,78 @@ pub struct Bucket {
     pub creation_date: LastModifiedTimestamp,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+/// Response contents of the create multipart upload api call.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_CreateMultipartUpload.html#API_CreateMultipartUpload_ResponseSyntax
+pub struct CreateMultipartUploadResponse {
+    /// Bucket to which the multipart upload was initiated.
+    pub bucket: Option<String>,
+    /// Object key for which the multipart upload was initiated.
+    pub key: S3ObjectKey,
+    /// ID for the initiated multipart upload.
+    pub upload_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+/// Response contents of the complete multipart upload api call.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_CompleteMultipartUpload.html#API_CompleteMultipartUpload_ResponseSyntax
+pub struct CompleteMultipartUploadResponse {
+    /// URI identifying the newly created object.
+    pub location: Option<String>,
+    /// Bucket containing the newly created object.
+    pub bucket: Option<String>,
+    /// Object key of the newly created object.
+    pub key: Option<S3ObjectKey>,
+    /// Entity tag of the newly created object.
+    pub e_tag: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+/// Error returned within the body of a successful complete multipart upload response.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_CompleteMultipartUpload.html#API_CompleteMultipartUpload_Errors
+struct CompleteMultipartUploadError {
+    /// Error code identifying the error condition.
+    code: Option<String>,
+    /// Generic error description.
+    message: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+/// Response contents of the list multipart uploads api call.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_ListMultipartUploads.html#API_ListMultipartUploads_ResponseSyntax
+pub struct ListMultipartUploadsResponse {
+    /// Flag indication if response was truncated because of upload limits.
+    #[serde(default)]
+    pub is_truncated: bool,
+    /// Key marker to fetch the next set of uploads for truncated responses.
+    pub next_key_marker: Option<String>,
+    /// Upload ID marker to fetch the next set of uploads for truncated responses.
+    pub next_upload_id_marker: Option<String>,
+    /// List of in-progress multipart uploads.
+    #[serde(default)]
+    pub upload: Vec<MultipartUpload>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+/// Subset of contents used to deserialize the in-progress uploads of a list multipart uploads
+/// response.
+/// https://docs.aws.amazon.com/AmazonS3/latest/API/API_MultipartUpload.html
+pub struct MultipartUpload {
+    /// Object key for which the multipart upload was initiated.
+    pub key: S3ObjectKey,
+    /// ID of the multipart upload.
+    pub upload_id: String,
+    /// Timestamp when the multipart upload was initiated.
+    pub initiated: LastModifiedTimestamp,
+    /// Storage class the object will be stored on.
+    pub storage_class: Option<String>,
+}
+
 pub(crate) enum DeleteError {
     Response(DeleteObjectError),
     Parsing(Error),
@@ -330,6 +602,35 @@ impl ResponseReader {
         Ok(response.with_optional_date(date))
     }
 
+    /// Read and parse the list object versions response.
+    ///
+    /// Returns with error if the bucket cannot be found, an unexpected status code is encountered
+    /// or the response body cannot be parsed.
+    pub(crate) async fn list_object_versions_response(
+        self,
+    ) -> Result<ListObjectVersionsResponse, Error> {
+        let (parts, body) = self.response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        match parts.status {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => bail!("bucket does not exist"),
+            status_code => {
+                Self::log_error_response_utf8(body);
+                bail!("unexpected status code {status_code}")
+            }
+        }
+
+        let body = String::from_utf8(body.to_vec())?;
+
+        let date = Self::parse_optional_header(header::DATE, &parts.headers)?;
+
+        let response: ListObjectVersionsResponseBody =
+            serde_xml_rs::from_str(&body).context("failed to parse response body")?;
+
+        Ok(response.with_optional_date(date))
+    }
+
     /// Read and parse the head object response.
     ///
     /// Returns with error if an unexpected status code is encountered or the response headers or
@@ -355,6 +656,30 @@ impl ResponseReader {
         let e_tag = Self::parse_header(header::ETAG, &parts.headers)?;
         let date = Self::parse_optional_header(header::DATE, &parts.headers)?;
         let last_modified = Self::parse_header(header::LAST_MODIFIED, &parts.headers)?;
+        let version_id = Self::parse_optional_header(
+            HeaderName::from_static(VERSION_ID_HEADER),
+            &parts.headers,
+        )?;
+        let object_lock_mode: Option<ObjectLockMode> = Self::parse_optional_header(
+            HeaderName::from_static(OBJECT_LOCK_MODE_HEADER),
+            &parts.headers,
+        )?;
+        let retain_until: Option<RetainUntilDate> = Self::parse_optional_header(
+            HeaderName::from_static(OBJECT_LOCK_RETAIN_UNTIL_DATE_HEADER),
+            &parts.headers,
+        )?;
+        // Retention is only in effect with both mode and date, treat partial headers as absent
+        let object_lock_retention = match (object_lock_mode, retain_until) {
+            (Some(mode), Some(retain_until)) => Some(ObjectLockRetention {
+                mode,
+                retain_until: retain_until.0,
+            }),
+            _ => None,
+        };
+        let object_lock_legal_hold: Option<LegalHoldStatus> = Self::parse_optional_header(
+            HeaderName::from_static(OBJECT_LOCK_LEGAL_HOLD_HEADER),
+            &parts.headers,
+        )?;
 
         Ok(Some(HeadObjectResponse {
             content_length,
@@ -362,6 +687,10 @@ impl ResponseReader {
             date,
             e_tag,
             last_modified,
+            version_id,
+            object_lock_retention,
+            object_lock_legal_hold: object_lock_legal_hold
+                .map(|status| status == LegalHoldStatus::On),
         }))
     }
 
@@ -370,36 +699,83 @@ impl ResponseReader {
     /// Returns with error if the object is not accessible, an unexpected status code is encountered
     /// or the response headers or body cannot be parsed.
     pub(crate) async fn get_object_response(self) -> Result<Option<GetObjectResponse>, Error> {
+        match self.conditional_get_object_response().await? {
+            Some(ConditionalGetObjectResponse::Success(response)) => Ok(Some(response)),
+            Some(ConditionalGetObjectResponse::NotModified) => {
+                bail!("unexpected status code {}", StatusCode::NOT_MODIFIED)
+            }
+            Some(ConditionalGetObjectResponse::PreconditionFailed) => {
+                bail!("unexpected status code {}", StatusCode::PRECONDITION_FAILED)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Read and parse the response of a conditional and/or ranged get object request.
+    ///
+    /// Returns with error if the object is not accessible, the requested range is not satisfiable,
+    /// an unexpected status code is encountered or the response headers cannot be parsed.
+    pub(crate) async fn conditional_get_object_response(
+        self,
+    ) -> Result<Option<ConditionalGetObjectResponse>, Error> {
         let (parts, incoming) = self.response.into_parts();
         let content = Content {
             incoming,
             request_counters: self.request_counters.clone(),
         };
 
-        match parts.status {
-            StatusCode::OK => (),
+        let content_range = match parts.status {
+            StatusCode::OK => None,
+            StatusCode::PARTIAL_CONTENT => Some(Self::parse_header::<ContentRange>(
+                header::CONTENT_RANGE,
+                &parts.headers,
+            )?),
             StatusCode::NOT_FOUND => return Ok(None),
+            // Drain the (error) body, so the connection can be reused
+            StatusCode::NOT_MODIFIED => {
+                content.collect().await?;
+                return Ok(Some(ConditionalGetObjectResponse::NotModified));
+            }
+            StatusCode::PRECONDITION_FAILED => {
+                content.collect().await?;
+                return Ok(Some(ConditionalGetObjectResponse::PreconditionFailed));
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => bail!("requested range not satisfiable"),
             status_code => {
                 let body = content.collect().await?.to_bytes();
                 Self::log_error_response_utf8(body);
                 bail!("unexpected status code {status_code}")
             }
-        }
+        };
 
         let content_length: u64 = Self::parse_header(header::CONTENT_LENGTH, &parts.headers)?;
         let content_type = Self::parse_header(header::CONTENT_TYPE, &parts.headers)?;
         let e_tag = Self::parse_header(header::ETAG, &parts.headers)?;
         let date = Self::parse_optional_header(header::DATE, &parts.headers)?;
         let last_modified = Self::parse_header(header::LAST_MODIFIED, &parts.headers)?;
+        let version_id = Self::parse_optional_header(
+            HeaderName::from_static(VERSION_ID_HEADER),
+            &parts.headers,
+        )?;
 
-        Ok(Some(GetObjectResponse {
-            content_length,
-            content_type,
-            date,
-            e_tag,
-            last_modified,
-            content,
-        }))
+        if let Some(content_range) = &content_range {
+            if content_range.range.end - content_range.range.start != content_length {
+                bail!("content range does not match content length");
+            }
+        }
+
+        Ok(Some(ConditionalGetObjectResponse::Success(
+            GetObjectResponse {
+                content_length,
+                content_type,
+                date,
+                e_tag,
+                last_modified,
+                version_id,
+                content_range,
+                content,
+            },
+        )))
     }
 
     /// Read and parse the put object response.
@@ -439,6 +815,7 @@ impl ResponseReader {
     pub(crate) async fn delete_object_response(
         self,
         key: S3ObjectKey,
+        version_id: Option<String>,
     ) -> Result<DeletedObject, DeleteError> {
         let (parts, _body) = self.response.into_parts();
 
@@ -449,7 +826,7 @@ impl ResponseReader {
                     code: Some(status_code.to_string()),
                     key: Some(key),
                     message: None,
-                    version_id: None,
+                    version_id,
                 }));
             }
         };
@@ -459,17 +836,15 @@ impl ResponseReader {
             &parts.headers,
         )
         .map_err(DeleteError::Parsing)?;
-        let delete_marker_version_id = Self::parse_optional_header(
-            HeaderName::from_static("x-amz-version-id"),
-            &parts.headers,
-        )
-        .map_err(DeleteError::Parsing)?;
+        let delete_marker_version_id =
+            Self::parse_optional_header(HeaderName::from_static(VERSION_ID_HEADER), &parts.headers)
+                .map_err(DeleteError::Parsing)?;
 
         Ok(DeletedObject {
             delete_marker,
             delete_marker_version_id,
             key: Some(key),
-            version_id: None,
+            version_id,
         })
     }
 
@@ -521,7 +896,7 @@ impl ResponseReader {
 
         let body = String::from_utf8(body.to_vec())?;
 
-        let x_amz_version_id = match parts.headers.get("x-amz-version-id") {
+        let x_amz_version_id = match parts.headers.get(VERSION_ID_HEADER) {
             Some(version_id) => Some(
                 version_id
                     .to_str()
@@ -565,6 +940,147 @@ impl ResponseReader {
         Ok(ListBucketsResponse { buckets })
     }
 
+    /// Read and parse the create multipart upload response.
+    ///
+    /// Returns with error on bad request, an unexpected status code is encountered or the response
+    /// body cannot be parsed.
+    pub(crate) async fn create_multipart_upload_response(
+        self,
+    ) -> Result<CreateMultipartUploadResponse, Error> {
+        let (parts, body) = self.response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        match parts.status {
+            StatusCode::OK => (),
+            StatusCode::BAD_REQUEST => {
+                Self::log_error_response_utf8(body);
+                bail!("invalid request");
+            }
+            status_code => {
+                Self::log_error_response_utf8(body);
+                bail!("unexpected status code {status_code}")
+            }
+        }
+
+        let body = String::from_utf8(body.to_vec())?;
+
+        let response: CreateMultipartUploadResponse =
+            serde_xml_rs::from_str(&body).context("failed to parse response body")?;
+
+        Ok(response)
+    }
+
+    /// Read and parse the upload part response, returning the entity tag of the uploaded part.
+    ///
+    /// Returns with error if the multipart upload does not exist, an unexpected status code is
+    /// encountered or the response headers cannot be parsed.
+    pub(crate) async fn upload_part_response(self) -> Result<String, Error> {
+        let (parts, body) = self.response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        match parts.status {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => bail!("multipart upload does not exist"),
+            StatusCode::BAD_REQUEST => {
+                Self::log_error_response_utf8(body);
+                bail!("invalid request");
+            }
+            status_code => {
+                Self::log_error_response_utf8(body);
+                bail!("unexpected status code {status_code}")
+            }
+        }
+
+        Self::parse_header(header::ETAG, &parts.headers)
+    }
+
+    /// Read and parse the complete multipart upload response.
+    ///
+    /// Returns with error if the multipart upload does not exist, an unexpected status code or an
+    /// error response body is encountered or the response body cannot be parsed.
+    pub(crate) async fn complete_multipart_upload_response(
+        self,
+    ) -> Result<CompleteMultipartUploadResponse, Error> {
+        let (parts, body) = self.response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        match parts.status {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => bail!("multipart upload does not exist"),
+            StatusCode::BAD_REQUEST => {
+                Self::log_error_response_utf8(body);
+                bail!("invalid request");
+            }
+            status_code => {
+                Self::log_error_response_utf8(body);
+                bail!("unexpected status code {status_code}")
+            }
+        }
+
+        let body = String::from_utf8(body.to_vec())?;
+
+        // The request may fail after the initial 200 OK response was sent, in which case the
+        // error is only contained in the response body.
+        if let Ok(error) = serde_xml_rs::from_str::<CompleteMultipartUploadError>(&body) {
+            if let Some(code) = error.code {
+                bail!(
+                    "failed to complete multipart upload: {code} - {}",
+                    error.message.unwrap_or_default()
+                );
+            }
+        }
+
+        let response: CompleteMultipartUploadResponse =
+            serde_xml_rs::from_str(&body).context("failed to parse response body")?;
+
+        Ok(response)
+    }
+
+    /// Read and parse the abort multipart upload response.
+    ///
+    /// Returns with error if the multipart upload does not exist or an unexpected status code is
+    /// encountered.
+    pub(crate) async fn abort_multipart_upload_response(self) -> Result<(), Error> {
+        let (parts, body) = self.response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        match parts.status {
+            StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
+            StatusCode::NOT_FOUND => bail!("multipart upload does not exist"),
+            status_code => {
+                Self::log_error_response_utf8(body);
+                bail!("unexpected status code {status_code}")
+            }
+        }
+    }
+
+    /// Read and parse the list multipart uploads response.
+    ///
+    /// Returns with error if the bucket cannot be found, an unexpected status code is encountered
+    /// or the response body cannot be parsed.
+    pub(crate) async fn list_multipart_uploads_response(
+        self,
+    ) -> Result<ListMultipartUploadsResponse, Error> {
+        let (parts, body) = self.response.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        match parts.status {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => bail!("bucket does not exist"),
+            status_code => {
+                Self::log_error_response_utf8(body);
+                bail!("unexpected status code {status_code}")
+            }
+        }
+
+        let body = String::from_utf8(body.to_vec())?;
+
+        let response: ListMultipartUploadsResponse =
+            serde_xml_rs::from_str(&body).context("failed to parse response body")?;
+
+        Ok(response)
+    }
+
     fn log_error_response_utf8(body: Bytes) {
         if let Ok(body) = String::from_utf8(body.to_vec()) {
             if !body.is_empty() {
@@ -710,6 +1226,113 @@ fn parse_list_buckets_response_test() {
     );
 }
 
+#[test]
+fn parse_create_multipart_upload_response_test() {
+    let response_body = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <InitiateMultipartUploadResult>
+            <Bucket>bucket0</Bucket>
+            <Key>teststore/object0</Key>
+            <UploadId>VXBsb2FkIElEIGZvciA2aWWpbmcncyBteS1tb3ZpZS5tMnRzIHVwbG9hZA</UploadId>
+        </InitiateMultipartUploadResult>
+    "#;
+    let result: CreateMultipartUploadResponse = serde_xml_rs::from_str(response_body).unwrap();
+    assert_eq!(result.bucket, Some("bucket0".to_string()));
+    assert_eq!(
+        result.key,
+        S3ObjectKey::try_from("/teststore/object0").unwrap()
+    );
+    assert_eq!(
+        result.upload_id,
+        "VXBsb2FkIElEIGZvciA2aWWpbmcncyBteS1tb3ZpZS5tMnRzIHVwbG9hZA"
+    );
+}
+
+#[test]
+fn parse_complete_multipart_upload_response_test() {
+    let response_body = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <CompleteMultipartUploadResult>
+            <Location>https://bucket0.s3.example.com/teststore/object0</Location>
+            <Bucket>bucket0</Bucket>
+            <Key>teststore/object0</Key>
+            <ETag>"3858f62230ac3c915f300c664312c11f-9"</ETag>
+        </CompleteMultipartUploadResult>
+    "#;
+    let result: CompleteMultipartUploadResponse = serde_xml_rs::from_str(response_body).unwrap();
+    assert_eq!(result.e_tag, "\"3858f62230ac3c915f300c664312c11f-9\"");
+    let error = serde_xml_rs::from_str::<CompleteMultipartUploadError>(response_body);
+    assert!(error.is_err() || error.unwrap().code.is_none());
+
+    let response_body = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <Error>
+            <Code>InternalError</Code>
+            <Message>We encountered an internal error. Please try again.</Message>
+        </Error>
+    "#;
+    let error: CompleteMultipartUploadError = serde_xml_rs::from_str(response_body).unwrap();
+    assert_eq!(error.code, Some("InternalError".to_string()));
+}
+
+#[test]
+fn parse_list_multipart_uploads_response_test() {
+    let response_body = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <ListMultipartUploadsResult>
+            <Bucket>bucket0</Bucket>
+            <KeyMarker></KeyMarker>
+            <UploadIdMarker></UploadIdMarker>
+            <NextKeyMarker>teststore/object1</NextKeyMarker>
+            <NextUploadIdMarker>upload1</NextUploadIdMarker>
+            <MaxUploads>2</MaxUploads>
+            <IsTruncated>true</IsTruncated>
+            <Upload>
+                <Key>teststore/object0</Key>
+                <UploadId>upload0</UploadId>
+                <StorageClass>STANDARD</StorageClass>
+                <Initiated>2010-11-10T20:48:33.000Z</Initiated>
+            </Upload>
+            <Upload>
+                <Key>teststore/object1</Key>
+                <UploadId>upload1</UploadId>
+                <StorageClass>STANDARD</StorageClass>
+                <Initiated>2010-11-10T20:49:33.000Z</Initiated>
+            </Upload>
+        </ListMultipartUploadsResult>
+    "#;
+    let result: ListMultipartUploadsResponse = serde_xml_rs::from_str(response_body).unwrap();
+    assert!(result.is_truncated);
+    assert_eq!(
+        result.next_key_marker,
+        Some("teststore/object1".to_string())
+    );
+    assert_eq!(result.next_upload_id_marker, Some("upload1".to_string()));
+    assert_eq!(
+        result.upload,
+        vec![
+            MultipartUpload {
+                key: S3ObjectKey::try_from("/teststore/object0").unwrap(),
+                upload_id: "upload0".to_string(),
+                initiated: LastModifiedTimestamp::from_str("2010-11-10T20:48:33.000Z").unwrap(),
+                storage_class: Some("STANDARD".to_string()),
+            },
+            MultipartUpload {
+                key: S3ObjectKey::try_from("/teststore/object1").unwrap(),
+                upload_id: "upload1".to_string(),
+                initiated: LastModifiedTimestamp::from_str("2010-11-10T20:49:33.000Z").unwrap(),
+                storage_class: Some("STANDARD".to_string()),
+            },
+        ]
+    );
+
+    let response_body = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <ListMultipartUploadsResult>
+            <Bucket>bucket0</Bucket>
+            <IsTruncated>false</IsTruncated>
+        </ListMultipartUploadsResult>
+    "#;
+    let result: ListMultipartUploadsResponse = serde_xml_rs::from_str(response_body).unwrap();
+    assert!(!result.is_truncated);
+    assert!(result.upload.is_empty());
+}
+
 #[test]
 fn test_optional_date_header_parsing() {
     let mut header_map = HeaderMap::new();
@@ -736,3 +1359,100 @@ fn test_optional_date_header_parsing() {
             .is_none()
     );
 }
+
+#[test]
+fn parse_content_range_test() {
+    let content_range = ContentRange::from_str("bytes 0-499/1234").unwrap();
+    assert_eq!(content_range.range, 0..500);
+    assert_eq!(content_range.size, Some(1234));
+
+    let content_range = ContentRange::from_str("bytes 500-999/*").unwrap();
+    assert_eq!(content_range.range, 500..1000);
+    assert_eq!(content_range.size, None);
+
+    assert!(ContentRange::from_str("bytes */1234").is_err());
+    assert!(ContentRange::from_str("bytes 500-499/1234").is_err());
+    assert!(ContentRange::from_str("bytes 0-1234/1234").is_err());
+    assert!(ContentRange::from_str("items 0-10/20").is_err());
+    assert!(ContentRange::from_str("bytes 0-10").is_err());
+}
+
+#[test]
+fn parse_list_object_versions_response_test() {
+    let response_body = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <ListVersionsResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+            <Name>bucket0</Name>
+            <Prefix>.cnt</Prefix>
+            <KeyMarker/>
+            <VersionIdMarker/>
+            <NextKeyMarker>.cnt/key1</NextKeyMarker>
+            <NextVersionIdMarker>version2</NextVersionIdMarker>
+            <MaxKeys>3</MaxKeys>
+            <IsTruncated>true</IsTruncated>
+            <Version>
+                <Key>.cnt/key0</Key>
+                <VersionId>version0</VersionId>
+                <IsLatest>true</IsLatest>
+                <LastModified>2011-02-26T01:56:20.000Z</LastModified>
+                <ETag>"bf1d737a4d46a19f3bced6905cc8b902"</ETag>
+                <Size>2</Size>
+                <Owner>
+                    <ID>75aa57f09aa0c8caeab4f8c24e99d10f8e7faeebf76c078efc7c6caea54ba06a</ID>
+                    <DisplayName>mtd@amazon.com</DisplayName>
+                </Owner>
+                <StorageClass>STANDARD</StorageClass>
+            </Version>
+            <DeleteMarker>
+                <Key>.cnt/key1</Key>
+                <VersionId>version1</VersionId>
+                <IsLatest>true</IsLatest>
+                <LastModified>2011-02-26T01:56:21.000Z</LastModified>
+            </DeleteMarker>
+            <Version>
+                <Key>.cnt/key1</Key>
+                <VersionId>version2</VersionId>
+                <IsLatest>false</IsLatest>
+                <LastModified>2011-02-26T01:56:20.000Z</LastModified>
+                <ETag>"9b2cf535f27731c974343645a3985328"</ETag>
+                <Size>4</Size>
+            </Version>
+        </ListVersionsResult>
+    "#;
+    let result: ListObjectVersionsResponseBody = serde_xml_rs::from_str(response_body).unwrap();
+    let result = result.with_optional_date(None);
+    assert!(result.is_truncated);
+    assert_eq!(result.next_key_marker.as_deref(), Some(".cnt/key1"));
+    assert_eq!(result.next_version_id_marker.as_deref(), Some("version2"));
+    assert_eq!(
+        result.versions,
+        vec![
+            ObjectVersion {
+                key: S3ObjectKey::try_from("/.cnt/key0").unwrap(),
+                version_id: "version0".to_string(),
+                is_latest: true,
+                last_modified: LastModifiedTimestamp::from_str("2011-02-26T01:56:20.000Z").unwrap(),
+                e_tag: "\"bf1d737a4d46a19f3bced6905cc8b902\"".to_string(),
+                size: 2,
+                storage_class: Some("STANDARD".to_string()),
+            },
+            ObjectVersion {
+                key: S3ObjectKey::try_from("/.cnt/key1").unwrap(),
+                version_id: "version2".to_string(),
+                is_latest: false,
+                last_modified: LastModifiedTimestamp::from_str("2011-02-26T01:56:20.000Z").unwrap(),
+                e_tag: "\"9b2cf535f27731c974343645a3985328\"".to_string(),
+                size: 4,
+                storage_class: None,
+            },
+        ]
+    );
+    assert_eq!(
+        result.delete_markers,
+        vec![DeleteMarkerEntry {
+            key: S3ObjectKey::try_from("/.cnt/key1").unwrap(),
+            version_id: "version1".to_string(),
+            is_latest: true,
+            last_modified: LastModifiedTimestamp::from_str("2011-02-26T01:56:21.000Z").unwrap(),
+        }]
+    );
+}
@@ -8,15 +8,36 @@ const VALID_MONTHS: [&str; 12] = [
 #[derive(Debug, PartialEq)]
 /// Last modified timestamp as obtained from API response http headers.
 pub struct LastModifiedTimestamp {
-    _datetime: iso8601::DateTime,
+    datetime: iso8601::DateTime,
 }
 
 impl std::str::FromStr for LastModifiedTimestamp {
     type Err = Error;
 
     fn from_str(timestamp: &str) -> Result<Self, Self::Err> {
-        let _datetime = iso8601::datetime(timestamp).map_err(|err| anyhow!(err))?;
-        Ok(Self { _datetime })
+        let datetime = iso8601::datetime(timestamp).map_err(|err| anyhow!(err))?;
+        Ok(Self { datetime })
+    }
+}
+
+impl LastModifiedTimestamp {
+    /// Convert the timestamp to seconds since the UNIX epoch, ignoring fractional seconds.
+    pub fn to_epoch(&self) -> Result<i64, Error> {
+        let (year, month, day) = match self.datetime.date {
+            iso8601::Date::YMD { year, month, day } => (year, month as i32, day as i32),
+            _ => bail!("unsupported date format, expected calendar date"),
+        };
+        let time = &self.datetime.time;
+
+        let mut tm = proxmox_time::TmEditor::new(true);
+        tm.set_year(year)?;
+        tm.set_mon(month)?;
+        tm.set_mday(day)?;
+        tm.set_time(time.hour as i32, time.minute as i32, time.second as i32)?;
+
+        let offset = i64::from(time.tz_offset_hours * 60 + time.tz_offset_minutes) * 60;
+
+        Ok(tm.into_epoch()? - offset)
     }
 }
 
@@ -28,9 +49,41 @@ serde_plain::derive_deserialize_from_fromstr!(LastModifiedTimestamp, "last modif
 /// https://datatracker.ietf.org/doc/html/rfc2616#section-3.3
 /// https://datatracker.ietf.org/doc/html/rfc1123#section-5.2.14
 /// https://datatracker.ietf.org/doc/html/rfc822#section-5
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct HttpDate {
-    _epoch: i64,
+    epoch: i64,
+}
+
+impl HttpDate {
+    /// Create a http date from seconds since the UNIX epoch.
+    pub fn from_epoch(epoch: i64) -> Self {
+        Self { epoch }
+    }
+
+    /// Seconds since the UNIX epoch.
+    pub fn epoch(&self) -> i64 {
+        self.epoch
+    }
+}
+
+impl std::fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Format manually instead of via strftime, the day and month names must not depend on
+        // the current locale.
+        let tm = proxmox_time::gmtime(self.epoch).map_err(|_| std::fmt::Error)?;
+        // tm_wday counts from Sunday, VALID_DAYS_OF_WEEK starts on Monday.
+        let day_of_week = VALID_DAYS_OF_WEEK[(tm.tm_wday as usize + 6) % 7];
+        let month = VALID_MONTHS[tm.tm_mon as usize];
+        write!(
+            f,
+            "{day_of_week}, {:02} {month} {:04} {:02}:{:02}:{:02} GMT",
+            tm.tm_mday,
+            tm.tm_year + 1900,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec,
+        )
+    }
 }
 
 impl std::str::FromStr for HttpDate {
@@ -99,8 +152,32 @@ impl std::str::FromStr for HttpDate {
             bail!("unexpected timezone");
         }
 
-        let _epoch = tm.into_epoch()?;
+        let epoch = tm.into_epoch()?;
 
-        Ok(Self { _epoch })
+        Ok(Self { epoch })
     }
 }
+
+#[test]
+fn last_modified_timestamp_to_epoch_test() {
+    use std::str::FromStr;
+
+    let timestamp = LastModifiedTimestamp::from_str("2011-02-26T01:56:20.000Z").unwrap();
+    assert_eq!(timestamp.to_epoch().unwrap(), 1298685380);
+
+    let timestamp = LastModifiedTimestamp::from_str("2019-12-11T23:32:47+01:00").unwrap();
+    assert_eq!(timestamp.to_epoch().unwrap(), 1576103567);
+}
+
+#[test]
+fn http_date_format_test() {
+    use std::str::FromStr;
+
+    let date = HttpDate::from_str("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+    assert_eq!(date.epoch(), 1445412480);
+    assert_eq!(date.to_string(), "Wed, 21 Oct 2015 07:28:00 GMT");
+
+    let date = HttpDate::from_epoch(1298685380);
+    assert_eq!(date.to_string(), "Sat, 26 Feb 2011 01:56:20 GMT");
+    assert_eq!(HttpDate::from_str(&date.to_string()).unwrap(), date);
+}
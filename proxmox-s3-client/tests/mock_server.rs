@@ -0,0 +1,425 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Error;
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+use hyper::{Method, StatusCode};
+use tokio::io::AsyncReadExt;
+
+use proxmox_http::Body;
+use proxmox_s3_client::{
+    CompletedPart, ConditionalGetObjectResponse, GetObjectOptions, MockFault, MockS3Server,
+    MultipartUploadOptions, S3_MULTIPART_MIN_PART_SIZE, S3Client, S3ObjectKey, S3ObjectReader,
+    S3PathPrefix,
+};
+
+const BUCKET: &str = "testbucket";
+const PREFIX: &str = "teststore";
+
+async fn setup() -> Result<(MockS3Server, S3Client), Error> {
+    let server = MockS3Server::start().await?;
+    server.create_bucket(BUCKET);
+    let client = S3Client::new(server.client_options(Some(BUCKET), PREFIX))?;
+    Ok((server, client))
+}
+
+fn key(key: &str) -> S3ObjectKey {
+    S3ObjectKey::try_from(key).unwrap()
+}
+
+#[tokio::test]
+async fn bucket_operations() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    client.head_bucket().await?;
+
+    let client = S3Client::new(server.client_options(None, PREFIX))?;
+    let buckets = client.list_buckets().await?;
+    assert_eq!(buckets.buckets.len(), 1);
+    assert_eq!(buckets.buckets[0].name, BUCKET);
+
+    let client = S3Client::new(server.client_options(Some("missing"), PREFIX))?;
+    assert!(client.head_bucket().await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn object_operations() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    let data = Bytes::from_static(b"mock object contents");
+
+    assert!(
+        !client
+            .upload_no_replace_with_retry(key("object0"), data.clone())
+            .await?
+    );
+    // Object already present, upload skipped because of If-None-Match precondition
+    assert!(
+        client
+            .upload_no_replace_with_retry(key("object0"), Bytes::new())
+            .await?
+    );
+    assert_eq!(
+        server.object_data(BUCKET, "teststore/object0"),
+        Some(data.clone())
+    );
+
+    let head = client.head_object(key("object0")).await?.unwrap();
+    assert_eq!(head.content_length, data.len() as u64);
+    assert!(client.head_object(key("missing")).await?.is_none());
+
+    let response = client.get_object(key("object0")).await?.unwrap();
+    assert_eq!(response.e_tag, head.e_tag);
+    assert_eq!(response.content.collect().await?.to_bytes(), data);
+
+    let client = Arc::new(client);
+    let mut reader = S3ObjectReader::new(Arc::clone(&client), key("object0"))
+        .await?
+        .unwrap()
+        .with_read_ahead(4);
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents).await?;
+    assert_eq!(contents, data);
+
+    client.copy_object(key("object0"), key("object1")).await?;
+    assert_eq!(server.object_data(BUCKET, "teststore/object1"), Some(data));
+
+    client.delete_object(key("object0")).await?;
+    assert_eq!(server.object_keys(BUCKET), vec!["teststore/object1"]);
+
+    // Keys are passed as listed, without adding the common prefix
+    let response = client.delete_objects(&[key("/teststore/object1")]).await?;
+    assert_eq!(response.deleted.map(|deleted| deleted.len()), Some(1));
+    assert!(server.object_keys(BUCKET).is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn list_objects_continuation() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    server.set_max_keys(2);
+
+    for i in 0..5 {
+        client
+            .upload_replace_with_retry(key(&format!("object{i}")), Bytes::from(vec![0u8; i]))
+            .await?;
+    }
+    client
+        .upload_replace_with_retry(key("/other/object"), Bytes::new())
+        .await?;
+
+    let prefix = S3PathPrefix::Some("object".to_string());
+    let mut keys = Vec::new();
+    let mut continuation_token = None;
+    let mut requests = 0;
+    loop {
+        let response = client
+            .list_objects_v2(&prefix, continuation_token.as_deref())
+            .await?;
+        requests += 1;
+        keys.extend(
+            response
+                .contents
+                .into_iter()
+                .map(|item| item.key.to_string()),
+        );
+        if !response.is_truncated {
+            break;
+        }
+        continuation_token = response.next_continuation_token;
+    }
+
+    assert_eq!(requests, 3);
+    assert_eq!(
+        keys,
+        (0..5)
+            .map(|i| format!("teststore/object{i}"))
+            .collect::<Vec<String>>()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn signature_verification() -> Result<(), Error> {
+    let (server, _client) = setup().await?;
+
+    let mut options = server.client_options(Some(BUCKET), PREFIX);
+    options.secret_key = "wrongsecretkey".to_string();
+    let client = S3Client::new(options)?;
+    assert!(client.head_bucket().await.is_err());
+
+    let result = client
+        .put_object(key("object0"), Body::from("data".to_string()), None, true)
+        .await;
+    assert!(result.is_err());
+    assert!(server.object_keys(BUCKET).is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fault_injection() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    let data = Bytes::from_static(b"mock object contents");
+
+    // Conflicting operations are retried by the upload helper
+    server.inject_fault(
+        Some(Method::PUT),
+        MockFault::Error(StatusCode::CONFLICT, "OperationAborted".to_string()),
+        2,
+    );
+    let requests = server.request_count();
+    assert!(
+        !client
+            .upload_replace_with_retry(key("object0"), data.clone())
+            .await?
+    );
+    assert_eq!(server.request_count() - requests, 3);
+
+    server.inject_fault(
+        Some(Method::PUT),
+        MockFault::Error(StatusCode::CONFLICT, "OperationAborted".to_string()),
+        3,
+    );
+    assert!(
+        client
+            .upload_replace_with_retry(key("object0"), data.clone())
+            .await
+            .is_err()
+    );
+
+    // Throttled requests are retried by the client with backoff
+    server.inject_fault(Some(Method::GET), MockFault::SlowDown, 1);
+    let requests = server.request_count();
+    let response = client.get_object(key("object0")).await?.unwrap();
+    assert_eq!(response.content.collect().await?.to_bytes(), data);
+    assert_eq!(server.request_count() - requests, 2);
+
+    server.inject_fault(Some(Method::GET), MockFault::TruncatedBody, 1);
+    let response = client.get_object(key("object0")).await?.unwrap();
+    assert_eq!(response.content_length, data.len() as u64);
+    assert!(response.content.collect().await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn multipart_upload() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    let part_size = S3_MULTIPART_MIN_PART_SIZE;
+    let data: Vec<u8> = (0..2 * part_size + 100).map(|i| (i % 251) as u8).collect();
+    let options = MultipartUploadOptions {
+        part_size,
+        concurrency: 2,
+    };
+
+    let response = client
+        .put_object_multipart(key("object0"), &data[..], &options)
+        .await?;
+    assert!(response.e_tag.ends_with("-3\""));
+    assert_eq!(
+        server.object_data(BUCKET, "teststore/object0").as_deref(),
+        Some(&data[..])
+    );
+    assert_eq!(server.multipart_upload_count(BUCKET), 0);
+
+    // Resumed uploads skip the parts already uploaded
+    let mut state = client
+        .create_multipart_upload(key("object1"), part_size)
+        .await?;
+    let e_tag = client
+        .upload_part(
+            state.object_key.clone(),
+            &state.upload_id,
+            1,
+            Body::from(data[..part_size as usize].to_vec()),
+            None,
+        )
+        .await?;
+    state.completed_parts.push(CompletedPart {
+        part_number: 1,
+        e_tag,
+    });
+    let requests = server.request_count();
+    let offset = state.resume_offset() as usize;
+    client
+        .upload_multipart(&mut state, &data[offset..], 1)
+        .await?;
+    assert_eq!(server.request_count() - requests, 3);
+    assert_eq!(
+        server.object_data(BUCKET, "teststore/object1").as_deref(),
+        Some(&data[..])
+    );
+
+    // Parts of the upload are not assembled if they are too small
+    let mut state = client.create_multipart_upload(key("object2"), 1).await?;
+    assert!(
+        client
+            .upload_multipart(&mut state, &b"abc"[..], 1)
+            .await
+            .is_err()
+    );
+    assert_eq!(state.completed_parts.len(), 3);
+    assert!(server.object_data(BUCKET, "teststore/object2").is_none());
+    client
+        .abort_multipart_upload(state.object_key.clone(), &state.upload_id)
+        .await?;
+    assert!(
+        client
+            .abort_multipart_upload(state.object_key, &state.upload_id)
+            .await
+            .is_err()
+    );
+
+    // Failed uploads are aborted
+    server.inject_fault(
+        Some(Method::PUT),
+        MockFault::Error(StatusCode::BAD_REQUEST, "InvalidRequest".to_string()),
+        3,
+    );
+    let options = MultipartUploadOptions {
+        part_size,
+        concurrency: 1,
+    };
+    assert!(
+        client
+            .put_object_multipart(key("object3"), &data[..], &options)
+            .await
+            .is_err()
+    );
+    assert_eq!(server.multipart_upload_count(BUCKET), 0);
+    assert!(server.object_data(BUCKET, "teststore/object3").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn list_multipart_uploads() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    server.set_max_keys(1);
+
+    let mut upload_ids = Vec::new();
+    for name in ["object0", "object0", "object1"] {
+        let state = client
+            .create_multipart_upload(key(name), S3_MULTIPART_MIN_PART_SIZE)
+            .await?;
+        upload_ids.push(state.upload_id);
+    }
+
+    let mut listed = Vec::new();
+    let mut key_marker = None;
+    let mut upload_id_marker = None;
+    loop {
+        let response = client
+            .list_multipart_uploads(
+                &S3PathPrefix::None,
+                key_marker.as_deref(),
+                upload_id_marker.as_deref(),
+            )
+            .await?;
+        listed.extend(response.upload.into_iter().map(|upload| upload.upload_id));
+        if !response.is_truncated {
+            break;
+        }
+        key_marker = response.next_key_marker;
+        upload_id_marker = response.next_upload_id_marker;
+    }
+    assert_eq!(listed, upload_ids);
+
+    // Uploads are only considered stale if initiated before the given age
+    let aborted = client
+        .abort_stale_multipart_uploads(&S3PathPrefix::None, Duration::from_secs(3600))
+        .await?;
+    assert_eq!(aborted, 0);
+    assert_eq!(server.multipart_upload_count(BUCKET), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn object_versioning() -> Result<(), Error> {
+    let (server, client) = setup().await?;
+    server.enable_versioning(BUCKET);
+
+    client
+        .upload_replace_with_retry(key("object0"), Bytes::from_static(b"version 1"))
+        .await?;
+    let first = client.head_object(key("object0")).await?.unwrap();
+    client
+        .upload_replace_with_retry(key("object0"), Bytes::from_static(b"version 2"))
+        .await?;
+    let second = client.head_object(key("object0")).await?.unwrap();
+    let first_version = first.version_id.unwrap();
+    assert_ne!(second.version_id.as_ref(), Some(&first_version));
+
+    let head = client
+        .head_object_version(key("object0"), &first_version)
+        .await?
+        .unwrap();
+    assert_eq!(head.e_tag, first.e_tag);
+    let options = GetObjectOptions {
+        version_id: Some(first_version.clone()),
+        ..Default::default()
+    };
+    match client
+        .get_object_with_options(key("object0"), &options)
+        .await?
+    {
+        Some(ConditionalGetObjectResponse::Success(response)) => {
+            assert_eq!(response.content.collect().await?.to_bytes(), "version 1");
+        }
+        _ => panic!("failed to get object version"),
+    }
+
+    // Deleting the object adds a delete marker and keeps the previous versions
+    let deleted = client.delete_object(key("object0")).await?;
+    assert_eq!(deleted.delete_marker, Some(true));
+    let marker_version = deleted.delete_marker_version_id.unwrap();
+    assert!(client.head_object(key("object0")).await?.is_none());
+    assert!(server.object_keys(BUCKET).is_empty());
+
+    let prefix = S3PathPrefix::Some("object".to_string());
+    let response = client.list_object_versions(&prefix, None, None).await?;
+    assert_eq!(response.versions.len(), 2);
+    assert!(response.versions.iter().all(|version| !version.is_latest));
+    assert_eq!(response.delete_markers.len(), 1);
+    assert!(response.delete_markers[0].is_latest);
+    assert_eq!(response.delete_markers[0].version_id, marker_version);
+
+    server.set_max_keys(2);
+    let response = client.list_object_versions(&prefix, None, None).await?;
+    assert!(response.is_truncated);
+    assert_eq!(response.versions.len() + response.delete_markers.len(), 2);
+    let response = client
+        .list_object_versions(
+            &prefix,
+            response.next_key_marker.as_deref(),
+            response.next_version_id_marker.as_deref(),
+        )
+        .await?;
+    assert!(!response.is_truncated);
+    assert_eq!(response.versions.len(), 1);
+    assert_eq!(response.versions[0].version_id, first_version);
+
+    // Removing the delete marker restores the previous version
+    client
+        .delete_object_version(key("object0"), &marker_version, false)
+        .await?;
+    assert_eq!(
+        server.object_data(BUCKET, "teststore/object0"),
+        Some(Bytes::from_static(b"version 2"))
+    );
+
+    client
+        .delete_object_version(key("object0"), &first_version, false)
+        .await?;
+    let response = client.list_object_versions(&prefix, None, None).await?;
+    assert_eq!(response.versions.len(), 1);
+    assert!(response.versions[0].is_latest);
+    assert!(response.delete_markers.is_empty());
+
+    Ok(())
+}
@@ -98,3 +98,183 @@ pub struct JournalFilter {
     pub startcursor: Option<String>,
     pub endcursor: Option<String>,
 }
+
+#[api]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Journal entry priority, using the syslog log levels.
+pub enum JournalPriority {
+    /// System is unusable.
+    Emerg,
+    /// Action must be taken immediately.
+    Alert,
+    /// Critical conditions.
+    Crit,
+    /// Error conditions.
+    Err,
+    /// Warning conditions.
+    Warning,
+    /// Normal but significant conditions.
+    Notice,
+    /// Informational messages.
+    Info,
+    /// Debug-level messages.
+    Debug,
+}
+
+impl JournalPriority {
+    /// The numeric syslog level, as used by the journal's `PRIORITY` field.
+    pub fn level(self) -> u8 {
+        self as u8
+    }
+
+    /// Get the priority for a numeric syslog level.
+    pub fn from_level(level: u8) -> Option<Self> {
+        Some(match level {
+            0 => Self::Emerg,
+            1 => Self::Alert,
+            2 => Self::Crit,
+            3 => Self::Err,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Info,
+            7 => Self::Debug,
+            _ => return None,
+        })
+    }
+}
+
+#[api(
+    properties: {
+        cursor: {
+            type: String,
+            description: "Continue after the entry with the given cursor. Conflicts with 'since' and 'until' as start position.",
+            optional: true,
+        },
+        reverse: {
+            type: Boolean,
+            description: "Read from newest to oldest entry.",
+            optional: true,
+            default: false,
+        },
+        limit: {
+            type: Integer,
+            description: "Max. number of entries.",
+            optional: true,
+            minimum: 1,
+            default: 50,
+        },
+        since: {
+            type: Integer,
+            description: "Only return entries since this UNIX epoch.",
+            optional: true,
+            minimum: 0,
+        },
+        until: {
+            type: Integer,
+            description: "Only return entries until this UNIX epoch.",
+            optional: true,
+            minimum: 0,
+        },
+        unit: {
+            type: String,
+            description: "Only return entries of this systemd unit. Defaults to a service if no unit type suffix is given.",
+            optional: true,
+            max_length: 256,
+        },
+        identifier: {
+            type: String,
+            description: "Only return entries with this syslog identifier.",
+            optional: true,
+            max_length: 256,
+        },
+        priority: {
+            type: JournalPriority,
+            optional: true,
+        },
+        boot: {
+            type: String,
+            description: "Only return entries of this boot ID, or 'current' for the running boot.",
+            optional: true,
+            max_length: 36,
+        },
+        search: {
+            type: String,
+            description: "Only return entries whose message contains this text (case-insensitive).",
+            optional: true,
+            max_length: 256,
+        },
+    }
+)]
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Journal query options with cursor based paging.
+pub struct JournalQuery {
+    pub cursor: Option<String>,
+    pub reverse: Option<bool>,
+    pub limit: Option<u64>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub unit: Option<String>,
+    pub identifier: Option<String>,
+    /// Only return entries of this or a more important priority.
+    pub priority: Option<JournalPriority>,
+    pub boot: Option<String>,
+    pub search: Option<String>,
+}
+
+#[api(
+    properties: {
+        priority: {
+            type: JournalPriority,
+            optional: true,
+        },
+    }
+)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// A structured journal entry.
+pub struct JournalEntry {
+    /// The cursor of the entry.
+    pub cursor: String,
+    /// Time of the entry in microseconds since the UNIX epoch.
+    pub time: u64,
+    /// The boot ID of the entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boot_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<JournalPriority>,
+    /// The host name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// The syslog identifier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    /// The systemd unit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// The process ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// The message text.
+    pub message: String,
+}
+
+#[api(
+    properties: {
+        entries: {
+            type: Array,
+            items: {
+                type: JournalEntry,
+            },
+        },
+    }
+)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+/// A page of journal entries.
+pub struct JournalPage {
+    /// The journal entries.
+    pub entries: Vec<JournalEntry>,
+    /// Cursor of the last returned entry, pass it as 'cursor' to continue reading.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
@@ -1,28 +1,107 @@
-use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
+use std::io;
 
-use anyhow::Error;
+use anyhow::{Error, bail, format_err};
 
-use super::{JournalFilter, SyslogFilter, SyslogLine};
+use proxmox_systemd::journal::Journal;
+
+use super::{
+    JournalEntry, JournalFilter, JournalPage, JournalPriority, JournalQuery, SyslogFilter,
+    SyslogLine,
+};
+
+/// The journal operations used by the API implementations, so that they can be tested without
+/// the system journal.
+trait JournalReader {
+    fn add_match(&mut self, field: &str, value: &[u8]) -> io::Result<()>;
+    fn add_disjunction(&mut self) -> io::Result<()>;
+    fn add_conjunction(&mut self) -> io::Result<()>;
+    fn seek_head(&mut self) -> io::Result<()>;
+    fn seek_tail(&mut self) -> io::Result<()>;
+    fn seek_realtime_usec(&mut self, usec: u64) -> io::Result<()>;
+    fn seek_cursor(&mut self, cursor: &str) -> io::Result<()>;
+    fn test_cursor(&mut self, cursor: &str) -> io::Result<bool>;
+    fn next_entry(&mut self) -> io::Result<bool>;
+    fn previous_entry(&mut self) -> io::Result<bool>;
+    fn cursor(&mut self) -> io::Result<String>;
+    fn realtime_usec(&mut self) -> io::Result<u64>;
+    fn data_string(&mut self, field: &str) -> io::Result<Option<String>>;
+}
+
+impl JournalReader for Journal {
+    fn add_match(&mut self, field: &str, value: &[u8]) -> io::Result<()> {
+        Journal::add_match(self, field, value)
+    }
+    fn add_disjunction(&mut self) -> io::Result<()> {
+        Journal::add_disjunction(self)
+    }
+    fn add_conjunction(&mut self) -> io::Result<()> {
+        Journal::add_conjunction(self)
+    }
+    fn seek_head(&mut self) -> io::Result<()> {
+        Journal::seek_head(self)
+    }
+    fn seek_tail(&mut self) -> io::Result<()> {
+        Journal::seek_tail(self)
+    }
+    fn seek_realtime_usec(&mut self, usec: u64) -> io::Result<()> {
+        Journal::seek_realtime_usec(self, usec)
+    }
+    fn seek_cursor(&mut self, cursor: &str) -> io::Result<()> {
+        Journal::seek_cursor(self, cursor)
+    }
+    fn test_cursor(&mut self, cursor: &str) -> io::Result<bool> {
+        Journal::test_cursor(self, cursor)
+    }
+    fn next_entry(&mut self) -> io::Result<bool> {
+        Journal::next_entry(self)
+    }
+    fn previous_entry(&mut self) -> io::Result<bool> {
+        Journal::previous_entry(self)
+    }
+    fn cursor(&mut self) -> io::Result<String> {
+        Journal::cursor(self)
+    }
+    fn realtime_usec(&mut self) -> io::Result<u64> {
+        Journal::realtime_usec(self)
+    }
+    fn data_string(&mut self, field: &str) -> io::Result<Option<String>> {
+        Journal::data_string(self, field)
+    }
+}
 
 /// Syslog API implementation
 ///
-/// The syslog api uses `journalctl' to get the log entries, and
+/// The syslog api reads the log entries directly from the system journal, and
 /// uses paging to limit the amount of data returned (start, limit).
 ///
 /// Note: Please use [dump_journal] for live view, because that is more performant
 /// for that case.
 pub fn dump_syslog(filter: SyslogFilter) -> Result<(u64, Vec<SyslogLine>), Error> {
-    let mut args = vec!["-o", "short", "--no-pager"];
+    dump_syslog_from(&mut Journal::open_local()?, filter)
+}
 
+fn dump_syslog_from(
+    journal: &mut impl JournalReader,
+    filter: SyslogFilter,
+) -> Result<(u64, Vec<SyslogLine>), Error> {
     if let Some(service) = &filter.service {
-        args.extend(["--unit", service]);
+        add_unit_match(journal, service)?;
     }
-    if let Some(since) = &filter.since {
-        args.extend(["--since", since]);
-    }
-    if let Some(until) = &filter.until {
-        args.extend(["--until", until]);
+
+    let since = filter
+        .since
+        .as_deref()
+        .map(parse_systemd_datetime)
+        .transpose()?;
+    let until = filter
+        .until
+        .as_deref()
+        .map(parse_systemd_datetime)
+        .transpose()?;
+
+    match since {
+        Some(since) => journal.seek_realtime_usec(since)?,
+        None => journal.seek_head()?,
     }
 
     let mut lines: Vec<SyslogLine> = Vec::new();
@@ -30,41 +109,30 @@ pub fn dump_syslog(filter: SyslogFilter) -> Result<(u64, Vec<SyslogLine>), Error
     let start = filter.start.unwrap_or(0);
     let mut count: u64 = 0;
 
-    let mut child = Command::new("journalctl")
-        .args(&args)
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    use std::io::{BufRead, BufReader};
-
-    if let Some(ref mut stdout) = child.stdout {
-        for line in BufReader::new(stdout).lines() {
-            match line {
-                Ok(line) => {
-                    count += 1;
-                    if count < start {
-                        continue;
-                    };
-                    if limit == 0 {
-                        continue;
-                    };
+    while journal.next_entry()? {
+        let time = journal.realtime_usec()?;
+        if since.is_some_and(|since| time < since) {
+            continue;
+        }
+        if until.is_some_and(|until| time > until) {
+            break;
+        }
 
-                    lines.push(SyslogLine { n: count, t: line });
+        count += 1;
+        if count < start {
+            continue;
+        };
+        if limit == 0 {
+            continue;
+        };
 
-                    limit -= 1;
-                }
-                Err(err) => {
-                    log::error!("reading journal failed: {}", err);
-                    let _ = child.kill();
-                    break;
-                }
-            }
-        }
-    }
+        let entry = read_entry(journal, time)?;
+        lines.push(SyslogLine {
+            n: count,
+            t: format_short(&entry)?,
+        });
 
-    let status = child.wait().unwrap();
-    if !status.success() {
-        log::error!("journalctl failed with {}", status);
+        limit -= 1;
     }
 
     // HACK: ExtJS store.guaranteeRange() does not like empty array
@@ -82,62 +150,812 @@ pub fn dump_syslog(filter: SyslogFilter) -> Result<(u64, Vec<SyslogLine>), Error
 
 /// Journal API implementation
 ///
-/// The journal api uses `mini-journalreader' binary to get the log entries.
-/// The cursor based api allows to implement live view efficiently.
+/// The cursor based api allows to implement live view efficiently. The first
+/// and last line of the result are the cursors of the first and last entry, so
+/// that the caller can continue reading in both directions.
 pub fn dump_journal(filter: JournalFilter) -> Result<Vec<String>, Error> {
-    let mut args = vec![];
+    dump_journal_from(&mut Journal::open_local()?, filter)
+}
+
+fn dump_journal_from(
+    journal: &mut impl JournalReader,
+    filter: JournalFilter,
+) -> Result<Vec<String>, Error> {
+    let since = filter.since.map(|since| since.saturating_mul(1_000_000));
+    let until = filter
+        .until
+        .map(|until| until.saturating_add(1).saturating_mul(1_000_000));
+
+    let mut entries = Vec::new();
+
+    if filter.startcursor.is_some() || since.is_some() {
+        // read forward from the start position
+        let mut skip_cursor = None;
+        if let Some(cursor) = &filter.startcursor {
+            journal.seek_cursor(cursor)?;
+            skip_cursor = Some(cursor);
+        } else if let Some(since) = since {
+            journal.seek_realtime_usec(since)?;
+        }
+
+        let limit = filter.lastentries.unwrap_or(u64::MAX);
+        while (entries.len() as u64) < limit && journal.next_entry()? {
+            if let Some(cursor) = skip_cursor.take() {
+                if journal.test_cursor(cursor)? {
+                    continue;
+                }
+            }
+            if let Some(cursor) = &filter.endcursor {
+                if journal.test_cursor(cursor)? {
+                    break;
+                }
+            }
+            let time = journal.realtime_usec()?;
+            if since.is_some_and(|since| time < since) {
+                continue;
+            }
+            if until.is_some_and(|until| time >= until) {
+                break;
+            }
+            entries.push(read_entry(journal, time)?);
+        }
+    } else {
+        // read the last entries backwards from the end position
+        let mut skip_cursor = None;
+        if let Some(cursor) = &filter.endcursor {
+            journal.seek_cursor(cursor)?;
+            skip_cursor = Some(cursor);
+        } else if let Some(until) = until {
+            journal.seek_realtime_usec(until)?;
+        } else {
+            journal.seek_tail()?;
+        }
+
+        let limit = filter.lastentries.unwrap_or(50);
+        while (entries.len() as u64) < limit && journal.previous_entry()? {
+            if let Some(cursor) = skip_cursor.take() {
+                if journal.test_cursor(cursor)? {
+                    continue;
+                }
+            }
+            let time = journal.realtime_usec()?;
+            if until.is_some_and(|until| time >= until) {
+                continue;
+            }
+            entries.push(read_entry(journal, time)?);
+        }
+        entries.reverse();
+    }
+
+    let start_cursor = match entries.first() {
+        Some(entry) => entry.cursor.clone(),
+        None => filter
+            .startcursor
+            .clone()
+            .or_else(|| filter.endcursor.clone())
+            .unwrap_or_default(),
+    };
+    let end_cursor = match entries.last() {
+        Some(entry) => entry.cursor.clone(),
+        None => filter.startcursor.or(filter.endcursor).unwrap_or_default(),
+    };
+
+    let mut lines = Vec::with_capacity(entries.len() + 2);
+    lines.push(start_cursor);
+
+    let mut last_boot_id = None;
+    for entry in entries.iter() {
+        if last_boot_id.is_some() && last_boot_id != entry.boot_id.as_ref() {
+            lines.push(String::from("-- Reboot --"));
+        }
+        last_boot_id = entry.boot_id.as_ref();
+        lines.push(format_short(entry)?);
+    }
+
+    lines.push(end_cursor);
+
+    Ok(lines)
+}
+
+/// Read structured entries from the system journal.
+///
+/// Returns at most `limit` entries matching the query, starting after `cursor`, or at
+/// `since`/`until` (depending on the reading direction) if no cursor is given. The returned
+/// cursor can be passed to the next query to continue reading.
+pub fn read_journal(query: JournalQuery) -> Result<JournalPage, Error> {
+    read_journal_from(&mut Journal::open_local()?, query)
+}
+
+fn read_journal_from(
+    journal: &mut impl JournalReader,
+    query: JournalQuery,
+) -> Result<JournalPage, Error> {
+    if let Some(unit) = &query.unit {
+        add_unit_match(journal, unit)?;
+        journal.add_conjunction()?;
+    }
+    if let Some(identifier) = &query.identifier {
+        journal.add_match("SYSLOG_IDENTIFIER", identifier.as_bytes())?;
+    }
+    if let Some(priority) = query.priority {
+        // matches on the same field are combined with OR
+        for level in 0..=priority.level() {
+            journal.add_match("PRIORITY", level.to_string().as_bytes())?;
+        }
+    }
+    if let Some(boot) = &query.boot {
+        journal.add_match("_BOOT_ID", parse_boot_id(boot)?.as_bytes())?;
+    }
+
+    let reverse = query.reverse.unwrap_or(false);
+    let limit = query.limit.unwrap_or(50);
+    let since = query.since.map(|since| since.saturating_mul(1_000_000));
+    let until = query
+        .until
+        .map(|until| until.saturating_add(1).saturating_mul(1_000_000));
+    let search = query.search.as_deref().map(str::to_lowercase);
+
+    let mut skip_cursor = None;
+    match (&query.cursor, reverse) {
+        (Some(cursor), _) => {
+            journal.seek_cursor(cursor)?;
+            skip_cursor = Some(cursor);
+        }
+        (None, false) => match since {
+            Some(since) => journal.seek_realtime_usec(since)?,
+            None => journal.seek_head()?,
+        },
+        (None, true) => match until {
+            Some(until) => journal.seek_realtime_usec(until)?,
+            None => journal.seek_tail()?,
+        },
+    }
+
+    let mut entries = Vec::new();
+    while (entries.len() as u64) < limit {
+        let found = if reverse {
+            journal.previous_entry()?
+        } else {
+            journal.next_entry()?
+        };
+        if !found {
+            break;
+        }
+
+        if let Some(cursor) = skip_cursor.take() {
+            if journal.test_cursor(cursor)? {
+                continue;
+            }
+        }
+
+        let time = journal.realtime_usec()?;
+        let too_old = since.is_some_and(|since| time < since);
+        let too_new = until.is_some_and(|until| time >= until);
+        match (reverse, too_old, too_new) {
+            (false, _, true) | (true, true, _) => break,
+            (_, true, _) | (_, _, true) => continue,
+            _ => (),
+        }
+
+        let entry = read_entry(journal, time)?;
+        if let Some(search) = &search {
+            if !entry.message.to_lowercase().contains(search) {
+                continue;
+            }
+        }
+        entries.push(entry);
+    }
+
+    let cursor = match entries.last() {
+        Some(entry) => Some(entry.cursor.clone()),
+        None => query.cursor,
+    };
+
+    Ok(JournalPage { entries, cursor })
+}
+
+/// Match the entries of a systemd unit like `journalctl --unit` does, including the messages
+/// systemd itself logs about the unit.
+fn add_unit_match(journal: &mut impl JournalReader, unit: &str) -> Result<(), Error> {
+    let unit = unit_name(unit);
+
+    journal.add_match("_SYSTEMD_UNIT", unit.as_bytes())?;
+    journal.add_disjunction()?;
+    journal.add_match("_PID", b"1")?;
+    journal.add_match("UNIT", unit.as_bytes())?;
+
+    Ok(())
+}
 
-    if let Some(lastentries) = filter.lastentries {
-        args.push(String::from("-n"));
-        args.push(format!("{lastentries}"));
+/// Append `.service` to unit names without a unit type suffix.
+fn unit_name(unit: &str) -> String {
+    if unit.contains('.') {
+        unit.to_string()
+    } else {
+        format!("{unit}.service")
     }
+}
+
+/// Parse a boot ID in hex notation, with or without dashes, or 'current' for the running boot.
+fn parse_boot_id(boot: &str) -> Result<String, Error> {
+    let boot_id = if boot == "current" {
+        std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+            .map_err(|err| format_err!("unable to read current boot ID - {err}"))?
+    } else {
+        boot.to_string()
+    };
 
-    if let Some(since) = filter.since {
-        args.push(String::from("-b"));
-        args.push(since.to_string());
+    let boot_id: String = boot_id
+        .trim()
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    if boot_id.len() != 32 || !boot_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("invalid boot ID '{boot}'");
     }
 
-    if let Some(until) = filter.until {
-        args.push(String::from("-e"));
-        args.push(until.to_string());
+    Ok(boot_id)
+}
+
+/// Parse a local date-time string in the format `YYYY-MM-DD[ HH:MM[:SS]]` to microseconds since
+/// the UNIX epoch.
+///
+/// All components are range checked, as the conversion would silently normalize them, e.g. the
+/// 31st of February to the 2nd or 3rd of March.
+fn parse_systemd_datetime(value: &str) -> Result<u64, Error> {
+    let err = || format_err!("invalid date-time string '{value}'");
+
+    let (date, time) = value.split_once(' ').unwrap_or((value, "00:00"));
+
+    let parse = |component: &str| -> Result<i32, Error> {
+        if component.is_empty() || !component.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+        component.parse().map_err(|_| err())
+    };
+
+    let date = date.split('-').map(parse).collect::<Result<Vec<_>, _>>()?;
+    let time = time.split(':').map(parse).collect::<Result<Vec<_>, _>>()?;
+
+    let (year, month, day) = match date[..] {
+        [year, month, day] => (year, month, day),
+        _ => return Err(err()),
+    };
+    let (hour, min, sec) = match time[..] {
+        [hour, min] => (hour, min, 0),
+        [hour, min, sec] => (hour, min, sec),
+        _ => return Err(err()),
+    };
+
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || !(0..=23).contains(&hour)
+        || !(0..=59).contains(&min)
+        || !(0..=59).contains(&sec)
+    {
+        return Err(err());
     }
 
-    if let Some(startcursor) = &filter.startcursor {
-        args.push(String::from("-f"));
-        args.push(startcursor.to_string());
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = year - 1900;
+    tm.tm_mon = month - 1;
+    tm.tm_mday = day;
+    tm.tm_hour = hour;
+    tm.tm_min = min;
+    tm.tm_sec = sec;
+    tm.tm_isdst = -1;
+
+    let epoch = proxmox_time::timelocal(&mut tm)?;
+
+    Ok(u64::try_from(epoch).map_err(|_| err())? * 1_000_000)
+}
+
+fn days_in_month(year: i32, month: i32) -> i32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
     }
+}
+
+fn read_entry(journal: &mut impl JournalReader, time: u64) -> Result<JournalEntry, Error> {
+    let priority = journal
+        .data_string("PRIORITY")?
+        .and_then(|priority| priority.parse().ok())
+        .and_then(JournalPriority::from_level);
+
+    let identifier = match journal.data_string("SYSLOG_IDENTIFIER")? {
+        Some(identifier) => Some(identifier),
+        None => journal.data_string("_COMM")?,
+    };
 
-    if let Some(endcursor) = &filter.endcursor {
-        args.push(String::from("-t"));
-        args.push(endcursor.to_string());
+    let pid = match journal.data_string("_PID")? {
+        Some(pid) => Some(pid),
+        None => journal.data_string("SYSLOG_PID")?,
+    };
+
+    Ok(JournalEntry {
+        cursor: journal.cursor()?,
+        time,
+        boot_id: journal.data_string("_BOOT_ID")?,
+        priority,
+        hostname: journal.data_string("_HOSTNAME")?,
+        identifier,
+        unit: journal.data_string("_SYSTEMD_UNIT")?,
+        pid: pid.and_then(|pid| pid.parse().ok()),
+        message: journal.data_string("MESSAGE")?.unwrap_or_default(),
+    })
+}
+
+/// Format an entry like the `short` output format of `journalctl`.
+fn format_short(entry: &JournalEntry) -> Result<String, Error> {
+    let mut line = proxmox_time::strftime_local("%b %d %H:%M:%S", (entry.time / 1_000_000) as i64)?;
+
+    if let Some(hostname) = &entry.hostname {
+        line.push(' ');
+        line.push_str(hostname);
     }
 
-    let mut lines: Vec<String> = vec![];
+    line.push(' ');
+    line.push_str(entry.identifier.as_deref().unwrap_or("unknown"));
+    if let Some(pid) = entry.pid {
+        line.push_str(&format!("[{pid}]"));
+    }
+    line.push_str(": ");
+    line.push_str(&entry.message);
 
-    let mut child = Command::new("mini-journalreader")
-        .args(&args)
-        .stdout(Stdio::piped())
-        .spawn()?;
+    Ok(line)
+}
 
-    if let Some(ref mut stdout) = child.stdout {
-        for line in BufReader::new(stdout).lines() {
-            match line {
-                Ok(line) => {
-                    lines.push(line);
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unit_name() {
+        assert_eq!(unit_name("pveproxy"), "pveproxy.service");
+        assert_eq!(unit_name("pveproxy.service"), "pveproxy.service");
+        assert_eq!(unit_name("proxmox-backup.timer"), "proxmox-backup.timer");
+    }
+
+    #[test]
+    fn test_parse_boot_id() {
+        assert_eq!(
+            parse_boot_id("0B1B3D7E-5C0C-4A24-9F3E-21B7C6B0E5A1").unwrap(),
+            "0b1b3d7e5c0c4a249f3e21b7c6b0e5a1"
+        );
+        assert!(parse_boot_id("0b1b3d7e").is_err());
+        assert!(parse_boot_id("zz1b3d7e5c0c4a249f3e21b7c6b0e5a1").is_err());
+    }
+
+    #[test]
+    fn test_parse_systemd_datetime() {
+        let date = parse_systemd_datetime("2024-02-29").unwrap();
+        let minutes = parse_systemd_datetime("2024-02-29 12:30").unwrap();
+        let seconds = parse_systemd_datetime("2024-02-29 12:30:15").unwrap();
+
+        assert_eq!(minutes - date, (12 * 3600 + 30 * 60) * 1_000_000);
+        assert_eq!(seconds - minutes, 15 * 1_000_000);
+
+        assert!(parse_systemd_datetime("2024-02").is_err());
+        assert!(parse_systemd_datetime("2024-02-29 12").is_err());
+        assert!(parse_systemd_datetime("yesterday").is_err());
+
+        // out of range components are not normalized
+        assert!(parse_systemd_datetime("2023-02-29").is_err());
+        assert!(parse_systemd_datetime("2024-04-31").is_err());
+        assert!(parse_systemd_datetime("2024-13-01").is_err());
+        assert!(parse_systemd_datetime("2024-00-10").is_err());
+        assert!(parse_systemd_datetime("2024-01-00").is_err());
+        assert!(parse_systemd_datetime("2024-01-01 24:00").is_err());
+        assert!(parse_systemd_datetime("2024-01-01 12:60").is_err());
+        assert!(parse_systemd_datetime("2024-01-01 12:00:60").is_err());
+        assert!(parse_systemd_datetime("1969-12-31").is_err());
+        assert!(parse_systemd_datetime("2024-+1-01").is_err());
+        assert!(parse_systemd_datetime("2024-01-01 12:-5").is_err());
+        assert!(parse_systemd_datetime("2000-02-29").is_ok());
+        assert!(parse_systemd_datetime("2100-02-29").is_err());
+    }
+
+    /// Field matches, where matches of the same field are combined with OR, others with AND.
+    type MatchSet = Vec<(String, Vec<u8>)>;
+
+    /// In-memory journal implementing the seek and match semantics of `sd-journal(3)`.
+    #[derive(Default)]
+    struct MockJournal {
+        entries: Vec<(u64, Vec<(&'static str, String)>)>,
+        /// Matches as conjunction of disjunctions of match sets.
+        matches: Vec<Vec<MatchSet>>,
+        position: Position,
+    }
+
+    #[derive(Default)]
+    enum Position {
+        #[default]
+        Head,
+        Tail,
+        Realtime(u64),
+        Cursor(String),
+        Entry(usize),
+    }
+
+    impl MockJournal {
+        fn new(entries: &[(u64, &str, &str, &str)]) -> Self {
+            let entries = entries
+                .iter()
+                .map(|(time, unit, boot_id, message)| {
+                    let fields = vec![
+                        ("_SYSTEMD_UNIT", unit.to_string()),
+                        (
+                            "SYSLOG_IDENTIFIER",
+                            unit.trim_end_matches(".service").to_string(),
+                        ),
+                        ("_BOOT_ID", boot_id.to_string()),
+                        ("_PID", "42".to_string()),
+                        (
+                            "PRIORITY",
+                            if message.contains("error") { "3" } else { "6" }.to_string(),
+                        ),
+                        ("MESSAGE", message.to_string()),
+                    ];
+                    (*time, fields)
+                })
+                .collect();
+            Self {
+                entries,
+                ..Default::default()
+            }
+        }
+
+        fn field(&self, index: usize, field: &str) -> Option<&str> {
+            self.entries[index]
+                .1
+                .iter()
+                .find(|(name, _)| *name == field)
+                .map(|(_, value)| value.as_str())
+        }
+
+        fn matches(&self, index: usize) -> bool {
+            let set_matches = |set: &MatchSet| {
+                set.iter().all(|(field, _)| {
+                    set.iter().any(|(other, value)| {
+                        other == field && self.field(index, field).map(str::as_bytes) == Some(value)
+                    })
+                })
+            };
+            self.matches.iter().all(|disjunction| {
+                disjunction.iter().all(Vec::is_empty) || disjunction.iter().any(set_matches)
+            })
+        }
+
+        fn current(&self) -> io::Result<usize> {
+            match self.position {
+                Position::Entry(index) => Ok(index),
+                _ => Err(io::Error::from_raw_os_error(libc::EADDRNOTAVAIL)),
+            }
+        }
+
+        fn cursor_of(index: usize) -> String {
+            format!("s=mock;i={index}")
+        }
+
+        fn step(&mut self, next: bool) -> io::Result<bool> {
+            let indices: Vec<usize> = (0..self.entries.len())
+                .filter(|i| self.matches(*i))
+                .collect();
+            let time = |i: &usize| self.entries[*i].0;
+            let found = match (&self.position, next) {
+                (Position::Head, true) => indices.first().copied(),
+                (Position::Tail, false) => indices.last().copied(),
+                (Position::Head, false) | (Position::Tail, true) => None,
+                (Position::Realtime(usec), true) => {
+                    indices.iter().copied().find(|i| time(i) >= *usec)
                 }
-                Err(err) => {
-                    log::error!("reading journal failed: {}", err);
-                    let _ = child.kill();
-                    break;
+                (Position::Realtime(usec), false) => {
+                    indices.iter().copied().rev().find(|i| time(i) < *usec)
+                }
+                (Position::Cursor(cursor), _) => {
+                    let cursor = cursor.clone();
+                    let mut candidates = indices.iter().copied();
+                    if next {
+                        candidates.find(|i| Self::cursor_of(*i) >= cursor)
+                    } else {
+                        candidates.rev().find(|i| Self::cursor_of(*i) <= cursor)
+                    }
+                }
+                (Position::Entry(index), true) => indices.iter().copied().find(|i| i > index),
+                (Position::Entry(index), false) => {
+                    indices.iter().copied().rev().find(|i| i < index)
                 }
+            };
+            match found {
+                Some(index) => {
+                    self.position = Position::Entry(index);
+                    Ok(true)
+                }
+                None => Ok(false),
             }
         }
     }
 
-    let status = child.wait().unwrap();
-    if !status.success() {
-        log::error!("journalctl failed with {}", status);
+    impl JournalReader for MockJournal {
+        fn add_match(&mut self, field: &str, value: &[u8]) -> io::Result<()> {
+            if self.matches.is_empty() {
+                self.matches.push(vec![Vec::new()]);
+            }
+            let disjunction = self.matches.last_mut().unwrap();
+            if disjunction.is_empty() {
+                disjunction.push(Vec::new());
+            }
+            let set = disjunction.last_mut().unwrap();
+            set.push((field.to_string(), value.to_vec()));
+            Ok(())
+        }
+        fn add_disjunction(&mut self) -> io::Result<()> {
+            if let Some(disjunction) = self.matches.last_mut() {
+                disjunction.push(Vec::new());
+            }
+            Ok(())
+        }
+        fn add_conjunction(&mut self) -> io::Result<()> {
+            self.matches.push(vec![Vec::new()]);
+            Ok(())
+        }
+        fn seek_head(&mut self) -> io::Result<()> {
+            self.position = Position::Head;
+            Ok(())
+        }
+        fn seek_tail(&mut self) -> io::Result<()> {
+            self.position = Position::Tail;
+            Ok(())
+        }
+        fn seek_realtime_usec(&mut self, usec: u64) -> io::Result<()> {
+            self.position = Position::Realtime(usec);
+            Ok(())
+        }
+        fn seek_cursor(&mut self, cursor: &str) -> io::Result<()> {
+            self.position = Position::Cursor(cursor.to_string());
+            Ok(())
+        }
+        fn test_cursor(&mut self, cursor: &str) -> io::Result<bool> {
+            Ok(Self::cursor_of(self.current()?) == cursor)
+        }
+        fn next_entry(&mut self) -> io::Result<bool> {
+            self.step(true)
+        }
+        fn previous_entry(&mut self) -> io::Result<bool> {
+            self.step(false)
+        }
+        fn cursor(&mut self) -> io::Result<String> {
+            Ok(Self::cursor_of(self.current()?))
+        }
+        fn realtime_usec(&mut self) -> io::Result<u64> {
+            Ok(self.entries[self.current()?].0)
+        }
+        fn data_string(&mut self, field: &str) -> io::Result<Option<String>> {
+            let index = self.current()?;
+            Ok(self.field(index, field).map(String::from))
+        }
     }
 
-    Ok(lines)
+    const BOOT_1: &str = "0b1b3d7e5c0c4a249f3e21b7c6b0e5a1";
+    const BOOT_2: &str = "1c2c4e8f6d1d5b35a04f32c8d7c1f6b2";
+
+    fn test_journal() -> MockJournal {
+        MockJournal::new(&[
+            (10_000_000, "pveproxy.service", BOOT_1, "starting"),
+            (20_000_000, "sshd.service", BOOT_1, "accepted key"),
+            (30_000_000, "pveproxy.service", BOOT_1, "an error occurred"),
+            (40_000_000, "pveproxy.service", BOOT_2, "restarted"),
+            (
+                50_000_000,
+                "sshd.service",
+                BOOT_2,
+                "Error: connection reset",
+            ),
+        ])
+    }
+
+    fn messages(page: &JournalPage) -> Vec<&str> {
+        page.entries
+            .iter()
+            .map(|entry| entry.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_read_journal() -> Result<(), Error> {
+        let page = read_journal_from(&mut test_journal(), JournalQuery::default())?;
+        assert_eq!(page.entries.len(), 5);
+        assert_eq!(page.entries[0].identifier.as_deref(), Some("pveproxy"));
+        assert_eq!(page.entries[0].pid, Some(42));
+        assert_eq!(page.entries[2].priority, Some(JournalPriority::Err));
+        assert_eq!(page.cursor, Some(MockJournal::cursor_of(4)));
+
+        // paging forward and backward continues after the cursor
+        let query = JournalQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = read_journal_from(&mut test_journal(), query.clone())?;
+        assert_eq!(messages(&page), ["starting", "accepted key"]);
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                cursor: page.cursor,
+                ..query.clone()
+            },
+        )?;
+        assert_eq!(messages(&page), ["an error occurred", "restarted"]);
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                reverse: Some(true),
+                cursor: page.cursor,
+                ..query.clone()
+            },
+        )?;
+        assert_eq!(messages(&page), ["an error occurred", "accepted key"]);
+
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                reverse: Some(true),
+                ..query
+            },
+        )?;
+        assert_eq!(messages(&page), ["Error: connection reset", "restarted"]);
+
+        // the time range includes the whole second of 'until'
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                since: Some(20),
+                until: Some(40),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(
+            messages(&page),
+            ["accepted key", "an error occurred", "restarted"]
+        );
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                since: Some(20),
+                until: Some(39),
+                reverse: Some(true),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(messages(&page), ["an error occurred", "accepted key"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_journal_filters() -> Result<(), Error> {
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                unit: Some("sshd".into()),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(messages(&page), ["accepted key", "Error: connection reset"]);
+
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                unit: Some("pveproxy".into()),
+                priority: Some(JournalPriority::Warning),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(messages(&page), ["an error occurred"]);
+
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                boot: Some(BOOT_2.to_uppercase()),
+                search: Some("ERROR".into()),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(messages(&page), ["Error: connection reset"]);
+
+        // no entries keep the cursor, so polling can continue
+        let page = read_journal_from(
+            &mut test_journal(),
+            JournalQuery {
+                cursor: Some(MockJournal::cursor_of(4)),
+                ..Default::default()
+            },
+        )?;
+        assert!(page.entries.is_empty());
+        assert_eq!(page.cursor, Some(MockJournal::cursor_of(4)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_journal() -> Result<(), Error> {
+        let filter = JournalFilter {
+            since: None,
+            until: None,
+            lastentries: None,
+            startcursor: None,
+            endcursor: None,
+        };
+
+        // the last entries, between the cursors of the first and last entry
+        let lines = dump_journal_from(
+            &mut test_journal(),
+            JournalFilter {
+                lastentries: Some(3),
+                ..filter.clone()
+            },
+        )?;
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], MockJournal::cursor_of(2));
+        assert!(lines[1].ends_with("pveproxy[42]: an error occurred"));
+        assert_eq!(lines[2], "-- Reboot --");
+        assert!(lines[3].ends_with("pveproxy[42]: restarted"));
+        assert!(lines[4].ends_with("sshd[42]: Error: connection reset"));
+        assert_eq!(lines[5], MockJournal::cursor_of(4));
+
+        // live view, continue after the last cursor
+        let lines = dump_journal_from(
+            &mut test_journal(),
+            JournalFilter {
+                startcursor: Some(MockJournal::cursor_of(3)),
+                ..filter.clone()
+            },
+        )?;
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with("Error: connection reset"));
+
+        // nothing new keeps the cursor
+        let lines = dump_journal_from(
+            &mut test_journal(),
+            JournalFilter {
+                startcursor: Some(MockJournal::cursor_of(4)),
+                ..filter.clone()
+            },
+        )?;
+        assert_eq!(
+            lines,
+            [MockJournal::cursor_of(4), MockJournal::cursor_of(4)]
+        );
+
+        // reading backwards ends before the end cursor
+        let lines = dump_journal_from(
+            &mut test_journal(),
+            JournalFilter {
+                lastentries: Some(1),
+                endcursor: Some(MockJournal::cursor_of(2)),
+                ..filter.clone()
+            },
+        )?;
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with("accepted key"));
+
+        let lines = dump_journal_from(
+            &mut test_journal(),
+            JournalFilter {
+                since: Some(20),
+                until: Some(30),
+                ..filter
+            },
+        )?;
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].ends_with("accepted key"));
+        assert!(lines[2].ends_with("an error occurred"));
+
+        Ok(())
+    }
 }
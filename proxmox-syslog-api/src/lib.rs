@@ -6,4 +6,4 @@ pub use api_types::*;
 #[cfg(feature = "impl")]
 mod journal;
 #[cfg(feature = "impl")]
-pub use journal::{dump_journal, dump_syslog};
+pub use journal::{dump_journal, dump_syslog, read_journal};
@@ -1,7 +1,8 @@
-use std::ffi::{CString, OsStr, c_int};
+use std::ffi::{CStr, CString, OsStr, c_char, c_int, c_void};
 use std::io;
 use std::os::fd::{FromRawFd, OwnedFd};
 use std::os::unix::ffi::OsStrExt;
+use std::ptr::NonNull;
 
 use crate::sys;
 
@@ -21,3 +22,162 @@ pub fn stream_fd<I: AsRef<OsStr>>(
         Ok(unsafe { OwnedFd::from_raw_fd(fd) })
     }
 }
+
+/// A reader for the local system journal, see `sd-journal(3)`.
+///
+/// The reader has a current position, which is moved with [`next_entry`](Self::next_entry) and
+/// [`previous_entry`](Self::previous_entry) after seeking to a start position. The data of the entry at the
+/// current position can then be accessed, e.g. via [`data`](Self::data). Only entries matching
+/// the configured matches are visited.
+pub struct Journal {
+    journal: NonNull<sys::sd_journal>,
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        unsafe { sys::sd_journal_close(self.journal.as_ptr()) }
+    }
+}
+
+fn to_cstring(value: &str) -> Result<CString, io::Error> {
+    CString::new(value).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid string"))
+}
+
+impl Journal {
+    /// Open the journal files of the local machine.
+    pub fn open_local() -> Result<Self, io::Error> {
+        let mut journal = std::ptr::null_mut();
+        sys::check_call(unsafe { sys::sd_journal_open(&mut journal, sys::SD_JOURNAL_LOCAL_ONLY) })?;
+        let journal = NonNull::new(journal)
+            .ok_or_else(|| io::Error::other("sd_journal_open returned no journal"))?;
+        Ok(Self { journal })
+    }
+
+    fn as_ptr(&self) -> *mut sys::sd_journal {
+        self.journal.as_ptr()
+    }
+
+    /// Only visit entries where `field` has the given `value`.
+    ///
+    /// Matches on the same field are combined with OR, matches on different fields with AND,
+    /// unless separated by [`add_disjunction`](Self::add_disjunction).
+    pub fn add_match(&mut self, field: &str, value: &[u8]) -> Result<(), io::Error> {
+        let mut data = Vec::with_capacity(field.len() + 1 + value.len());
+        data.extend_from_slice(field.as_bytes());
+        data.push(b'=');
+        data.extend_from_slice(value);
+        sys::check_call(unsafe {
+            sys::sd_journal_add_match(self.as_ptr(), data.as_ptr().cast(), data.len())
+        })
+        .map(drop)
+    }
+
+    /// Combine the matches added before and after this call with OR.
+    pub fn add_disjunction(&mut self) -> Result<(), io::Error> {
+        sys::check_call(unsafe { sys::sd_journal_add_disjunction(self.as_ptr()) }).map(drop)
+    }
+
+    /// Combine the matches added before and after this call with AND.
+    pub fn add_conjunction(&mut self) -> Result<(), io::Error> {
+        sys::check_call(unsafe { sys::sd_journal_add_conjunction(self.as_ptr()) }).map(drop)
+    }
+
+    /// Remove all matches.
+    pub fn flush_matches(&mut self) {
+        unsafe { sys::sd_journal_flush_matches(self.as_ptr()) }
+    }
+
+    /// Seek to the start of the journal, the next call to [`next_entry`](Self::next_entry) moves to the
+    /// oldest entry.
+    pub fn seek_head(&mut self) -> Result<(), io::Error> {
+        sys::check_call(unsafe { sys::sd_journal_seek_head(self.as_ptr()) }).map(drop)
+    }
+
+    /// Seek to the end of the journal, the next call to [`previous_entry`](Self::previous_entry) moves to
+    /// the newest entry.
+    pub fn seek_tail(&mut self) -> Result<(), io::Error> {
+        sys::check_call(unsafe { sys::sd_journal_seek_tail(self.as_ptr()) }).map(drop)
+    }
+
+    /// Seek to the given time in microseconds since the UNIX epoch.
+    pub fn seek_realtime_usec(&mut self, usec: u64) -> Result<(), io::Error> {
+        sys::check_call(unsafe { sys::sd_journal_seek_realtime_usec(self.as_ptr(), usec) })
+            .map(drop)
+    }
+
+    /// Seek to the entry with the given cursor, or the closest one if it does not exist anymore.
+    ///
+    /// The next call to [`next_entry`](Self::next_entry) or [`previous_entry`](Self::previous_entry) moves to that
+    /// entry, use [`test_cursor`](Self::test_cursor) to check whether it is the exact entry.
+    pub fn seek_cursor(&mut self, cursor: &str) -> Result<(), io::Error> {
+        let cursor = to_cstring(cursor)?;
+        sys::check_call(unsafe { sys::sd_journal_seek_cursor(self.as_ptr(), cursor.as_ptr()) })
+            .map(drop)
+    }
+
+    /// Check whether the current entry has the given cursor.
+    pub fn test_cursor(&mut self, cursor: &str) -> Result<bool, io::Error> {
+        let cursor = to_cstring(cursor)?;
+        let res = sys::check_call(unsafe {
+            sys::sd_journal_test_cursor(self.as_ptr(), cursor.as_ptr())
+        })?;
+        Ok(res > 0)
+    }
+
+    /// Move to the next entry, returns `false` at the end of the journal.
+    pub fn next_entry(&mut self) -> Result<bool, io::Error> {
+        let res = sys::check_call(unsafe { sys::sd_journal_next(self.as_ptr()) })?;
+        Ok(res > 0)
+    }
+
+    /// Move to the previous entry, returns `false` at the start of the journal.
+    pub fn previous_entry(&mut self) -> Result<bool, io::Error> {
+        let res = sys::check_call(unsafe { sys::sd_journal_previous(self.as_ptr()) })?;
+        Ok(res > 0)
+    }
+
+    /// The cursor of the current entry, which can be used to seek back to it later.
+    pub fn cursor(&mut self) -> Result<String, io::Error> {
+        let mut cursor: *mut c_char = std::ptr::null_mut();
+        sys::check_call(unsafe { sys::sd_journal_get_cursor(self.as_ptr(), &mut cursor) })?;
+        let value = unsafe { CStr::from_ptr(cursor) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { libc::free(cursor.cast()) };
+        Ok(value)
+    }
+
+    /// The time of the current entry in microseconds since the UNIX epoch.
+    pub fn realtime_usec(&mut self) -> Result<u64, io::Error> {
+        let mut usec = 0;
+        sys::check_call(unsafe { sys::sd_journal_get_realtime_usec(self.as_ptr(), &mut usec) })?;
+        Ok(usec)
+    }
+
+    /// The value of `field` of the current entry, or `None` if the entry does not have it.
+    pub fn data(&mut self, field: &str) -> Result<Option<Vec<u8>>, io::Error> {
+        let c_field = to_cstring(field)?;
+        let mut data: *const c_void = std::ptr::null();
+        let mut length: usize = 0;
+        let res = unsafe {
+            sys::sd_journal_get_data(self.as_ptr(), c_field.as_ptr(), &mut data, &mut length)
+        };
+        if res == -libc::ENOENT {
+            return Ok(None);
+        }
+        sys::check_call(res)?;
+
+        // the data is only valid until the next call, and has the form `FIELD=value`
+        let data = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), length) };
+        Ok(Some(
+            data.get(field.len() + 1..).unwrap_or_default().to_vec(),
+        ))
+    }
+
+    /// The value of `field` of the current entry as (lossy) UTF-8 string.
+    pub fn data_string(&mut self, field: &str) -> Result<Option<String>, io::Error> {
+        Ok(self
+            .data(field)?
+            .map(|data| String::from_utf8_lossy(&data).into_owned()))
+    }
+}
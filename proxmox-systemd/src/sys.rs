@@ -1,4 +1,4 @@
-use std::ffi::{c_char, c_int, c_uchar, c_uint};
+use std::ffi::{c_char, c_int, c_uchar, c_uint, c_void};
 use std::io;
 use std::os::fd::RawFd;
 
@@ -9,6 +9,14 @@ pub struct sd_id128_t {
     pub bytes: [u8; 16],
 }
 
+/// Opaque journal handle, see `sd_journal_open(3)`.
+#[repr(C)]
+pub struct sd_journal {
+    _private: [u8; 0],
+}
+
+pub const SD_JOURNAL_LOCAL_ONLY: c_int = 1 << 0;
+
 #[link(name = "systemd")]
 unsafe extern "C" {
     pub fn sd_journal_stream_fd(
@@ -30,6 +38,28 @@ unsafe extern "C" {
         names: *mut *mut *mut c_char,
     ) -> c_int;
     pub fn sd_id128_get_machine_app_specific(app_id: sd_id128_t, ret: *mut sd_id128_t) -> c_int;
+
+    pub fn sd_journal_open(ret: *mut *mut sd_journal, flags: c_int) -> c_int;
+    pub fn sd_journal_close(j: *mut sd_journal);
+    pub fn sd_journal_next(j: *mut sd_journal) -> c_int;
+    pub fn sd_journal_previous(j: *mut sd_journal) -> c_int;
+    pub fn sd_journal_seek_head(j: *mut sd_journal) -> c_int;
+    pub fn sd_journal_seek_tail(j: *mut sd_journal) -> c_int;
+    pub fn sd_journal_seek_realtime_usec(j: *mut sd_journal, usec: u64) -> c_int;
+    pub fn sd_journal_seek_cursor(j: *mut sd_journal, cursor: *const c_char) -> c_int;
+    pub fn sd_journal_test_cursor(j: *mut sd_journal, cursor: *const c_char) -> c_int;
+    pub fn sd_journal_get_cursor(j: *mut sd_journal, cursor: *mut *mut c_char) -> c_int;
+    pub fn sd_journal_get_realtime_usec(j: *mut sd_journal, ret: *mut u64) -> c_int;
+    pub fn sd_journal_get_data(
+        j: *mut sd_journal,
+        field: *const c_char,
+        data: *mut *const c_void,
+        length: *mut usize,
+    ) -> c_int;
+    pub fn sd_journal_add_match(j: *mut sd_journal, data: *const c_void, size: usize) -> c_int;
+    pub fn sd_journal_add_disjunction(j: *mut sd_journal) -> c_int;
+    pub fn sd_journal_add_conjunction(j: *mut sd_journal) -> c_int;
+    pub fn sd_journal_flush_matches(j: *mut sd_journal);
 }
 
 pub fn check_call(ret: c_int) -> Result<c_int, io::Error> {
@@ -4,6 +4,8 @@ use std::{io::Write, path::PathBuf};
 use anyhow::{Error, format_err};
 use const_format::concatcp;
 use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 use proxmox_apt::repositories;
@@ -21,6 +23,8 @@ pub struct UpgradeCheckerBuilder {
     api_server_package: Option<String>,
     running_api_server_version: String,
     services_list: Vec<String>,
+    checks: Vec<Box<dyn UpgradeCheck>>,
+    output_format: OutputFormat,
 }
 
 impl UpgradeCheckerBuilder {
@@ -53,6 +57,8 @@ impl UpgradeCheckerBuilder {
             api_server_package: None,
             running_api_server_version: running_api_server_version.into(),
             services_list: Vec::new(),
+            checks: Vec::new(),
+            output_format: OutputFormat::default(),
         }
     }
 
@@ -74,10 +80,22 @@ impl UpgradeCheckerBuilder {
         self
     }
 
+    /// Add a product specific check, run after the built-in checks.
+    pub fn add_check<C: UpgradeCheck + 'static>(mut self, check: C) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// Set the output format, defaults to [`OutputFormat::Console`].
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
     /// Construct the UpgradeChecker, consumes the UpgradeCheckerBuilder
     pub fn build(mut self) -> UpgradeChecker {
         UpgradeChecker {
-            output: ConsoleOutput::new(),
+            output: CheckOutput::new(self.output_format),
             upgraded: false,
             old_suite: self.old_suite,
             new_suite: self.new_suite,
@@ -96,13 +114,55 @@ impl UpgradeCheckerBuilder {
             running_api_server_version: self.running_api_server_version,
             meta_package_name: self.meta_package_name,
             services_list: self.services_list,
+            checks: self.checks,
         }
     }
 }
 
+/// Information about the upgrade, available to [`UpgradeCheck`] implementations.
+pub struct CheckContext<'a> {
+    old_suite: &'a str,
+    new_suite: &'a str,
+    meta_package_name: &'a str,
+    upgraded: bool,
+}
+
+impl CheckContext<'_> {
+    /// The Debian suite before the upgrade.
+    pub fn old_suite(&self) -> &str {
+        self.old_suite
+    }
+
+    /// The Debian suite after the upgrade.
+    pub fn new_suite(&self) -> &str {
+        self.new_suite
+    }
+
+    /// The name of the product's meta package.
+    pub fn meta_package_name(&self) -> &str {
+        self.meta_package_name
+    }
+
+    /// Whether the meta package is already upgraded, only known once the package checks ran.
+    pub fn upgraded(&self) -> bool {
+        self.upgraded
+    }
+}
+
+/// A product specific check, see [`UpgradeCheckerBuilder::add_check`].
+pub trait UpgradeCheck {
+    /// A unique identifier for the machine-readable report, e.g. `ceph-version`.
+    fn id(&self) -> &str;
+
+    /// Run the check, results are reported via the `output`, e.g. [`CheckOutput::log_pass`].
+    ///
+    /// Returning an error is reported as failure of this check, the remaining checks still run.
+    fn run(&self, context: &CheckContext, output: &mut CheckOutput) -> Result<(), Error>;
+}
+
 /// Helpers to easily construct a set of upgrade checks.
 pub struct UpgradeChecker {
-    output: ConsoleOutput,
+    output: CheckOutput,
     upgraded: bool,
     old_suite: String,
     new_suite: String,
@@ -114,6 +174,7 @@ pub struct UpgradeChecker {
     api_server_package: String,
     running_api_server_version: String,
     services_list: Vec<String>,
+    checks: Vec<Box<dyn UpgradeCheck>>,
 }
 
 impl UpgradeChecker {
@@ -121,25 +182,67 @@ impl UpgradeChecker {
     pub fn run(&mut self) -> Result<(), Error> {
         self.check_packages()?;
         self.check_misc()?;
+        self.check_additional()?;
         self.summary()
     }
 
     /// Run miscellaneous checks.
     pub fn check_misc(&mut self) -> Result<(), Error> {
         self.output.print_header("MISCELLANEOUS CHECKS")?;
-        self.check_services()?;
-        self.check_time_sync()?;
-        self.check_apt_repos()?;
-        self.check_bootloader()?;
-        self.check_dkms_modules()?;
+        self.run_check("services", Self::check_services)?;
+        self.run_check("time-sync", Self::check_time_sync)?;
+        self.run_check("apt-repositories", Self::check_apt_repos)?;
+        self.run_check("bootloader", Self::check_bootloader)?;
+        self.run_check("dkms-modules", Self::check_dkms_modules)?;
+        Ok(())
+    }
+
+    /// Run a built-in check, an error is reported as failure of the check.
+    ///
+    /// Only errors writing the output are returned, so that the remaining checks still run.
+    fn run_check<F>(&mut self, id: &str, check: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Error>,
+    {
+        self.output.start_check(id);
+        let result = check(self);
+        self.output.finish_check(result)
+    }
+
+    /// Run the product specific checks added via [`UpgradeCheckerBuilder::add_check`].
+    pub fn check_additional(&mut self) -> Result<(), Error> {
+        if self.checks.is_empty() {
+            return Ok(());
+        }
+        self.output.print_header("ADDITIONAL CHECKS")?;
+
+        let context = CheckContext {
+            old_suite: &self.old_suite,
+            new_suite: &self.new_suite,
+            meta_package_name: &self.meta_package_name,
+            upgraded: self.upgraded,
+        };
+
+        for check in &self.checks {
+            self.output.start_check(check.id());
+            let result = check.run(&context, &mut self.output);
+            self.output.finish_check(result)?;
+        }
         Ok(())
     }
 
     /// Print a summary of all checks run so far.
+    ///
+    /// With [`OutputFormat::Json`] this prints the whole report.
     pub fn summary(&mut self) -> Result<(), Error> {
         self.output.print_summary()
     }
 
+    /// The results of all checks finished so far.
+    pub fn records(&self) -> &[CheckRecord] {
+        &self.output.records
+    }
+
     /// Run all package related checks.
     pub fn check_packages(&mut self) -> Result<(), Error> {
         self.output.print_header(&format!(
@@ -147,17 +250,25 @@ impl UpgradeChecker {
             self.meta_package_name.to_uppercase()
         ))?;
 
-        self.check_upgradable_packages()?;
+        self.run_check("upgradable-packages", Self::check_upgradable_packages)?;
 
         let pkg_versions = proxmox_apt::get_package_versions(
             &self.meta_package_name,
             &self.api_server_package,
             &self.running_api_server_version,
             &[],
-        )?;
-
-        self.check_meta_package_version(&pkg_versions)?;
-        self.check_kernel_compat(&pkg_versions)?;
+        );
+
+        self.run_check("meta-package-version", |this| {
+            let pkg_versions = pkg_versions
+                .as_deref()
+                .map_err(|err| format_err!("failed to get package versions - {err}"))?;
+            this.check_meta_package_version(pkg_versions)
+        })?;
+        // the installed packages are only used to explain an unsuitable running kernel
+        self.run_check("kernel-version", |this| {
+            this.check_kernel_compat(pkg_versions.as_deref().unwrap_or_default())
+        })?;
         Ok(())
     }
 
@@ -645,31 +756,117 @@ struct Counters {
     fail: u64,
 }
 
-enum LogLevel {
-    Pass,
+impl Counters {
+    fn total(&self) -> u64 {
+        self.fail + self.pass + self.notice + self.skip + self.warn
+    }
+}
+
+/// The level of a reported check result, ordered by severity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
     Info,
+    Pass,
     Skip,
     Notice,
     Warn,
     Fail,
 }
 
-struct ConsoleOutput {
+/// Output format of the [`UpgradeChecker`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Coloured, human-readable lines on the terminal.
+    #[default]
+    Console,
+    /// A JSON report with all results, printed by [`UpgradeChecker::summary`].
+    Json,
+}
+
+/// A single line reported by a check.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckMessage {
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// The results reported by a single check.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckRecord {
+    /// The identifier of the check, e.g. `kernel-version`.
+    pub id: String,
+    /// The most severe level of all messages, [`LogLevel::Info`] if there are only
+    /// informational messages.
+    pub level: LogLevel,
+    pub messages: Vec<CheckMessage>,
+}
+
+impl CheckRecord {
+    fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            level: LogLevel::Info,
+            messages: Vec::new(),
+        }
+    }
+}
+
+/// Identifier for results reported outside of any check.
+const GENERAL_CHECK_ID: &str = "general";
+
+/// Collects the results of the checks and prints them in the configured [`OutputFormat`].
+pub struct CheckOutput {
     stream: StandardStream,
+    format: OutputFormat,
     first_header: bool,
     counters: Counters,
+    current_check: Option<CheckRecord>,
+    records: Vec<CheckRecord>,
 }
 
-impl ConsoleOutput {
-    fn new() -> Self {
+impl CheckOutput {
+    fn new(format: OutputFormat) -> Self {
         Self {
             stream: StandardStream::stdout(ColorChoice::Always),
+            format,
             first_header: true,
             counters: Counters::default(),
+            current_check: None,
+            records: Vec::new(),
+        }
+    }
+
+    /// Start collecting the results of the check `id`.
+    fn start_check(&mut self, id: &str) {
+        self.finish_current_check();
+        self.current_check = Some(CheckRecord::new(id));
+    }
+
+    /// Finish the current check, reporting `result` as failure if it is an error.
+    fn finish_check(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        if let Err(err) = result {
+            let id = self
+                .current_check
+                .as_ref()
+                .map(|record| record.id.clone())
+                .unwrap_or_else(|| GENERAL_CHECK_ID.to_string());
+            self.log_fail(format!("check '{id}' failed - {err}"))?;
+        }
+        self.finish_current_check();
+        Ok(())
+    }
+
+    fn finish_current_check(&mut self) {
+        if let Some(record) = self.current_check.take() {
+            self.records.push(record);
         }
     }
 
     fn print_header(&mut self, message: &str) -> Result<(), Error> {
+        if self.format == OutputFormat::Json {
+            return Ok(());
+        }
         if !self.first_header {
             writeln!(&mut self.stream)?;
         }
@@ -689,9 +886,29 @@ impl ConsoleOutput {
     }
 
     fn log_line(&mut self, level: LogLevel, message: &str) -> Result<(), Error> {
+        match level {
+            LogLevel::Pass => self.counters.pass += 1,
+            LogLevel::Info => (),
+            LogLevel::Skip => self.counters.skip += 1,
+            LogLevel::Notice => self.counters.notice += 1,
+            LogLevel::Warn => self.counters.warn += 1,
+            LogLevel::Fail => self.counters.fail += 1,
+        }
+        let record = self
+            .current_check
+            .get_or_insert_with(|| CheckRecord::new(GENERAL_CHECK_ID));
+        record.level = record.level.max(level);
+        record.messages.push(CheckMessage {
+            level,
+            message: message.to_string(),
+        });
+
+        if self.format == OutputFormat::Json {
+            return Ok(());
+        }
+
         match level {
             LogLevel::Pass => {
-                self.counters.pass += 1;
                 self.set_color(Color::Green, false)?;
                 writeln!(&mut self.stream, "PASS: {message}")?;
             }
@@ -699,21 +916,17 @@ impl ConsoleOutput {
                 writeln!(&mut self.stream, "INFO: {message}")?;
             }
             LogLevel::Skip => {
-                self.counters.skip += 1;
                 writeln!(&mut self.stream, "SKIP: {message}")?;
             }
             LogLevel::Notice => {
-                self.counters.notice += 1;
                 self.set_color(Color::White, true)?;
                 writeln!(&mut self.stream, "NOTICE: {message}")?;
             }
             LogLevel::Warn => {
-                self.counters.warn += 1;
                 self.set_color(Color::Yellow, false)?;
                 writeln!(&mut self.stream, "WARN: {message}")?;
             }
             LogLevel::Fail => {
-                self.counters.fail += 1;
                 self.set_color(Color::Red, true)?;
                 writeln!(&mut self.stream, "FAIL: {message}")?;
             }
@@ -722,38 +935,62 @@ impl ConsoleOutput {
         Ok(())
     }
 
-    fn log_pass<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
+    /// Report a passed check.
+    pub fn log_pass<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
         self.log_line(LogLevel::Pass, message.as_ref())
     }
 
-    fn log_info<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
+    /// Report an informational message, not counted in the summary.
+    pub fn log_info<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
         self.log_line(LogLevel::Info, message.as_ref())
     }
 
-    fn log_skip<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
+    /// Report a skipped check.
+    pub fn log_skip<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
         self.log_line(LogLevel::Skip, message.as_ref())
     }
 
-    fn log_notice<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
+    /// Report something the admin should be aware of.
+    pub fn log_notice<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
         self.log_line(LogLevel::Notice, message.as_ref())
     }
 
-    fn log_warn<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
+    /// Report a potential problem.
+    pub fn log_warn<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
         self.log_line(LogLevel::Warn, message.as_ref())
     }
 
-    fn log_fail<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
+    /// Report a problem that needs to be fixed before upgrading.
+    pub fn log_fail<T: AsRef<str>>(&mut self, message: T) -> Result<(), Error> {
         self.log_line(LogLevel::Fail, message.as_ref())
     }
 
+    fn report(&self) -> serde_json::Value {
+        let counters = &self.counters;
+        json!({
+            "checks": self.records,
+            "summary": {
+                "total": counters.total(),
+                "pass": counters.pass,
+                "skip": counters.skip,
+                "notice": counters.notice,
+                "warn": counters.warn,
+                "fail": counters.fail,
+            },
+        })
+    }
+
     fn print_summary(&mut self) -> Result<(), Error> {
+        if self.format == OutputFormat::Json {
+            let report = self.report();
+            serde_json::to_writer_pretty(&mut self.stream, &report)?;
+            writeln!(&mut self.stream)?;
+            return Ok(());
+        }
+
         self.print_header("SUMMARY")?;
 
-        let total = self.counters.fail
-            + self.counters.pass
-            + self.counters.notice
-            + self.counters.skip
-            + self.counters.warn;
+        let total = self.counters.total();
 
         writeln!(&mut self.stream, "TOTAL:     {total}")?;
         self.set_color(Color::Green, false)?;
@@ -829,6 +1066,55 @@ mod tests {
         }
     }
 
+    struct DummyCheck;
+
+    impl UpgradeCheck for DummyCheck {
+        fn id(&self) -> &str {
+            "dummy"
+        }
+
+        fn run(&self, context: &CheckContext, output: &mut CheckOutput) -> Result<(), Error> {
+            output.log_info("Checking dummy..")?;
+            output.log_pass(format!("upgrading to {}", context.new_suite()))?;
+            anyhow::bail!("broken");
+        }
+    }
+
+    #[test]
+    fn test_additional_checks_report() {
+        let mut checker =
+            UpgradeCheckerBuilder::new("bookworm", "trixie", "proxmox-backup", 3, 4, 0, "3.4")
+                .output_format(OutputFormat::Json)
+                .add_check(DummyCheck)
+                .build();
+
+        checker.check_additional().unwrap();
+
+        let records = checker.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "dummy");
+        assert_eq!(records[0].level, LogLevel::Fail);
+        let messages: Vec<(LogLevel, &str)> = records[0]
+            .messages
+            .iter()
+            .map(|message| (message.level, message.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (LogLevel::Info, "Checking dummy.."),
+                (LogLevel::Pass, "upgrading to trixie"),
+                (LogLevel::Fail, "check 'dummy' failed - broken"),
+            ]
+        );
+
+        let report = checker.output.report();
+        assert_eq!(report["checks"][0]["level"], "fail");
+        assert_eq!(report["checks"][0]["messages"][1]["level"], "pass");
+        assert_eq!(report["summary"]["total"], 2);
+        assert_eq!(report["summary"]["fail"], 1);
+    }
+
     #[test]
     fn test_before_upgrade_kernel_version_compatibility() {
         let expected_versions = &["6.2.16-20-pve", "6.5.13-6-pve", "6.8.12-1-pve"];